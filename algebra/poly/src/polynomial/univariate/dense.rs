@@ -476,7 +476,11 @@ impl<'a, 'b, F: FftField> Mul<&'a DensePolynomial<F>> for &'b DensePolynomial<F>
             let mut self_evals = self.evaluate_over_domain_by_ref(domain);
             let other_evals = other.evaluate_over_domain_by_ref(domain);
             self_evals *= &other_evals;
-            self_evals.interpolate()
+            let mut result = self_evals.interpolate();
+            // The coefficients past the product's degree are zero, but for secret-shared
+            // coefficients `is_zero` cannot tell, so drop them explicitly.
+            result.coeffs.truncate(self.coeffs.len() + other.coeffs.len() - 1);
+            result
         }
     }
 }
@@ -169,6 +169,7 @@ impl<F: PrimeField, PC: PolynomialCommitment<F, DensePolynomial<F>>, D: Digest>
 
         // --------------------------------------------------------------------
         // First round
+        let phase = mpc_net::phase("first round");
         let (mut prover_first_msg, prover_first_oracles, prover_state) =
             AHPForR1CS::prover_first_round(prover_init_state, zk_rng)?;
         prover_first_msg.publicize();
@@ -191,11 +192,12 @@ impl<F: PrimeField, PC: PolynomialCommitment<F, DensePolynomial<F>>, D: Digest>
         let (verifier_first_msg, verifier_state) =
             AHPForR1CS::verifier_first_round(index_pk.index_vk.index_info, &mut fs_rng)?;
         end_timer!(t);
+        drop(phase);
         // --------------------------------------------------------------------
 
         // --------------------------------------------------------------------
         // Second round
-
+        let phase = mpc_net::phase("second round");
         let (mut prover_second_msg, prover_second_oracles, prover_state) =
             AHPForR1CS::prover_second_round(&verifier_first_msg, prover_state, zk_rng);
 
@@ -214,10 +216,12 @@ impl<F: PrimeField, PC: PolynomialCommitment<F, DensePolynomial<F>>, D: Digest>
 
         let (verifier_second_msg, verifier_state) =
             AHPForR1CS::verifier_second_round(verifier_state, &mut fs_rng);
+        drop(phase);
         // --------------------------------------------------------------------
 
         // --------------------------------------------------------------------
         // Third round
+        let phase = mpc_net::phase("third round");
         let (mut prover_third_msg, prover_third_oracles) =
             AHPForR1CS::prover_third_round(&verifier_second_msg, prover_state, zk_rng)?;
 
@@ -235,6 +239,7 @@ impl<F: PrimeField, PC: PolynomialCommitment<F, DensePolynomial<F>>, D: Digest>
         fs_rng.absorb(&to_bytes![third_comms, prover_third_msg].unwrap());
 
         let verifier_state = AHPForR1CS::verifier_third_round(verifier_state, &mut fs_rng);
+        drop(phase);
         // --------------------------------------------------------------------
 
         // Gather prover polynomials in one vector.
@@ -284,6 +289,7 @@ impl<F: PrimeField, PC: PolynomialCommitment<F, DensePolynomial<F>>, D: Digest>
         )?;
 
         let eval_time = start_timer!(|| "Evaluating linear combinations over query set");
+        let phase = mpc_net::phase("evaluations");
         let mut evaluations = Vec::new();
         for (label, (_, point)) in &query_set {
             let lc = lc_s
@@ -299,12 +305,14 @@ impl<F: PrimeField, PC: PolynomialCommitment<F, DensePolynomial<F>>, D: Digest>
         evaluations.sort_by(|a, b| a.0.cmp(&b.0));
         let mut evaluations = evaluations.into_iter().map(|x| x.1).collect::<Vec<F>>();
         evaluations.iter_mut().for_each(|e| e.publicize());
+        drop(phase);
         end_timer!(eval_time);
 
         fs_rng.absorb(&evaluations);
         let opening_challenge: F = u128::rand(&mut fs_rng).into();
 
         let open_time = start_timer!(|| "Openning combinations");
+        let phase = mpc_net::phase("open combinations");
         let pc_proof = PC::open_combinations(
             &index_pk.committer_key,
             &lc_s,
@@ -316,6 +324,7 @@ impl<F: PrimeField, PC: PolynomialCommitment<F, DensePolynomial<F>>, D: Digest>
             Some(zk_rng),
         )
         .map_err(Error::from_pc_err)?;
+        drop(phase);
         end_timer!(open_time);
 
         // Gather prover messages together.
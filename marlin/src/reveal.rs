@@ -19,10 +19,17 @@ use ark_std::{end_timer, start_timer};
 impl<F: PrimeField, S: FieldShare<F>> Reveal for ProverMsg<MpcField<F, S>> {
     type Base = ProverMsg<F>;
 
-    fn reveal(self) -> Self::Base {
-        match self {
+    fn try_reveal(self) -> Result<Self::Base, MpcNetError> {
+        Ok(match self {
             ProverMsg::EmptyMessage => ProverMsg::EmptyMessage,
-            ProverMsg::FieldElements(d) => ProverMsg::FieldElements(d.reveal()),
+            ProverMsg::FieldElements(d) => ProverMsg::FieldElements(d.try_reveal()?),
+        })
+    }
+
+    fn reveal_to(self, to: usize) -> Option<Self::Base> {
+        match self {
+            ProverMsg::EmptyMessage => only_to(to, ProverMsg::EmptyMessage),
+            ProverMsg::FieldElements(d) => d.reveal_to(to).map(ProverMsg::FieldElements),
         }
     }
 
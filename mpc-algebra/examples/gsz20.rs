@@ -6,7 +6,7 @@ use mpc_algebra::{
     msm::NaiveMsm, share::field::FieldShare, share::group::GroupShare, share::gsz20::*,
     share::pairing::PairingShare, Reveal,
 };
-use mpc_net::{MpcNet, MpcMultiNet as Net};
+use mpc_net::{MpcNet, MpcMultiNet as Net, Transport};
 
 use std::path::PathBuf;
 use structopt::StructOpt;
@@ -209,7 +209,7 @@ fn main() {
     debug!("Start");
     let opt = Opt::from_args();
     println!("{:?}", opt);
-    Net::init_from_file(opt.input.to_str().unwrap(), opt.id);
+    Net::init_from_file(opt.input.to_str().unwrap(), opt.id, Transport::Tcp).unwrap();
 
     test::<ark_bls12_377::Fr>();
     test_ip::<ark_bls12_377::Fr>();
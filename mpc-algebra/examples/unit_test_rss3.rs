@@ -6,7 +6,7 @@ use mpc_algebra::{
     msm::NaiveMsm, share::field::FieldShare, share::group::GroupShare, share::rss3::*,
     share::pairing::PairingShare, Reveal,
 };
-use mpc_net::{MpcNet, MpcMultiNet as Net};
+use mpc_net::{MpcNet, MpcMultiNet as Net, Transport};
 use mpc_algebra::share::PanicBeaverSource;
 
 use std::path::PathBuf;
@@ -238,7 +238,7 @@ fn main() {
     debug!("Start");
     let opt = Opt::from_args();
     println!("{:?}", opt);
-    Net::init_from_file(opt.input.to_str().unwrap(), opt.id);
+    Net::init_from_file(opt.input.to_str().unwrap(), opt.id, Transport::Tcp).unwrap();
 
     test_sum_field::<ark_bls12_377::Fr>();
     test_mul_field::<ark_bls12_377::Fr>();
@@ -3,95 +3,430 @@ use digest::Digest;
 use rand::RngCore;
 use sha2::Sha256;
 use std::cell::Cell;
+use std::marker::PhantomData;
 
 use mpc_net::two as net_two;
 
-use mpc_net::MpcNet;
+use mpc_net::{MpcNet, MpcNetError, MpcSession};
 
+/// Serializing versions of the [MpcNet] operations.
+///
+/// Like the byte-level operations, these run in the calling thread's current session. A value
+/// that does not deserialize is [MpcNetError::Malformed], blamed on the party that sent it. To
+/// name the session instead, use the same operations on its handle (see [SessionSerNet]).
 pub trait MpcSerNet: MpcNet {
     #[inline]
-    fn broadcast<T: CanonicalDeserialize + CanonicalSerialize>(out: &T) -> Vec<T> {
-        let mut bytes_out = Vec::new();
-        out.serialize(&mut bytes_out).unwrap();
-        let bytes_in = Self::broadcast_bytes(&bytes_out);
-        bytes_in
-            .into_iter()
-            .map(|b| T::deserialize(&b[..]).unwrap())
-            .collect()
+    fn broadcast<T: CanonicalDeserialize + CanonicalSerialize>(
+        out: &T,
+    ) -> Result<Vec<T>, MpcNetError> {
+        broadcast(&Current::<Self>::default(), out)
     }
 
     #[inline]
-    fn pass_to_next<T: CanonicalDeserialize + CanonicalSerialize>(out: &T) -> T {
-        let mut bytes_out = Vec::new();
-        out.serialize(&mut bytes_out).unwrap();
-        let bytes_in = Self::pass_to_next_bytes(&bytes_out);
-        T::deserialize(&bytes_in[..]).unwrap()
+    fn pass_to_next<T: CanonicalDeserialize + CanonicalSerialize>(
+        out: &T,
+    ) -> Result<T, MpcNetError> {
+        pass_to_next(&Current::<Self>::default(), out)
     }
 
+    #[inline]
+    fn pass_around<T: CanonicalDeserialize + CanonicalSerialize>(
+        out: &T,
+        hops: usize,
+    ) -> Result<Vec<T>, MpcNetError> {
+        pass_around(&Current::<Self>::default(), out, hops)
+    }
 
     #[inline]
-    fn send_to_king<T: CanonicalDeserialize + CanonicalSerialize>(out: &T) -> Option<Vec<T>> {
-        let mut bytes_out = Vec::new();
-        out.serialize(&mut bytes_out).unwrap();
-        Self::send_bytes_to_king(&bytes_out).map(|bytes_in| {
-            bytes_in
-                .into_iter()
-                .map(|b| T::deserialize(&b[..]).unwrap())
-                .collect()
-        })
+    fn all_to_all<T: CanonicalDeserialize + CanonicalSerialize>(
+        outs: &[T],
+    ) -> Result<Vec<T>, MpcNetError> {
+        all_to_all(&Current::<Self>::default(), outs)
     }
 
     #[inline]
-    fn recv_from_king<T: CanonicalDeserialize + CanonicalSerialize>(out: Option<Vec<T>>) -> T {
-        let bytes_in = Self::recv_bytes_from_king(out.map(|outs| {
-            outs.iter()
-                .map(|out| {
-                    let mut bytes_out = Vec::new();
-                    out.serialize(&mut bytes_out).unwrap();
-                    bytes_out
-                })
-                .collect()
-        }));
-        T::deserialize(&bytes_in[..]).unwrap()
-    }
-
-    #[inline]
-    fn atomic_broadcast<T: CanonicalDeserialize + CanonicalSerialize>(out: &T) -> Vec<T> {
-        let mut bytes_out = Vec::new();
-        out.serialize(&mut bytes_out).unwrap();
-        let ser_len = bytes_out.len();
-        bytes_out.resize(ser_len + COMMIT_RAND_BYTES, 0);
-        rand::thread_rng().fill_bytes(&mut bytes_out[ser_len..]);
-        let commitment = CommitHash::new().chain(&bytes_out).finalize();
-        // exchange commitments
-        let all_commits = Self::broadcast_bytes(&commitment[..]);
-        // exchange (data || randomness)
-        let all_data = Self::broadcast_bytes(&bytes_out);
-        let self_id = Self::party_id();
-        for i in 0..all_commits.len() {
-            if i != self_id {
-                // check other commitment
-                assert_eq!(
-                    &all_commits[i][..],
-                    &CommitHash::new().chain(&all_data[i]).finalize()[..]
-                );
-            }
-        }
-        all_data
-            .into_iter()
-            .map(|d| T::deserialize(&d[..ser_len]).unwrap())
-            .collect()
+    fn send_to_king<T: CanonicalDeserialize + CanonicalSerialize>(
+        out: &T,
+    ) -> Result<Option<Vec<T>>, MpcNetError> {
+        send_to_king(&Current::<Self>::default(), out)
+    }
+
+    /// All parties send a value to party `to`, which gets them all, in party order.
+    #[inline]
+    fn send_to<T: CanonicalDeserialize + CanonicalSerialize>(
+        to: usize,
+        out: &T,
+    ) -> Result<Option<Vec<T>>, MpcNetError> {
+        send_to(&Current::<Self>::default(), to, out)
     }
 
     #[inline]
-    fn king_compute<T: CanonicalDeserialize + CanonicalSerialize>(x: &T, f: impl Fn(Vec<T>) -> Vec<T>) -> T {
-        let king_response = Self::send_to_king(x).map(f);
+    fn recv_from_king<T: CanonicalDeserialize + CanonicalSerialize>(
+        out: Option<Vec<T>>,
+    ) -> Result<T, MpcNetError> {
+        recv_from_king(&Current::<Self>::default(), out)
+    }
+
+    /// Broadcast, committing to our value before we see anyone else's.
+    ///
+    /// A party that opens something other than what it committed to is
+    /// [MpcNetError::CommitmentMismatch].
+    #[inline]
+    fn atomic_broadcast<T: CanonicalDeserialize + CanonicalSerialize>(
+        out: &T,
+    ) -> Result<Vec<T>, MpcNetError> {
+        atomic_broadcast(&Current::<Self>::default(), out)
+    }
+
+    #[inline]
+    fn king_compute<T: CanonicalDeserialize + CanonicalSerialize>(
+        x: &T,
+        f: impl Fn(Vec<T>) -> Vec<T>,
+    ) -> Result<T, MpcNetError> {
+        let king_response = Self::send_to_king(x)?.map(f);
         Self::recv_from_king(king_response)
     }
 }
 
 impl<N: MpcNet> MpcSerNet for N {}
 
+/// The [MpcSerNet] operations on a session handle: they run in `self`, whether or not it is the
+/// calling thread's current session.
+///
+/// Pass the handle to code that talks to the other parties, rather than entering the session
+/// around it, so that it cannot run in the wrong session by accident.
+pub trait SessionSerNet {
+    fn broadcast<T: CanonicalDeserialize + CanonicalSerialize>(
+        &self,
+        out: &T,
+    ) -> Result<Vec<T>, MpcNetError>;
+    fn pass_to_next<T: CanonicalDeserialize + CanonicalSerialize>(
+        &self,
+        out: &T,
+    ) -> Result<T, MpcNetError>;
+    fn pass_around<T: CanonicalDeserialize + CanonicalSerialize>(
+        &self,
+        out: &T,
+        hops: usize,
+    ) -> Result<Vec<T>, MpcNetError>;
+    fn all_to_all<T: CanonicalDeserialize + CanonicalSerialize>(
+        &self,
+        outs: &[T],
+    ) -> Result<Vec<T>, MpcNetError>;
+    fn send_to_king<T: CanonicalDeserialize + CanonicalSerialize>(
+        &self,
+        out: &T,
+    ) -> Result<Option<Vec<T>>, MpcNetError>;
+    /// All parties send a value to party `to`, which gets them all, in party order.
+    fn send_to<T: CanonicalDeserialize + CanonicalSerialize>(
+        &self,
+        to: usize,
+        out: &T,
+    ) -> Result<Option<Vec<T>>, MpcNetError>;
+    fn recv_from_king<T: CanonicalDeserialize + CanonicalSerialize>(
+        &self,
+        out: Option<Vec<T>>,
+    ) -> Result<T, MpcNetError>;
+    /// Broadcast, committing to our value before we see anyone else's. See
+    /// [MpcSerNet::atomic_broadcast].
+    fn atomic_broadcast<T: CanonicalDeserialize + CanonicalSerialize>(
+        &self,
+        out: &T,
+    ) -> Result<Vec<T>, MpcNetError>;
+}
+
+impl SessionSerNet for MpcSession {
+    #[inline]
+    fn broadcast<T: CanonicalDeserialize + CanonicalSerialize>(
+        &self,
+        out: &T,
+    ) -> Result<Vec<T>, MpcNetError> {
+        broadcast(self, out)
+    }
+
+    #[inline]
+    fn pass_to_next<T: CanonicalDeserialize + CanonicalSerialize>(
+        &self,
+        out: &T,
+    ) -> Result<T, MpcNetError> {
+        pass_to_next(self, out)
+    }
+
+    #[inline]
+    fn pass_around<T: CanonicalDeserialize + CanonicalSerialize>(
+        &self,
+        out: &T,
+        hops: usize,
+    ) -> Result<Vec<T>, MpcNetError> {
+        pass_around(self, out, hops)
+    }
+
+    #[inline]
+    fn all_to_all<T: CanonicalDeserialize + CanonicalSerialize>(
+        &self,
+        outs: &[T],
+    ) -> Result<Vec<T>, MpcNetError> {
+        all_to_all(self, outs)
+    }
+
+    #[inline]
+    fn send_to_king<T: CanonicalDeserialize + CanonicalSerialize>(
+        &self,
+        out: &T,
+    ) -> Result<Option<Vec<T>>, MpcNetError> {
+        send_to_king(self, out)
+    }
+
+    #[inline]
+    fn send_to<T: CanonicalDeserialize + CanonicalSerialize>(
+        &self,
+        to: usize,
+        out: &T,
+    ) -> Result<Option<Vec<T>>, MpcNetError> {
+        send_to(self, to, out)
+    }
+
+    #[inline]
+    fn recv_from_king<T: CanonicalDeserialize + CanonicalSerialize>(
+        &self,
+        out: Option<Vec<T>>,
+    ) -> Result<T, MpcNetError> {
+        recv_from_king(self, out)
+    }
+
+    #[inline]
+    fn atomic_broadcast<T: CanonicalDeserialize + CanonicalSerialize>(
+        &self,
+        out: &T,
+    ) -> Result<Vec<T>, MpcNetError> {
+        atomic_broadcast(self, out)
+    }
+}
+
+/// The byte-level operations that the serializing ones are built on, whether from a session
+/// handle or from the current session of an [MpcNet].
+trait Channel {
+    fn party_id(&self) -> usize;
+    fn n_parties(&self) -> usize;
+    fn broadcast_bytes(&self, bytes: &[u8]) -> Result<Vec<Vec<u8>>, MpcNetError>;
+    fn pass_to_next_bytes(&self, bytes: &[u8]) -> Result<Vec<u8>, MpcNetError>;
+    fn pass_around_bytes(&self, bytes: &[u8], hops: usize) -> Result<Vec<Vec<u8>>, MpcNetError>;
+    fn all_to_all_bytes(&self, bytes: Vec<Vec<u8>>) -> Result<Vec<Vec<u8>>, MpcNetError>;
+    fn send_bytes_to_king(&self, bytes: &[u8]) -> Result<Option<Vec<Vec<u8>>>, MpcNetError>;
+    fn send_bytes_to(&self, to: usize, bytes: &[u8]) -> Result<Option<Vec<Vec<u8>>>, MpcNetError>;
+    fn recv_bytes_from_king(&self, bytes: Option<Vec<Vec<u8>>>) -> Result<Vec<u8>, MpcNetError>;
+}
+
+/// The current session of `N`.
+struct Current<N: ?Sized>(PhantomData<N>);
+
+impl<N: ?Sized> Default for Current<N> {
+    fn default() -> Self {
+        Current(PhantomData)
+    }
+}
+
+impl<N: MpcNet + ?Sized> Channel for Current<N> {
+    fn party_id(&self) -> usize {
+        N::party_id()
+    }
+    fn n_parties(&self) -> usize {
+        N::n_parties()
+    }
+    fn broadcast_bytes(&self, bytes: &[u8]) -> Result<Vec<Vec<u8>>, MpcNetError> {
+        N::broadcast_bytes(bytes)
+    }
+    fn pass_to_next_bytes(&self, bytes: &[u8]) -> Result<Vec<u8>, MpcNetError> {
+        N::pass_to_next_bytes(bytes)
+    }
+    fn pass_around_bytes(&self, bytes: &[u8], hops: usize) -> Result<Vec<Vec<u8>>, MpcNetError> {
+        N::pass_around_bytes(bytes, hops)
+    }
+    fn all_to_all_bytes(&self, bytes: Vec<Vec<u8>>) -> Result<Vec<Vec<u8>>, MpcNetError> {
+        N::all_to_all_bytes(bytes)
+    }
+    fn send_bytes_to_king(&self, bytes: &[u8]) -> Result<Option<Vec<Vec<u8>>>, MpcNetError> {
+        N::send_bytes_to_king(bytes)
+    }
+    fn send_bytes_to(&self, to: usize, bytes: &[u8]) -> Result<Option<Vec<Vec<u8>>>, MpcNetError> {
+        N::send_bytes_to(to, bytes)
+    }
+    fn recv_bytes_from_king(&self, bytes: Option<Vec<Vec<u8>>>) -> Result<Vec<u8>, MpcNetError> {
+        N::recv_bytes_from_king(bytes)
+    }
+}
+
+impl Channel for MpcSession {
+    fn party_id(&self) -> usize {
+        MpcSession::party_id(self)
+    }
+    fn n_parties(&self) -> usize {
+        MpcSession::n_parties(self)
+    }
+    fn broadcast_bytes(&self, bytes: &[u8]) -> Result<Vec<Vec<u8>>, MpcNetError> {
+        MpcSession::broadcast_bytes(self, bytes)
+    }
+    fn pass_to_next_bytes(&self, bytes: &[u8]) -> Result<Vec<u8>, MpcNetError> {
+        MpcSession::pass_to_next_bytes(self, bytes)
+    }
+    fn pass_around_bytes(&self, bytes: &[u8], hops: usize) -> Result<Vec<Vec<u8>>, MpcNetError> {
+        MpcSession::pass_around_bytes(self, bytes, hops)
+    }
+    fn all_to_all_bytes(&self, bytes: Vec<Vec<u8>>) -> Result<Vec<Vec<u8>>, MpcNetError> {
+        MpcSession::all_to_all_bytes(self, bytes)
+    }
+    fn send_bytes_to_king(&self, bytes: &[u8]) -> Result<Option<Vec<Vec<u8>>>, MpcNetError> {
+        MpcSession::send_bytes_to_king(self, bytes)
+    }
+    fn send_bytes_to(&self, to: usize, bytes: &[u8]) -> Result<Option<Vec<Vec<u8>>>, MpcNetError> {
+        MpcSession::send_bytes_to(self, to, bytes)
+    }
+    fn recv_bytes_from_king(&self, bytes: Option<Vec<Vec<u8>>>) -> Result<Vec<u8>, MpcNetError> {
+        MpcSession::recv_bytes_from_king(self, bytes)
+    }
+}
+
+fn broadcast<C: Channel, T: CanonicalDeserialize + CanonicalSerialize>(
+    net: &C,
+    out: &T,
+) -> Result<Vec<T>, MpcNetError> {
+    let bytes_in = net.broadcast_bytes(&serialize(out))?;
+    bytes_in
+        .iter()
+        .enumerate()
+        .map(|(i, b)| deserialize(i, b))
+        .collect()
+}
+
+fn pass_to_next<C: Channel, T: CanonicalDeserialize + CanonicalSerialize>(
+    net: &C,
+    out: &T,
+) -> Result<T, MpcNetError> {
+    let bytes_in = net.pass_to_next_bytes(&serialize(out))?;
+    let prev = (net.party_id() + net.n_parties() - 1) % net.n_parties();
+    deserialize(prev, &bytes_in)
+}
+
+fn pass_around<C: Channel, T: CanonicalDeserialize + CanonicalSerialize>(
+    net: &C,
+    out: &T,
+    hops: usize,
+) -> Result<Vec<T>, MpcNetError> {
+    let n = net.n_parties();
+    let bytes_in = net.pass_around_bytes(&serialize(out), hops)?;
+    bytes_in
+        .iter()
+        .enumerate()
+        .map(|(h, b)| deserialize((net.party_id() + n - h - 1) % n, b))
+        .collect()
+}
+
+fn all_to_all<C: Channel, T: CanonicalDeserialize + CanonicalSerialize>(
+    net: &C,
+    outs: &[T],
+) -> Result<Vec<T>, MpcNetError> {
+    let bytes_out = outs.iter().map(serialize).collect();
+    net.all_to_all_bytes(bytes_out)?
+        .iter()
+        .enumerate()
+        .map(|(i, b)| deserialize(i, b))
+        .collect()
+}
+
+fn send_to_king<C: Channel, T: CanonicalDeserialize + CanonicalSerialize>(
+    net: &C,
+    out: &T,
+) -> Result<Option<Vec<T>>, MpcNetError> {
+    net.send_bytes_to_king(&serialize(out))?
+        .map(|bytes_in| {
+            bytes_in
+                .iter()
+                .enumerate()
+                .map(|(i, b)| deserialize(i, b))
+                .collect()
+        })
+        .transpose()
+}
+
+fn send_to<C: Channel, T: CanonicalDeserialize + CanonicalSerialize>(
+    net: &C,
+    to: usize,
+    out: &T,
+) -> Result<Option<Vec<T>>, MpcNetError> {
+    net.send_bytes_to(to, &serialize(out))?
+        .map(|bytes_in| {
+            bytes_in
+                .iter()
+                .enumerate()
+                .map(|(i, b)| deserialize(i, b))
+                .collect()
+        })
+        .transpose()
+}
+
+fn recv_from_king<C: Channel, T: CanonicalDeserialize + CanonicalSerialize>(
+    net: &C,
+    out: Option<Vec<T>>,
+) -> Result<T, MpcNetError> {
+    let bytes_in = net.recv_bytes_from_king(out.map(|outs| outs.iter().map(serialize).collect()))?;
+    deserialize(0, &bytes_in)
+}
+
+fn atomic_broadcast<C: Channel, T: CanonicalDeserialize + CanonicalSerialize>(
+    net: &C,
+    out: &T,
+) -> Result<Vec<T>, MpcNetError> {
+    let mut bytes_out = serialize(out);
+    let ser_len = bytes_out.len();
+    bytes_out.resize(ser_len + COMMIT_RAND_BYTES, 0);
+    rand::thread_rng().fill_bytes(&mut bytes_out[ser_len..]);
+    let commitment = CommitHash::new().chain(&bytes_out).finalize();
+    // exchange commitments
+    let all_commits = net.broadcast_bytes(&commitment[..])?;
+    // exchange (data || randomness)
+    let all_data = net.broadcast_bytes(&bytes_out)?;
+    let self_id = net.party_id();
+    for i in 0..all_commits.len() {
+        // check other commitment
+        if i != self_id
+            && all_commits[i][..] != CommitHash::new().chain(&all_data[i]).finalize()[..]
+        {
+            return Err(MpcNetError::CommitmentMismatch { party: i });
+        }
+    }
+    all_data
+        .iter()
+        .enumerate()
+        .map(|(i, d)| match d.len().checked_sub(COMMIT_RAND_BYTES) {
+            Some(len) => deserialize(i, &d[..len]),
+            None => Err(MpcNetError::Malformed { party: i }),
+        })
+        .collect()
+}
+
+fn serialize<T: CanonicalSerialize>(t: &T) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    t.serialize(&mut bytes).unwrap();
+    bytes
+}
+
+/// Deserialize a value sent by `party`, which must be all of `bytes`.
+fn deserialize<T: CanonicalDeserialize>(party: usize, mut bytes: &[u8]) -> Result<T, MpcNetError> {
+    match T::deserialize(&mut bytes) {
+        Ok(t) if bytes.is_empty() => Ok(t),
+        _ => Err(MpcNetError::Malformed { party }),
+    }
+}
+
+/// The other party, for the two-party channel.
+fn other_party() -> usize {
+    if net_two::am_first() {
+        1
+    } else {
+        0
+    }
+}
+
 const ALLOW_CHEATING: Cell<bool> = Cell::new(true);
 
 /// Number of randomness bytes to use in the commitment scheme
@@ -101,11 +436,9 @@ const COMMIT_RAND_BYTES: usize = 32;
 type CommitHash = Sha256;
 
 #[inline]
-pub fn exchange<F: CanonicalSerialize + CanonicalDeserialize>(f: &F) -> F {
-    let mut bytes_out = Vec::new();
-    f.serialize(&mut bytes_out).unwrap();
-    let bytes_in = net_two::exchange_bytes(&bytes_out).unwrap();
-    F::deserialize(&bytes_in[..]).unwrap()
+pub fn exchange<F: CanonicalSerialize + CanonicalDeserialize>(f: &F) -> Result<F, MpcNetError> {
+    let bytes_in = net_two::exchange_bytes(&serialize(f))?;
+    deserialize(other_party(), &bytes_in)
 }
 
 #[inline]
@@ -113,24 +446,28 @@ pub fn exchange<F: CanonicalSerialize + CanonicalDeserialize>(f: &F) -> F {
 ///
 /// Ensures that if both parties get a value, each party chose its value independently of the
 /// other.
-pub fn atomic_exchange<F: CanonicalSerialize + CanonicalDeserialize>(f: &F) -> F {
-    let mut bytes_out = Vec::new();
-    f.serialize(&mut bytes_out).unwrap();
+pub fn atomic_exchange<F: CanonicalSerialize + CanonicalDeserialize>(
+    f: &F,
+) -> Result<F, MpcNetError> {
+    let mut bytes_out = serialize(f);
     let ser_len = bytes_out.len();
     bytes_out.resize(ser_len + COMMIT_RAND_BYTES, 0);
     rand::thread_rng().fill_bytes(&mut bytes_out[ser_len..]);
     let commitment = CommitHash::new().chain(&bytes_out).finalize();
     // exchange commitments
-    let other_commitment = net_two::exchange_bytes(&commitment[..]).unwrap();
+    let other_commitment = net_two::exchange_bytes(&commitment[..])?;
     // exchange (data || randomness)
-    let other_bytes = net_two::exchange_bytes(&bytes_out).unwrap();
+    let other_bytes = net_two::exchange_bytes(&bytes_out)?;
     // check other commitment
-    assert_eq!(
-        &other_commitment[..],
-        &CommitHash::new().chain(&other_bytes).finalize()[..]
-    );
+    let party = other_party();
+    if other_commitment[..] != CommitHash::new().chain(&other_bytes).finalize()[..] {
+        return Err(MpcNetError::CommitmentMismatch { party });
+    }
     // parse data
-    F::deserialize(&other_bytes[..ser_len]).unwrap()
+    match other_bytes.len().checked_sub(COMMIT_RAND_BYTES) {
+        Some(len) => deserialize(party, &other_bytes[..len]),
+        None => Err(MpcNetError::Malformed { party }),
+    }
 }
 
 #[inline]
@@ -151,3 +488,56 @@ pub fn without_cheating<O, F: FnOnce() -> O>(f: F) -> O {
     set_cheating_allowed(allowed);
     r
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::share::add::AdditiveFieldShare;
+    use crate::{MpcField, Reveal};
+    use ark_bls12_377::Fr;
+    use mpc_net::{MpcMultiNet, OrAbort};
+
+    #[test]
+    fn malformed_message_names_sender() {
+        let results = MpcMultiNet::simulate(3, || {
+            let session = MpcMultiNet::session(1);
+            let r = session.run(|| {
+                if MpcMultiNet::party_id() == 2 {
+                    MpcMultiNet::broadcast_bytes(&[1, 2, 3]).or_abort();
+                } else {
+                    MpcMultiNet::broadcast(&Fr::from(1u8)).or_abort();
+                }
+            });
+            session.end();
+            r
+        });
+        let malformed = Err(MpcNetError::Malformed { party: 2 });
+        assert_eq!(results, vec![malformed.clone(), malformed, Ok(())]);
+    }
+
+    #[test]
+    fn session_handles_need_no_entered_session() {
+        let results = MpcMultiNet::simulate(3, || {
+            let session = MpcMultiNet::session(1);
+            // A thread of our own, which has no current session.
+            std::thread::spawn(move || {
+                let id = session.party_id() as u64;
+                let sum = session.atomic_broadcast(&id).unwrap().into_iter().sum::<u64>();
+                let prev: u64 = session.pass_to_next(&id).unwrap();
+                let shares = MpcField::<Fr, AdditiveFieldShare<Fr>>::from_add_shared_batch_in(
+                    vec![Fr::from(id)],
+                    &session,
+                );
+                let revealed = shares[0].reveal_in(&session);
+                session.end();
+                (sum, prev, revealed)
+            })
+            .join()
+            .unwrap()
+        });
+        assert_eq!(
+            results,
+            vec![(3, 2, Fr::from(3u64)), (3, 0, Fr::from(3u64)), (3, 1, Fr::from(3u64))]
+        );
+    }
+}
@@ -0,0 +1,287 @@
+//! Secret inputs from a client outside the parties (see [`mpc_net::client`]).
+//!
+//! [`Reveal::king_share_batch`] has the king deal the shares of an input, so the king sees it.
+//! Here the client that holds the input deals the shares itself: [`send_to_parties`] splits the
+//! values and sends each party its shares, and the parties, together, turn what they received
+//! into shares with [`recv_from_client`].
+//!
+//! The other way, [`Reveal::reveal`] shows an output to every party. Instead, the parties can
+//! each send the client their shares of it with [`reveal_to_client`], and the client puts them
+//! together with [`recv_from_parties`].
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use rand::Rng;
+
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+
+use mpc_net::client;
+use mpc_net::noise::{PublicKey, SecretKey};
+use mpc_net::{MpcMultiNet as Net, MpcNet, MpcNetError, MpcSession};
+
+use crate::channel::SessionSerNet;
+use crate::wire::field::MpcField;
+use crate::{FieldShare, Reveal};
+
+/// A sharing whose shares a client outside the parties can deal.
+pub trait ClientShare: Reveal {
+    /// What the client sends a party for one value.
+    type Dealt: CanonicalSerialize + CanonicalDeserialize;
+
+    /// At the client: split each of `values` among `n` parties. Entry `i` of the result is what
+    /// party `i` gets, in the order of `values`.
+    ///
+    /// `threshold` is for sharings where the parties choose how many of them may collude (see
+    /// [`prss::set_threshold`](crate::share::rss::prss::set_threshold)); it must be the one the
+    /// parties use. `None` means the default.
+    fn deal<R: Rng>(
+        values: &[Self::Base],
+        n: usize,
+        threshold: Option<usize>,
+        rng: &mut R,
+    ) -> Vec<Vec<Self::Dealt>>;
+
+    /// At every party, together: our shares of the client's values, from what it sent us.
+    fn from_dealt(dealt: Vec<Self::Dealt>) -> Result<Vec<Self>, MpcNetError>;
+
+    /// What a party sends the client with its shares of an output, besides the shares, for the
+    /// client to combine them: with RSS, the threshold. Never anything secret.
+    type Keys: CanonicalSerialize + CanonicalDeserialize + Send + 'static;
+
+    /// At a party: our [`Keys`](Self::Keys) in this session.
+    fn output_keys() -> Self::Keys;
+
+    /// At the client, standing in for a party: take on that party's keys.
+    fn use_output_keys(keys: Self::Keys);
+}
+
+impl<F: ark_ff::Field, S: FieldShare<F> + ClientShare<Base = F>> ClientShare for MpcField<F, S> {
+    type Dealt = S::Dealt;
+
+    fn deal<R: Rng>(
+        values: &[F],
+        n: usize,
+        threshold: Option<usize>,
+        rng: &mut R,
+    ) -> Vec<Vec<Self::Dealt>> {
+        S::deal(values, n, threshold, rng)
+    }
+
+    fn from_dealt(dealt: Vec<Self::Dealt>) -> Result<Vec<Self>, MpcNetError> {
+        Ok(S::from_dealt(dealt)?
+            .into_iter()
+            .map(MpcField::Shared)
+            .collect())
+    }
+
+    type Keys = S::Keys;
+
+    fn output_keys() -> S::Keys {
+        S::output_keys()
+    }
+
+    fn use_output_keys(keys: S::Keys) {
+        S::use_output_keys(keys)
+    }
+}
+
+/// At the client: share `values` among `parties` (their addresses and public keys, as from
+/// [`client::read_hosts`]), and send each its shares. The connections are secured with Noise, with
+/// `key`, our secret key.
+pub fn send_to_parties<S: ClientShare>(
+    parties: &[(SocketAddr, Option<PublicKey>)],
+    values: &[S::Base],
+    threshold: Option<usize>,
+    key: &SecretKey,
+) -> Result<(), MpcNetError> {
+    send_to_parties_with::<S, _>(parties, &(), values, threshold, key)
+}
+
+/// At the client: as [`send_to_parties`], and send every party `header` ahead of its shares, to
+/// say what to do with them.
+pub fn send_to_parties_with<S: ClientShare, H: CanonicalSerialize>(
+    parties: &[(SocketAddr, Option<PublicKey>)],
+    header: &H,
+    values: &[S::Base],
+    threshold: Option<usize>,
+    key: &SecretKey,
+) -> Result<(), MpcNetError> {
+    let mut header_bytes = Vec::new();
+    header.serialize(&mut header_bytes).unwrap();
+    let messages: Vec<Vec<u8>> =
+        deal_messages::<S, _>(values, parties.len(), threshold, &mut rand::thread_rng())
+            .into_iter()
+            .map(|message| [&header_bytes[..], &message[..]].concat())
+            .collect();
+    client::send_to_parties(parties, &messages, key)
+}
+
+/// The message that [`send_to_parties`] sends each of `n` parties.
+pub fn deal_messages<S: ClientShare, R: Rng>(
+    values: &[S::Base],
+    n: usize,
+    threshold: Option<usize>,
+    rng: &mut R,
+) -> Vec<Vec<u8>> {
+    S::deal(values, n, threshold, rng)
+        .into_iter()
+        .map(|dealt| {
+            let mut bytes = Vec::new();
+            dealt.serialize(&mut bytes).unwrap();
+            bytes
+        })
+        .collect()
+}
+
+/// At every party, together, in `session`: wait for the client at `addr` (our address in the
+/// hosts file), and return our shares of its values. The connection must be secured with Noise,
+/// with `keys`, our secret key and the client's public key.
+pub fn recv_from_client<S: ClientShare>(
+    session: &MpcSession,
+    addr: SocketAddr,
+    keys: (&SecretKey, &PublicKey),
+) -> Result<Vec<S>, MpcNetError> {
+    recv_from_client_with::<S, ()>(session, addr, keys).map(|((), shares)| shares)
+}
+
+/// At every party, together: as [`recv_from_client`], from a client that sent a header with
+/// [`send_to_parties_with`]. The parties check that they all got the same header.
+pub fn recv_from_client_with<S: ClientShare, H: CanonicalDeserialize>(
+    session: &MpcSession,
+    addr: SocketAddr,
+    keys: (&SecretKey, &PublicKey),
+) -> Result<(H, Vec<S>), MpcNetError> {
+    let message = client::recv_from_client(addr, keys);
+    // Even if we got nothing, go on to the check, so the other parties do not wait for us.
+    let received = message.and_then(|bytes| {
+        let mut rest = &bytes[..];
+        let header = H::deserialize(&mut rest).map_err(|e| MpcNetError::Client {
+            reason: format!("sent a header that does not deserialize: {}", e),
+        })?;
+        let header_bytes = bytes[..bytes.len() - rest.len()].to_vec();
+        let dealt = Vec::<S::Dealt>::deserialize(rest).map_err(|e| MpcNetError::Client {
+            reason: format!("sent shares that do not deserialize: {}", e),
+        })?;
+        Ok((header, header_bytes, dealt))
+    });
+    let (header, dealt) = agree_on_request(session, received)?;
+    Ok((header, session.enter(|| S::from_dealt(dealt))?))
+}
+
+/// Check that every party received the same header and the same number of values, so that a
+/// client cannot make the parties run out of step.
+fn agree_on_request<H, T>(
+    session: &MpcSession,
+    received: Result<(H, Vec<u8>, Vec<T>), MpcNetError>,
+) -> Result<(H, Vec<T>), MpcNetError> {
+    let ours: Option<(u64, Vec<u8>)> = received
+        .as_ref()
+        .ok()
+        .map(|(_, header_bytes, dealt)| (dealt.len() as u64, header_bytes.clone()));
+    let all: Vec<Option<(u64, Vec<u8>)>> = session.broadcast(&ours)?;
+    let (header, _, dealt) = received?;
+    if all.iter().any(Option::is_none) {
+        return Err(MpcNetError::Client {
+            reason: "another party did not get its shares".to_owned(),
+        });
+    }
+    if all.iter().any(|theirs| *theirs != ours) {
+        let lens: Vec<u64> = all.iter().flatten().map(|(len, _)| *len).collect();
+        return Err(MpcNetError::Client {
+            reason: format!(
+                "sent the parties different headers or numbers of values: {:?}",
+                lens
+            ),
+        });
+    }
+    Ok((header, dealt))
+}
+
+/// At every party, together: send our shares of `value` to the client, which contacts us at `addr`
+/// (our address in the hosts file), so that only it learns `value`. `S` is the sharing of the
+/// field that `value` is over. The connection must be secured with Noise, with `keys`, our secret
+/// key and the client's public key.
+pub fn reveal_to_client<S: ClientShare, T: Reveal + CanonicalSerialize>(
+    value: &T,
+    addr: SocketAddr,
+    keys: (&SecretKey, &PublicKey),
+) -> Result<(), MpcNetError> {
+    client::send_to_client(addr, keys, &output_message::<S, T>(value))
+}
+
+/// The message that [`reveal_to_client`] sends the client: our keys, then our shares of `value`.
+pub fn output_message<S: ClientShare, T: Reveal + CanonicalSerialize>(value: &T) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    S::output_keys().serialize(&mut bytes).unwrap();
+    value.serialize(&mut bytes).unwrap();
+    bytes
+}
+
+/// At the client: receive each party's shares of a value, as [`reveal_to_client`] sends them, and
+/// put the value together. `S` and `T` must be as the parties have them. The connections are
+/// secured with Noise, with `key`, our secret key.
+pub fn recv_from_parties<S, T>(
+    parties: &[(SocketAddr, Option<PublicKey>)],
+    key: &SecretKey,
+) -> Result<T::Base, MpcNetError>
+where
+    S: ClientShare,
+    T: Reveal + CanonicalDeserialize + Send + 'static,
+    T::Base: Send + 'static,
+{
+    from_output_messages::<S, T>(&client::recv_from_parties(parties, key)?)
+}
+
+/// At the client: put together the value that `messages` (from [`output_message`], one per party,
+/// in party order) share.
+///
+/// We check the shares as the parties would check them if they revealed the value to each
+/// other, by standing in for each of them, as far as the sharing lets us without their secrets
+/// (SPDZ MACs are not checked); an error blames the party that sent bad shares, where the sharing
+/// can tell.
+pub fn from_output_messages<S, T>(messages: &[Vec<u8>]) -> Result<T::Base, MpcNetError>
+where
+    S: ClientShare,
+    T: Reveal + CanonicalDeserialize + Send + 'static,
+    T::Base: Send + 'static,
+{
+    let shares: Vec<Option<(S::Keys, T)>> = messages
+        .iter()
+        .enumerate()
+        .map(|(party, bytes)| {
+            let mut bytes = &bytes[..];
+            match <(S::Keys, T)>::deserialize(&mut bytes) {
+                Ok(shares) if bytes.is_empty() => Ok(Some(shares)),
+                _ => Err(MpcNetError::Malformed { party }),
+            }
+        })
+        .collect::<Result<_, _>>()?;
+    let n = shares.len();
+    let shares = Arc::new(Mutex::new(shares));
+    let mut values = Net::simulate(n, move || {
+        let (keys, value) = shares.lock().unwrap()[Net::party_id()].take().unwrap();
+        S::use_output_keys(keys);
+        value.try_reveal()
+    });
+    // A party that lost its connection is usually not the one at fault.
+    let mut errors: Vec<MpcNetError> =
+        values.iter().filter_map(|v| v.as_ref().err().cloned()).collect();
+    errors.sort_by_key(|e| matches!(e, MpcNetError::Disconnected { .. }));
+    match errors.into_iter().next() {
+        Some(e) => Err(e),
+        None => values.swap_remove(0),
+    }
+}
+
+/// Split each of `values` into additive shares for `n` parties, one vector per party.
+pub(crate) fn add_split<F: ark_ff::Field, R: Rng>(values: &[F], n: usize, rng: &mut R) -> Vec<Vec<F>> {
+    let mut shares: Vec<Vec<F>> = (0..n - 1)
+        .map(|_| values.iter().map(|_| F::rand(rng)).collect())
+        .collect();
+    let last = values
+        .iter()
+        .enumerate()
+        .map(|(i, v)| *v - shares.iter().map(|s| &s[i]).sum::<F>())
+        .collect();
+    shares.push(last);
+    shares
+}
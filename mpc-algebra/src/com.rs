@@ -8,6 +8,7 @@ use mpc_trait::MpcWire;
 
 use crate::{channel, Reveal};
 use mpc_net::two as net_two;
+use mpc_net::OrAbort;
 use crate::wire::field::MpcField;
 use crate::share::field::FieldShare;
 
@@ -58,7 +59,7 @@ impl<Fr: PrimeField, S: FieldShare<Fr>>  ComField for MpcField<Fr, S> {
             tree.push(std::mem::replace(&mut hashes, new));
         }
         let slf = hashes.pop().unwrap();
-        let other = net_two::exchange_bytes(&slf).unwrap();
+        let other = net_two::exchange_bytes(&slf).or_abort();
         if net_two::am_first() {
             (tree, (other, slf))
         } else {
@@ -67,7 +68,7 @@ impl<Fr: PrimeField, S: FieldShare<Fr>>  ComField for MpcField<Fr, S> {
     }
     fn open_at(inputs: &[Self], tree: &Self::Key, mut i: usize) -> (Self, Self::OpeningProof) {
         let self_f = inputs[i].unwrap_as_public();
-        let other_f = channel::exchange(&self_f);
+        let other_f = channel::exchange(&self_f).or_abort();
         let mut siblings = Vec::new();
         for level in 0..tree.len() {
             siblings.push(tree[level][i ^ 1].clone());
@@ -76,7 +77,7 @@ impl<Fr: PrimeField, S: FieldShare<Fr>>  ComField for MpcField<Fr, S> {
         assert_eq!(i / 2, 0);
         let other: Vec<_> = siblings
             .iter()
-            .map(|s| net_two::exchange_bytes(s).unwrap())
+            .map(|s| net_two::exchange_bytes(s).or_abort())
             .collect();
         let p = if net_two::am_first() {
             siblings.into_iter().zip(other.into_iter()).collect()
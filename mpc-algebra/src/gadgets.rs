@@ -0,0 +1,192 @@
+//! Constraint-system gadgets that also work on shared witnesses.
+//!
+//! The `ark-r1cs-std` gadgets compute their witnesses from `into_repr` and `bool`s, which shared
+//! field elements do not have, so `FpVar`'s own `to_bits_le`, comparisons and range checks panic
+//! on shared values. The gadgets here compute their witnesses with MPC instead: use
+//! [`to_bits_le`], [`enforce_in_range`] and [`enforce_less_than`] in their place.
+use ark_ff::prelude::*;
+use ark_r1cs_std::eq::EqGadget;
+use ark_r1cs_std::fields::fp::{AllocatedFp, FpVar};
+use ark_r1cs_std::fields::FieldVar;
+use ark_relations::{
+    lc,
+    r1cs::{LinearCombination, SynthesisError, Variable},
+};
+
+use crate::share::field::FieldShare;
+use crate::wire::field::MpcField;
+use crate::preprocessing::PreprocessedFieldTripleSource;
+
+/// Prime fields whose elements can be split into bits, even if they are secret-shared.
+pub trait BitDecomposition: PrimeField {
+    /// The little-endian bits of `self` as field elements (zero or one), one for each bit of the
+    /// modulus.
+    fn bit_decomposition(&self) -> Vec<Self>;
+}
+
+impl<F: PrimeField, S: FieldShare<F>> BitDecomposition for MpcField<F, S> {
+    fn bit_decomposition(&self) -> Vec<Self> {
+        match self {
+            Self::Public(x) => {
+                let repr = x.into_repr();
+                (0..F::Params::MODULUS_BITS as usize)
+                    .map(|i| Self::Public(F::from(repr.get_bit(i))))
+                    .collect()
+            }
+            Self::Shared(x) => x
+                .bit_decompose(&mut PreprocessedFieldTripleSource::default())
+                .into_iter()
+                .map(Self::Shared)
+                .collect(),
+        }
+    }
+}
+
+/// As `ToBitsGadget::to_non_unique_bits_le`, for a field whose values may be shared.
+///
+/// Returns one variable per bit of the modulus, each constrained to be zero or one, whose
+/// little-endian sum is `v`. As with `to_non_unique_bits_le`, the constraints let the bits
+/// encode `v + p` too; [`to_bits_le`] rules that out.
+pub fn to_non_unique_bits_le<F: BitDecomposition>(
+    v: &FpVar<F>,
+) -> Result<Vec<FpVar<F>>, SynthesisError> {
+    let v = match v {
+        FpVar::Constant(c) => {
+            return Ok(c.bit_decomposition().into_iter().map(FpVar::Constant).collect())
+        }
+        FpVar::Var(v) => v,
+    };
+    let cs = v.cs.clone();
+    let bits = match v.value() {
+        Ok(value) => value.bit_decomposition().into_iter().map(Some).collect(),
+        Err(_) => vec![None; F::Params::MODULUS_BITS as usize],
+    };
+    let mut sum = LinearCombination::zero();
+    let mut coeff = F::one();
+    let mut vars = Vec::with_capacity(bits.len());
+    for bit in bits {
+        let variable = cs.new_witness_variable(|| bit.ok_or(SynthesisError::AssignmentMissing))?;
+        // Constrain: (1 - b) * b = 0
+        cs.enforce_constraint(lc!() + Variable::One - variable, lc!() + variable, lc!())?;
+        sum = sum + (coeff, variable);
+        coeff.double_in_place();
+        vars.push(FpVar::Var(AllocatedFp::new(bit, variable, cs.clone())));
+    }
+    cs.enforce_constraint(lc!(), lc!(), sum - v.variable)?;
+    Ok(vars)
+}
+
+/// As `ToBitsGadget::to_bits_le`, for a field whose values may be shared: one variable per bit of
+/// the modulus, each constrained to be zero or one, that encode `v` and nothing else.
+///
+/// These are the bits of [`to_non_unique_bits_le`], constrained to encode a number below the
+/// modulus. The check multiplies the bits in turn, so on shared values it takes a round for each
+/// one bit of `p - 1`.
+pub fn to_bits_le<F: BitDecomposition>(v: &FpVar<F>) -> Result<Vec<FpVar<F>>, SynthesisError> {
+    let bits = to_non_unique_bits_le(v)?;
+    let mut p_minus_one = F::Params::MODULUS;
+    p_minus_one.sub_noborrow(&F::BigInt::from(1));
+    enforce_at_most(&bits, &p_minus_one)?;
+    Ok(bits)
+}
+
+/// Constrain `v` to be below `2^bits`, which must be less than the modulus.
+///
+/// The non-unique bits suffice: if the top ones are zero, they encode less than the modulus, so
+/// they cannot encode `v + p`.
+pub fn enforce_in_range<F: BitDecomposition>(v: &FpVar<F>, bits: usize) -> Result<(), SynthesisError> {
+    assert!(bits < F::Params::MODULUS_BITS as usize, "2^{} is not below the modulus", bits);
+    for bit in &to_non_unique_bits_le(v)?[bits..] {
+        bit.enforce_equal(&FpVar::zero())?;
+    }
+    Ok(())
+}
+
+/// Constrain `a < b`, for `a` and `b` that are below `2^bits`, which must be less than half the
+/// modulus (constrain them with [`enforce_in_range`] if nothing else does).
+///
+/// Then `b - a - 1` is below `2^bits` exactly when `a < b`; otherwise it wraps around to at least
+/// `p - 2^bits`.
+pub fn enforce_less_than<F: BitDecomposition>(
+    a: &FpVar<F>,
+    b: &FpVar<F>,
+    bits: usize,
+) -> Result<(), SynthesisError> {
+    assert!(bits + 1 < F::Params::MODULUS_BITS as usize, "2^{} is not below half the modulus", bits);
+    enforce_in_range(&(b - a - F::one()), bits)
+}
+
+/// Constrain the little-endian `bits`, each zero or one, to encode at most `max`.
+fn enforce_at_most<F: PrimeField>(bits: &[FpVar<F>], max: &F::BigInt) -> Result<(), SynthesisError> {
+    // One if the bits above the current one are those of `max`.
+    let mut prefix_equal = FpVar::one();
+    for (i, bit) in bits.iter().enumerate().rev() {
+        if max.get_bit(i) {
+            prefix_equal *= bit;
+        } else {
+            // Where `max` has a zero, the bit must be zero too, unless a higher bit is already
+            // below that of `max`.
+            prefix_equal.mul_equals(bit, &FpVar::zero())?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::share::add::AdditiveFieldShare;
+    use ark_bls12_377::Fr;
+    use ark_r1cs_std::alloc::AllocVar;
+    use ark_relations::r1cs::ConstraintSystem;
+
+    type F = MpcField<Fr, AdditiveFieldShare<Fr>>;
+
+    /// Whether `bits`, as a witness, satisfy [`enforce_at_most`] with `max`.
+    fn at_most(bits: &[bool], max: F) -> bool {
+        let cs = ConstraintSystem::<F>::new_ref();
+        let vars: Vec<FpVar<F>> = bits
+            .iter()
+            .map(|b| FpVar::new_witness(cs.clone(), || Ok(F::from(*b))).unwrap())
+            .collect();
+        enforce_at_most(&vars, &max.into_repr()).unwrap();
+        cs.is_satisfied().unwrap()
+    }
+
+    fn bits_of(x: &<F as PrimeField>::BigInt) -> Vec<bool> {
+        (0..<F as PrimeField>::size_in_bits()).map(|i| x.get_bit(i)).collect()
+    }
+
+    #[test]
+    fn bits_must_be_below_the_modulus() {
+        let p_minus_one = -F::one();
+        assert!(at_most(&bits_of(&p_minus_one.into_repr()), p_minus_one));
+        assert!(at_most(&bits_of(&F::from(5u8).into_repr()), p_minus_one));
+        // p + 1, the other encoding of one.
+        let mut p_plus_one = <F as PrimeField>::Params::MODULUS;
+        p_plus_one.add_nocarry(&From::from(1));
+        assert!(!at_most(&bits_of(&p_plus_one), p_minus_one));
+        assert!(!at_most(&bits_of(&<F as PrimeField>::Params::MODULUS), p_minus_one));
+    }
+
+    #[test]
+    fn ranges_and_comparisons() {
+        let satisfied = |f: &dyn Fn(&[FpVar<F>])| {
+            let cs = ConstraintSystem::<F>::new_ref();
+            let vars: Vec<FpVar<F>> = [3u8, 8, 200]
+                .iter()
+                .map(|x| FpVar::new_witness(cs.clone(), || Ok(F::from(*x))).unwrap())
+                .collect();
+            f(&vars);
+            cs.is_satisfied().unwrap()
+        };
+        assert!(satisfied(&|v| enforce_in_range(&v[1], 4).unwrap()));
+        assert!(!satisfied(&|v| enforce_in_range(&v[1], 3).unwrap()));
+        assert!(satisfied(&|v| enforce_less_than(&v[0], &v[2], 8).unwrap()));
+        assert!(!satisfied(&|v| enforce_less_than(&v[2], &v[0], 8).unwrap()));
+        assert!(!satisfied(&|v| enforce_less_than(&v[1], &v[1], 8).unwrap()));
+        assert!(satisfied(&|v| {
+            to_bits_le(&v[2]).unwrap();
+        }));
+    }
+}
@@ -4,7 +4,10 @@
 pub mod reveal;
 pub use reveal::*;
 pub mod channel;
+pub mod client;
 pub mod com;
+pub mod gadgets;
+pub mod preprocessing;
 pub mod group;
 pub mod share;
 pub use share::*;
@@ -47,19 +50,19 @@ pub mod malicious_majority {
 
 pub mod honest_majority {
     use super::{
+        share::gsz20::*,
         share::msm::NaiveMsm,
-        share::gsz20::{field::GszFieldShare, group::GszGroupShare},
-        wire::{field, group},
+        wire::{field, group, pairing},
     };
     pub type MpcField<F> = field::MpcField<F, GszFieldShare<F>>;
     pub type MpcGroup<G> = group::MpcGroup<G, GszGroupShare<G, NaiveMsm<G>>>;
-    // pub type MpcG1Affine<E> = pairing::MpcG1Affine<E, GszPairingShare<E>>;
-    // pub type MpcG2Affine<E> = pairing::MpcG2Affine<E, GszPairingShare<E>>;
-    // pub type MpcG1Projective<E> = pairing::MpcG1Projective<E, GszPairingShare<E>>;
-    // pub type MpcG2Projective<E> = pairing::MpcG2Projective<E, GszPairingShare<E>>;
-    // pub type MpcG1Prep<E> = pairing::MpcG1Prep<E, GszPairingShare<E>>;
-    // pub type MpcG2Prep<E> = pairing::MpcG2Prep<E, GszPairingShare<E>>;
-    // pub type MpcPairingEngine<E> = pairing::MpcPairingEngine<E, GszPairingShare<E>>;
+    pub type MpcG1Affine<E> = pairing::MpcG1Affine<E, GszPairingShare<E>>;
+    pub type MpcG2Affine<E> = pairing::MpcG2Affine<E, GszPairingShare<E>>;
+    pub type MpcG1Projective<E> = pairing::MpcG1Projective<E, GszPairingShare<E>>;
+    pub type MpcG2Projective<E> = pairing::MpcG2Projective<E, GszPairingShare<E>>;
+    pub type MpcG1Prep<E> = pairing::MpcG1Prep<E, GszPairingShare<E>>;
+    pub type MpcG2Prep<E> = pairing::MpcG2Prep<E, GszPairingShare<E>>;
+    pub type MpcPairingEngine<E> = pairing::MpcPairingEngine<E, GszPairingShare<E>>;
 }
 
 pub mod honest_majority_rss3 {
@@ -77,4 +80,21 @@ pub mod honest_majority_rss3 {
     pub type MpcG1Prep<E> = pairing::MpcG1Prep<E, RSS3PairingShare<E>>;
     pub type MpcG2Prep<E> = pairing::MpcG2Prep<E, RSS3PairingShare<E>>;
     pub type MpcPairingEngine<E> = pairing::MpcPairingEngine<E, RSS3PairingShare<E>>;
+}
+
+pub mod honest_majority_rss {
+    use super::{
+        share::rss::*,
+        share::msm::NaiveMsm,
+        wire::{field, group, pairing},
+    };
+    pub type MpcField<F> = field::MpcField<F, RssFieldShare<F>>;
+    pub type MpcGroup<G> = group::MpcGroup<G, RssGroupShare<G, NaiveMsm<G>>>;
+    pub type MpcG1Affine<E> = pairing::MpcG1Affine<E, RssPairingShare<E>>;
+    pub type MpcG2Affine<E> = pairing::MpcG2Affine<E, RssPairingShare<E>>;
+    pub type MpcG1Projective<E> = pairing::MpcG1Projective<E, RssPairingShare<E>>;
+    pub type MpcG2Projective<E> = pairing::MpcG2Projective<E, RssPairingShare<E>>;
+    pub type MpcG1Prep<E> = pairing::MpcG1Prep<E, RssPairingShare<E>>;
+    pub type MpcG2Prep<E> = pairing::MpcG2Prep<E, RssPairingShare<E>>;
+    pub type MpcPairingEngine<E> = pairing::MpcPairingEngine<E, RssPairingShare<E>>;
 }
\ No newline at end of file
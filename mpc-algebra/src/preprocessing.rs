@@ -0,0 +1,597 @@
+//! Offline-phase preprocessing.
+//!
+//! The offline phase is run by a dealer that takes no part in the online phase. It samples Beaver
+//! triples, inverse pairs, random values and input masks and, for authenticated shares, every
+//! party's share of the MAC key, and deals each party its own store
+//! ([`FieldPreprocessing::deal`]). Each party writes its store to disk, without any network.
+//!
+//! An online run loads its store and [`install`]s it. From then on, field multiplications and
+//! inversions draw their correlated randomness from the store through
+//! [`PreprocessedFieldTripleSource`], random shares are drawn with [`take_rand`], and parties
+//! authenticate their inputs with [`take_input_masks`].
+//!
+//! Without a store, shares that multiply without triples (see [`FieldShare::USES_TRIPLES`]) make
+//! their inverse pairs online. Anything else fails with [`PreprocessingError::NotInstalled`],
+//! unless the session opted into fixed, public values with [`use_dummy`].
+//!
+//! The dealer learns every preprocessed value and the MAC key, so it must not collude with any
+//! party.
+use derivative::Derivative;
+use log::debug;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use ark_ff::prelude::*;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, SerializationError};
+use ark_std::{end_timer, start_timer};
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+
+use crate::share::field::FieldShare;
+use crate::share::{BeaverSource, PanicFieldTripleSource};
+use crate::wire::field::DummyFieldTripleSource;
+use mpc_net::{MpcMultiNet as Net, MpcNet};
+
+/// Correlated randomness that was not there.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PreprocessingError {
+    /// No store is installed for `share`, and the session does not use dummy preprocessing.
+    NotInstalled { share: &'static str },
+    /// The store had only `remaining` of the `needed` values of `kind`.
+    Exhausted {
+        kind: &'static str,
+        needed: usize,
+        remaining: usize,
+    },
+    /// The store was dealt to `party` of `n_parties`, not to us.
+    WrongParty { party: usize, n_parties: usize },
+}
+
+impl fmt::Display for PreprocessingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PreprocessingError::NotInstalled { share } => write!(
+                f,
+                "no preprocessing is installed for {}: install a dealt store, or opt into insecure dummy preprocessing",
+                share
+            ),
+            PreprocessingError::Exhausted {
+                kind,
+                needed,
+                remaining,
+            } => write!(
+                f,
+                "preprocessing exhausted: needed {} {}, but only {} remain",
+                needed, kind, remaining
+            ),
+            PreprocessingError::WrongParty { party, n_parties } => write!(
+                f,
+                "the preprocessing was dealt to party {} of {}, but we are party {} of {}",
+                party,
+                n_parties,
+                Net::party_id(),
+                Net::n_parties()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PreprocessingError {}
+
+/// Shares that a dealer outside the computation can make by itself.
+pub trait DealtFieldShare<F: Field>: FieldShare<F> {
+    /// Sample every party's share of the key that shares are authenticated under, if there is
+    /// one.
+    fn deal_mac_key_shares<R: Rng>(n_parties: usize, rng: &mut R) -> Option<Vec<F>>;
+
+    /// Share each of `values` among `n_parties` parties, authenticated under the key shares
+    /// `keys`: one vector of shares per party.
+    fn deal_shares<R: Rng>(
+        values: &[F],
+        n_parties: usize,
+        keys: Option<&[F]>,
+        rng: &mut R,
+    ) -> Vec<Vec<Self>>;
+}
+
+/// The correlated randomness held by one party.
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct FieldPreprocessing<F: Field, S: FieldShare<F>> {
+    /// The party this store was dealt to, and how many parties there were.
+    party: usize,
+    n_parties: usize,
+    /// Beaver triples `(a, b, ab)`, stored column-wise.
+    triple_a: Vec<S>,
+    triple_b: Vec<S>,
+    triple_c: Vec<S>,
+    /// Inverse pairs `(x, 1/x)`, stored column-wise.
+    inv_x: Vec<S>,
+    inv_x_inv: Vec<S>,
+    /// Uniformly random values.
+    rands: Vec<S>,
+    /// Input masks: for each party, shares of random values that only that party knows.
+    input_masks: Vec<Vec<S>>,
+    /// The values of our own input masks.
+    own_masks: Vec<F>,
+    /// This party's share of the key the values are authenticated under, if there is one.
+    mac_key_share: Option<F>,
+    _field: PhantomData<F>,
+}
+
+/// How much correlated randomness a store holds (or a computation used).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PreprocessingCounts {
+    pub triples: usize,
+    pub inv_pairs: usize,
+    pub rands: usize,
+    /// Input masks, for each party.
+    pub inputs: usize,
+}
+
+impl<F: Field, S: FieldShare<F>> FieldPreprocessing<F, S> {
+    /// Run the offline phase as the dealer: one store for each of `n_parties` parties.
+    pub fn deal<R: Rng>(counts: PreprocessingCounts, n_parties: usize, rng: &mut R) -> Vec<Self>
+    where
+        S: DealtFieldShare<F>,
+    {
+        let timer = start_timer!(|| "offline phase");
+        let a: Vec<F> = (0..counts.triples).map(|_| F::rand(rng)).collect();
+        let b: Vec<F> = (0..counts.triples).map(|_| F::rand(rng)).collect();
+        let c: Vec<F> = a.iter().zip(&b).map(|(a, b)| *a * b).collect();
+        let x: Vec<F> = (0..counts.inv_pairs)
+            .map(|_| loop {
+                let x = F::rand(rng);
+                if !x.is_zero() {
+                    break x;
+                }
+            })
+            .collect();
+        let x_inv: Vec<F> = x.iter().map(|x| x.inverse().unwrap()).collect();
+        let mut values = Vec::new();
+        values.extend(a);
+        values.extend(b);
+        values.extend(c);
+        values.extend(x);
+        values.extend(x_inv);
+        values.extend((0..counts.rands).map(|_| F::rand(rng)));
+        let masks: Vec<Vec<F>> = (0..n_parties)
+            .map(|_| (0..counts.inputs).map(|_| F::rand(rng)).collect())
+            .collect();
+        values.extend(masks.iter().flatten());
+        let keys = S::deal_mac_key_shares(n_parties, rng);
+        let stores = S::deal_shares(&values, n_parties, keys.as_deref(), rng)
+            .into_iter()
+            .enumerate()
+            .map(|(party, shares)| {
+                let mut shares = shares.into_iter();
+                let mut take = |n: usize| -> Vec<S> { (&mut shares).take(n).collect() };
+                Self {
+                    party,
+                    n_parties,
+                    triple_a: take(counts.triples),
+                    triple_b: take(counts.triples),
+                    triple_c: take(counts.triples),
+                    inv_x: take(counts.inv_pairs),
+                    inv_x_inv: take(counts.inv_pairs),
+                    rands: take(counts.rands),
+                    input_masks: (0..n_parties).map(|_| take(counts.inputs)).collect(),
+                    own_masks: masks[party].clone(),
+                    mac_key_share: keys.as_ref().map(|keys| keys[party]),
+                    _field: PhantomData::default(),
+                }
+            })
+            .collect();
+        end_timer!(timer);
+        stores
+    }
+
+    pub fn counts(&self) -> PreprocessingCounts {
+        PreprocessingCounts {
+            triples: self.triple_c.len(),
+            inv_pairs: self.inv_x.len(),
+            rands: self.rands.len(),
+            inputs: self.own_masks.len(),
+        }
+    }
+
+    fn map<T: FieldShare<F>>(self, f: impl Fn(S) -> T) -> FieldPreprocessing<F, T> {
+        let map = |xs: Vec<S>| xs.into_iter().map(&f).collect();
+        FieldPreprocessing {
+            party: self.party,
+            n_parties: self.n_parties,
+            triple_a: map(self.triple_a),
+            triple_b: map(self.triple_b),
+            triple_c: map(self.triple_c),
+            inv_x: map(self.inv_x),
+            inv_x_inv: map(self.inv_x_inv),
+            rands: map(self.rands),
+            input_masks: self.input_masks.into_iter().map(map).collect(),
+            own_masks: self.own_masks,
+            mac_key_share: self.mac_key_share,
+            _field: PhantomData::default(),
+        }
+    }
+
+    /// The path of `party`'s store in directory `dir`.
+    pub fn store_path(dir: &Path, party: usize) -> PathBuf {
+        dir.join(format!("preprocessing.{}", party))
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut w = BufWriter::new(File::create(path)?);
+        self.serialize(&mut w)
+            .map_err(crate::share::encoding::ser_err_to_io)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, SerializationError> {
+        Self::deserialize(BufReader::new(File::open(path)?))
+    }
+
+    fn take_triples(&mut self, n: usize) -> Result<(Vec<S>, Vec<S>, Vec<S>), PreprocessingError> {
+        let at = self
+            .triple_c
+            .len()
+            .checked_sub(n)
+            .ok_or(PreprocessingError::Exhausted {
+                kind: "triples",
+                needed: n,
+                remaining: self.triple_c.len(),
+            })?;
+        Ok((
+            self.triple_a.split_off(at),
+            self.triple_b.split_off(at),
+            self.triple_c.split_off(at),
+        ))
+    }
+
+    fn take_inv_pairs(&mut self, n: usize) -> Result<(Vec<S>, Vec<S>), PreprocessingError> {
+        let at = self
+            .inv_x
+            .len()
+            .checked_sub(n)
+            .ok_or(PreprocessingError::Exhausted {
+                kind: "inverse pairs",
+                needed: n,
+                remaining: self.inv_x.len(),
+            })?;
+        Ok((self.inv_x.split_off(at), self.inv_x_inv.split_off(at)))
+    }
+
+    fn take_input_masks(
+        &mut self,
+        owner: usize,
+        n: usize,
+    ) -> Result<(Vec<S>, Option<Vec<F>>), PreprocessingError> {
+        let masks = &mut self.input_masks[owner];
+        let at = masks.len().checked_sub(n).ok_or(PreprocessingError::Exhausted {
+            kind: "input masks",
+            needed: n,
+            remaining: masks.len(),
+        })?;
+        let values = if owner == self.party {
+            Some(self.own_masks.split_off(at))
+        } else {
+            None
+        };
+        Ok((masks.split_off(at), values))
+    }
+
+    fn take_rand(&mut self) -> Result<S, PreprocessingError> {
+        self.rands.pop().ok_or(PreprocessingError::Exhausted {
+            kind: "random shares",
+            needed: 1,
+            remaining: 0,
+        })
+    }
+}
+
+struct Installed<F: Field, S: FieldShare<F>> {
+    store: FieldPreprocessing<F, S>,
+    used: PreprocessingCounts,
+}
+
+/// The store installed in the current session for shares of type `S`, if any.
+fn with_installed<F: Field, S: FieldShare<F>, R>(
+    f: impl FnOnce(Option<&mut Installed<F, S>>) -> R,
+) -> R {
+    Net::with_state(|| None::<Installed<F, S>>, |i| f(i.as_mut()))
+}
+
+/// Make `store` the source of correlated randomness for shares of type `S`, in the current
+/// session. Fails if the store was dealt to another party, or for another number of parties.
+///
+/// If the store's values are authenticated, this also adopts the key share they were
+/// authenticated under, so it must be installed before any other shares are created.
+pub fn install<F: Field, S: FieldShare<F>>(
+    store: FieldPreprocessing<F, S>,
+) -> Result<(), PreprocessingError> {
+    if store.party != Net::party_id() || store.n_parties != Net::n_parties() {
+        return Err(PreprocessingError::WrongParty {
+            party: store.party,
+            n_parties: store.n_parties,
+        });
+    }
+    debug!("Installing preprocessing: {:?}", store.counts());
+    if let Some(share) = store.mac_key_share {
+        S::set_mac_key_share(share);
+    }
+    let installed = Installed {
+        store,
+        used: PreprocessingCounts::default(),
+    };
+    Net::with_state(|| None, |i| *i = Some(installed));
+    Ok(())
+}
+
+/// Remove the store for shares of type `S`, returning how much of it was used.
+pub fn uninstall<F: Field, S: FieldShare<F>>() -> Option<PreprocessingCounts> {
+    Net::take_state::<Option<Installed<F, S>>>()
+        .flatten()
+        .map(|i| i.used)
+}
+
+/// Move the store installed for shares of type `S`, if any, to shares of type `T`, converting
+/// each share with `f`. What was used of it stays counted.
+pub fn reinstall<F: Field, S: FieldShare<F>, T: FieldShare<F>>(f: impl Fn(S) -> T) {
+    if let Some(Installed { store, used }) = Net::take_state::<Option<Installed<F, S>>>().flatten() {
+        let installed = Installed {
+            store: store.map(f),
+            used,
+        };
+        Net::with_state(|| None, |i| *i = Some(installed));
+    }
+}
+
+pub fn is_installed<F: Field, S: FieldShare<F>>() -> bool {
+    with_installed::<F, S, _>(|i| i.is_some())
+}
+
+/// How much of the store for shares of type `S` has been used.
+pub fn used<F: Field, S: FieldShare<F>>() -> Option<PreprocessingCounts> {
+    with_installed::<F, S, _>(|i| i.map(|i| i.used))
+}
+
+/// Take a random share from the installed store, or `None` if there is no store.
+pub fn take_rand<F: Field, S: FieldShare<F>>() -> Result<Option<S>, PreprocessingError> {
+    with_installed::<F, S, _>(|i| {
+        i.map(|i| {
+            let r = i.store.take_rand()?;
+            i.used.rands += 1;
+            Ok(r)
+        })
+        .transpose()
+    })
+}
+
+/// Take a random share from the installed store or, with dummy preprocessing, make a public one.
+pub fn take_rand_or_dummy<F: Field, S: FieldShare<F>>() -> Result<S, PreprocessingError> {
+    match take_rand::<F, S>()? {
+        Some(r) => Ok(r),
+        None => dummy_values::<F>(1)
+            .map(|mut r| S::from_public(r.pop().unwrap()))
+            .ok_or_else(not_installed::<F, S>),
+    }
+}
+
+/// Take `n` of party `owner`'s input masks from the installed store: our shares of them, and at
+/// `owner`, their values. With dummy preprocessing and no store, the masks are public.
+///
+/// Every party must take the same masks at the same point.
+pub fn take_input_masks<F: Field, S: FieldShare<F>>(
+    owner: usize,
+    n: usize,
+) -> Result<(Vec<S>, Option<Vec<F>>), PreprocessingError> {
+    let taken = with_installed::<F, S, _>(|i| {
+        i.map(|i| {
+            let masks = i.store.take_input_masks(owner, n)?;
+            i.used.inputs += n;
+            Ok(masks)
+        })
+    });
+    match taken {
+        Some(masks) => masks,
+        None => {
+            let values = dummy_values::<F>(n).ok_or_else(not_installed::<F, S>)?;
+            let shares = values.iter().map(|r| S::from_public(*r)).collect();
+            Ok((shares, Some(values).filter(|_| owner == Net::party_id())))
+        }
+    }
+}
+
+/// With dummy preprocessing, the public randomness behind it: the same at every party.
+struct Dummy(Option<StdRng>);
+
+/// In the current session, draw correlated randomness for shares without a store from
+/// [`DummyFieldTripleSource`] and a public seed, instead of failing.
+///
+/// Its values are public, so this is insecure: for tests and benchmarks only.
+pub fn use_dummy() {
+    Net::with_state(|| Dummy(None), |d| d.0 = Some(StdRng::seed_from_u64(0)));
+}
+
+pub fn uses_dummy() -> bool {
+    Net::with_state(|| Dummy(None), |d| d.0.is_some())
+}
+
+/// `n` public random values, with dummy preprocessing.
+fn dummy_values<F: Field>(n: usize) -> Option<Vec<F>> {
+    Net::with_state(
+        || Dummy(None),
+        |d| d.0.as_mut().map(|rng| (0..n).map(|_| F::rand(rng)).collect()),
+    )
+}
+
+fn not_installed<F: Field, S: FieldShare<F>>() -> PreprocessingError {
+    PreprocessingError::NotInstalled {
+        share: std::any::type_name::<S>(),
+    }
+}
+
+/// Draws triples and inverse pairs from the installed store for `S`.
+///
+/// If no store is installed, see the [module docs](self).
+#[derive(Derivative)]
+#[derivative(Default(bound = ""), Clone(bound = ""), Copy(bound = ""))]
+pub struct PreprocessedFieldTripleSource<T, S> {
+    _scalar: PhantomData<T>,
+    _share: PhantomData<S>,
+}
+
+impl<T: Field, S: FieldShare<T>> PreprocessedFieldTripleSource<T, S> {
+    pub fn try_triples(&mut self, n: usize) -> Result<(Vec<S>, Vec<S>, Vec<S>), PreprocessingError> {
+        match with_installed::<T, S, _>(|i| {
+            i.map(|i| {
+                let triples = i.store.take_triples(n)?;
+                i.used.triples += n;
+                Ok(triples)
+            })
+        }) {
+            Some(triples) => triples,
+            None if uses_dummy() => Ok(DummyFieldTripleSource::<T, S>::default().triples(n)),
+            None => Err(not_installed::<T, S>()),
+        }
+    }
+
+    pub fn try_inv_pairs(&mut self, n: usize) -> Result<(Vec<S>, Vec<S>), PreprocessingError> {
+        match with_installed::<T, S, _>(|i| {
+            i.map(|i| {
+                let pairs = i.store.take_inv_pairs(n)?;
+                i.used.inv_pairs += n;
+                Ok(pairs)
+            })
+        }) {
+            Some(pairs) => pairs,
+            None if !S::USES_TRIPLES => Ok(online_inv_pairs::<T, S>(n)),
+            None if uses_dummy() => Ok(DummyFieldTripleSource::<T, S>::default().inv_pairs(n)),
+            None => Err(not_installed::<T, S>()),
+        }
+    }
+}
+
+/// `n` inverse pairs, made online by shares that multiply without triples: for random `r` and
+/// `s`, open `rs`, and then `1/r = s/(rs)`.
+fn online_inv_pairs<F: Field, S: FieldShare<F>>(n: usize) -> (Vec<S>, Vec<S>) {
+    let rng = &mut rand::thread_rng();
+    let r: Vec<S> = (0..n).map(|_| S::rand(rng)).collect();
+    let s: Vec<S> = (0..n).map(|_| S::rand(rng)).collect();
+    let rs = S::batch_open(S::batch_mul(
+        r.clone(),
+        s.clone(),
+        &mut PanicFieldTripleSource::default(),
+    ));
+    let r_inv = s
+        .into_iter()
+        .zip(rs)
+        .map(|(mut s, rs)| {
+            s.scale(&rs.inverse().expect("a random share was zero"));
+            s
+        })
+        .collect();
+    (r, r_inv)
+}
+
+impl<T: Field, S: FieldShare<T>> BeaverSource<S, S, S> for PreprocessedFieldTripleSource<T, S> {
+    #[inline]
+    fn triple(&mut self) -> (S, S, S) {
+        let (mut a, mut b, mut c) = self.triples(1);
+        (a.pop().unwrap(), b.pop().unwrap(), c.pop().unwrap())
+    }
+    fn triples(&mut self, n: usize) -> (Vec<S>, Vec<S>, Vec<S>) {
+        self.try_triples(n).unwrap_or_else(|e| panic!("{}", e))
+    }
+    #[inline]
+    fn inv_pair(&mut self) -> (S, S) {
+        let (mut x, mut x_inv) = self.inv_pairs(1);
+        (x.pop().unwrap(), x_inv.pop().unwrap())
+    }
+    fn inv_pairs(&mut self, n: usize) -> (Vec<S>, Vec<S>) {
+        self.try_inv_pairs(n).unwrap_or_else(|e| panic!("{}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::share::spdz::SpdzFieldShare;
+    use crate::{MpcField, Reveal};
+    use ark_bls12_377::Fr;
+    use ark_std::test_rng;
+
+    type S = SpdzFieldShare<Fr>;
+    type MpcFr = MpcField<Fr, S>;
+
+    #[test]
+    fn spdz_inputs_are_authenticated_with_dealt_masks() {
+        let counts = PreprocessingCounts {
+            triples: 1,
+            inv_pairs: 0,
+            rands: 0,
+            inputs: 3,
+        };
+        let stores = FieldPreprocessing::<Fr, S>::deal(counts, 3, &mut test_rng());
+        Net::simulate(3, move || {
+            MpcFr::init_protocol();
+            install(stores[Net::party_id()].clone()).unwrap();
+            let rng = &mut test_rng();
+            let xs: Vec<Fr> = (0..2).map(|_| Fr::rand(rng)).collect();
+            let kings = if Net::am_king() { xs.clone() } else { Vec::new() };
+            let shared = MpcFr::king_share_batch(kings, rng);
+            let ours = Fr::from(Net::party_id() as u64 + 1);
+            let summed = MpcFr::from_add_shared_batch(vec![ours]);
+            let product = shared[0] * summed[0];
+            let revealed = (product + shared[1]).reveal_to(1);
+
+            let expected = xs[0] * Fr::from(6u64) + xs[1];
+            assert_eq!(revealed, Some(expected).filter(|_| Net::party_id() == 1));
+            assert_eq!(summed[0].reveal(), Fr::from(6u64));
+            // Two masks from the king, one from every party, and one for the reveal to party 1.
+            assert_eq!(used::<Fr, S>().unwrap().inputs, 6);
+            uninstall::<Fr, S>();
+            MpcFr::deinit_protocol();
+        });
+    }
+
+    #[test]
+    fn fails_without_a_store_or_when_exhausted() {
+        let counts = PreprocessingCounts {
+            triples: 1,
+            inv_pairs: 0,
+            rands: 0,
+            inputs: 0,
+        };
+        let stores = FieldPreprocessing::<Fr, S>::deal(counts, 2, &mut test_rng());
+        Net::simulate(2, move || {
+            MpcFr::init_protocol();
+            let mut source = PreprocessedFieldTripleSource::<Fr, S>::default();
+            assert!(matches!(
+                source.try_triples(1),
+                Err(PreprocessingError::NotInstalled { .. })
+            ));
+            let (ours, theirs) = (Net::party_id(), 1 - Net::party_id());
+            assert_eq!(
+                install(stores[theirs].clone()),
+                Err(PreprocessingError::WrongParty {
+                    party: theirs,
+                    n_parties: 2
+                })
+            );
+            install(stores[ours].clone()).unwrap();
+            assert_eq!(
+                source.try_triples(2).map(|_| ()),
+                Err(PreprocessingError::Exhausted {
+                    kind: "triples",
+                    needed: 2,
+                    remaining: 1
+                })
+            );
+            let (a, b, c) = source.try_triples(1).unwrap();
+            assert_eq!(S::batch_open(a)[0] * S::batch_open(b)[0], S::batch_open(c)[0]);
+            uninstall::<Fr, S>();
+            MpcFr::deinit_protocol();
+        });
+    }
+}
@@ -1,5 +1,7 @@
 #![macro_use]
 use ark_std::{collections::BTreeMap, marker::PhantomData, rc::Rc};
+use mpc_net::{MpcMultiNet as Net, MpcNet, MpcSession, OrAbort};
+pub use mpc_net::MpcNetError;
 use rand::Rng;
 
 /// A type should implement [Reveal] if it represents the MPC abstraction of some base type.
@@ -15,10 +17,42 @@ use rand::Rng;
 pub trait Reveal: Sized {
     type Base;
 
-    /// Reveal shared data, yielding plain data.
-    fn reveal(self) -> Self::Base;
+    /// Reveal shared data, yielding plain data, or the error if the network failed or a party
+    /// cheated.
+    fn try_reveal(self) -> Result<Self::Base, MpcNetError>;
+    /// Reveal shared data, yielding plain data. Aborts on error; see [`OrAbort`].
+    fn reveal(self) -> Self::Base {
+        self.try_reveal().or_abort()
+    }
+    /// Like [`try_reveal`](Self::try_reveal), but in `session`, whether or not it is the
+    /// calling thread's current session.
+    fn try_reveal_in(self, session: &MpcSession) -> Result<Self::Base, MpcNetError> {
+        session.enter(|| self.try_reveal())
+    }
+    /// Like [`reveal`](Self::reveal), but in `session`. Aborts on error; see [`OrAbort`].
+    fn reveal_in(self, session: &MpcSession) -> Self::Base {
+        self.try_reveal_in(session).or_abort()
+    }
+    /// Reveal shared data to party `to` only: it gets the plain data, and the other parties get
+    /// `None`. Every party must call this, as for [`reveal`](Self::reveal).
+    ///
+    /// The others send `to` their shares, and whatever it needs to check them, but learn nothing
+    /// themselves. With SPDZ, every party opens the data masked by one of `to`'s input masks; see
+    /// [`SpdzFieldShare`](crate::share::spdz::SpdzFieldShare). To reveal to a client outside the
+    /// parties, see [`client::reveal_to_client`](crate::client::reveal_to_client).
+    fn reveal_to(self, _to: usize) -> Option<Self::Base> {
+        unimplemented!("No reveal_to for {}", std::any::type_name::<Self>())
+    }
     /// Construct a share of the sum of the `b` over all machines in the protocol.
     fn from_add_shared(b: Self::Base) -> Self;
+    /// Construct shares of the sums of the `bs` over all machines in the protocol, together.
+    fn from_add_shared_batch(bs: Vec<Self::Base>) -> Vec<Self> {
+        bs.into_iter().map(Self::from_add_shared).collect()
+    }
+    /// Like [`from_add_shared_batch`](Self::from_add_shared_batch), but in `session`.
+    fn from_add_shared_batch_in(bs: Vec<Self::Base>, session: &MpcSession) -> Vec<Self> {
+        session.enter(|| Self::from_add_shared_batch(bs))
+    }
     /// Lift public data (same in all machines) into shared data.
     fn from_public(b: Self::Base) -> Self;
     /// If this share type has some underlying value of the base type, grabs it.
@@ -55,8 +89,12 @@ pub trait Reveal: Sized {
 impl Reveal for usize {
     type Base = usize;
 
-    fn reveal(self) -> Self::Base {
-        self
+    fn try_reveal(self) -> Result<Self::Base, MpcNetError> {
+        Ok(self)
+    }
+
+    fn reveal_to(self, to: usize) -> Option<Self::Base> {
+        only_to(to, self)
     }
 
     fn from_add_shared(b: Self::Base) -> Self {
@@ -79,8 +117,12 @@ impl Reveal for usize {
 impl<T: Reveal> Reveal for PhantomData<T> {
     type Base = PhantomData<T::Base>;
 
-    fn reveal(self) -> Self::Base {
-        PhantomData::default()
+    fn try_reveal(self) -> Result<Self::Base, MpcNetError> {
+        Ok(PhantomData::default())
+    }
+
+    fn reveal_to(self, to: usize) -> Option<Self::Base> {
+        only_to(to, PhantomData::default())
     }
 
     fn from_add_shared(_b: Self::Base) -> Self {
@@ -108,8 +150,12 @@ impl<T: Reveal> Reveal for PhantomData<T> {
 
 impl<T: Reveal> Reveal for Vec<T> {
     type Base = Vec<T::Base>;
-    fn reveal(self) -> Self::Base {
-        self.into_iter().map(|x| x.reveal()).collect()
+    fn try_reveal(self) -> Result<Self::Base, MpcNetError> {
+        self.into_iter().map(|x| x.try_reveal()).collect()
+    }
+    fn reveal_to(self, to: usize) -> Option<Self::Base> {
+        let revealed: Vec<Option<T::Base>> = self.into_iter().map(|x| x.reveal_to(to)).collect();
+        only_to(to, ()).and(revealed.into_iter().collect())
     }
     fn from_public(other: Self::Base) -> Self {
         other
@@ -118,10 +164,7 @@ impl<T: Reveal> Reveal for Vec<T> {
             .collect()
     }
     fn from_add_shared(other: Self::Base) -> Self {
-        other
-            .into_iter()
-            .map(|x| <T as Reveal>::from_add_shared(x))
-            .collect()
+        T::from_add_shared_batch(other)
     }
     fn unwrap_as_public(self) -> Self::Base {
         self
@@ -147,8 +190,13 @@ where
     K::Base: Ord,
 {
     type Base = BTreeMap<K::Base, V::Base>;
-    fn reveal(self) -> Self::Base {
-        self.into_iter().map(|x| x.reveal()).collect()
+    fn try_reveal(self) -> Result<Self::Base, MpcNetError> {
+        self.into_iter().map(|x| x.try_reveal()).collect()
+    }
+    fn reveal_to(self, to: usize) -> Option<Self::Base> {
+        let revealed: Vec<Option<(K::Base, V::Base)>> =
+            self.into_iter().map(|x| x.reveal_to(to)).collect();
+        only_to(to, ()).and(revealed.into_iter().collect())
     }
     fn from_public(other: Self::Base) -> Self {
         other.into_iter().map(|x| Reveal::from_public(x)).collect()
@@ -179,8 +227,14 @@ where
 
 impl<T: Reveal> Reveal for Option<T> {
     type Base = Option<T::Base>;
-    fn reveal(self) -> Self::Base {
-        self.map(|x| x.reveal())
+    fn try_reveal(self) -> Result<Self::Base, MpcNetError> {
+        self.map(|x| x.try_reveal()).transpose()
+    }
+    fn reveal_to(self, to: usize) -> Option<Self::Base> {
+        match self {
+            Some(x) => x.reveal_to(to).map(Some),
+            None => only_to(to, None),
+        }
     }
     fn from_public(other: Self::Base) -> Self {
         other.map(|x| <T as Reveal>::from_public(x))
@@ -205,8 +259,11 @@ where
     T::Base: Clone,
 {
     type Base = Rc<T::Base>;
-    fn reveal(self) -> Self::Base {
-        Rc::new((*self).clone().reveal())
+    fn try_reveal(self) -> Result<Self::Base, MpcNetError> {
+        (*self).clone().try_reveal().map(Rc::new)
+    }
+    fn reveal_to(self, to: usize) -> Option<Self::Base> {
+        (*self).clone().reveal_to(to).map(Rc::new)
     }
     fn from_public(other: Self::Base) -> Self {
         Rc::new(Reveal::from_public((*other).clone()))
@@ -227,8 +284,13 @@ where
 
 impl<A: Reveal, B: Reveal> Reveal for (A, B) {
     type Base = (A::Base, B::Base);
-    fn reveal(self) -> Self::Base {
-        (self.0.reveal(), self.1.reveal())
+    fn try_reveal(self) -> Result<Self::Base, MpcNetError> {
+        Ok((self.0.try_reveal()?, self.1.try_reveal()?))
+    }
+    fn reveal_to(self, to: usize) -> Option<Self::Base> {
+        let a = self.0.reveal_to(to);
+        let b = self.1.reveal_to(to);
+        a.zip(b)
     }
     fn from_public(other: Self::Base) -> Self {
         (
@@ -256,15 +318,43 @@ impl<A: Reveal, B: Reveal> Reveal for (A, B) {
     }
 }
 
+/// `Some(value)` at party `to`, and `None` elsewhere: what [`Reveal::reveal_to`] gives for data
+/// that every party already knows.
+pub fn only_to<T>(to: usize, value: T) -> Option<T> {
+    if Net::party_id() == to {
+        Some(value)
+    } else {
+        None
+    }
+}
+
 #[macro_export]
 macro_rules! struct_reveal_impl {
     ($s:ty, $con:tt ; $( ($x_ty:ty, $x:tt) ),*) => {
-        fn reveal(self) -> Self::Base {
-            $con {
-                $(
-                    $x: self.$x.reveal(),
-                )*
-            }
+        fn try_reveal(self) -> Result<Self::Base, $crate::MpcNetError> {
+            Ok({
+                $con {
+                    $(
+                        $x: self.$x.try_reveal()?,
+                    )*
+                }
+            })
+        }
+        fn reveal_to(self, to: usize) -> Option<Self::Base> {
+            // Every field takes part, even once we know we are not the receiver.
+            $(
+                let $x = self.$x.reveal_to(to);
+            )*
+            $(
+                let $x = $x?;
+            )*
+            Some({
+                $con {
+                    $(
+                        $x,
+                    )*
+                }
+            })
         }
         fn from_public(other: Self::Base) -> Self {
             $con {
@@ -293,12 +383,30 @@ macro_rules! struct_reveal_impl {
 #[macro_export]
 macro_rules! struct_reveal_simp_impl {
     ($con:path ; $( $x:tt ),*) => {
-        fn reveal(self) -> Self::Base {
-            $con {
-                $(
-                    $x: self.$x.reveal(),
-                )*
-            }
+        fn try_reveal(self) -> Result<Self::Base, $crate::MpcNetError> {
+            Ok({
+                $con {
+                    $(
+                        $x: self.$x.try_reveal()?,
+                    )*
+                }
+            })
+        }
+        fn reveal_to(self, to: usize) -> Option<Self::Base> {
+            // Every field takes part, even once we know we are not the receiver.
+            $(
+                let $x = self.$x.reveal_to(to);
+            )*
+            $(
+                let $x = $x?;
+            )*
+            Some({
+                $con {
+                    $(
+                        $x,
+                    )*
+                }
+            })
         }
         fn from_public(other: Self::Base) -> Self {
             $con {
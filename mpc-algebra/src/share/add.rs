@@ -19,16 +19,18 @@ use std::hash::Hash;
 use std::io::{self, Read, Write};
 use std::marker::PhantomData;
 
-use mpc_net::{MpcNet, MpcMultiNet as Net};
+use mpc_net::{MpcNet, MpcMultiNet as Net, MpcNetError, OrAbort};
 use crate::channel::MpcSerNet;
 
 use super::field::{
     DenseOrSparsePolynomial, DensePolynomial, ExtFieldShare, FieldShare, SparsePolynomial,
 };
 use super::group::GroupShare;
-use super::pairing::{AffProjShare, PairingShare};
+use super::pairing::{miller_loop_one, AffProjShare, PairingShare};
 use super::BeaverSource;
+use crate::client::{self, ClientShare};
 use crate::msm::*;
+use crate::preprocessing::DealtFieldShare;
 use crate::Reveal;
 
 #[derive(Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
@@ -82,8 +84,11 @@ impl<F: Field> AdditiveFieldShare<F> {
 impl<F: Field> Reveal for AdditiveFieldShare<F> {
     type Base = F;
 
-    fn reveal(self) -> F {
-        Net::broadcast(&self.val).into_iter().sum()
+    fn try_reveal(self) -> Result<F, MpcNetError> {
+        Ok(Net::broadcast(&self.val)?.into_iter().sum())
+    }
+    fn reveal_to(self, to: usize) -> Option<F> {
+        Net::send_to(to, &self.val).or_abort().map(|vals| vals.into_iter().sum())
     }
     fn from_public(f: F) -> Self {
         Self {
@@ -100,7 +105,7 @@ impl<F: Field> Reveal for AdditiveFieldShare<F> {
         let mut r: Vec<F> = (0..(Net::n_parties()-1)).map(|_| F::rand(rng)).collect();
         let sum_r: F = r.iter().sum();
         r.push(f - sum_r);
-        Self::from_add_shared(Net::recv_from_king( if Net::am_king() { Some(r) } else { None }))
+        Self::from_add_shared(Net::recv_from_king( if Net::am_king() { Some(r) } else { None }).or_abort())
     }
     fn king_share_batch<R: Rng>(f: Vec<Self::Base>, rng: &mut R) -> Vec<Self> {
         let mut rs: Vec<Vec<Self::Base>> =
@@ -113,14 +118,47 @@ impl<F: Field> Reveal for AdditiveFieldShare<F> {
             f[i] - &rs.iter().map(|r| &r[i]).sum()
         }).collect();
         rs.push(final_shares);
-        Net::recv_from_king(if Net::am_king() { Some(rs) } else {None}).into_iter().map(Self::from_add_shared).collect()
+        Net::recv_from_king(if Net::am_king() { Some(rs) } else {None}).or_abort().into_iter().map(Self::from_add_shared).collect()
+    }
+}
+
+impl<F: Field> ClientShare for AdditiveFieldShare<F> {
+    type Dealt = F;
+
+    fn deal<R: Rng>(values: &[F], n: usize, _threshold: Option<usize>, rng: &mut R) -> Vec<Vec<F>> {
+        client::add_split(values, n, rng)
+    }
+    fn from_dealt(dealt: Vec<F>) -> Result<Vec<Self>, MpcNetError> {
+        Ok(dealt.into_iter().map(Self::from_add_shared).collect())
+    }
+
+    type Keys = ();
+
+    fn output_keys() {}
+    fn use_output_keys(_keys: ()) {}
+}
+
+impl<F: Field> DealtFieldShare<F> for AdditiveFieldShare<F> {
+    fn deal_mac_key_shares<R: Rng>(_n_parties: usize, _rng: &mut R) -> Option<Vec<F>> {
+        None
+    }
+    fn deal_shares<R: Rng>(
+        values: &[F],
+        n_parties: usize,
+        _keys: Option<&[F]>,
+        rng: &mut R,
+    ) -> Vec<Vec<Self>> {
+        client::add_split(values, n_parties, rng)
+            .into_iter()
+            .map(|shares| shares.into_iter().map(Self::from_add_shared).collect())
+            .collect()
     }
 }
 
 impl<F: Field> FieldShare<F> for AdditiveFieldShare<F> {
     fn batch_open(selfs: impl IntoIterator<Item = Self>) -> Vec<F> {
         let self_vec: Vec<F> = selfs.into_iter().map(|s| s.val).collect();
-        let all_vals = Net::broadcast(&self_vec);
+        let all_vals = Net::broadcast(&self_vec).or_abort();
         (0..self_vec.len()).map(|i| all_vals.iter().map(|v| &v[i]).sum()).collect()
     }
     fn add(&mut self, other: &Self) -> &mut Self {
@@ -175,8 +213,11 @@ pub struct AdditiveGroupShare<T, M> {
 impl<G: Group, M> Reveal for AdditiveGroupShare<G, M> {
     type Base = G;
 
-    fn reveal(self) -> G {
-        Net::broadcast(&self.val).into_iter().sum()
+    fn try_reveal(self) -> Result<G, MpcNetError> {
+        Ok(Net::broadcast(&self.val)?.into_iter().sum())
+    }
+    fn reveal_to(self, to: usize) -> Option<G> {
+        Net::send_to(to, &self.val).or_abort().map(|vals| vals.into_iter().sum())
     }
     fn from_public(f: G) -> Self {
         Self {
@@ -197,7 +238,7 @@ impl<G: Group, M> Reveal for AdditiveGroupShare<G, M> {
         let mut r: Vec<G> = (0..(Net::n_parties()-1)).map(|_| G::rand(rng)).collect();
         let sum_r: G = r.iter().sum();
         r.push(f - sum_r);
-        Self::from_add_shared(Net::recv_from_king( if Net::am_king() { Some(r) } else { None }))
+        Self::from_add_shared(Net::recv_from_king( if Net::am_king() { Some(r) } else { None }).or_abort())
     }
     fn king_share_batch<R: Rng>(f: Vec<Self::Base>, rng: &mut R) -> Vec<Self> {
         let mut rs: Vec<Vec<Self::Base>> =
@@ -210,7 +251,7 @@ impl<G: Group, M> Reveal for AdditiveGroupShare<G, M> {
             f[i] - &rs.iter().map(|r| &r[i]).sum()
         }).collect();
         rs.push(final_shares);
-        Net::recv_from_king(if Net::am_king() { Some(rs) } else {None}).into_iter().map(Self::from_add_shared).collect()
+        Net::recv_from_king(if Net::am_king() { Some(rs) } else {None}).or_abort().into_iter().map(Self::from_add_shared).collect()
     }
 }
 
@@ -219,7 +260,7 @@ impl<G: Group, M: Msm<G, G::ScalarField>> GroupShare<G> for AdditiveGroupShare<G
 
     fn batch_open(selfs: impl IntoIterator<Item = Self>) -> Vec<G> {
         let self_vec: Vec<G> = selfs.into_iter().map(|s| s.val).collect();
-        let all_vals = Net::broadcast(&self_vec);
+        let all_vals = Net::broadcast(&self_vec).or_abort();
         (0..self_vec.len()).map(|i| all_vals.iter().map(|v| &v[i]).sum()).collect()
     }
 
@@ -271,49 +312,7 @@ macro_rules! impl_basics {
                 write!(f, "{:?}", self.val)
             }
         }
-        impl<T: $bound> ToBytes for $share<T> {
-            fn write<W: Write>(&self, _writer: W) -> io::Result<()> {
-                unimplemented!("write")
-            }
-        }
-        impl<T: $bound> FromBytes for $share<T> {
-            fn read<R: Read>(_reader: R) -> io::Result<Self> {
-                unimplemented!("read")
-            }
-        }
-        impl<T: $bound> CanonicalSerialize for $share<T> {
-            fn serialize<W: Write>(&self, _writer: W) -> Result<(), SerializationError> {
-                unimplemented!("serialize")
-            }
-            fn serialized_size(&self) -> usize {
-                unimplemented!("serialized_size")
-            }
-        }
-        impl<T: $bound> CanonicalSerializeWithFlags for $share<T> {
-            fn serialize_with_flags<W: Write, F: Flags>(
-                &self,
-                _writer: W,
-                _flags: F,
-            ) -> Result<(), SerializationError> {
-                unimplemented!("serialize_with_flags")
-            }
-
-            fn serialized_size_with_flags<F: Flags>(&self) -> usize {
-                unimplemented!("serialized_size_with_flags")
-            }
-        }
-        impl<T: $bound> CanonicalDeserialize for $share<T> {
-            fn deserialize<R: Read>(_reader: R) -> Result<Self, SerializationError> {
-                unimplemented!("deserialize")
-            }
-        }
-        impl<T: $bound> CanonicalDeserializeWithFlags for $share<T> {
-            fn deserialize_with_flags<R: Read, F: Flags>(
-                _reader: R,
-            ) -> Result<(Self, F), SerializationError> {
-                unimplemented!("deserialize_with_flags")
-            }
-        }
+        impl_share_encoding!([T: $bound], $share<T>, [val], []);
         impl<T: $bound> UniformRand for $share<T> {
             fn rand<R: Rng + ?Sized>(rng: &mut R) -> Self {
                 Self::from_add_shared(<T as UniformRand>::rand(rng))
@@ -333,49 +332,7 @@ macro_rules! impl_basics_2_param {
                 write!(f, "{:?}", self.val)
             }
         }
-        impl<T: $bound, M> ToBytes for $share<T, M> {
-            fn write<W: Write>(&self, _writer: W) -> io::Result<()> {
-                unimplemented!("write")
-            }
-        }
-        impl<T: $bound, M> FromBytes for $share<T, M> {
-            fn read<R: Read>(_reader: R) -> io::Result<Self> {
-                unimplemented!("read")
-            }
-        }
-        impl<T: $bound, M> CanonicalSerialize for $share<T, M> {
-            fn serialize<W: Write>(&self, _writer: W) -> Result<(), SerializationError> {
-                unimplemented!("serialize")
-            }
-            fn serialized_size(&self) -> usize {
-                unimplemented!("serialized_size")
-            }
-        }
-        impl<T: $bound, M> CanonicalSerializeWithFlags for $share<T, M> {
-            fn serialize_with_flags<W: Write, F: Flags>(
-                &self,
-                _writer: W,
-                _flags: F,
-            ) -> Result<(), SerializationError> {
-                unimplemented!("serialize_with_flags")
-            }
-
-            fn serialized_size_with_flags<F: Flags>(&self) -> usize {
-                unimplemented!("serialized_size_with_flags")
-            }
-        }
-        impl<T: $bound, M> CanonicalDeserialize for $share<T, M> {
-            fn deserialize<R: Read>(_reader: R) -> Result<Self, SerializationError> {
-                unimplemented!("deserialize")
-            }
-        }
-        impl<T: $bound, M> CanonicalDeserializeWithFlags for $share<T, M> {
-            fn deserialize_with_flags<R: Read, F: Flags>(
-                _reader: R,
-            ) -> Result<(Self, F), SerializationError> {
-                unimplemented!("deserialize_with_flags")
-            }
-        }
+        impl_share_encoding!([T: $bound, M], $share<T, M>, [val], [_phants]);
         impl<T: $bound, M> UniformRand for $share<T, M> {
             fn rand<R: Rng + ?Sized>(rng: &mut R) -> Self {
                 Reveal::from_add_shared(<T as UniformRand>::rand(rng))
@@ -411,8 +368,8 @@ pub struct MulFieldShare<T> {
 impl<F: Field> Reveal for MulFieldShare<F> {
     type Base = F;
 
-    fn reveal(self) -> F {
-        Net::broadcast(&self.val).into_iter().product()
+    fn try_reveal(self) -> Result<F, MpcNetError> {
+        Ok(Net::broadcast(&self.val)?.into_iter().product())
     }
     fn from_public(f: F) -> Self {
         Self {
@@ -433,7 +390,7 @@ impl<F: Field> FieldShare<F> for MulFieldShare<F> {
     }
     fn batch_open(selfs: impl IntoIterator<Item = Self>) -> Vec<F> {
         let self_vec: Vec<F> = selfs.into_iter().map(|s| s.val).collect();
-        let all_vals = Net::broadcast(&self_vec);
+        let all_vals = Net::broadcast(&self_vec).or_abort();
         (0..self_vec.len()).map(|i| all_vals.iter().map(|v| &v[i]).product()).collect()
     }
 
@@ -566,4 +523,25 @@ impl<E: PairingEngine> PairingShare<E> for AdditivePairingShare<E> {
         AdditiveGroupShare<E::G2Projective, crate::msm::ProjectiveMsm<E::G2Projective>>;
     type G1 = AdditiveG1Share<E>;
     type G2 = AdditiveG2Share<E>;
+
+    fn miller_loop_sh_pub(a: &Self::G1AffineShare, b: &E::G2Prepared) -> MulFieldShare<E::Fqk> {
+        MulFieldShare {
+            val: miller_loop_one::<E>(a.val.into(), b.clone()),
+        }
+    }
+    fn miller_loop_pub_sh(a: &E::G1Prepared, b: &Self::G2AffineShare) -> MulFieldShare<E::Fqk> {
+        MulFieldShare {
+            val: miller_loop_one::<E>(a.clone(), b.val.into()),
+        }
+    }
+    fn final_exp_sh(f: MulFieldShare<E::Fqk>) -> MulFieldShare<E::Fqk> {
+        MulFieldShare {
+            val: E::final_exponentiation(&f.val).unwrap(),
+        }
+    }
+    fn pow_pub_base(base: &E::Fqk, e: &Self::FrShare) -> MulFieldShare<E::Fqk> {
+        MulFieldShare {
+            val: base.pow(&e.val.into_repr()),
+        }
+    }
 }
@@ -0,0 +1,313 @@
+//! Bit decomposition of shared prime field elements.
+//!
+//! To decompose a shared `x`, we draw a random `r < p` as shared bits, open `c = x - r`, and add
+//! the public bits of `c` to the shared bits of `r` with a ripple-carry adder. The sum `s = c + r`
+//! lies in `[0, 2p)`; a second adder computes `s - p` and its carry tells us which of the two is
+//! `x`.
+//!
+//! Random shared bits are made as in Damgård et al. '06: for a random shared `u`, open `u^2` and
+//! take `(u / sqrt(u^2) + 1) / 2`, which is a uniformly random bit that no party knows.
+use ark_ff::{BigInteger, FftParameters, FpParameters, LegendreSymbol, PrimeField};
+use ark_std::{end_timer, start_timer};
+
+use super::field::FieldShare;
+use super::BeaverSource;
+
+/// The `m` least significant bits of `b`, little-endian.
+fn bits_of<B: BigInteger>(b: &B, m: usize) -> Vec<bool> {
+    (0..m).map(|i| b.get_bit(i)).collect()
+}
+
+/// The `m`-bit two's complement of `bits`, i.e. `2^m - bits`.
+fn neg_bits(bits: &[bool]) -> Vec<bool> {
+    let mut carry = true;
+    bits.iter()
+        .map(|b| {
+            let s = !b ^ carry;
+            carry &= !b;
+            s
+        })
+        .collect()
+}
+
+fn one_minus<F: PrimeField, S: FieldShare<F>>(mut s: S) -> S {
+    s.neg();
+    s.shift(&F::one());
+    s
+}
+
+/// Add the public bits `pubs[k]` to the shared bits `shs[k]`, for each `k`.
+///
+/// All inputs must have the same length. Returns the sum bits and the carry out of the top bit.
+/// Each bit position costs one round of (batched) multiplication.
+fn add_public_bits<F: PrimeField, S: FieldShare<F>, Src: BeaverSource<S, S, S>>(
+    pubs: &[Vec<bool>],
+    shs: &[Vec<S>],
+    source: &mut Src,
+) -> (Vec<Vec<S>>, Vec<S>) {
+    let len = shs.first().map_or(0, |s| s.len());
+    assert!(len > 0);
+    assert_eq!(pubs.len(), shs.len());
+    let mut sums: Vec<Vec<S>> = vec![Vec::with_capacity(len); shs.len()];
+    // There is no carry into the lowest bit.
+    let mut carries: Vec<S> = Vec::with_capacity(shs.len());
+    for ((p, s), sum) in pubs.iter().zip(shs).zip(sums.iter_mut()) {
+        if p[0] {
+            sum.push(one_minus(s[0]));
+            carries.push(s[0]);
+        } else {
+            sum.push(s[0]);
+            carries.push(S::from_public(F::zero()));
+        }
+    }
+    for i in 1..len {
+        let rs: Vec<S> = shs.iter().map(|s| s[i]).collect();
+        let rcs = S::batch_mul(rs, carries.clone(), source);
+        for (k, rc) in rcs.into_iter().enumerate() {
+            // r + c - 2rc, the xor of r and c
+            let mut r_plus_c = shs[k][i];
+            r_plus_c.add(&carries[k]);
+            let mut xor = r_plus_c;
+            xor.sub(&rc).sub(&rc);
+            if pubs[k][i] {
+                // 1 + r + c has sum bit not(r xor c) and carry r or c
+                sums[k].push(one_minus(xor));
+                carries[k] = *r_plus_c.sub(&rc);
+            } else {
+                // r + c has sum bit r xor c and carry r and c
+                sums[k].push(xor);
+                carries[k] = rc;
+            }
+        }
+    }
+    (sums, carries)
+}
+
+/// Shares of `count` uniformly random bits.
+pub fn rand_bits<F: PrimeField, S: FieldShare<F>, Src: BeaverSource<S, S, S>>(
+    count: usize,
+    source: &mut Src,
+) -> Vec<S> {
+    let rng = &mut rand::thread_rng();
+    let two_inv = F::from(2u8).inverse().unwrap();
+    let mut bits = Vec::with_capacity(count);
+    while bits.len() < count {
+        let us: Vec<S> = (bits.len()..count).map(|_| S::rand(rng)).collect();
+        let squares = S::batch_open(S::batch_mul(us.clone(), us.clone(), source));
+        for (mut u, sq) in us.into_iter().zip(squares) {
+            // u = 0 happens with negligible probability; we just draw again.
+            if sq.is_zero() {
+                continue;
+            }
+            // u / sqrt(u^2) is 1 or -1, each with probability 1/2.
+            u.scale(&sqrt(sq).unwrap().inverse().unwrap());
+            u.shift(&F::one());
+            u.scale(&two_inv);
+            bits.push(u);
+        }
+    }
+    bits
+}
+
+/// Shares of the little-endian bits of `count` uniformly random elements of `F`.
+pub fn rand_field_bits<F: PrimeField, S: FieldShare<F>, Src: BeaverSource<S, S, S>>(
+    count: usize,
+    source: &mut Src,
+) -> Vec<Vec<S>> {
+    let m = F::Params::MODULUS_BITS as usize;
+    let neg_p = neg_bits(&bits_of(&F::Params::MODULUS, m));
+    let mut out = Vec::with_capacity(count);
+    while out.len() < count {
+        let need = count - out.len();
+        let candidates: Vec<Vec<S>> = rand_bits(need * m, source)
+            .chunks(m)
+            .map(|c| c.to_vec())
+            .collect();
+        // r < p iff r + (2^m - p) does not carry out of m bits. Opening the carry only reveals
+        // whether the candidate is rejected.
+        let (_, carries) = add_public_bits(&vec![neg_p.clone(); need], &candidates, source);
+        let carries = S::batch_open(carries);
+        out.extend(
+            candidates
+                .into_iter()
+                .zip(carries)
+                .filter(|(_, c)| c.is_zero())
+                .map(|(r, _)| r),
+        );
+    }
+    out
+}
+
+/// The shared element whose little-endian bits are `bits`.
+pub fn from_bits<F: PrimeField, S: FieldShare<F>>(bits: &[S]) -> S {
+    let two = F::from(2u8);
+    let mut acc = *bits.last().unwrap();
+    for b in bits.iter().rev().skip(1) {
+        acc.scale(&two);
+        acc.add(b);
+    }
+    acc
+}
+
+/// Shares of the little-endian bits of each of `xs`, `F::Params::MODULUS_BITS` of them.
+pub fn batch_bit_decompose<F: PrimeField, S: FieldShare<F>, Src: BeaverSource<S, S, S>>(
+    xs: Vec<S>,
+    source: &mut Src,
+) -> Vec<Vec<S>> {
+    if xs.is_empty() {
+        return Vec::new();
+    }
+    let timer = start_timer!(|| format!("Bit decomposition: {} elements", xs.len()));
+    let n = xs.len();
+    let m = F::Params::MODULUS_BITS as usize;
+    let rs = rand_field_bits(n, source);
+    let cs: Vec<Vec<bool>> = S::batch_open(xs.into_iter().zip(&rs).map(|(mut x, r)| {
+        x.sub(&from_bits(r));
+        x
+    }))
+    .into_iter()
+    .map(|c| bits_of(&c.into_repr(), m))
+    .collect();
+
+    // s = c + r, as m + 1 bits. It is x or x + p.
+    let (mut ss, carries) = add_public_bits(&cs, &rs, source);
+    for (s, c) in ss.iter_mut().zip(carries) {
+        s.push(c);
+    }
+    // t = s + 2^(m+1) - p carries out iff s >= p, in which case its bits are those of s - p.
+    let neg_p = neg_bits(&bits_of(&F::Params::MODULUS, m + 1));
+    let (ts, ge) = add_public_bits(&vec![neg_p; n], &ss, source);
+
+    // x_i = s_i + ge * (t_i - s_i). Both s and s - p are below 2^m when they equal x, so the top
+    // bit is dropped.
+    let (conds, diffs): (Vec<S>, Vec<S>) = ss
+        .iter()
+        .zip(&ts)
+        .zip(&ge)
+        .flat_map(|((s, t), g)| {
+            (0..m).map(move |i| {
+                let mut d = t[i];
+                d.sub(&s[i]);
+                (*g, d)
+            })
+        })
+        .unzip();
+    let prods = S::batch_mul(conds, diffs, source);
+    let out = ss
+        .into_iter()
+        .zip(prods.chunks(m))
+        .map(|(s, p)| {
+            s.into_iter()
+                .take(m)
+                .zip(p)
+                .map(|(mut si, pi)| {
+                    si.add(pi);
+                    si
+                })
+                .collect()
+        })
+        .collect();
+    end_timer!(timer);
+    out
+}
+
+/// The Legendre symbol of `a`, computed by Euler's criterion.
+pub fn legendre<F: PrimeField>(a: F) -> LegendreSymbol {
+    let s = a.pow(F::modulus_minus_one_div_two());
+    if s.is_zero() {
+        LegendreSymbol::Zero
+    } else if s.is_one() {
+        LegendreSymbol::QuadraticResidue
+    } else {
+        LegendreSymbol::QuadraticNonResidue
+    }
+}
+
+/// A square root of `a`, if there is one, by Tonelli-Shanks.
+///
+/// This only needs `PrimeField`, so that shared fields can take square roots of opened values
+/// without requiring `SquareRootField` of the underlying field.
+pub fn sqrt<F: PrimeField>(a: F) -> Option<F> {
+    match legendre(a) {
+        LegendreSymbol::Zero => return Some(F::zero()),
+        LegendreSymbol::QuadraticNonResidue => return None,
+        LegendreSymbol::QuadraticResidue => {}
+    }
+    let mut z = F::qnr_to_t();
+    let mut w = a.pow(F::trace_minus_one_div_two());
+    let mut x = w * a;
+    let mut b = x * w;
+    let mut v = F::Params::TWO_ADICITY as usize;
+    while !b.is_one() {
+        let mut k = 0usize;
+        let mut b2k = b;
+        while !b2k.is_one() {
+            b2k.square_in_place();
+            k += 1;
+        }
+        w = z;
+        for _ in 1..(v - k) {
+            w.square_in_place();
+        }
+        z = w.square();
+        b *= z;
+        x *= w;
+        v = k;
+    }
+    Some(x)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::gadgets::{self, BitDecomposition};
+    use crate::share::pairing::{simulate, PairingShare};
+    use crate::share::{add::AdditivePairingShare, gsz20::GszPairingShare, rss::RssPairingShare};
+    use crate::share::{rss3::RSS3PairingShare, spdz::SpdzPairingShare};
+    use crate::{MpcField, Reveal};
+    use ark_bls12_377::Bls12_377 as E;
+    use ark_ec::PairingEngine;
+    use ark_ff::{BigInteger, FftField, PrimeField, SquareRootField, UniformRand, Zero};
+    use ark_r1cs_std::{alloc::AllocVar, fields::fp::FpVar, R1CSVar};
+    use ark_relations::r1cs::ConstraintSystem;
+    use ark_std::test_rng;
+
+    type Fr = <E as PairingEngine>::Fr;
+    type MpcFr<S> = MpcField<Fr, <S as PairingShare<E>>::FrShare>;
+
+    /// Bit decomposition, directly and through the constraint gadget, and square roots.
+    fn bit_decomposition<S: PairingShare<E>>() {
+        let rng = &mut test_rng();
+        let xs = vec![Fr::from(0u8), Fr::from(5u8), -Fr::from(1u8), Fr::rand(rng)];
+        let shared = MpcFr::<S>::king_share_batch(xs.clone(), rng);
+        let cs = ConstraintSystem::<MpcFr<S>>::new_ref();
+        for (x, shared) in xs.into_iter().zip(shared) {
+            let repr = x.into_repr();
+            let expected: Vec<Fr> = (0..Fr::size_in_bits()).map(|i| Fr::from(repr.get_bit(i))).collect();
+            assert_eq!(shared.bit_decomposition().reveal(), expected);
+            let var = FpVar::new_witness(cs.clone(), || Ok(shared)).unwrap();
+            let bits = gadgets::to_bits_le(&var).unwrap();
+            let bits: Vec<MpcFr<S>> = bits.iter().map(|b| b.value().unwrap()).collect();
+            assert_eq!(bits.reveal(), expected);
+            if x < Fr::from(256u16) {
+                gadgets::enforce_in_range(&var, 8).unwrap();
+            }
+
+            let square = shared * shared;
+            assert_eq!(square.legendre().is_zero(), x.is_zero());
+            let root = square.sqrt().unwrap().reveal();
+            assert!(root == x || root == -x);
+        }
+        let non_square = MpcFr::<S>::king_share(Fr::multiplicative_generator(), rng);
+        assert!(non_square.legendre().is_qnr());
+        assert!(non_square.sqrt().is_none());
+    }
+
+    #[test]
+    fn bit_decomposition_for_each_scheme() {
+        simulate::<E, AdditivePairingShare<E>>(3, bit_decomposition::<AdditivePairingShare<E>>);
+        simulate::<E, SpdzPairingShare<E>>(3, bit_decomposition::<SpdzPairingShare<E>>);
+        simulate::<E, GszPairingShare<E>>(3, bit_decomposition::<GszPairingShare<E>>);
+        simulate::<E, RSS3PairingShare<E>>(3, bit_decomposition::<RSS3PairingShare<E>>);
+        simulate::<E, RssPairingShare<E>>(3, bit_decomposition::<RssPairingShare<E>>);
+    }
+}
@@ -0,0 +1,229 @@
+#![macro_use]
+//! Versioned encodings for secret shares.
+//!
+//! Every share is encoded as a single version byte followed by its components, in declaration
+//! order, using their canonical encodings. The `*_with_flags` variants append one byte holding the
+//! flags, since group shares have no spare bits to pack them into.
+//!
+//! `ToBytes`/`FromBytes` use the same encoding as `CanonicalSerialize`/`CanonicalDeserialize`.
+
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Flags, SerializationError};
+
+use std::io::{self, Read, Write};
+
+/// The version of the share encoding. Bump this whenever the layout of any share changes.
+pub const SHARE_ENCODING_VERSION: u8 = 1;
+
+pub fn write_version<W: Write>(writer: W) -> Result<(), SerializationError> {
+    SHARE_ENCODING_VERSION.serialize(writer)
+}
+
+pub fn read_version<R: Read>(reader: R) -> Result<(), SerializationError> {
+    let version = u8::deserialize(reader)?;
+    if version == SHARE_ENCODING_VERSION {
+        Ok(())
+    } else {
+        Err(SerializationError::InvalidData)
+    }
+}
+
+pub fn write_flags<W: Write, F: Flags>(writer: W, flags: F) -> Result<(), SerializationError> {
+    flags.u8_bitmask().serialize(writer)
+}
+
+pub fn read_flags<R: Read, F: Flags>(reader: R) -> Result<F, SerializationError> {
+    let byte = u8::deserialize(reader)?;
+    match F::from_u8(byte) {
+        Some(flags) if flags.u8_bitmask() == byte => Ok(flags),
+        _ => Err(SerializationError::UnexpectedFlags),
+    }
+}
+
+pub fn ser_err_to_io(e: SerializationError) -> io::Error {
+    match e {
+        SerializationError::IoError(e) => e,
+        e => io::Error::new(io::ErrorKind::InvalidData, e),
+    }
+}
+
+/// Implements `ToBytes`, `FromBytes`, and the `Canonical*` (de)serialization traits for a share.
+///
+/// Takes the impl generics, the share type, the serialized fields, and the `PhantomData` fields.
+macro_rules! impl_share_encoding {
+    ([$($gen:tt)*], $ty:ty, [$($field:ident),*], [$($phant:ident),*]) => {
+        impl<$($gen)*> ToBytes for $ty {
+            fn write<W: Write>(&self, writer: W) -> io::Result<()> {
+                CanonicalSerialize::serialize(self, writer)
+                    .map_err($crate::share::encoding::ser_err_to_io)
+            }
+        }
+        impl<$($gen)*> FromBytes for $ty {
+            fn read<R: Read>(reader: R) -> io::Result<Self> {
+                <Self as CanonicalDeserialize>::deserialize(reader)
+                    .map_err($crate::share::encoding::ser_err_to_io)
+            }
+        }
+        impl<$($gen)*> CanonicalSerialize for $ty {
+            fn serialize<W: Write>(&self, mut writer: W) -> Result<(), SerializationError> {
+                $crate::share::encoding::write_version(&mut writer)?;
+                $(CanonicalSerialize::serialize(&self.$field, &mut writer)?;)*
+                Ok(())
+            }
+            fn serialized_size(&self) -> usize {
+                1 $(+ CanonicalSerialize::serialized_size(&self.$field))*
+            }
+        }
+        impl<$($gen)*> CanonicalSerializeWithFlags for $ty {
+            fn serialize_with_flags<W: Write, Fl: Flags>(
+                &self,
+                mut writer: W,
+                flags: Fl,
+            ) -> Result<(), SerializationError> {
+                CanonicalSerialize::serialize(self, &mut writer)?;
+                $crate::share::encoding::write_flags(&mut writer, flags)
+            }
+
+            fn serialized_size_with_flags<Fl: Flags>(&self) -> usize {
+                CanonicalSerialize::serialized_size(self) + 1
+            }
+        }
+        impl<$($gen)*> CanonicalDeserialize for $ty {
+            fn deserialize<R: Read>(mut reader: R) -> Result<Self, SerializationError> {
+                $crate::share::encoding::read_version(&mut reader)?;
+                Ok(Self {
+                    $($field: CanonicalDeserialize::deserialize(&mut reader)?,)*
+                    $($phant: PhantomData::default(),)*
+                })
+            }
+        }
+        impl<$($gen)*> CanonicalDeserializeWithFlags for $ty {
+            fn deserialize_with_flags<R: Read, Fl: Flags>(
+                mut reader: R,
+            ) -> Result<(Self, Fl), SerializationError> {
+                let s = <Self as CanonicalDeserialize>::deserialize(&mut reader)?;
+                let flags = $crate::share::encoding::read_flags(&mut reader)?;
+                Ok((s, flags))
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::share::pairing::simulate;
+    use crate::share::{add::AdditivePairingShare, gsz20::GszPairingShare, rss::RssPairingShare};
+    use crate::share::{rss3::RSS3PairingShare, spdz::SpdzPairingShare, PairingShare};
+    use crate::{MpcExtField, MpcField, MpcG1Projective, MpcG2Projective, MpcGroup, MpcPairingEngine, Reveal};
+    use ark_bls12_377::Bls12_377 as E;
+    use ark_ec::PairingEngine;
+    use ark_ff::{FromBytes, Field, ToBytes, UniformRand};
+    use ark_std::test_rng;
+    use std::fmt::Debug;
+
+    type Fr = <E as PairingEngine>::Fr;
+    type MpcFr<S> = MpcField<Fr, <S as PairingShare<E>>::FrShare>;
+
+    /// Check that share `s` round-trips through its canonical and byte encodings, which agree and
+    /// start with the encoding version, and that another version is rejected. Returns the decoded
+    /// share.
+    fn assert_share_round_trips<T>(s: &T) -> T
+    where
+        T: CanonicalSerialize + CanonicalDeserialize + ToBytes + FromBytes + PartialEq + Debug,
+    {
+        let mut bytes = Vec::new();
+        s.serialize(&mut bytes).unwrap();
+        assert_eq!(bytes.len(), s.serialized_size());
+        assert_eq!(bytes[0], SHARE_ENCODING_VERSION);
+        assert_eq!(ark_ff::to_bytes![s].unwrap(), bytes);
+        assert_eq!(&T::read(&bytes[..]).unwrap(), s);
+        let decoded = T::deserialize(&bytes[..]).unwrap();
+        assert_eq!(&decoded, s);
+        bytes[0] = SHARE_ENCODING_VERSION + 1;
+        assert!(T::deserialize(&bytes[..]).is_err());
+        assert!(T::read(&bytes[..]).is_err());
+        decoded
+    }
+
+    /// Round-trip shared scalars, points and pairings under `S`, and reveal the decoded shares.
+    /// Revealing checks SPDZ MACs, so a MAC lost in encoding fails the check.
+    fn share_encodings<S: PairingShare<E>>() {
+        type G1 = <E as PairingEngine>::G1Projective;
+        type G2 = <E as PairingEngine>::G2Projective;
+        let rng = &mut test_rng();
+        // Only the king draws while sharing, so draw everything before.
+        let (x, g, h) = (Fr::rand(rng), G1::rand(rng), G2::rand(rng));
+
+        let shared = MpcFr::<S>::king_share(x, rng);
+        let mut bytes = Vec::new();
+        shared.serialize(&mut bytes).unwrap();
+        assert_eq!(bytes[..2], [1, SHARE_ENCODING_VERSION]);
+        assert_eq!(MpcFr::<S>::deserialize(&bytes[..]).unwrap(), shared);
+        // Transcripts take only public values, written without a tag.
+        assert!(ark_ff::to_bytes![shared].is_err());
+        let public = MpcFr::<S>::from_public(x);
+        assert_eq!(ark_ff::to_bytes![public].unwrap(), ark_ff::to_bytes![x].unwrap());
+        assert_eq!(MpcFr::<S>::read(&ark_ff::to_bytes![public].unwrap()[..]).unwrap(), public);
+        match shared {
+            MpcField::Shared(s) => {
+                let decoded = MpcField::<Fr, S::FrShare>::Shared(assert_share_round_trips(&s));
+                assert_eq!(decoded.reveal(), x);
+            }
+            MpcField::Public(_) => panic!("king_share gave a public value"),
+        }
+
+        let shared_g = MpcGroup::<G1, S::G1ProjectiveShare>::king_share(g, rng);
+        match shared_g {
+            MpcGroup::Shared(s) => {
+                let decoded = MpcGroup::<G1, S::G1ProjectiveShare>::Shared(assert_share_round_trips(&s));
+                assert_eq!(decoded.reveal(), g);
+            }
+            MpcGroup::Public(_) => panic!("king_share gave a public value"),
+        }
+
+        let paired = MpcPairingEngine::<E, S>::pairing(
+            MpcG1Projective { val: shared_g },
+            MpcG2Projective { val: MpcGroup::Public(h) },
+        );
+        match paired.val {
+            MpcField::Shared(s) => {
+                let decoded = MpcExtField::<<E as PairingEngine>::Fqk, S::FqkShare> {
+                    val: MpcField::Shared(assert_share_round_trips(&s)),
+                };
+                assert_eq!(decoded.reveal(), E::pairing(g, h));
+            }
+            MpcField::Public(_) => panic!("pairing a shared point gave a public value"),
+        }
+    }
+
+    #[test]
+    fn share_encodings_for_each_scheme() {
+        simulate::<E, AdditivePairingShare<E>>(3, share_encodings::<AdditivePairingShare<E>>);
+        simulate::<E, SpdzPairingShare<E>>(3, share_encodings::<SpdzPairingShare<E>>);
+        simulate::<E, GszPairingShare<E>>(3, share_encodings::<GszPairingShare<E>>);
+        simulate::<E, RSS3PairingShare<E>>(3, share_encodings::<RSS3PairingShare<E>>);
+        simulate::<E, RssPairingShare<E>>(3, share_encodings::<RssPairingShare<E>>);
+    }
+
+    #[test]
+    fn gsz_share_encoding_keeps_degree() {
+        use crate::share::gsz20::field::GszFieldShare;
+        simulate::<E, GszPairingShare<E>>(3, || {
+            let rng = &mut test_rng();
+            let x = Fr::rand(rng);
+            let shared = match MpcFr::<GszPairingShare<E>>::king_share(x, rng) {
+                MpcField::Shared(s) => s,
+                MpcField::Public(_) => panic!("king_share gave a public value"),
+            };
+            assert_eq!(assert_share_round_trips(&shared).degree, shared.degree);
+            // A product before degree reduction has twice the degree, and opens as such.
+            let unreduced = GszFieldShare {
+                val: shared.val * shared.val,
+                degree: 2 * shared.degree,
+            };
+            let decoded = assert_share_round_trips(&unreduced);
+            assert_eq!(decoded.degree, 2 * shared.degree);
+            assert_eq!(decoded.reveal(), x.square());
+        });
+    }
+}
@@ -38,6 +38,18 @@ pub trait FieldShare<F: Field>:
         <Self as Reveal>::reveal(*self)
     }
 
+    /// This party's share of the key that authenticates shares of this type, if there is one.
+    fn mac_key_share() -> Option<F> {
+        None
+    }
+
+    /// Adopt `share` as this party's share of the authentication key, if there is one.
+    fn set_mac_key_share(_share: F) {}
+
+    /// Whether multiplying these shares consumes Beaver triples. Shares that multiply without
+    /// them make their inverse pairs online when there is no preprocessing.
+    const USES_TRIPLES: bool = true;
+
     fn map_homo<FF: Field, SS: FieldShare<FF>, Fun: Fn(F) -> FF>(self, f: Fun) -> SS {
         SS::from_add_shared(f(self.unwrap_as_public()))
     }
@@ -128,15 +140,16 @@ pub trait FieldShare<F: Field>:
     }
 
     fn inv<S: BeaverSource<Self, Self, Self>>(self, source: &mut S) -> Self {
-        let (x, mut y) = source.inv_pair();
-        let xa = x.mul(self, source).open().inverse().unwrap();
-        *y.scale(&xa)
+        // With the pair (r, 1/r): 1/x = r / open(r * x).
+        let (mut r, _) = source.inv_pair();
+        let rx = r.mul(self, source).open().inverse().unwrap();
+        *r.scale(&rx)
     }
 
     fn batch_inv<S: BeaverSource<Self, Self, Self>>(xs: Vec<Self>, source: &mut S) -> Vec<Self> {
-        let (bs, cs) = source.inv_pairs(xs.len());
-        let open_mul = Self::batch_open(Self::batch_mul(xs, bs, source));
-        cs.into_iter()
+        let (bs, _) = source.inv_pairs(xs.len());
+        let open_mul = Self::batch_open(Self::batch_mul(xs, bs.clone(), source));
+        bs.into_iter()
             .zip(open_mul.into_iter()
                     .map(|i| i.inverse().unwrap()),
             )
@@ -160,6 +173,24 @@ pub trait FieldShare<F: Field>:
         Self::batch_mul(xs, Self::batch_inv(ys, source), source)
     }
 
+    /// Shares of the little-endian bits of each of `xs`, one per bit of the modulus.
+    fn batch_bit_decompose<S: BeaverSource<Self, Self, Self>>(
+        xs: Vec<Self>,
+        source: &mut S,
+    ) -> Vec<Vec<Self>>
+    where
+        F: PrimeField,
+    {
+        super::bits::batch_bit_decompose(xs, source)
+    }
+
+    fn bit_decompose<S: BeaverSource<Self, Self, Self>>(self, source: &mut S) -> Vec<Self>
+    where
+        F: PrimeField,
+    {
+        Self::batch_bit_decompose(vec![self], source).pop().unwrap()
+    }
+
     fn partial_products<S: BeaverSource<Self, Self, Self>>(x: Vec<Self>, src: &mut S) -> Vec<Self> {
         let n = x.len();
         let (m, m_inv): (Vec<Self>, Vec<Self>) = (0..(n + 1)).map(|_| src.inv_pair()).unzip();
@@ -33,9 +33,8 @@ use ark_serialize::{
     CanonicalSerializeWithFlags, Flags, SerializationError,
 };
 use ark_std::{end_timer, start_timer};
-use mpc_net::{MpcMultiNet as Net, MpcNet};
+use mpc_net::{MpcMultiNet as Net, MpcNet, MpcNetError, OrAbort};
 
-use once_cell::sync::OnceCell;
 use std::any::{Any, TypeId};
 use std::borrow::Cow;
 use std::cmp::Ord;
@@ -44,6 +43,7 @@ use std::fmt::{self, Debug, Display, Formatter};
 use std::hash::Hash;
 use std::io::{self, Read, Write};
 use std::marker::PhantomData;
+use std::ops::{AddAssign, MulAssign};
 use std::sync::Mutex;
 
 use derivative::Derivative;
@@ -55,34 +55,24 @@ use super::field::{
     DenseOrSparsePolynomial, DensePolynomial, ExtFieldShare, FieldShare, SparsePolynomial,
 };
 use super::BeaverSource;
+use crate::client::ClientShare;
 use crate::msm::Msm;
-use crate::share::pairing::{AffProjShare, PairingShare};
+use crate::share::pairing::{miller_loop_one, AffProjShare, PairingShare};
 use crate::Reveal;
 
+pub mod preprocessing;
+
 lazy_static! {
-    static ref TYPE_LISTS: Mutex<HashMap<TypeId, Vec<Box<dyn Any + Send>>>> =
+    /// Share domains, keyed by field and number of parties. Each is built once and never freed.
+    static ref SHARE_DOMAINS: Mutex<HashMap<(TypeId, usize), &'static (dyn Any + Send + Sync)>> =
         Mutex::new(HashMap::new());
-    static ref SHARE_DOMAIN: OnceCell<Box<dyn Any + Send + Sync>> = OnceCell::new();
 }
 
 fn take_types<T: Any + Send>() -> Vec<T> {
-    let mut lists = TYPE_LISTS.lock().unwrap();
-    let type_id = TypeId::of::<T>();
-    let list = lists.remove(&type_id).unwrap_or_else(|| Vec::new());
-    list.into_iter()
-        .map(|x| *x.downcast::<T>().unwrap())
-        .collect()
+    Net::with_state(Vec::<T>::new, std::mem::take)
 }
 fn add_type<T: Any + Send>(t: T) {
-    let mut lists = TYPE_LISTS.lock().unwrap();
-    let type_id = TypeId::of::<T>();
-    use std::collections::hash_map::Entry;
-    match lists.entry(type_id) {
-        Entry::Occupied(o) => o.into_mut().push(Box::new(t)),
-        Entry::Vacant(v) => {
-            v.insert(vec![Box::new(t)]);
-        }
-    };
+    Net::with_state(Vec::<T>::new, |l| l.push(t));
 }
 fn add_types<T: Any + Send>(ts: Vec<T>) {
     for t in ts {
@@ -96,12 +86,72 @@ pub fn t() -> usize {
 }
 
 pub fn domain<F: FftField>() -> &'static MixedRadixEvaluationDomain<F> {
-    SHARE_DOMAIN.get_or_init(|| {
-        let d = MixedRadixEvaluationDomain::<F>::new(Net::n_parties()).unwrap();
-        assert_eq!(d.size(), Net::n_parties(),
-            "Attempted to build an evaluation domain of size {}, but could only get one of size {}.\nThis domain is needed in order to support Shamir shares for this many parties", Net::n_parties(), d.size(), );
-        Box::new(d)
-    }).downcast_ref().unwrap()
+    domain_for(Net::n_parties())
+}
+
+/// The share domain for `n` parties: party `i` holds the evaluation at its `i`th element.
+pub fn domain_for<F: FftField>(n: usize) -> &'static MixedRadixEvaluationDomain<F> {
+    let mut domains = SHARE_DOMAINS.lock().unwrap();
+    let d: &'static (dyn Any + Send + Sync) = *domains.entry((TypeId::of::<F>(), n)).or_insert_with(|| {
+        let d = MixedRadixEvaluationDomain::<F>::new(n).unwrap();
+        assert_eq!(d.size(), n,
+            "Attempted to build an evaluation domain of size {}, but could only get one of size {}.\nThis domain is needed in order to support Shamir shares for this many parties", n, d.size(), );
+        Box::leak(Box::new(d))
+    });
+    d.downcast_ref().unwrap()
+}
+
+/// Each party's shares of `values`, under fresh random polynomials of degree `degree` with
+/// coefficients in `T`: party `i`'s share is the evaluation at `point(i)`.
+fn deal<T, P, R>(
+    values: &[T],
+    degree: usize,
+    point: impl Fn(usize) -> P,
+    rng: &mut R,
+) -> Vec<Vec<T>>
+where
+    T: Copy + Zero + AddAssign + MulAssign<P> + UniformRand,
+    P: Copy,
+    R: Rng,
+{
+    let n = Net::n_parties();
+    let mut shares = vec![Vec::with_capacity(values.len()); n];
+    for v in values {
+        let coeffs: Vec<T> = (0..degree).map(|_| T::rand(rng)).collect();
+        for (i, share) in shares.iter_mut().enumerate() {
+            let x = point(i);
+            let mut eval = T::zero();
+            for c in coeffs.iter().rev() {
+                eval += *c;
+                eval *= x;
+            }
+            eval += *v;
+            share.push(eval);
+        }
+    }
+    shares
+}
+
+/// Our shares of `values`, which the king deals at degree `t()`. Only the king's `values` are
+/// used.
+fn king_deal<T, P, R>(values: Vec<T>, point: impl Fn(usize) -> P, rng: &mut R) -> Vec<T>
+where
+    T: Copy
+        + Zero
+        + AddAssign
+        + MulAssign<P>
+        + UniformRand
+        + CanonicalSerialize
+        + CanonicalDeserialize,
+    P: Copy,
+    R: Rng,
+{
+    let dealt = if Net::am_king() {
+        Some(deal(&values, t(), point, rng))
+    } else {
+        None
+    };
+    Net::recv_from_king(dealt).or_abort()
 }
 
 pub mod field {
@@ -127,49 +177,7 @@ pub mod field {
             write!(f, "{:?}", self.val)
         }
     }
-    impl<T: FftField> ToBytes for GszFieldShare<T> {
-        fn write<W: Write>(&self, _writer: W) -> io::Result<()> {
-            unimplemented!("write")
-        }
-    }
-    impl<T: FftField> FromBytes for GszFieldShare<T> {
-        fn read<R: Read>(_reader: R) -> io::Result<Self> {
-            unimplemented!("read")
-        }
-    }
-    impl<T: FftField> CanonicalSerialize for GszFieldShare<T> {
-        fn serialize<W: Write>(&self, _writer: W) -> Result<(), SerializationError> {
-            unimplemented!("serialize")
-        }
-        fn serialized_size(&self) -> usize {
-            unimplemented!("serialized_size")
-        }
-    }
-    impl<T: FftField> CanonicalSerializeWithFlags for GszFieldShare<T> {
-        fn serialize_with_flags<W: Write, F: Flags>(
-            &self,
-            _writer: W,
-            _flags: F,
-        ) -> Result<(), SerializationError> {
-            unimplemented!("serialize_with_flags")
-        }
-
-        fn serialized_size_with_flags<F: Flags>(&self) -> usize {
-            unimplemented!("serialized_size_with_flags")
-        }
-    }
-    impl<T: FftField> CanonicalDeserialize for GszFieldShare<T> {
-        fn deserialize<R: Read>(_reader: R) -> Result<Self, SerializationError> {
-            unimplemented!("deserialize")
-        }
-    }
-    impl<T: FftField> CanonicalDeserializeWithFlags for GszFieldShare<T> {
-        fn deserialize_with_flags<R: Read, F: Flags>(
-            _reader: R,
-        ) -> Result<(Self, F), SerializationError> {
-            unimplemented!("deserialize_with_flags")
-        }
-    }
+    impl_share_encoding!([T: FftField], GszFieldShare<T>, [val, degree], []);
     impl<T: FftField> UniformRand for GszFieldShare<T> {
         fn rand<R: Rng + ?Sized>(_rng: &mut R) -> Self {
             rand()
@@ -179,8 +187,11 @@ pub mod field {
     impl<F: FftField> Reveal for GszFieldShare<F> {
         type Base = F;
 
-        fn reveal(self) -> F {
-            open(&self)
+        fn try_reveal(self) -> Result<F, MpcNetError> {
+            try_open(&self)
+        }
+        fn reveal_to(self, to: usize) -> Option<F> {
+            open_to(&self, to)
         }
         fn from_public(f: F) -> Self {
             Self { val: f, degree: 0 }
@@ -191,26 +202,59 @@ pub mod field {
         fn unwrap_as_public(self) -> F {
             self.val
         }
-        fn king_share<R: Rng>(f: Self::Base, _rng: &mut R) -> Self {
-            let fs = vec![f; Net::n_parties()];
-            let king_f = Net::recv_from_king(if Net::am_king() { Some(fs) } else { None });
-            Self {
-                val: king_f,
-                degree: t(),
-            }
+        fn king_share<R: Rng>(f: Self::Base, rng: &mut R) -> Self {
+            Self::king_share_batch(vec![f], rng).pop().unwrap()
         }
-        fn king_share_batch<R: Rng>(f: Vec<Self::Base>, _rng: &mut R) -> Vec<Self> {
-            let fs = vec![f; Net::n_parties()];
-            let king_fs = Net::recv_from_king(if Net::am_king() { Some(fs) } else { None });
-            king_fs
+        fn king_share_batch<R: Rng>(f: Vec<Self::Base>, rng: &mut R) -> Vec<Self> {
+            let domain = domain::<F>();
+            king_deal(f, |i| domain.element(i), rng)
                 .into_iter()
-                .map(|king_f| Self {
-                    val: king_f,
-                    degree: t(),
+                .map(|val| Self { val, degree: t() })
+                .collect()
+        }
+    }
+    /// The client deals `t`-shares: the evaluations of a random polynomial of degree `t` through
+    /// the value.
+    impl<F: FftField> ClientShare for GszFieldShare<F> {
+        type Dealt = F;
+
+        fn deal<R: Rng>(
+            values: &[F],
+            n: usize,
+            _threshold: Option<usize>,
+            rng: &mut R,
+        ) -> Vec<Vec<F>> {
+            let domain = domain_for::<F>(n);
+            // As `t()` is for the parties.
+            let t = (n - 1) / 2;
+            let evals: Vec<Vec<F>> = values
+                .iter()
+                .map(|v| {
+                    let mut coeffs: Vec<F> = std::iter::once(*v)
+                        .chain((0..t).map(|_| F::rand(rng)))
+                        .collect();
+                    coeffs.resize(n, F::zero());
+                    domain.fft_in_place(&mut coeffs);
+                    coeffs
                 })
+                .collect();
+            (0..n)
+                .map(|party| evals.iter().map(|e| e[party]).collect())
                 .collect()
         }
+        fn from_dealt(dealt: Vec<F>) -> Result<Vec<Self>, MpcNetError> {
+            Ok(dealt
+                .into_iter()
+                .map(|val| Self { val, degree: t() })
+                .collect())
+        }
+
+        type Keys = ();
+
+        fn output_keys() {}
+        fn use_output_keys(_keys: ()) {}
     }
+
     impl<F: FftField> GszFieldShare<F> {
         fn poly_share<'a>(
             p: DenseOrSparsePolynomial<Self>,
@@ -258,8 +302,11 @@ pub mod field {
     }
 
     impl<F: FftField> FieldShare<F> for GszFieldShare<F> {
+        const USES_TRIPLES: bool = false;
+
         fn add(&mut self, other: &Self) -> &mut Self {
             self.val += other.val;
+            self.degree = std::cmp::max(self.degree, other.degree);
             self
         }
 
@@ -275,6 +322,7 @@ pub mod field {
 
         fn sub(&mut self, other: &Self) -> &mut Self {
             self.val -= other.val;
+            self.degree = std::cmp::max(self.degree, other.degree);
             self
         }
 
@@ -287,11 +335,11 @@ pub mod field {
             let (self_vec, mut deg_vec): (Vec<F>, Vec<usize>) =
                 selfs.into_iter().map(|s| (s.val, s.degree)).unzip();
             let timer = start_timer!(|| format!("Batch open: {}", self_vec.len()));
-            let mut all_vals = Net::broadcast(&self_vec);
+            let mut all_vals = Net::broadcast(&self_vec).or_abort();
             let mut out = Vec::new();
             while all_vals[0].len() > 0 {
                 let vals: Vec<F> = all_vals.iter_mut().map(|v| v.pop().unwrap()).collect();
-                out.push(open_degree_vec(vals, deg_vec.pop().unwrap()));
+                out.push(open_degree_vec(vals, deg_vec.pop().unwrap()).or_abort());
             }
             out.reverse();
             end_timer!(timer);
@@ -377,36 +425,41 @@ pub mod field {
 
     /// Yields a t-share of a random r.
     ///
-    /// Stubbed b/c it can be pre-processed.
-    ///
     /// Protocol 3.
-    pub fn rand<F: Field>() -> GszFieldShare<F> {
-        GszFieldShare {
-            val: F::one(),
-            degree: t(),
-        }
+    pub fn rand<F: FftField>() -> GszFieldShare<F> {
+        batch_rand(1).pop().unwrap()
+    }
+
+    pub fn batch_rand<F: FftField>(n: usize) -> Vec<GszFieldShare<F>> {
+        preprocessing::take_rands::<F, F>(n)
+            .into_iter()
+            .map(|val| GszFieldShare { val, degree: t() })
+            .collect()
     }
 
     /// Yields two shares of a random `r`, one of degree t, one of degree 2t
     ///
-    /// Stubbed b/c it can be pre-processed.
-    ///
     /// Protocol 4.
-    pub fn double_rand<F: Field>() -> (GszFieldShare<F>, GszFieldShare<F>) {
-        (
-            GszFieldShare {
-                val: F::one(),
-                degree: t(),
-            },
-            GszFieldShare {
-                val: F::one(),
-                degree: 2 * t(),
-            },
-        )
+    pub fn double_rand<F: FftField>() -> (GszFieldShare<F>, GszFieldShare<F>) {
+        let (mut r, mut r2) = batch_double_rand(1);
+        (r.pop().unwrap(), r2.pop().unwrap())
     }
 
-    pub fn batch_double_rand<F: Field>(n: usize) -> (Vec<GszFieldShare<F>>, Vec<GszFieldShare<F>>) {
-        (0..n).map(|_| double_rand()).unzip()
+    pub fn batch_double_rand<F: FftField>(
+        n: usize,
+    ) -> (Vec<GszFieldShare<F>>, Vec<GszFieldShare<F>>) {
+        preprocessing::take_double_rands::<F, F>(n)
+            .into_iter()
+            .map(|(r, r2)| {
+                (
+                    GszFieldShare { val: r, degree: t() },
+                    GszFieldShare {
+                        val: r2,
+                        degree: 2 * t(),
+                    },
+                )
+            })
+            .unzip()
     }
 
     pub fn check_accumulated_field_products<F: FftField>() {
@@ -432,12 +485,24 @@ pub mod field {
 
     /// Open a t-share.
     pub fn open<F: FftField>(s: &GszFieldShare<F>) -> F {
+        try_open(s).or_abort()
+    }
+
+    /// Open a t-share, or return the error if the network failed or the shares are inconsistent.
+    pub fn try_open<F: FftField>(s: &GszFieldShare<F>) -> Result<F, MpcNetError> {
         check_accumulated_field_products::<F>();
-        let shares = Net::broadcast(&s.val);
+        let shares = Net::broadcast(&s.val)?;
         open_degree_vec(shares, s.degree)
     }
 
-    fn open_degree_vec<F: FftField>(mut shares: Vec<F>, d: usize) -> F {
+    /// Open a share to party `to` only.
+    pub fn open_to<F: FftField>(s: &GszFieldShare<F>, to: usize) -> Option<F> {
+        check_accumulated_field_products::<F>();
+        let shares = Net::send_to(to, &s.val).or_abort()?;
+        Some(open_degree_vec(shares, s.degree).or_abort())
+    }
+
+    fn open_degree_vec<F: FftField>(mut shares: Vec<F>, d: usize) -> Result<F, MpcNetError> {
         //let build_domain_timer = start_timer!(|| "domain");
         let domain = domain::<F>();
         //end_timer!(build_domain_timer);
@@ -446,16 +511,18 @@ pub mod field {
         //end_timer!(ifft_timer);
         //let eval_timer = start_timer!(|| "polyeval");
         let p = ark_poly::univariate::DensePolynomial::from_coefficients_vec(shares);
-        assert!(
-            p.degree() <= d,
-            "Polynomial\n{:?}\nhas degree {} (> degree bound {})",
-            p,
-            p.degree(),
-            d
-        );
+        if p.degree() > d {
+            debug!(
+                "Polynomial\n{:?}\nhas degree {} (> degree bound {})",
+                p,
+                p.degree(),
+                d
+            );
+            return Err(MpcNetError::InconsistentShares);
+        }
         let r = p.evaluate(&F::zero());
         //end_timer!(eval_timer);
-        r
+        Ok(r)
     }
 
     /// Given
@@ -470,14 +537,14 @@ pub mod field {
         new_degree: usize,
         f: Func,
     ) -> GszFieldShare<F> {
-        let king_answer = Net::send_to_king(&share.val).map(|shares| {
+        let king_answer = Net::send_to_king(&share.val).or_abort().map(|shares| {
             let n = shares.len();
-            let value = open_degree_vec(shares, share.degree);
+            let value = open_degree_vec(shares, share.degree).or_abort();
             let output = f(value);
             // TODO: randomize
             vec![output; n]
         });
-        let from_king = Net::recv_from_king(king_answer);
+        let from_king = Net::recv_from_king(king_answer).or_abort();
         GszFieldShare {
             degree: new_degree,
             val: from_king,
@@ -497,13 +564,13 @@ pub mod field {
         f: Func,
     ) -> Vec<GszFieldShare<F>> {
         let values: Vec<F> = shares.iter().map(|s| s.val).collect();
-        let king_answer = Net::send_to_king(&values).map(|all_shares| {
+        let king_answer = Net::send_to_king(&values).or_abort().map(|all_shares| {
             let kc_timer = start_timer!(|| format!("King computation"));
             let n = all_shares.len();
             let mut outputs = vec![Vec::new(); n];
             for i in 0..all_shares[0].len() {
                 let these_shares: Vec<F> = all_shares.iter().map(|s| s[i]).collect();
-                let value = open_degree_vec(these_shares, shares[i].degree);
+                let value = open_degree_vec(these_shares, shares[i].degree).or_abort();
                 let output = f(value);
                 // TODO: randomize
                 outputs.iter_mut().for_each(|o| o.push(output));
@@ -513,7 +580,7 @@ pub mod field {
             end_timer!(kc_timer);
             outputs
         });
-        let from_king = Net::recv_from_king(king_answer);
+        let from_king = Net::recv_from_king(king_answer).or_abort();
         from_king
             .into_iter()
             .map(|from_king| GszFieldShare {
@@ -541,10 +608,10 @@ pub mod field {
         let (r, r2) = double_rand::<F>();
         let mut x_cp = x.clone();
         x_cp.val *= y.val;
-        x_cp.degree *= 2;
+        x_cp.degree = std::cmp::max(x.degree + y.degree, r2.degree);
         x_cp.val += r2.val;
         // king just reduces the sharing degree
-        let mut shift_res = king_compute(&x_cp, x_cp.degree / 2, |r| r);
+        let mut shift_res = king_compute(&x_cp, r.degree, |r| r);
         shift_res.val -= r.val;
         if queue_check {
             let triple = GszFieldTriple(x, y.clone(), shift_res);
@@ -563,19 +630,17 @@ pub mod field {
     ) -> Vec<GszFieldShare<F>> {
         let timer = start_timer!(|| format!("Batch mult: {}", x.len()));
         let n = x.len();
-        let d = x[0].degree;
         assert_eq!(x.len(), y.len());
         let (r, r2) = batch_double_rand::<F>(n);
         let mut x_cp = x.clone();
         for ((x, y), r2) in x_cp.iter_mut().zip(y).zip(r2) {
-            assert_eq!(x.degree, d);
             x.val *= y.val;
-            x.degree *= 2;
+            x.degree = std::cmp::max(x.degree + y.degree, r2.degree);
             x.val += r2.val;
         }
         // king just reduces the sharing degree
         let kc_timer = start_timer!(|| format!("King compute wrapper"));
-        let mut shift_res = batch_king_compute(&x_cp, x_cp[0].degree / 2, |r| r);
+        let mut shift_res = batch_king_compute(&x_cp, t(), |r| r);
         end_timer!(kc_timer);
         for (shift_res, r) in shift_res.iter_mut().zip(r) {
             shift_res.val -= r.val;
@@ -797,12 +862,13 @@ pub mod field {
         let mut degree = 0;
         for (x, y) in xs.iter().zip(ys) {
             acc += x.val * &y.val;
-            degree = std::cmp::max(degree, 2 * std::cmp::max(x.degree, y.degree));
+            degree = std::cmp::max(degree, x.degree + y.degree);
         }
         let (r, r2) = double_rand::<F>();
         acc += r2.val;
+        let degree = std::cmp::max(degree, r2.degree);
         let acc_share = GszFieldShare { val: acc, degree };
-        let mut shifted_result = king_compute(&acc_share, degree / 2, |r| r);
+        let mut shifted_result = king_compute(&acc_share, r.degree, |r| r);
         shifted_result.sub(&r);
         shifted_result
     }
@@ -847,61 +913,23 @@ pub mod group {
             write!(f, "{:?}", self.val)
         }
     }
-    impl<T: Group, M> ToBytes for GszGroupShare<T, M> {
-        fn write<W: Write>(&self, _writer: W) -> io::Result<()> {
-            unimplemented!("write")
-        }
-    }
-    impl<T: Group, M> FromBytes for GszGroupShare<T, M> {
-        fn read<R: Read>(_reader: R) -> io::Result<Self> {
-            unimplemented!("read")
-        }
-    }
-    impl<T: Group, M> CanonicalSerialize for GszGroupShare<T, M> {
-        fn serialize<W: Write>(&self, _writer: W) -> Result<(), SerializationError> {
-            unimplemented!("serialize")
-        }
-        fn serialized_size(&self) -> usize {
-            unimplemented!("serialized_size")
-        }
-    }
-    impl<T: Group, M> CanonicalSerializeWithFlags for GszGroupShare<T, M> {
-        fn serialize_with_flags<W: Write, F: Flags>(
-            &self,
-            _writer: W,
-            _flags: F,
-        ) -> Result<(), SerializationError> {
-            unimplemented!("serialize_with_flags")
-        }
-
-        fn serialized_size_with_flags<F: Flags>(&self) -> usize {
-            unimplemented!("serialized_size_with_flags")
-        }
-    }
-    impl<T: Group, M> CanonicalDeserialize for GszGroupShare<T, M> {
-        fn deserialize<R: Read>(_reader: R) -> Result<Self, SerializationError> {
-            unimplemented!("deserialize")
-        }
-    }
-    impl<T: Group, M> CanonicalDeserializeWithFlags for GszGroupShare<T, M> {
-        fn deserialize_with_flags<R: Read, F: Flags>(
-            _reader: R,
-        ) -> Result<(Self, F), SerializationError> {
-            unimplemented!("deserialize_with_flags")
-        }
-    }
+    impl_share_encoding!([T: Group, M], GszGroupShare<T, M>, [val, degree], [_phants]);
     impl<T: Group, M> UniformRand for GszGroupShare<T, M> {
         fn rand<R: Rng + ?Sized>(_rng: &mut R) -> Self {
-            todo!()
+            rand()
         }
     }
 
     impl<G: Group, M: Send + 'static + Msm<G, G::ScalarField>> Reveal for GszGroupShare<G, M> {
         type Base = G;
 
-        fn reveal(self) -> G {
+        fn try_reveal(self) -> Result<G, MpcNetError> {
+            M::pre_reveal_check();
+            try_open(&self)
+        }
+        fn reveal_to(self, to: usize) -> Option<G> {
             M::pre_reveal_check();
-            open(&self)
+            open_to(&self, to)
         }
         fn from_public(f: G) -> Self {
             Self {
@@ -916,22 +944,15 @@ pub mod group {
         fn unwrap_as_public(self) -> G {
             self.val
         }
-        fn king_share<R: Rng>(f: Self::Base, _rng: &mut R) -> Self {
-            let fs = vec![f; Net::n_parties()];
-            let king_f = Net::recv_from_king(if Net::am_king() { Some(fs) } else { None });
-            Self {
-                val: king_f,
-                degree: t(),
-                _phants: Default::default(),
-            }
+        fn king_share<R: Rng>(f: Self::Base, rng: &mut R) -> Self {
+            Self::king_share_batch(vec![f], rng).pop().unwrap()
         }
-        fn king_share_batch<R: Rng>(f: Vec<Self::Base>, _rng: &mut R) -> Vec<Self> {
-            let fs = vec![f; Net::n_parties()];
-            let king_fs = Net::recv_from_king(if Net::am_king() { Some(fs) } else { None });
-            king_fs
+        fn king_share_batch<R: Rng>(f: Vec<Self::Base>, rng: &mut R) -> Vec<Self> {
+            let domain = domain::<G::ScalarField>();
+            king_deal(f, |i| domain.element(i), rng)
                 .into_iter()
-                .map(|king_f| Self {
-                    val: king_f,
+                .map(|val| Self {
+                    val,
                     degree: t(),
                     _phants: Default::default(),
                 })
@@ -944,11 +965,13 @@ pub mod group {
 
         fn add(&mut self, other: &Self) -> &mut Self {
             self.val += &other.val;
+            self.degree = std::cmp::max(self.degree, other.degree);
             self
         }
 
         fn sub(&mut self, other: &Self) -> &mut Self {
             self.val -= &other.val;
+            self.degree = std::cmp::max(self.degree, other.degree);
             self
         }
 
@@ -980,12 +1003,7 @@ pub mod group {
         }
 
         fn multi_scale_pub_group(bases: &[G], scalars: &[Self::FieldShare]) -> Self {
-            let degree = if scalars.len() > 0 {
-                scalars[0].degree
-            } else {
-                0
-            };
-            assert!(scalars.iter().all(|s| s.degree == degree));
+            let degree = scalars.iter().map(|s| s.degree).max().unwrap_or(0);
             let s_t = start_timer!(|| "Collecting scalar shares");
             let scalars: Vec<G::ScalarField> = scalars.into_iter().map(|s| s.val.clone()).collect();
             end_timer!(s_t);
@@ -1002,12 +1020,10 @@ pub mod group {
 
     /// Yields a t-share of a random r.
     ///
-    /// Stubbed b/c it can be pre-processed.
-    ///
     /// Protocol 3.
     pub fn rand<G: Group, M>() -> GszGroupShare<G, M> {
         GszGroupShare {
-            val: G::zero(),
+            val: preprocessing::take_rands::<G::ScalarField, G>(1)[0],
             degree: t(),
             _phants: Default::default(),
         }
@@ -1015,18 +1031,17 @@ pub mod group {
 
     /// Yields two shares of a random `r`, one of degree t, one of degree 2t
     ///
-    /// Stubbed b/c it can be pre-processed.
-    ///
     /// Protocol 4.
     pub fn double_rand<G: Group, M>() -> (GszGroupShare<G, M>, GszGroupShare<G, M>) {
+        let (r, r2) = preprocessing::take_double_rands::<G::ScalarField, G>(1)[0];
         (
             GszGroupShare {
-                val: G::zero(),
+                val: r,
                 degree: t(),
                 _phants: Default::default(),
             },
             GszGroupShare {
-                val: G::zero(),
+                val: r2,
                 degree: 2 * t(),
                 _phants: Default::default(),
             },
@@ -1041,11 +1056,24 @@ pub mod group {
 
     /// Open a t-share.
     pub fn open<G: Group, M: Send + 'static>(s: &GszGroupShare<G, M>) -> G {
-        let shares = Net::broadcast(&s.val);
+        try_open(s).or_abort()
+    }
+
+    /// Open a t-share, or return the error if the network failed or the shares are inconsistent.
+    pub fn try_open<G: Group, M: Send + 'static>(
+        s: &GszGroupShare<G, M>,
+    ) -> Result<G, MpcNetError> {
+        let shares = Net::broadcast(&s.val)?;
         open_degree_vec(shares, s.degree)
     }
 
-    fn open_degree_vec<G: Group>(shares: Vec<G>, d: usize) -> G {
+    /// Open a t-share to party `to` only.
+    pub fn open_to<G: Group, M: Send + 'static>(s: &GszGroupShare<G, M>, to: usize) -> Option<G> {
+        let shares = Net::send_to(to, &s.val).or_abort()?;
+        Some(open_degree_vec(shares, s.degree).or_abort())
+    }
+
+    fn open_degree_vec<G: Group>(shares: Vec<G>, d: usize) -> Result<G, MpcNetError> {
         let domain = domain::<G::ScalarField>();
         let n = Net::n_parties();
         let n_inv = G::ScalarField::from(n as u32).inverse().unwrap();
@@ -1058,8 +1086,8 @@ pub mod group {
                 let mut coeff = G::zero();
                 // 1/N * w^{-ij}
                 let mut w_inv_ij = n_inv;
-                for _j in 0..n {
-                    coeff += shares[i].mul(&w_inv_ij);
+                for j in 0..n {
+                    coeff += shares[j].mul(&w_inv_ij);
                     w_inv_ij *= &w_inv_i;
                 }
                 w_inv_i *= &w_inv;
@@ -1068,15 +1096,17 @@ pub mod group {
             .collect();
         assert_eq!(coeffs.len(), n);
         for i in d + 1..n {
-            assert!(
-                coeffs[i].is_zero(),
-                "Non-identity coeffs {} ({}), when expecting a degree <= {} poly",
-                i,
-                coeffs[i],
-                d
-            );
+            if !coeffs[i].is_zero() {
+                debug!(
+                    "Non-identity coeffs {} ({}), when expecting a degree <= {} poly",
+                    i,
+                    coeffs[i],
+                    d
+                );
+                return Err(MpcNetError::InconsistentShares);
+            }
         }
-        coeffs[0]
+        Ok(coeffs[0])
     }
 
     /// Given
@@ -1092,14 +1122,14 @@ pub mod group {
         new_degree: usize,
         f: Func,
     ) -> GszGroupShare<G, M> {
-        let king_answer = Net::send_to_king(&share.val).map(|shares| {
+        let king_answer = Net::send_to_king(&share.val).or_abort().map(|shares| {
             let n = shares.len();
-            let value = open_degree_vec(shares, share.degree);
+            let value = open_degree_vec(shares, share.degree).or_abort();
             let output = f(value);
             // TODO: randomize
             vec![output; n]
         });
-        let from_king = Net::recv_from_king(king_answer);
+        let from_king = Net::recv_from_king(king_answer).or_abort();
         GszGroupShare {
             degree: new_degree,
             val: from_king,
@@ -1117,10 +1147,10 @@ pub mod group {
         let mut y_cp = y.clone();
         let (r, r2) = double_rand::<G, M>();
         y_cp.val *= x.val;
-        y_cp.degree *= 2;
+        y_cp.degree = std::cmp::max(x.degree + y.degree, r2.degree);
         y_cp.val += r2.val;
         // king just reduces the sharing degree
-        let mut shift_res = king_compute(&y_cp, x.degree / 2, |r| r);
+        let mut shift_res = king_compute(&y_cp, r.degree, |r| r);
         shift_res.val -= r.val;
         if queue_check {
             let t = GszGroupTriple(x.clone(), y, shift_res);
@@ -1140,16 +1170,16 @@ pub mod group {
         let mut degree = 0;
         for (x, y) in xs.iter().zip(ys) {
             acc += y.val.mul(&x.val);
-            degree = std::cmp::max(degree, 2 * std::cmp::max(x.degree, y.degree));
+            degree = std::cmp::max(degree, x.degree + y.degree);
         }
         let (r, r2) = double_rand::<G, M>();
         acc += r2.val;
         let acc_share = GszGroupShare {
             val: acc,
-            degree,
+            degree: std::cmp::max(degree, r2.degree),
             _phants: Default::default(),
         };
-        let mut shifted_result = king_compute(&acc_share, degree / 2, |r| r);
+        let mut shifted_result = king_compute(&acc_share, r.degree, |r| r);
         shifted_result.sub(&r);
         shifted_result
     }
@@ -1435,8 +1465,14 @@ macro_rules! groups_share {
                 a.val.add_assign_mixed(&o);
                 a
             }
-            fn add_pub_proj_sh_aff(_a: &E::$proj, _o: Self::AffineShare) -> Self::ProjectiveShare {
-                unimplemented!()
+            fn add_pub_proj_sh_aff(a: &E::$proj, o: Self::AffineShare) -> Self::ProjectiveShare {
+                let mut a = a.clone();
+                a.add_assign_mixed(&o.val);
+                GszGroupShare {
+                    val: a,
+                    degree: o.degree,
+                    _phants: Default::default(),
+                }
             }
         }
     };
@@ -1488,49 +1524,7 @@ pub mod mul_field {
                     write!(f, "{:?}", self.val)
                 }
             }
-            impl<T: $bound, M> ToBytes for $share<T, M> {
-                fn write<W: Write>(&self, _writer: W) -> io::Result<()> {
-                    unimplemented!("write")
-                }
-            }
-            impl<T: $bound, M> FromBytes for $share<T, M> {
-                fn read<R: Read>(_reader: R) -> io::Result<Self> {
-                    unimplemented!("read")
-                }
-            }
-            impl<T: $bound, M> CanonicalSerialize for $share<T, M> {
-                fn serialize<W: Write>(&self, _writer: W) -> Result<(), SerializationError> {
-                    unimplemented!("serialize")
-                }
-                fn serialized_size(&self) -> usize {
-                    unimplemented!("serialized_size")
-                }
-            }
-            impl<T: $bound, M> CanonicalSerializeWithFlags for $share<T, M> {
-                fn serialize_with_flags<W: Write, F: Flags>(
-                    &self,
-                    _writer: W,
-                    _flags: F,
-                ) -> Result<(), SerializationError> {
-                    unimplemented!("serialize_with_flags")
-                }
-
-                fn serialized_size_with_flags<F: Flags>(&self) -> usize {
-                    unimplemented!("serialized_size_with_flags")
-                }
-            }
-            impl<T: $bound, M> CanonicalDeserialize for $share<T, M> {
-                fn deserialize<R: Read>(_reader: R) -> Result<Self, SerializationError> {
-                    unimplemented!("deserialize")
-                }
-            }
-            impl<T: $bound, M> CanonicalDeserializeWithFlags for $share<T, M> {
-                fn deserialize_with_flags<R: Read, F: Flags>(
-                    _reader: R,
-                ) -> Result<(Self, F), SerializationError> {
-                    unimplemented!("deserialize_with_flags")
-                }
-            }
+            impl_share_encoding!([T: $bound, M], $share<T, M>, [val, degree], [_phants]);
             impl<T: $bound, M> UniformRand for $share<T, M> {
                 fn rand<R: Rng + ?Sized>(_rng: &mut R) -> Self {
                     todo!()
@@ -1545,8 +1539,8 @@ pub mod mul_field {
     impl<F: Field, S: PrimeField> Reveal for MulFieldShare<F, S> {
         type Base = F;
 
-        fn reveal(self) -> F {
-            open_mul_field(&self)
+        fn try_reveal(self) -> Result<F, MpcNetError> {
+            try_open_mul_field(&self)
         }
         fn from_public(f: F) -> Self {
             Self {
@@ -1584,7 +1578,7 @@ pub mod mul_field {
         fn mul<SS: BeaverSource<Self, Self, Self>>(self, other: Self, _source: &mut SS) -> Self {
             Self {
                 val: self.val * other.val,
-                degree: self.degree,
+                degree: std::cmp::max(self.degree, other.degree),
                 _phants: Default::default(),
             }
         }
@@ -1600,6 +1594,7 @@ pub mod mul_field {
         ) -> Vec<Self> {
             for (x, y) in xs.iter_mut().zip(ys.iter()) {
                 x.val *= y.val;
+                x.degree = std::cmp::max(x.degree, y.degree);
             }
             xs
         }
@@ -1614,11 +1609,18 @@ pub mod mul_field {
 
     /// Open a t-share.
     pub fn open_mul_field<F: Field, S: PrimeField>(s: &MulFieldShare<F, S>) -> F {
-        let shares = Net::broadcast(&s.val);
+        try_open_mul_field(s).or_abort()
+    }
+
+    /// Open a t-share, or return the error if the network failed or the shares are inconsistent.
+    pub fn try_open_mul_field<F: Field, S: PrimeField>(
+        s: &MulFieldShare<F, S>,
+    ) -> Result<F, MpcNetError> {
+        let shares = Net::broadcast(&s.val)?;
         open_degree_vec::<F, S>(shares, s.degree)
     }
 
-    fn open_degree_vec<F: Field, S: PrimeField>(shares: Vec<F>, d: usize) -> F {
+    fn open_degree_vec<F: Field, S: PrimeField>(shares: Vec<F>, d: usize) -> Result<F, MpcNetError> {
         let domain = domain::<S>();
         let n = Net::n_parties();
         let n_inv = S::from(n as u32).inverse().unwrap();
@@ -1631,8 +1633,8 @@ pub mod mul_field {
                 let mut coeff = F::one();
                 // 1/N * w^{-ij}
                 let mut w_inv_ij = n_inv;
-                for _j in 0..n {
-                    coeff *= shares[i].pow(&w_inv_ij.into_repr());
+                for j in 0..n {
+                    coeff *= shares[j].pow(&w_inv_ij.into_repr());
                     w_inv_ij *= &w_inv_i;
                 }
                 w_inv_i *= &w_inv;
@@ -1641,15 +1643,277 @@ pub mod mul_field {
             .collect();
         assert_eq!(coeffs.len(), n);
         for i in d + 1..n {
-            assert!(
-                coeffs[i].is_one(),
-                "Non-one coeffs {} ({}), when expecting a degree <= {} poly",
-                i,
-                coeffs[i],
-                d
-            );
+            if !coeffs[i].is_one() {
+                debug!(
+                    "Non-one coeffs {} ({}), when expecting a degree <= {} poly",
+                    i,
+                    coeffs[i],
+                    d
+                );
+                return Err(MpcNetError::InconsistentShares);
+            }
         }
-        coeffs[0]
+        Ok(coeffs[0])
+    }
+}
+
+pub mod ext_field {
+    use super::*;
+
+    /// A Shamir share of an element of an extension field.
+    ///
+    /// The sharing polynomial has coefficients in the extension, and party `i` holds its
+    /// evaluation at `i + 1`, in the base prime field, so it is a Shamir sharing of each
+    /// coordinate. These points need no share domain, which the base prime field may not have
+    /// (BLS12-377's `Fq` has no subgroup of order 3, for one); shares are opened by interpolation
+    /// instead, and the random sharings they consume are made here rather than in
+    /// [`preprocessing`].
+    #[derive(Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+    pub struct ExtShamirShare<F: Field> {
+        pub val: F,
+        pub degree: usize,
+    }
+
+    impl<T: Field> Display for ExtShamirShare<T> {
+        fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+            write!(f, "{}", self.val)
+        }
+    }
+    impl<T: Field> Debug for ExtShamirShare<T> {
+        fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+            write!(f, "{:?}", self.val)
+        }
+    }
+    impl_share_encoding!([T: Field], ExtShamirShare<T>, [val, degree], []);
+    impl<T: Field> UniformRand for ExtShamirShare<T> {
+        fn rand<R: Rng + ?Sized>(_rng: &mut R) -> Self {
+            rands(1).pop().unwrap()
+        }
+    }
+
+    /// The point party `i` evaluates sharing polynomials at.
+    fn point<F: Field>(i: usize) -> F {
+        lift(F::BasePrimeField::from((i + 1) as u64))
+    }
+
+    /// Our shares of the sum of the parties' `values`, each of which its party deals at each of
+    /// `degrees`. Returns the shares for each degree.
+    fn sum_dealt<F: Field>(values: &[F], degrees: &[usize]) -> Vec<Vec<ExtShamirShare<F>>> {
+        let rng = &mut rand::thread_rng();
+        let n = Net::n_parties();
+        let mut to_parties = vec![Vec::with_capacity(values.len() * degrees.len()); n];
+        for &d in degrees {
+            for (msg, shares) in to_parties.iter_mut().zip(deal(values, d, point::<F>, rng)) {
+                msg.extend(shares);
+            }
+        }
+        let from_parties: Vec<Vec<F>> = Net::all_to_all(&to_parties).or_abort();
+        degrees
+            .iter()
+            .enumerate()
+            .map(|(di, &degree)| {
+                (0..values.len())
+                    .map(|k| ExtShamirShare {
+                        val: from_parties.iter().map(|m| m[di * values.len() + k]).sum(),
+                        degree,
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Shares of `count` random values: every party deals `count` random values, and we sum
+    /// what we get. Each sum is unknown while one dealer is honest.
+    fn rands<F: Field>(count: usize) -> Vec<ExtShamirShare<F>> {
+        let rng = &mut rand::thread_rng();
+        let values: Vec<F> = (0..count).map(|_| F::rand(rng)).collect();
+        sum_dealt(&values, &[t()]).pop().unwrap()
+    }
+
+    /// Shares of `count` random values, of degrees t and 2t, made as by [`rands`].
+    fn double_rands<F: Field>(count: usize) -> Vec<(ExtShamirShare<F>, ExtShamirShare<F>)> {
+        let rng = &mut rand::thread_rng();
+        let values: Vec<F> = (0..count).map(|_| F::rand(rng)).collect();
+        let mut shares = sum_dealt(&values, &[t(), 2 * t()]);
+        let two_t = shares.pop().unwrap();
+        let one_t = shares.pop().unwrap();
+        one_t.into_iter().zip(two_t).collect()
+    }
+
+    impl<F: Field> Reveal for ExtShamirShare<F> {
+        type Base = F;
+
+        fn try_reveal(self) -> Result<F, MpcNetError> {
+            open_degree_vec(Net::broadcast(&self.val)?, self.degree)
+        }
+        fn from_public(f: F) -> Self {
+            Self { val: f, degree: 0 }
+        }
+        /// Every party deals its summand, and each sums the shares it gets.
+        fn from_add_shared(f: F) -> Self {
+            sum_dealt(&[f], &[t()]).pop().unwrap().pop().unwrap()
+        }
+        fn unwrap_as_public(self) -> F {
+            self.val
+        }
+        fn king_share<R: Rng>(f: Self::Base, rng: &mut R) -> Self {
+            Self::king_share_batch(vec![f], rng).pop().unwrap()
+        }
+        fn king_share_batch<R: Rng>(f: Vec<Self::Base>, rng: &mut R) -> Vec<Self> {
+            king_deal(f, point::<F>, rng)
+                .into_iter()
+                .map(|val| Self { val, degree: t() })
+                .collect()
+        }
+    }
+
+    impl<F: Field> FieldShare<F> for ExtShamirShare<F> {
+        const USES_TRIPLES: bool = false;
+
+        fn batch_open(selfs: impl IntoIterator<Item = Self>) -> Vec<F> {
+            let (vals, degrees): (Vec<F>, Vec<usize>) =
+                selfs.into_iter().map(|s| (s.val, s.degree)).unzip();
+            let all_vals = Net::broadcast(&vals).or_abort();
+            degrees
+                .into_iter()
+                .enumerate()
+                .map(|(i, d)| {
+                    open_degree_vec(all_vals.iter().map(|v| v[i]).collect(), d).or_abort()
+                })
+                .collect()
+        }
+
+        fn add(&mut self, other: &Self) -> &mut Self {
+            self.val += other.val;
+            self.degree = std::cmp::max(self.degree, other.degree);
+            self
+        }
+
+        fn sub(&mut self, other: &Self) -> &mut Self {
+            self.val -= other.val;
+            self.degree = std::cmp::max(self.degree, other.degree);
+            self
+        }
+
+        fn neg(&mut self) -> &mut Self {
+            self.val = -self.val;
+            self
+        }
+
+        fn shift(&mut self, other: &F) -> &mut Self {
+            self.val += other;
+            self
+        }
+
+        fn scale(&mut self, other: &F) -> &mut Self {
+            self.val *= other;
+            self
+        }
+
+        fn mul<S: BeaverSource<Self, Self, Self>>(self, other: Self, source: &mut S) -> Self {
+            Self::batch_mul(vec![self], vec![other], source).pop().unwrap()
+        }
+
+        /// Multiply locally, then bring the products back to degree t, consuming double
+        /// sharings: the king opens each product masked with the degree-2t half, and the
+        /// degree-t half is taken from what it sends back.
+        ///
+        /// Unlike field products, these are not checked later.
+        fn batch_mul<S: BeaverSource<Self, Self, Self>>(
+            xs: Vec<Self>,
+            ys: Vec<Self>,
+            _source: &mut S,
+        ) -> Vec<Self> {
+            assert_eq!(xs.len(), ys.len());
+            let rs = double_rands::<F>(xs.len());
+            let (masked, degrees): (Vec<F>, Vec<usize>) = xs
+                .iter()
+                .zip(&ys)
+                .zip(&rs)
+                .map(|((x, y), (_, r2))| {
+                    (x.val * y.val + r2.val, std::cmp::max(x.degree + y.degree, r2.degree))
+                })
+                .unzip();
+            let king_answer = Net::send_to_king(&masked).or_abort().map(|all_masked| {
+                let opened: Vec<F> = degrees
+                    .iter()
+                    .enumerate()
+                    .map(|(i, d)| {
+                        open_degree_vec(all_masked.iter().map(|m| m[i]).collect(), *d).or_abort()
+                    })
+                    .collect();
+                vec![opened; all_masked.len()]
+            });
+            let opened: Vec<F> = Net::recv_from_king(king_answer).or_abort();
+            opened
+                .into_iter()
+                .zip(rs)
+                .map(|(z, (r, _))| Self {
+                    val: z - r.val,
+                    degree: r.degree,
+                })
+                .collect()
+        }
+
+        fn inv<S: BeaverSource<Self, Self, Self>>(self, source: &mut S) -> Self {
+            Self::batch_inv(vec![self], source).pop().unwrap()
+        }
+
+        /// Open each value times a random share `r`, and scale `r` by the inverse.
+        fn batch_inv<S: BeaverSource<Self, Self, Self>>(
+            xs: Vec<Self>,
+            source: &mut S,
+        ) -> Vec<Self> {
+            let rs = rands::<F>(xs.len());
+            let xrs = Self::batch_open(Self::batch_mul(xs, rs.clone(), source));
+            rs.into_iter()
+                .zip(xrs)
+                .map(|(mut r, xr)| {
+                    r.scale(&xr.inverse().unwrap());
+                    r
+                })
+                .collect()
+        }
+    }
+
+    /// Embed a base prime field element in `F`.
+    fn lift<F: Field>(x: F::BasePrimeField) -> F {
+        let mut elems = vec![F::BasePrimeField::zero(); F::extension_degree() as usize];
+        elems[0] = x;
+        F::from_base_prime_field_elems(&elems).unwrap()
+    }
+
+    /// The value at `x` of the polynomial of degree below `shares.len()` through the shares.
+    fn interpolate<F: Field>(shares: &[F], x: F) -> F {
+        (0..shares.len())
+            .map(|i| {
+                let basis = (0..shares.len())
+                    .filter(|j| *j != i)
+                    .map(|j| {
+                        (x - point::<F>(j)) * (point::<F>(i) - point::<F>(j)).inverse().unwrap()
+                    })
+                    .product::<F>();
+                shares[i] * basis
+            })
+            .sum()
+    }
+
+    /// The secret of a sharing of degree at most `d`: the first `d + 1` shares give it, and the
+    /// rest must agree with them.
+    fn open_degree_vec<F: Field>(shares: Vec<F>, d: usize) -> Result<F, MpcNetError> {
+        let k = std::cmp::min(d + 1, shares.len());
+        for j in k..shares.len() {
+            if interpolate(&shares[..k], point::<F>(j)) != shares[j] {
+                debug!(
+                    "Share {} is off the polynomial through the first {} shares (degree <= {})",
+                    j,
+                    k,
+                    d
+                );
+                return Err(MpcNetError::InconsistentShares);
+            }
+        }
+        Ok(interpolate(&shares[..k], F::zero()))
     }
 }
 
@@ -1681,8 +1945,7 @@ impl<F: Field, S: PrimeField> ExtFieldShare<F> for GszMulExtFieldShare<F, S> {
 pub struct GszExtFieldShare<F: Field>(pub PhantomData<F>);
 
 impl<F: Field> ExtFieldShare<F> for GszExtFieldShare<F> {
-    // TODO: wrong!
-    type Ext = mul_field::MulFieldShare<F, F::BasePrimeField>;
+    type Ext = ext_field::ExtShamirShare<F>;
     type Base = GszFieldShare<F::BasePrimeField>;
 }
 
@@ -1788,4 +2051,114 @@ impl<E: PairingEngine> PairingShare<E> for GszPairingShare<E> {
     type G2ProjectiveShare = GszGroupShare<E::G2Projective, msm::GszG2ProjectiveMsm<E>>;
     type G1 = GszG1Share<E>;
     type G2 = GszG2Share<E>;
+
+    // The Fqk shares are Shamir shares in the exponent, so mapping each share through a pairing
+    // with a public point gives a share of the same degree.
+    fn miller_loop_sh_pub(
+        a: &Self::G1AffineShare,
+        b: &E::G2Prepared,
+    ) -> mul_field::MulFieldShare<E::Fqk, E::Fr> {
+        mul_field::MulFieldShare {
+            val: miller_loop_one::<E>(a.val.into(), b.clone()),
+            degree: a.degree,
+            _phants: PhantomData::default(),
+        }
+    }
+    fn miller_loop_pub_sh(
+        a: &E::G1Prepared,
+        b: &Self::G2AffineShare,
+    ) -> mul_field::MulFieldShare<E::Fqk, E::Fr> {
+        mul_field::MulFieldShare {
+            val: miller_loop_one::<E>(a.clone(), b.val.into()),
+            degree: b.degree,
+            _phants: PhantomData::default(),
+        }
+    }
+    fn final_exp_sh(
+        f: mul_field::MulFieldShare<E::Fqk, E::Fr>,
+    ) -> mul_field::MulFieldShare<E::Fqk, E::Fr> {
+        mul_field::MulFieldShare {
+            val: E::final_exponentiation(&f.val).unwrap(),
+            ..f
+        }
+    }
+    fn pow_pub_base(
+        base: &E::Fqk,
+        e: &Self::FrShare,
+    ) -> mul_field::MulFieldShare<E::Fqk, E::Fr> {
+        mul_field::MulFieldShare {
+            val: base.pow(&e.val.into_repr()),
+            degree: e.degree,
+            _phants: PhantomData::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MpcField;
+    use ark_bls12_377::Bls12_377 as E;
+    use ark_std::test_rng;
+
+    type Fr = <E as PairingEngine>::Fr;
+
+    #[test]
+    fn king_shares_are_shamir_sharings() {
+        use ext_field::ExtShamirShare;
+        use field::GszFieldShare;
+        type Fqe = <E as PairingEngine>::Fqe;
+        type MpcFqe = MpcField<Fqe, ExtShamirShare<Fqe>>;
+        let (n, t) = (4, 1);
+        let draws = Net::simulate(n, move || {
+            let rng = &mut test_rng();
+            let x = Fr::rand(rng);
+            let (a, b) = (Fqe::rand(rng), Fqe::rand(rng));
+            let g = <E as PairingEngine>::G1Projective::rand(rng);
+            let x_share = GszFieldShare::king_share(x, rng);
+            let g_share = <GszPairingShare<E> as PairingShare<E>>::G1ProjectiveShare::king_share(g, rng);
+            assert_ne!(g_share.val, g);
+            assert_eq!(g_share.reveal(), g);
+            let ext_shares = ExtShamirShare::king_share_batch(vec![a, b], rng);
+            let (sa, sb) = (MpcFqe::Shared(ext_shares[0]), MpcFqe::Shared(ext_shares[1]));
+            let product = sa * sb;
+            let product_degree = match product {
+                MpcField::Shared(s) => s.degree,
+                MpcField::Public(_) => unreachable!(),
+            };
+            assert_eq!(product_degree, t);
+            assert_eq!(product.reveal(), a * b);
+            assert_eq!(sa.inv().unwrap().reveal(), a.inverse().unwrap());
+            let summand = Fqe::from(Net::party_id() as u64 + 1);
+            let sum = MpcFqe::from_add_shared(summand);
+            assert_eq!(sum.reveal(), Fqe::from(10u64));
+            (x, x_share.val, a, ext_shares[0].val)
+        });
+        let fr_coeffs = domain_for::<Fr>(n).ifft(&draws.iter().map(|d| d.1).collect::<Vec<_>>());
+        assert_eq!(fr_coeffs[0], draws[0].0);
+        assert!(!fr_coeffs[t].is_zero());
+        assert!(fr_coeffs[t + 1..].iter().all(|c| c.is_zero()));
+        // Party i's extension share is at i + 1; with t = 1, they lie on a line through the value.
+        let ext: Vec<Fqe> = draws.iter().map(|d| d.3).collect();
+        let slope = ext[1] - ext[0];
+        assert!(!slope.is_zero());
+        assert!(ext.windows(2).all(|w| w[1] - w[0] == slope));
+        assert_eq!(ext[0] - slope, draws[0].2);
+    }
+
+    #[test]
+    fn try_reveal_returns_inconsistent_shares() {
+        use field::GszFieldShare;
+        let opened = Net::simulate(4, || {
+            let rng = &mut test_rng();
+            let x = Fr::rand(rng);
+            let mut share = GszFieldShare::king_share(x, rng);
+            assert_eq!(share.try_reveal(), Ok(x));
+            if Net::party_id() == 1 {
+                share.val += Fr::from(1u64);
+            }
+            share.try_reveal()
+        });
+        assert!(opened.iter().all(|o| *o == Err(MpcNetError::InconsistentShares)));
+    }
 }
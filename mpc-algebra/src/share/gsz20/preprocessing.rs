@@ -0,0 +1,308 @@
+//! Random sharings for the Goyal-Song '20 protocol (Protocols 3 and 4).
+//!
+//! In each round, every party deals a random secret with fresh Shamir sharings, and the parties
+//! apply a Vandermonde matrix to the `n` sharings they receive. At most `t` dealers are corrupt,
+//! so the first `n - t` outputs are uniformly random and unknown to the adversary: each round
+//! yields `n - t` random sharings. For double sharings, every dealer shares its secret at degree
+//! `t` and at degree `2t`, and both are extracted with the same matrix.
+//!
+//! As in the paper, the dealt sharings are not checked for consistency. An inconsistent double
+//! sharing only adds an error to the multiplication that consumes it, which the product checks
+//! catch.
+//!
+//! Sharings are drawn from a per-type pool. When the pool runs dry, the rounds needed to cover
+//! the request are run together, and the leftovers are kept for later. A pool can also be filled
+//! ahead of time from a [`GszPreprocessing`] store.
+use log::debug;
+
+use ark_ff::prelude::*;
+use ark_ff::FftField;
+use ark_poly::domain::EvaluationDomain;
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, SerializationError};
+use ark_std::{end_timer, start_timer};
+
+use std::fmt::Debug;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::marker::PhantomData;
+use std::ops::{AddAssign, MulAssign};
+use std::path::{Path, PathBuf};
+
+use super::{domain, t};
+use crate::channel::MpcSerNet;
+use mpc_net::{MpcMultiNet as Net, MpcNet, OrAbort};
+
+/// A value that can be Shamir-shared over the field `F`: an element of `F`, or of a group with
+/// scalar field `F`.
+pub trait ShamirValue<F: FftField>:
+    Copy
+    + Debug
+    + Zero
+    + AddAssign
+    + MulAssign<F>
+    + UniformRand
+    + CanonicalSerialize
+    + CanonicalDeserialize
+    + Send
+    + 'static
+{
+}
+
+impl<F: FftField, T> ShamirValue<F> for T where
+    T: Copy
+        + Debug
+        + Zero
+        + AddAssign
+        + MulAssign<F>
+        + UniformRand
+        + CanonicalSerialize
+        + CanonicalDeserialize
+        + Send
+        + 'static
+{
+}
+
+/// How many random sharings a store holds (or a computation used).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GszPreprocessingCounts {
+    pub rands: usize,
+    pub double_rands: usize,
+}
+
+/// Our shares of the random sharings produced by `rounds` parallel rounds of extraction.
+///
+/// Every dealer shares each of its secrets once per degree in `degrees`. Returns the extracted
+/// shares for each degree, `rounds * (n - t)` of them.
+fn extract<F: FftField, T: ShamirValue<F>>(rounds: usize, degrees: &[usize]) -> Vec<Vec<T>> {
+    let timer = start_timer!(|| format!("Random sharing extraction: {} rounds", rounds));
+    let n = Net::n_parties();
+    let domain = domain::<F>();
+    // w^i, for the exponents i of all evaluation points and Vandermonde entries.
+    let w_pow = |i: usize| domain.element(i % n);
+    let rng = &mut rand::thread_rng();
+
+    // Message to party j: for each round and degree, our sharing's evaluation at w^j.
+    let mut to_parties = vec![Vec::with_capacity(rounds * degrees.len()); n];
+    for _ in 0..rounds {
+        let secret = T::rand(rng);
+        for &d in degrees {
+            let coeffs: Vec<T> = (0..d).map(|_| T::rand(rng)).collect();
+            for (j, msg) in to_parties.iter_mut().enumerate() {
+                let mut share = secret;
+                for (k, c) in coeffs.iter().enumerate() {
+                    let mut term = *c;
+                    term *= w_pow(j * (k + 1));
+                    share += term;
+                }
+                msg.push(share);
+            }
+        }
+    }
+    let from_parties: Vec<Vec<T>> = Net::all_to_all(&to_parties).or_abort();
+
+    let outputs = (0..degrees.len())
+        .map(|di| {
+            let mut out = Vec::with_capacity(rounds * (n - t()));
+            for round in 0..rounds {
+                let idx = round * degrees.len() + di;
+                for a in 0..n - t() {
+                    let mut acc = T::zero();
+                    for (i, dealt) in from_parties.iter().enumerate() {
+                        let mut term = dealt[idx];
+                        term *= w_pow(i * a);
+                        acc += term;
+                    }
+                    out.push(acc);
+                }
+            }
+            out
+        })
+        .collect();
+    end_timer!(timer);
+    outputs
+}
+
+fn rounds_for(count: usize) -> usize {
+    let per_round = Net::n_parties() - t();
+    (count + per_round - 1) / per_round
+}
+
+/// Our shares of at least `count` fresh random t-sharings.
+///
+/// Protocol 3.
+fn fresh_rands<F: FftField, T: ShamirValue<F>>(count: usize) -> Vec<T> {
+    extract::<F, T>(rounds_for(count), &[t()]).pop().unwrap()
+}
+
+/// Our shares of at least `count` fresh random double sharings, of degrees t and 2t.
+///
+/// Protocol 4.
+fn fresh_double_rands<F: FftField, T: ShamirValue<F>>(count: usize) -> Vec<(T, T)> {
+    let mut shares = extract::<F, T>(rounds_for(count), &[t(), 2 * t()]);
+    let two_t = shares.pop().unwrap();
+    let one_t = shares.pop().unwrap();
+    one_t.into_iter().zip(two_t).collect()
+}
+
+struct Pool<T> {
+    rands: Vec<T>,
+    double_rands: Vec<(T, T)>,
+    /// How much has been drawn since a store was installed, if one is.
+    used: Option<GszPreprocessingCounts>,
+}
+
+impl<T> Default for Pool<T> {
+    fn default() -> Self {
+        Self {
+            rands: Vec::new(),
+            double_rands: Vec::new(),
+            used: None,
+        }
+    }
+}
+
+/// The current session's pool of random sharings of `T`.
+fn with_pool<T: Send + 'static, R>(f: impl FnOnce(&mut Pool<T>) -> R) -> R {
+    Net::with_state(Pool::<T>::default, f)
+}
+
+/// Draw our shares of `count` random t-sharings.
+pub fn take_rands<F: FftField, T: ShamirValue<F>>(count: usize) -> Vec<T> {
+    let missing = with_pool::<T, _>(|p| count.saturating_sub(p.rands.len()));
+    if missing > 0 {
+        let fresh = fresh_rands::<F, T>(missing);
+        with_pool::<T, _>(|p| p.rands.extend(fresh));
+    }
+    with_pool::<T, _>(|p| {
+        if let Some(used) = &mut p.used {
+            used.rands += count;
+        }
+        let at = p.rands.len() - count;
+        p.rands.split_off(at)
+    })
+}
+
+/// Draw our shares of `count` random double sharings, of degrees t and 2t.
+pub fn take_double_rands<F: FftField, T: ShamirValue<F>>(count: usize) -> Vec<(T, T)> {
+    let missing = with_pool::<T, _>(|p| count.saturating_sub(p.double_rands.len()));
+    if missing > 0 {
+        let fresh = fresh_double_rands::<F, T>(missing);
+        with_pool::<T, _>(|p| p.double_rands.extend(fresh));
+    }
+    with_pool::<T, _>(|p| {
+        if let Some(used) = &mut p.used {
+            used.double_rands += count;
+        }
+        let at = p.double_rands.len() - count;
+        p.double_rands.split_off(at)
+    })
+}
+
+/// Random sharings produced ahead of time, as held by one party.
+#[derive(Clone, Debug, CanonicalSerialize, CanonicalDeserialize)]
+pub struct GszPreprocessing<F: FftField, T: ShamirValue<F>> {
+    /// The number of parties the sharings are for.
+    n_parties: u64,
+    /// Random t-sharings.
+    rands: Vec<T>,
+    /// Random double sharings, of degrees t and 2t.
+    double_rands: Vec<(T, T)>,
+    _field: PhantomData<F>,
+}
+
+impl<F: FftField, T: ShamirValue<F>> GszPreprocessing<F, T> {
+    /// Run Protocols 3 and 4 to produce the given numbers of random sharings.
+    ///
+    /// All parties must call this with the same counts.
+    pub fn generate(counts: GszPreprocessingCounts) -> Self {
+        let timer = start_timer!(|| "GSZ offline phase");
+        let mut rands = fresh_rands::<F, T>(counts.rands);
+        rands.truncate(counts.rands);
+        let mut double_rands = fresh_double_rands::<F, T>(counts.double_rands);
+        double_rands.truncate(counts.double_rands);
+        let r = Self {
+            n_parties: Net::n_parties() as u64,
+            rands,
+            double_rands,
+            _field: PhantomData::default(),
+        };
+        end_timer!(timer);
+        r
+    }
+
+    pub fn counts(&self) -> GszPreprocessingCounts {
+        GszPreprocessingCounts {
+            rands: self.rands.len(),
+            double_rands: self.double_rands.len(),
+        }
+    }
+
+    /// The path of `party`'s store in directory `dir`.
+    pub fn store_path(dir: &Path, party: usize) -> PathBuf {
+        dir.join(format!("gsz-preprocessing.{}", party))
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut w = BufWriter::new(File::create(path)?);
+        self.serialize(&mut w)
+            .map_err(crate::share::encoding::ser_err_to_io)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, SerializationError> {
+        Self::deserialize(BufReader::new(File::open(path)?))
+    }
+}
+
+/// Make `store` the pool of random sharings of values of type `T`.
+pub fn install<F: FftField, T: ShamirValue<F>>(store: GszPreprocessing<F, T>) {
+    debug!("Installing GSZ preprocessing: {:?}", store.counts());
+    assert_eq!(
+        store.n_parties,
+        Net::n_parties() as u64,
+        "Preprocessing is for {} parties, but there are {}",
+        store.n_parties,
+        Net::n_parties()
+    );
+    with_pool::<T, _>(|p| {
+        *p = Pool {
+            rands: store.rands,
+            double_rands: store.double_rands,
+            used: Some(GszPreprocessingCounts::default()),
+        }
+    });
+}
+
+/// Empty the pool for values of type `T`, returning how much of the installed store was used.
+///
+/// If more was used than the store held, the rest was generated on demand.
+pub fn uninstall<F: FftField, T: ShamirValue<F>>() -> Option<GszPreprocessingCounts> {
+    with_pool::<T, _>(|p| std::mem::take(p).used)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::domain_for;
+    use ark_bls12_377::Fr;
+
+    #[test]
+    fn double_rands_have_their_degrees() {
+        let (n, t, count) = (4, 1, 7);
+        let draws = Net::simulate(n, move || take_double_rands::<Fr, Fr>(count));
+        let domain = domain_for::<Fr>(n);
+        let coeffs = |shares: Vec<Fr>| domain.ifft(&shares);
+        let mut secrets = Vec::new();
+        for k in 0..count {
+            let low = coeffs(draws.iter().map(|d| d[k].0).collect());
+            let high = coeffs(draws.iter().map(|d| d[k].1).collect());
+            assert!(low[t + 1..].iter().all(|c| c.is_zero()));
+            assert!(high[2 * t + 1..].iter().all(|c| c.is_zero()));
+            assert!(!high[2 * t].is_zero());
+            assert_eq!(low[0], high[0]);
+            secrets.push(low[0]);
+        }
+        secrets.sort();
+        secrets.dedup();
+        assert_eq!(secrets.len(), count);
+    }
+}
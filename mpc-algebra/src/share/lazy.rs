@@ -0,0 +1,613 @@
+//! Shares whose products wait until a value is needed.
+//!
+//! Multiplying two [`LazyFieldShare`]s costs nothing at first: the product becomes a pending node
+//! in this session's arena, and so do sums and scalings of pending values. When a pending value
+//! is needed (to open it, to invert it, to decompose it into bits, or with [`evaluate`]), every
+//! pending node is computed, a layer at a time: all the products whose factors are known go into
+//! one [`FieldShare::batch_mul`], so independent products share their rounds.
+//!
+//! Code written for eager shares, like a circuit's `generate_constraints`, thus gets its
+//! independent products batched without changing. A session's nodes stay until [`clear`]; after
+//! that, the pending shares made before it are no use.
+//!
+//! Products take their triples from the source they were made with, when they are computed. To
+//! draw from a store installed for `S`, hand it to the lazy shares with [`adopt_preprocessing`].
+use derivative::Derivative;
+use rand::Rng;
+
+use ark_ff::bytes::{FromBytes, ToBytes};
+use ark_ff::prelude::*;
+use ark_serialize::{
+    CanonicalDeserialize, CanonicalDeserializeWithFlags, CanonicalSerialize,
+    CanonicalSerializeWithFlags, Flags, SerializationError,
+};
+use ark_std::{end_timer, start_timer};
+
+use std::fmt::{self, Debug, Display, Formatter};
+use std::hash::Hash;
+use std::io::{self, Read, Write};
+use std::marker::PhantomData;
+
+use mpc_net::MpcMultiNet as Net;
+
+use super::field::FieldShare;
+use super::BeaverSource;
+use crate::preprocessing;
+use crate::wire::field::MpcField;
+use crate::{MpcNetError, Reveal};
+
+/// A share of type `S`, or a value that will be one once the products it depends on are computed.
+#[derive(Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LazyFieldShare<F, S> {
+    /// A share we have.
+    Done(S),
+    /// The node with this index in the session's arena.
+    Pending(usize, PhantomData<F>),
+}
+
+/// How a pending node is computed from its operands.
+#[derive(Clone, Copy)]
+enum Op<F, S> {
+    Add(LazyFieldShare<F, S>, LazyFieldShare<F, S>),
+    Sub(LazyFieldShare<F, S>, LazyFieldShare<F, S>),
+    Scale(LazyFieldShare<F, S>, F),
+    Shift(LazyFieldShare<F, S>, F),
+    Mul(LazyFieldShare<F, S>, LazyFieldShare<F, S>),
+}
+
+struct Node<F, S> {
+    op: Op<F, S>,
+    /// How many products lie on the longest path to this node from shares we have.
+    depth: usize,
+    value: Option<S>,
+}
+
+/// The nodes of a session. Those before `evaluated` have their values.
+struct Arena<F, S> {
+    nodes: Vec<Node<F, S>>,
+    evaluated: usize,
+    /// The source the latest product was made with, which the pending products are computed with.
+    source: Option<Stored<S>>,
+}
+
+impl<F, S> Default for Arena<F, S> {
+    fn default() -> Self {
+        Self {
+            nodes: Vec::new(),
+            evaluated: 0,
+            source: None,
+        }
+    }
+}
+
+/// A source of lazy shares, drawing shares of type `S`: what it hands out is computed first.
+#[derive(Derivative)]
+#[derivative(Clone(bound = "Src: Clone"))]
+struct Forward<F, Src>(Src, PhantomData<F>);
+
+impl<
+        F: Field,
+        S: FieldShare<F>,
+        Src: BeaverSource<LazyFieldShare<F, S>, LazyFieldShare<F, S>, LazyFieldShare<F, S>>,
+    > BeaverSource<S, S, S> for Forward<F, Src>
+{
+    fn triple(&mut self) -> (S, S, S) {
+        let (a, b, c) = self.0.triple();
+        (a.value(), b.value(), c.value())
+    }
+    fn triples(&mut self, n: usize) -> (Vec<S>, Vec<S>, Vec<S>) {
+        let (a, b, c) = self.0.triples(n);
+        let values =
+            |xs: Vec<LazyFieldShare<F, S>>| xs.into_iter().map(LazyFieldShare::value).collect();
+        (values(a), values(b), values(c))
+    }
+    fn inv_pair(&mut self) -> (S, S) {
+        let (x, x_inv) = self.0.inv_pair();
+        (x.value(), x_inv.value())
+    }
+    fn inv_pairs(&mut self, n: usize) -> (Vec<S>, Vec<S>) {
+        let (x, x_inv) = self.0.inv_pairs(n);
+        let values =
+            |xs: Vec<LazyFieldShare<F, S>>| xs.into_iter().map(LazyFieldShare::value).collect();
+        (values(x), values(x_inv))
+    }
+}
+
+/// A source of shares of type `S`, whatever its type.
+trait DynSource<S>: Send {
+    fn triples(&mut self, n: usize) -> (Vec<S>, Vec<S>, Vec<S>);
+    fn inv_pairs(&mut self, n: usize) -> (Vec<S>, Vec<S>);
+    fn clone_box(&self) -> Box<dyn DynSource<S>>;
+}
+
+impl<S, Src: BeaverSource<S, S, S>> DynSource<S> for Src {
+    fn triples(&mut self, n: usize) -> (Vec<S>, Vec<S>, Vec<S>) {
+        BeaverSource::triples(self, n)
+    }
+    fn inv_pairs(&mut self, n: usize) -> (Vec<S>, Vec<S>) {
+        BeaverSource::inv_pairs(self, n)
+    }
+    fn clone_box(&self) -> Box<dyn DynSource<S>> {
+        Box::new(self.clone())
+    }
+}
+
+/// A source kept in the arena until the products made with it are computed.
+struct Stored<S>(Box<dyn DynSource<S>>);
+
+impl<S> Clone for Stored<S> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone_box())
+    }
+}
+
+impl<S: Send + 'static> BeaverSource<S, S, S> for Stored<S> {
+    fn triple(&mut self) -> (S, S, S) {
+        let (mut a, mut b, mut c) = self.0.triples(1);
+        (a.pop().unwrap(), b.pop().unwrap(), c.pop().unwrap())
+    }
+    fn triples(&mut self, n: usize) -> (Vec<S>, Vec<S>, Vec<S>) {
+        self.0.triples(n)
+    }
+    fn inv_pair(&mut self) -> (S, S) {
+        let (mut x, mut x_inv) = self.0.inv_pairs(1);
+        (x.pop().unwrap(), x_inv.pop().unwrap())
+    }
+    fn inv_pairs(&mut self, n: usize) -> (Vec<S>, Vec<S>) {
+        self.0.inv_pairs(n)
+    }
+}
+
+fn with_arena<F: Field, S: FieldShare<F>, R>(f: impl FnOnce(&mut Arena<F, S>) -> R) -> R {
+    Net::with_state(Arena::default, f)
+}
+
+impl<F: Field, S: FieldShare<F>> Arena<F, S> {
+    fn depth(&self, x: &LazyFieldShare<F, S>) -> usize {
+        match x {
+            LazyFieldShare::Pending(i, _) if *i >= self.evaluated => self.nodes[*i].depth,
+            _ => 0,
+        }
+    }
+
+    fn push(&mut self, op: Op<F, S>) -> LazyFieldShare<F, S> {
+        let depth = match &op {
+            Op::Add(a, b) | Op::Sub(a, b) => self.depth(a).max(self.depth(b)),
+            Op::Scale(a, _) | Op::Shift(a, _) => self.depth(a),
+            Op::Mul(a, b) => self.depth(a).max(self.depth(b)) + 1,
+        };
+        self.nodes.push(Node {
+            op,
+            depth,
+            value: None,
+        });
+        LazyFieldShare::Pending(self.nodes.len() - 1, PhantomData)
+    }
+
+    /// Remember (a copy of) `source` for the products about to be made.
+    fn make_products_with<
+        Src: BeaverSource<LazyFieldShare<F, S>, LazyFieldShare<F, S>, LazyFieldShare<F, S>>,
+    >(
+        &mut self,
+        source: &Src,
+    ) {
+        self.source = Some(Stored(Box::new(Forward(source.clone(), PhantomData))));
+    }
+
+    fn get(&self, x: &LazyFieldShare<F, S>) -> S {
+        match x {
+            LazyFieldShare::Done(s) => *s,
+            LazyFieldShare::Pending(i, _) => self.nodes[*i]
+                .value
+                .expect("A lazy share outlived its session's arena"),
+        }
+    }
+
+    /// Compute every pending node. The products of each layer are computed together, then the
+    /// sums and scalings of that layer, in the order they were made.
+    fn evaluate(&mut self) {
+        let pending = self.evaluated..self.nodes.len();
+        let n_layers = match pending.clone().map(|i| self.nodes[i].depth).max() {
+            Some(depth) => depth + 1,
+            None => return,
+        };
+        let timer = start_timer!(|| format!("Lazy evaluation: {} nodes", pending.len()));
+        let mut layers = vec![Vec::new(); n_layers];
+        for i in pending {
+            layers[self.nodes[i].depth].push(i);
+        }
+        for layer in layers {
+            let (products, linear): (Vec<usize>, Vec<usize>) = layer
+                .into_iter()
+                .partition(|i| matches!(self.nodes[*i].op, Op::Mul(..)));
+            if !products.is_empty() {
+                let (xs, ys) = products
+                    .iter()
+                    .map(|i| match &self.nodes[*i].op {
+                        Op::Mul(a, b) => (self.get(a), self.get(b)),
+                        _ => unreachable!(),
+                    })
+                    .unzip();
+                let source = self
+                    .source
+                    .as_mut()
+                    .expect("Pending products always have a source");
+                let zs = S::batch_mul(xs, ys, source);
+                for (i, z) in products.into_iter().zip(zs) {
+                    self.nodes[i].value = Some(z);
+                }
+            }
+            for i in linear {
+                let value = match &self.nodes[i].op {
+                    Op::Add(a, b) => *self.get(a).add(&self.get(b)),
+                    Op::Sub(a, b) => *self.get(a).sub(&self.get(b)),
+                    Op::Scale(a, c) => *self.get(a).scale(c),
+                    Op::Shift(a, c) => *self.get(a).shift(c),
+                    Op::Mul(..) => unreachable!(),
+                };
+                self.nodes[i].value = Some(value);
+            }
+        }
+        self.evaluated = self.nodes.len();
+        end_timer!(timer);
+    }
+}
+
+/// Compute every pending share in this session. Every party must call this at the same point.
+pub fn evaluate<F: Field, S: FieldShare<F>>() {
+    // The arena leaves the session's state while we evaluate, since multiplying may use it.
+    if let Some(mut arena) = Net::take_state::<Arena<F, S>>() {
+        arena.evaluate();
+        Net::with_state(|| arena, |_| ());
+    }
+}
+
+/// Forget this session's pending nodes.
+pub fn clear<F: Field, S: FieldShare<F>>() {
+    Net::take_state::<Arena<F, S>>();
+}
+
+/// Move the store installed for `S` in this session, if any, to the lazy shares of `S`, so that
+/// sources of lazy shares draw from it.
+pub fn adopt_preprocessing<F: Field, S: FieldShare<F>>() {
+    preprocessing::reinstall::<F, S, LazyFieldShare<F, S>>(LazyFieldShare::Done);
+}
+
+/// Give the store taken by [`adopt_preprocessing`] back to `S`, computing every pending share.
+pub fn restore_preprocessing<F: Field, S: FieldShare<F>>() {
+    evaluate::<F, S>();
+    preprocessing::reinstall::<F, LazyFieldShare<F, S>, S>(LazyFieldShare::value);
+}
+
+/// `x`, with products that wait.
+pub fn defer<F: Field, S: FieldShare<F>>(x: MpcField<F, S>) -> MpcField<F, LazyFieldShare<F, S>> {
+    x.map(|p| p, LazyFieldShare::Done)
+}
+
+/// The value of `x`, computing what it waits for.
+pub fn force<F: Field, S: FieldShare<F>>(x: MpcField<F, LazyFieldShare<F, S>>) -> MpcField<F, S> {
+    x.map(|p| p, LazyFieldShare::value)
+}
+
+impl<F: Field, S: FieldShare<F>> LazyFieldShare<F, S> {
+    /// Our share, computing what it waits for.
+    pub fn value(self) -> S {
+        match self {
+            Self::Done(s) => s,
+            Self::Pending(..) => {
+                evaluate::<F, S>();
+                with_arena(|a: &mut Arena<F, S>| a.get(&self))
+            }
+        }
+    }
+
+    fn values(xs: impl IntoIterator<Item = Self>) -> Vec<S> {
+        evaluate::<F, S>();
+        with_arena(|a: &mut Arena<F, S>| xs.into_iter().map(|x| a.get(&x)).collect())
+    }
+
+    /// `op`'s result: computed now if it is local and its operands are known, or pending.
+    fn apply(op: Op<F, S>) -> Self {
+        match op {
+            Op::Add(Self::Done(mut a), Self::Done(b)) => Self::Done(*a.add(&b)),
+            Op::Sub(Self::Done(mut a), Self::Done(b)) => Self::Done(*a.sub(&b)),
+            Op::Scale(Self::Done(mut a), c) => Self::Done(*a.scale(&c)),
+            Op::Shift(Self::Done(mut a), c) => Self::Done(*a.shift(&c)),
+            op => with_arena(|a: &mut Arena<F, S>| a.push(op)),
+        }
+    }
+}
+
+impl<F: Field, S: FieldShare<F>> Display for LazyFieldShare<F, S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Done(s) => write!(f, "{}", s),
+            Self::Pending(i, _) => write!(f, "pending #{}", i),
+        }
+    }
+}
+
+impl<F: Field, S: FieldShare<F>> Debug for LazyFieldShare<F, S> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Done(s) => write!(f, "{:?}", s),
+            Self::Pending(i, _) => write!(f, "pending #{}", i),
+        }
+    }
+}
+
+// Encoding a pending share computes it; it is encoded as the share it becomes.
+impl<F: Field, S: FieldShare<F>> ToBytes for LazyFieldShare<F, S> {
+    fn write<W: Write>(&self, writer: W) -> io::Result<()> {
+        self.value().write(writer)
+    }
+}
+
+impl<F: Field, S: FieldShare<F>> FromBytes for LazyFieldShare<F, S> {
+    fn read<R: Read>(reader: R) -> io::Result<Self> {
+        S::read(reader).map(Self::Done)
+    }
+}
+
+impl<F: Field, S: FieldShare<F>> CanonicalSerialize for LazyFieldShare<F, S> {
+    fn serialize<W: Write>(&self, writer: W) -> Result<(), SerializationError> {
+        self.value().serialize(writer)
+    }
+    fn serialized_size(&self) -> usize {
+        self.value().serialized_size()
+    }
+}
+
+impl<F: Field, S: FieldShare<F>> CanonicalSerializeWithFlags for LazyFieldShare<F, S> {
+    fn serialize_with_flags<W: Write, Fl: Flags>(
+        &self,
+        writer: W,
+        flags: Fl,
+    ) -> Result<(), SerializationError> {
+        self.value().serialize_with_flags(writer, flags)
+    }
+    fn serialized_size_with_flags<Fl: Flags>(&self) -> usize {
+        self.value().serialized_size_with_flags::<Fl>()
+    }
+}
+
+impl<F: Field, S: FieldShare<F>> CanonicalDeserialize for LazyFieldShare<F, S> {
+    fn deserialize<R: Read>(reader: R) -> Result<Self, SerializationError> {
+        S::deserialize(reader).map(Self::Done)
+    }
+}
+
+impl<F: Field, S: FieldShare<F>> CanonicalDeserializeWithFlags for LazyFieldShare<F, S> {
+    fn deserialize_with_flags<R: Read, Fl: Flags>(
+        reader: R,
+    ) -> Result<(Self, Fl), SerializationError> {
+        S::deserialize_with_flags(reader).map(|(s, flags)| (Self::Done(s), flags))
+    }
+}
+
+impl<F: Field, S: FieldShare<F>> UniformRand for LazyFieldShare<F, S> {
+    fn rand<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        preprocessing::take_rand::<F, Self>()
+            .unwrap_or_else(|e| panic!("{}", e))
+            .unwrap_or_else(|| Self::Done(S::rand(rng)))
+    }
+}
+
+impl<F: Field, S: FieldShare<F>> Reveal for LazyFieldShare<F, S> {
+    type Base = F;
+
+    fn try_reveal(self) -> Result<F, MpcNetError> {
+        self.value().try_reveal()
+    }
+    fn reveal_to(self, to: usize) -> Option<F> {
+        self.value().reveal_to(to)
+    }
+    fn from_add_shared(f: F) -> Self {
+        Self::Done(S::from_add_shared(f))
+    }
+    fn from_add_shared_batch(fs: Vec<F>) -> Vec<Self> {
+        S::from_add_shared_batch(fs).into_iter().map(Self::Done).collect()
+    }
+    fn from_public(f: F) -> Self {
+        Self::Done(S::from_public(f))
+    }
+    fn unwrap_as_public(self) -> F {
+        self.value().unwrap_as_public()
+    }
+    fn unwrap_as_public_vec(self) -> Vec<F> {
+        self.value().unwrap_as_public_vec()
+    }
+    fn from_add_shared_vec(vals: Vec<F>) -> Self {
+        Self::Done(S::from_add_shared_vec(vals))
+    }
+    fn king_share<R: Rng>(f: F, rng: &mut R) -> Self {
+        Self::Done(S::king_share(f, rng))
+    }
+    fn king_share_batch<R: Rng>(fs: Vec<F>, rng: &mut R) -> Vec<Self> {
+        S::king_share_batch(fs, rng).into_iter().map(Self::Done).collect()
+    }
+    fn init_protocol() {
+        S::init_protocol()
+    }
+    fn deinit_protocol() {
+        S::deinit_protocol()
+    }
+}
+
+/// Multiplications, inversions and bit decompositions draw from the sources passed in; products
+/// do so when they are computed.
+impl<F: Field, S: FieldShare<F>> FieldShare<F> for LazyFieldShare<F, S> {
+    const USES_TRIPLES: bool = S::USES_TRIPLES;
+
+    fn mac_key_share() -> Option<F> {
+        S::mac_key_share()
+    }
+
+    fn set_mac_key_share(share: F) {
+        S::set_mac_key_share(share)
+    }
+
+    fn batch_open(selfs: impl IntoIterator<Item = Self>) -> Vec<F> {
+        S::batch_open(Self::values(selfs))
+    }
+
+    fn add(&mut self, other: &Self) -> &mut Self {
+        *self = Self::apply(Op::Add(*self, *other));
+        self
+    }
+
+    fn sub(&mut self, other: &Self) -> &mut Self {
+        *self = Self::apply(Op::Sub(*self, *other));
+        self
+    }
+
+    fn shift(&mut self, other: &F) -> &mut Self {
+        *self = Self::apply(Op::Shift(*self, *other));
+        self
+    }
+
+    fn scale(&mut self, other: &F) -> &mut Self {
+        *self = Self::apply(Op::Scale(*self, *other));
+        self
+    }
+
+    fn mul<Src: BeaverSource<Self, Self, Self>>(self, other: Self, source: &mut Src) -> Self {
+        with_arena(|a: &mut Arena<F, S>| {
+            a.make_products_with(source);
+            a.push(Op::Mul(self, other))
+        })
+    }
+
+    fn batch_mul<Src: BeaverSource<Self, Self, Self>>(
+        xs: Vec<Self>,
+        ys: Vec<Self>,
+        source: &mut Src,
+    ) -> Vec<Self> {
+        with_arena(|a: &mut Arena<F, S>| {
+            a.make_products_with(source);
+            xs.into_iter()
+                .zip(ys)
+                .map(|(x, y)| a.push(Op::Mul(x, y)))
+                .collect()
+        })
+    }
+
+    fn inv<Src: BeaverSource<Self, Self, Self>>(self, source: &mut Src) -> Self {
+        Self::Done(self.value().inv(&mut Forward(source.clone(), PhantomData)))
+    }
+
+    fn batch_inv<Src: BeaverSource<Self, Self, Self>>(
+        xs: Vec<Self>,
+        source: &mut Src,
+    ) -> Vec<Self> {
+        S::batch_inv(Self::values(xs), &mut Forward(source.clone(), PhantomData))
+            .into_iter()
+            .map(Self::Done)
+            .collect()
+    }
+
+    fn batch_bit_decompose<Src: BeaverSource<Self, Self, Self>>(
+        xs: Vec<Self>,
+        source: &mut Src,
+    ) -> Vec<Vec<Self>>
+    where
+        F: PrimeField,
+    {
+        S::batch_bit_decompose(Self::values(xs), &mut Forward(source.clone(), PhantomData))
+            .into_iter()
+            .map(|bits| bits.into_iter().map(Self::Done).collect())
+            .collect()
+    }
+
+    fn partial_products<Src: BeaverSource<Self, Self, Self>>(
+        x: Vec<Self>,
+        source: &mut Src,
+    ) -> Vec<Self> {
+        S::partial_products(Self::values(x), &mut Forward(source.clone(), PhantomData))
+            .into_iter()
+            .map(Self::Done)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::preprocessing::{FieldPreprocessing, PreprocessingCounts};
+    use crate::share::spdz::SpdzFieldShare;
+    use ark_bls12_377::Fr;
+    use ark_std::test_rng;
+    use mpc_net::MpcNet;
+
+    type S = SpdzFieldShare<Fr>;
+    type MpcFr = MpcField<Fr, S>;
+
+    #[test]
+    fn products_share_rounds() {
+        Net::simulate(3, || {
+            MpcFr::init_protocol();
+            preprocessing::use_dummy();
+            let rng = &mut test_rng();
+            let xs: Vec<Fr> = (0..8).map(|_| Fr::rand(rng)).collect();
+            let shared = MpcFr::king_share_batch(xs.clone(), rng);
+            let rounds = |f: &dyn Fn()| {
+                let before = Net::stats().rounds;
+                f();
+                Net::stats().rounds - before
+            };
+            let lazy_xs: Vec<_> = shared.iter().map(|x| defer(*x)).collect();
+            let product = lazy_xs[0] * lazy_xs[1];
+            let one_product = rounds(&|| evaluate::<Fr, S>());
+            assert!(one_product > 0);
+            assert_eq!(force(product).reveal(), xs[0] * xs[1]);
+
+            // Four independent products, then the square of their sum: two layers.
+            let sum = lazy_xs
+                .chunks(2)
+                .map(|pair| pair[0] * pair[1])
+                .fold(defer(MpcFr::from_public(Fr::from(3u64))), |acc, p| acc + p);
+            let square = sum.square();
+            let evaluating = rounds(&|| evaluate::<Fr, S>());
+            assert_eq!(evaluating, 2 * one_product);
+
+            let expected = xs.chunks(2).map(|pair| pair[0] * pair[1]).sum::<Fr>() + Fr::from(3u64);
+            assert_eq!(force(square).reveal(), expected.square());
+            clear::<Fr, S>();
+            MpcFr::deinit_protocol();
+        });
+    }
+
+    #[test]
+    fn shares_draw_from_the_installed_store() {
+        let counts = PreprocessingCounts {
+            triples: 8,
+            inv_pairs: 2,
+            rands: 0,
+            inputs: 4,
+        };
+        let stores = FieldPreprocessing::<Fr, S>::deal(counts, 3, &mut test_rng());
+        Net::simulate(3, move || {
+            MpcFr::init_protocol();
+            preprocessing::install(stores[Net::party_id()].clone()).unwrap();
+            let rng = &mut test_rng();
+            let xs: Vec<Fr> = (0..4).map(|_| Fr::rand(rng)).collect();
+            let shared = MpcFr::king_share_batch(xs.clone(), rng);
+            adopt_preprocessing::<Fr, S>();
+            let lazy_xs: Vec<_> = shared.iter().map(|x| defer(*x)).collect();
+            // An inverse takes an inverse pair and a triple; the products take three triples.
+            let inverse = lazy_xs[0].inverse().unwrap();
+            let product = (lazy_xs[0] * lazy_xs[1]) * (lazy_xs[2] * lazy_xs[3]);
+            evaluate::<Fr, S>();
+            let used = preprocessing::used::<Fr, LazyFieldShare<Fr, S>>().unwrap();
+            assert_eq!((used.triples, used.inv_pairs), (4, 1));
+            restore_preprocessing::<Fr, S>();
+            assert_eq!(preprocessing::used::<Fr, S>(), Some(used));
+
+            assert_eq!(force(inverse).reveal(), xs[0].inverse().unwrap());
+            assert_eq!(force(product).reveal(), xs.iter().product::<Fr>());
+            clear::<Fr, S>();
+            preprocessing::uninstall::<Fr, S>();
+            MpcFr::deinit_protocol();
+        });
+    }
+}
@@ -1,3 +1,5 @@
+pub mod bits;
+pub mod encoding;
 pub mod field;
 pub use field::*;
 pub mod group;
@@ -13,11 +15,13 @@ pub mod gsz20;
 pub use gsz20::*;
 pub mod rss3;
 pub use rss3::*;
+pub mod rss;
+pub mod lazy;
 
 use std::marker::PhantomData;
 use derivative::Derivative;
 
-pub trait BeaverSource<A, B, C>: Clone {
+pub trait BeaverSource<A, B, C>: Clone + Send + 'static {
     fn triple(&mut self) -> (A, B, C);
     fn triples(&mut self, n: usize) -> (Vec<A>, Vec<B>, Vec<C>) {
         let mut xs = Vec::new();
@@ -52,7 +56,9 @@ pub struct PanicBeaverSource<A, B, C>(PhantomData<(A, B, C)>);
 pub type PanicFieldTripleSource<F> = PanicBeaverSource<F, F, F>;
 pub type PanicGroupTripleSource<F, G> = PanicBeaverSource<G, F, G>;
 
-impl<A, B, C> BeaverSource<A, B, C> for PanicBeaverSource<A, B, C> {
+impl<A: Send + 'static, B: Send + 'static, C: Send + 'static> BeaverSource<A, B, C>
+    for PanicBeaverSource<A, B, C>
+{
     fn triple(&mut self) -> (A, B, C) {
         panic!("PanicBeaverSource")
     }
@@ -60,4 +60,37 @@ pub trait PairingShare<E: PairingEngine>:
         AffineShare = Self::G2AffineShare,
         ProjectiveShare = Self::G2ProjectiveShare,
     >;
+
+    /// Our share of the Miller loop of a shared G1 point and a public G2 point.
+    ///
+    /// Each party runs the loop on its own share. The results only share the right value after
+    /// [`PairingShare::final_exp_sh`], so they must not be opened before then.
+    fn miller_loop_sh_pub(a: &Self::G1AffineShare, b: &E::G2Prepared) -> FqkExtShare<E, Self>;
+    /// As [`PairingShare::miller_loop_sh_pub`], for a public G1 point and a shared G2 point.
+    fn miller_loop_pub_sh(a: &E::G1Prepared, b: &Self::G2AffineShare) -> FqkExtShare<E, Self>;
+    /// Raise a shared Miller loop output to the final exponent.
+    fn final_exp_sh(f: FqkExtShare<E, Self>) -> FqkExtShare<E, Self>;
+    /// Our share of `base^e`, for a shared exponent `e`.
+    fn pow_pub_base(base: &E::Fqk, e: &Self::FrShare) -> FqkExtShare<E, Self>;
+}
+
+/// The share type of target group elements under `PS`.
+pub type FqkExtShare<E, PS> =
+    <<PS as PairingShare<E>>::FqkShare as ExtFieldShare<<E as PairingEngine>::Fqk>>::Ext;
+
+/// The Miller loop of a single pair.
+pub fn miller_loop_one<E: PairingEngine>(a: E::G1Prepared, b: E::G2Prepared) -> E::Fqk {
+    E::miller_loop(&[(a, b)])
+}
+
+/// Run `f` as each of `n_parties` simulated parties, sharing with `PS`, with dummy preprocessing.
+#[cfg(test)]
+pub(crate) fn simulate<E: PairingEngine, PS: PairingShare<E>>(n_parties: usize, f: fn()) {
+    use crate::Reveal;
+    mpc_net::MpcMultiNet::simulate(n_parties, move || {
+        PS::FrShare::init_protocol();
+        crate::preprocessing::use_dummy();
+        f();
+        PS::FrShare::deinit_protocol();
+    });
 }
@@ -0,0 +1,6 @@
+//! Replicated secret sharing among `n` parties, with threshold `t < n / 2`: each party holds the
+//! shares of the sets of `t` parties that it is not in. For three parties, see
+//! [`rss3`](super::rss3), which is specialized to them.
+pub mod prss;
+pub mod share;
+pub use share::*;
@@ -0,0 +1,318 @@
+//! The share layout and pseudo-random secret sharing (PRSS) for replicated sharing among `n`
+//! parties, with threshold `t < n / 2`.
+//!
+//! A value is the sum of one share for each set of `t` parties, and each share is held by the
+//! `n - t` parties outside its set. So any `t` parties miss the share of their own set, and any
+//! `t + 1` parties hold every share between them.
+//!
+//! At setup, the first holder of each set samples a PRG seed for it and sends it to the other
+//! holders. All holders of a set draw from its PRG in lockstep, so from then on the parties get
+//! correlated randomness without talking:
+//!
+//! * a random sharing: each share is the next output of its set's PRG;
+//! * a resharing of values `z_i` that sum to `z`, as in a multiplication: party `i` splits `z_i`
+//!   into one part for each set it holds. Every part but one comes from the set's PRG, so the
+//!   other holders can draw it too; the last part makes the sum right, and party `i` sends it to
+//!   the other holders of that set, which are the next `n - t - 1` parties around the ring.
+//!
+//! Every party must draw the same amount of randomness, in the same order.
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use ark_ff::{UniformRand, Zero};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+
+use std::ops::{Add, Sub};
+use std::sync::Arc;
+
+use crate::channel::MpcSerNet;
+use mpc_net::{MpcMultiNet as Net, MpcNet, MpcNetError, OrAbort};
+
+/// The most shares a party can hold. A party holds `C(n - 1, t)` shares, which is 20 for seven
+/// parties and threshold three.
+pub const MAX_SHARES: usize = 20;
+
+/// Which shares each party holds, for `n` parties and threshold `t`.
+///
+/// The shares a party holds are its slots, in the order of their sets.
+#[derive(Debug)]
+pub struct Layout {
+    pub n: usize,
+    pub t: usize,
+    pub id: usize,
+    /// Every set of `t` parties, in lexicographic order.
+    pub sets: Vec<Vec<usize>>,
+    /// The slot of each set at each party, if the party holds it: `slot_at[party][set]`.
+    pub slot_at: Vec<Vec<Option<usize>>>,
+    /// The set of each of our slots.
+    pub held: Vec<usize>,
+    /// Pairs of our slots whose products are our part of a product of two shared values. Each
+    /// pair of sets is multiplied by the first party to hold both.
+    pub products: Vec<(usize, usize)>,
+    /// Our slots whose sets we are the first holder of, which make up our additive share.
+    pub owned: Vec<usize>,
+    /// The slot that we reshare through: that of the set held by us and the next `n - t - 1`
+    /// parties.
+    pub out_slot: usize,
+    /// The slots that each of the previous `n - t - 1` parties reshares through, nearest first.
+    pub in_slots: Vec<usize>,
+    /// For each slot, how many parties draw from its PRG in a resharing, and our turn among them.
+    pub draws: Vec<(usize, Option<usize>)>,
+}
+
+/// The sets of `k` parties from `from..n`, in lexicographic order.
+fn subsets(from: usize, n: usize, k: usize) -> Vec<Vec<usize>> {
+    if k == 0 {
+        return vec![vec![]];
+    }
+    (from..n)
+        .flat_map(|first| {
+            subsets(first + 1, n, k - 1).into_iter().map(move |mut rest| {
+                rest.insert(0, first);
+                rest
+            })
+        })
+        .collect()
+}
+
+impl Layout {
+    pub fn new(n: usize, t: usize, id: usize) -> Self {
+        assert!(
+            t >= 1 && 2 * t < n,
+            "RSS needs a threshold of at least 1 and below n / 2, not {} for {} parties",
+            t,
+            n
+        );
+        let sets = subsets(0, n, t);
+        let holds = |party: usize, set: usize| !sets[set].contains(&party);
+        let slot_at: Vec<Vec<Option<usize>>> = (0..n)
+            .map(|party| {
+                let mut next = 0;
+                (0..sets.len())
+                    .map(|set| {
+                        if holds(party, set) {
+                            next += 1;
+                            Some(next - 1)
+                        } else {
+                            None
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        let held: Vec<usize> = (0..sets.len()).filter(|set| holds(id, *set)).collect();
+        assert!(
+            held.len() <= MAX_SHARES,
+            "RSS with {} parties and threshold {} needs {} shares per party, more than {}",
+            n,
+            t,
+            held.len(),
+            MAX_SHARES
+        );
+        let first_holder = |set: usize| (0..n).find(|p| holds(*p, set)).unwrap();
+        let mut products = Vec::new();
+        for a in 0..sets.len() {
+            for b in 0..sets.len() {
+                let first = (0..n).find(|p| holds(*p, a) && holds(*p, b)).unwrap();
+                if first == id {
+                    products.push((slot_at[id][a].unwrap(), slot_at[id][b].unwrap()));
+                }
+            }
+        }
+        let owned = held
+            .iter()
+            .enumerate()
+            .filter(|(_, set)| first_holder(**set) == id)
+            .map(|(slot, _)| slot)
+            .collect();
+        // The set that party `i` reshares through: everyone but `i` and the next `n - t - 1`.
+        let reshare_set = |i: usize| {
+            let mut set: Vec<usize> = (n - t..n).map(|h| (i + h) % n).collect();
+            set.sort();
+            sets.iter().position(|s| *s == set).unwrap()
+        };
+        let out_slot = slot_at[id][reshare_set(id)].unwrap();
+        let in_slots = (1..n - t)
+            .map(|h| slot_at[id][reshare_set((id + n - h) % n)].unwrap())
+            .collect();
+        let draws = held
+            .iter()
+            .map(|set| {
+                let drawers: Vec<usize> = (0..n)
+                    .filter(|i| holds(*i, *set) && reshare_set(*i) != *set)
+                    .collect();
+                (drawers.len(), drawers.iter().position(|i| *i == id))
+            })
+            .collect();
+        Self {
+            n,
+            t,
+            id,
+            sets,
+            slot_at,
+            held,
+            products,
+            owned,
+            out_slot,
+            in_slots,
+            draws,
+        }
+    }
+
+    /// How many shares we hold.
+    pub fn slots(&self) -> usize {
+        self.held.len()
+    }
+
+    /// Our slot for the first set, `{0, .., t - 1}`, if we hold it. Public values are added to
+    /// this share.
+    pub fn first_slot(&self) -> Option<usize> {
+        self.slot_at[self.id][0]
+    }
+}
+
+/// The threshold for this session's RSS.
+struct Threshold(usize);
+
+/// This session's layout.
+struct LayoutState(Arc<Layout>);
+
+/// Use threshold `t` for RSS in this session, instead of the largest one possible,
+/// `(n - 1) / 2`. Call this before any RSS operation.
+pub fn set_threshold(t: usize) {
+    Net::with_state(|| Threshold(t), |threshold| threshold.0 = t);
+    deinit_prss();
+}
+
+/// This session's layout.
+pub fn layout() -> Arc<Layout> {
+    Net::with_state(
+        || {
+            let n = Net::n_parties();
+            let t = Net::with_state(|| Threshold((n - 1) / 2), |threshold| threshold.0);
+            LayoutState(Arc::new(Layout::new(n, t, Net::party_id())))
+        },
+        |layout| layout.0.clone(),
+    )
+}
+
+struct Prss {
+    /// The PRG for the set of each of our slots.
+    prgs: Vec<StdRng>,
+}
+
+fn setup() -> Prss {
+    let layout = layout();
+    type Seed = <StdRng as SeedableRng>::Seed;
+    let mut seeds: Vec<Option<Seed>> = vec![None; layout.slots()];
+    let mut out: Vec<Vec<Vec<u8>>> = vec![Vec::new(); layout.n];
+    for &slot in &layout.owned {
+        let mut seed = Seed::default();
+        rand::thread_rng().fill(&mut seed);
+        let set = layout.held[slot];
+        for party in (0..layout.n).filter(|p| *p != layout.id) {
+            if layout.slot_at[party][set].is_some() {
+                out[party].push(seed.to_vec());
+            }
+        }
+        seeds[slot] = Some(seed);
+    }
+    let bytes_in: Vec<Vec<Vec<u8>>> = Net::all_to_all(&out).or_abort();
+    for (party, bytes_in) in bytes_in.into_iter().enumerate() {
+        if party == layout.id {
+            continue;
+        }
+        // The sets that `party` holds first and we hold too, in order.
+        let from_party = layout.held.iter().enumerate().filter(|(_, set)| {
+            (0..layout.n).find(|p| layout.slot_at[*p][**set].is_some()) == Some(party)
+        });
+        let mut bytes_in = bytes_in.into_iter();
+        for (slot, _) in from_party {
+            let seed = match bytes_in.next() {
+                Some(bytes) if bytes.len() == Seed::default().len() => bytes,
+                _ => MpcNetError::Malformed { party }.abort(),
+            };
+            let mut s = Seed::default();
+            s.copy_from_slice(&seed);
+            seeds[slot] = Some(s);
+        }
+    }
+    Prss {
+        prgs: seeds
+            .into_iter()
+            .map(|seed| StdRng::from_seed(seed.unwrap()))
+            .collect(),
+    }
+}
+
+/// The current session's PRSS, set up on first use.
+fn with_prss<R>(f: impl FnOnce(&mut Prss) -> R) -> R {
+    Net::with_state(setup, f)
+}
+
+/// Exchange the PRSS seeds, if they have not been exchanged yet.
+pub fn init_prss() {
+    with_prss(|_| ());
+}
+
+/// Forget the PRSS seeds and the layout.
+pub fn deinit_prss() {
+    Net::take_state::<Prss>();
+    Net::take_state::<LayoutState>();
+}
+
+/// Our shares of `n` random sharings, one per slot.
+pub fn rand_shares<T: UniformRand>(n: usize) -> Vec<Vec<T>> {
+    with_prss(|p| {
+        (0..n)
+            .map(|_| p.prgs.iter_mut().map(|prg| T::rand(prg)).collect())
+            .collect()
+    })
+}
+
+/// Turn additive shares `zs` (ours of each value) into our shares of a replicated sharing of
+/// each value.
+pub fn reshare<T>(zs: Vec<T>) -> Vec<Vec<T>>
+where
+    T: UniformRand
+        + Zero
+        + Copy
+        + Add<Output = T>
+        + Sub<Output = T>
+        + CanonicalSerialize
+        + CanonicalDeserialize,
+{
+    let layout = layout();
+    let (mut shares, outs): (Vec<Vec<T>>, Vec<T>) = with_prss(|p| {
+        zs.into_iter()
+            .map(|z| {
+                let mut share = vec![T::zero(); layout.slots()];
+                let mut out = z;
+                for (slot, prg) in p.prgs.iter_mut().enumerate() {
+                    let (draws, ours) = layout.draws[slot];
+                    for turn in 0..draws {
+                        let r = T::rand(prg);
+                        share[slot] = share[slot] + r;
+                        if ours == Some(turn) {
+                            out = out - r;
+                        }
+                    }
+                }
+                share[layout.out_slot] = share[layout.out_slot] + out;
+                (share, out)
+            })
+            .unzip()
+    });
+    let ins: Vec<Vec<T>> = Net::pass_around(&outs, layout.n - layout.t - 1).or_abort();
+    for (h, ins) in ins.into_iter().enumerate() {
+        if ins.len() != shares.len() {
+            let party = (layout.id + layout.n - h - 1) % layout.n;
+            MpcNetError::Malformed { party }.abort();
+        }
+        let slot = layout.in_slots[h];
+        for (share, d) in shares.iter_mut().zip(ins) {
+            share[slot] = share[slot] + d;
+        }
+    }
+    shares
+}
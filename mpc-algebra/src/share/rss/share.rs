@@ -0,0 +1,999 @@
+use derivative::Derivative;
+use rand::Rng;
+
+use ark_ec::group::Group;
+use ark_ec::{PairingEngine, ProjectiveCurve};
+use ark_ff::bytes::{FromBytes, ToBytes};
+use ark_ff::prelude::*;
+use ark_poly::UVPolynomial;
+use ark_serialize::{
+    CanonicalDeserialize, CanonicalDeserializeWithFlags, CanonicalSerialize,
+    CanonicalSerializeWithFlags, Flags, SerializationError,
+};
+
+use std::borrow::Cow;
+use std::cmp::Ord;
+use std::fmt::{self, Debug, Display, Formatter};
+use std::hash::Hash;
+use std::io::{self, Read, Write};
+use std::marker::PhantomData;
+use std::ops::Sub;
+
+use crate::channel::MpcSerNet;
+use mpc_net::{MpcMultiNet as Net, MpcNet, MpcNetError, OrAbort};
+
+use super::prss::{self, Layout, MAX_SHARES};
+use crate::client::{self, ClientShare};
+use crate::msm::*;
+use crate::share::field::{
+    DenseOrSparsePolynomial, DensePolynomial, ExtFieldShare, FieldShare,
+};
+use crate::share::group::GroupShare;
+use crate::share::pairing::{miller_loop_one, AffProjShare, PairingShare};
+use crate::share::{AdditiveFieldShare, AdditiveGroupShare, BeaverSource, ShareConversion};
+use crate::Reveal;
+
+/// A replicated share among `n` parties: our share of each set of `t` parties that we are not in
+/// (see [`prss`]).
+#[derive(Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct RssFieldShare<T> {
+    /// How many of `vals` we hold.
+    pub len: usize,
+    /// Our shares, in the order of their sets, then unused space.
+    pub vals: [T; MAX_SHARES],
+}
+
+impl<F: Field> RssFieldShare<F> {
+    fn zero() -> Self {
+        Self::from_slots(Vec::new())
+    }
+
+    /// The share with slots `slots`, padded to this session's number of slots.
+    fn from_slots(slots: Vec<F>) -> Self {
+        let mut vals = [F::zero(); MAX_SHARES];
+        vals[..slots.len()].copy_from_slice(&slots);
+        Self {
+            len: prss::layout().slots(),
+            vals,
+        }
+    }
+
+    fn slots(&self) -> &[F] {
+        &self.vals[..self.len]
+    }
+
+    /// The polynomial made of the `k`th slot of each coefficient.
+    fn poly_slot<'a>(
+        p: &DenseOrSparsePolynomial<Self>,
+        k: usize,
+    ) -> ark_poly::univariate::DenseOrSparsePolynomial<'a, F> {
+        match p {
+            Ok(p) => ark_poly::univariate::DenseOrSparsePolynomial::DPolynomial(Cow::Owned(
+                ark_poly::univariate::DensePolynomial::from_coefficients_vec(
+                    p.iter().map(|s| s.vals[k]).collect(),
+                ),
+            )),
+            Err(p) => ark_poly::univariate::DenseOrSparsePolynomial::SPolynomial(Cow::Owned(
+                ark_poly::univariate::SparsePolynomial::from_coefficients_vec(
+                    p.iter().map(|(i, s)| (*i, s.vals[k])).collect(),
+                ),
+            )),
+        }
+    }
+
+    fn poly_public<'a>(
+        p: DenseOrSparsePolynomial<F>,
+    ) -> ark_poly::univariate::DenseOrSparsePolynomial<'a, F> {
+        match p {
+            Ok(p) => ark_poly::univariate::DenseOrSparsePolynomial::DPolynomial(Cow::Owned(
+                ark_poly::univariate::DensePolynomial::from_coefficients_vec(p),
+            )),
+            Err(p) => ark_poly::univariate::DenseOrSparsePolynomial::SPolynomial(Cow::Owned(
+                ark_poly::univariate::SparsePolynomial::from_coefficients_vec(p),
+            )),
+        }
+    }
+
+    /// The polynomial whose coefficients have slot `k` from `p[k]`.
+    fn poly_unslot(p: Vec<ark_poly::univariate::DensePolynomial<F>>) -> DensePolynomial<Self> {
+        let len = p.iter().map(|p| p.coeffs.len()).max().unwrap_or(0);
+        (0..len)
+            .map(|i| {
+                Self::from_slots(
+                    p.iter()
+                        .map(|p| p.coeffs.get(i).cloned().unwrap_or_else(F::zero))
+                        .collect(),
+                )
+            })
+            .collect()
+    }
+}
+
+/// Broadcast our slots of some values, and put each value back together from one copy of each
+/// set's share, with `op`. Fails if the holders of a set disagree on its share.
+fn open_slots<T>(
+    ours: Vec<Vec<T>>,
+    init: T,
+    op: impl Fn(T, T) -> T,
+) -> Result<Vec<T>, MpcNetError>
+where
+    T: Copy + PartialEq + CanonicalSerialize + CanonicalDeserialize,
+{
+    let len = ours.len();
+    let all: Vec<Vec<Vec<T>>> = Net::broadcast(&ours)?;
+    combine_slots(all, len, init, op)
+}
+
+/// Like [`open_slots`], but only party `to` gets the values.
+fn open_slots_to<T>(ours: Vec<Vec<T>>, to: usize, init: T, op: impl Fn(T, T) -> T) -> Option<Vec<T>>
+where
+    T: Copy + PartialEq + CanonicalSerialize + CanonicalDeserialize,
+{
+    let len = ours.len();
+    let all: Vec<Vec<Vec<T>>> = Net::send_to(to, &ours).or_abort()?;
+    Some(combine_slots(all, len, init, op).or_abort())
+}
+
+/// Put `len` values back together from every party's slots of them, `all`.
+fn combine_slots<T: Copy + PartialEq>(
+    all: Vec<Vec<Vec<T>>>,
+    len: usize,
+    init: T,
+    op: impl Fn(T, T) -> T,
+) -> Result<Vec<T>, MpcNetError> {
+    let layout = prss::layout();
+    for (party, theirs) in all.iter().enumerate() {
+        if theirs.len() != len || theirs.iter().any(|s| s.len() != layout.slots()) {
+            return Err(MpcNetError::Malformed { party });
+        }
+    }
+    (0..len)
+        .map(|i| {
+            (0..layout.sets.len()).try_fold(init, |acc, set| {
+                let mut copies = (0..layout.n).filter_map(|party| {
+                    layout.slot_at[party][set].map(|slot| all[party][i][slot])
+                });
+                let share = copies.next().unwrap();
+                if copies.any(|copy| copy != share) {
+                    return Err(MpcNetError::InconsistentShares);
+                }
+                Ok(op(acc, share))
+            })
+        })
+        .collect()
+}
+
+/// Draws a PRSS random sharing for each of `f` (which only the king needs to know), and has the
+/// king send `x` less the shares it holds, for each `x`, to the other parties. That goes in the
+/// first set's share, and the other sets with the king in them get zero.
+///
+/// Returns our slots of each sharing.
+fn king_share_slots<T>(f: Vec<T>) -> Vec<Vec<T>>
+where
+    T: UniformRand + Zero + Copy + Sub<Output = T> + CanonicalSerialize + CanonicalDeserialize,
+{
+    let layout = prss::layout();
+    let king_rands: Option<Vec<Vec<T>>> = if Net::am_king() {
+        Some(prss::rand_shares(f.len()))
+    } else {
+        None
+    };
+    let diffs: Vec<T> = Net::recv_from_king(king_rands.as_ref().map(|rands| {
+        let diffs: Vec<T> = f
+            .into_iter()
+            .zip(rands)
+            .map(|(x, r)| r.iter().fold(x, |x, r| x - *r))
+            .collect();
+        vec![diffs; layout.n]
+    }))
+    .or_abort();
+    let rands = king_rands.unwrap_or_else(|| prss::rand_shares(diffs.len()));
+    rands
+        .into_iter()
+        .zip(diffs)
+        .map(|(mut r, d)| {
+            for (slot, set) in layout.held.iter().enumerate() {
+                if *set == 0 {
+                    r[slot] = d;
+                } else if layout.sets[*set].contains(&0) {
+                    r[slot] = T::zero();
+                }
+            }
+            r
+        })
+        .collect()
+}
+
+impl<F: Field> ShareConversion for RssFieldShare<F> {
+    type Target = AdditiveFieldShare<F>;
+
+    fn share_conversion(self) -> Self::Target {
+        let layout = prss::layout();
+        AdditiveFieldShare {
+            val: layout.owned.iter().map(|slot| self.vals[*slot]).sum(),
+        }
+    }
+}
+
+impl<F: Field> Reveal for RssFieldShare<F> {
+    type Base = F;
+
+    fn try_reveal(self) -> Result<F, MpcNetError> {
+        Ok(open_slots(vec![self.slots().to_vec()], F::zero(), |a, b| a + b)?.pop().unwrap())
+    }
+
+    fn reveal_to(self, to: usize) -> Option<F> {
+        open_slots_to(vec![self.slots().to_vec()], to, F::zero(), |a, b| a + b)?.pop()
+    }
+
+    /// Reshare `f` as a product would be.
+    fn from_add_shared(f: Self::Base) -> Self {
+        Self::from_slots(prss::reshare(vec![f]).pop().unwrap())
+    }
+
+    fn from_public(f: F) -> Self {
+        let mut s = Self::zero();
+        if let Some(slot) = prss::layout().first_slot() {
+            s.vals[slot] = f;
+        }
+        s
+    }
+
+    fn unwrap_as_public(self) -> F {
+        unimplemented!()
+    }
+
+    fn king_share<R: Rng>(f: Self::Base, rng: &mut R) -> Self {
+        Self::king_share_batch(vec![f], rng).pop().unwrap()
+    }
+
+    fn king_share_batch<R: Rng>(f: Vec<Self::Base>, _rng: &mut R) -> Vec<Self> {
+        king_share_slots(f).into_iter().map(Self::from_slots).collect()
+    }
+
+    fn init_protocol() {
+        prss::init_prss()
+    }
+
+    fn deinit_protocol() {
+        prss::deinit_prss()
+    }
+}
+
+/// The client splits each value into one share per set, and sends each party the shares of the
+/// sets it holds, in order.
+impl<F: Field> ClientShare for RssFieldShare<F> {
+    type Dealt = Vec<F>;
+
+    fn deal<R: Rng>(
+        values: &[F],
+        n: usize,
+        threshold: Option<usize>,
+        rng: &mut R,
+    ) -> Vec<Vec<Vec<F>>> {
+        let layouts: Vec<Layout> = (0..n)
+            .map(|id| Layout::new(n, threshold.unwrap_or((n - 1) / 2), id))
+            .collect();
+        let by_set = client::add_split(values, layouts[0].sets.len(), rng);
+        layouts
+            .iter()
+            .map(|layout| {
+                (0..values.len())
+                    .map(|i| layout.held.iter().map(|set| by_set[*set][i]).collect())
+                    .collect()
+            })
+            .collect()
+    }
+    fn from_dealt(dealt: Vec<Vec<F>>) -> Result<Vec<Self>, MpcNetError> {
+        let slots = prss::layout().slots();
+        dealt
+            .into_iter()
+            .map(|d| {
+                if d.len() == slots {
+                    Ok(Self::from_slots(d))
+                } else {
+                    Err(MpcNetError::Client {
+                        reason: format!("sent {} shares of a value, not {}", d.len(), slots),
+                    })
+                }
+            })
+            .collect()
+    }
+
+    /// The threshold, which decides who holds which shares.
+    type Keys = usize;
+
+    fn output_keys() -> usize {
+        prss::layout().t
+    }
+    fn use_output_keys(t: usize) {
+        prss::set_threshold(t)
+    }
+}
+
+impl<F: Field> FieldShare<F> for RssFieldShare<F> {
+    const USES_TRIPLES: bool = false;
+
+    fn mul<S: BeaverSource<Self, Self, Self>>(self, other: Self, source: &mut S) -> Self {
+        Self::batch_mul(vec![self], vec![other], source).pop().unwrap()
+    }
+
+    /// Each party adds up the products of the pairs of sets it multiplies, and the sums are
+    /// reshared.
+    fn batch_mul<S: BeaverSource<Self, Self, Self>>(
+        xs: Vec<Self>,
+        ys: Vec<Self>,
+        _source: &mut S,
+    ) -> Vec<Self> {
+        let layout = prss::layout();
+        let zs: Vec<F> = xs
+            .iter()
+            .zip(ys.iter())
+            .map(|(x, y)| {
+                layout
+                    .products
+                    .iter()
+                    .map(|(a, b)| x.vals[*a] * y.vals[*b])
+                    .sum()
+            })
+            .collect();
+        prss::reshare(zs).into_iter().map(Self::from_slots).collect()
+    }
+
+    fn batch_open(selfs: impl IntoIterator<Item = Self>) -> Vec<F> {
+        let ours = selfs.into_iter().map(|s| s.slots().to_vec()).collect();
+        open_slots(ours, F::zero(), |a, b| a + b).or_abort()
+    }
+
+    fn add(&mut self, other: &Self) -> &mut Self {
+        for (a, b) in self.vals.iter_mut().zip(other.slots()) {
+            *a += b;
+        }
+        self
+    }
+
+    fn sub(&mut self, other: &Self) -> &mut Self {
+        for (a, b) in self.vals.iter_mut().zip(other.slots()) {
+            *a -= b;
+        }
+        self
+    }
+
+    fn scale(&mut self, other: &F) -> &mut Self {
+        for a in self.vals[..self.len].iter_mut() {
+            *a *= other;
+        }
+        self
+    }
+
+    fn shift(&mut self, other: &F) -> &mut Self {
+        if let Some(slot) = prss::layout().first_slot() {
+            self.vals[slot] += other;
+        }
+        self
+    }
+
+    fn univariate_div_qr<'a>(
+        num: DenseOrSparsePolynomial<Self>,
+        den: DenseOrSparsePolynomial<F>,
+    ) -> Option<(DensePolynomial<Self>, DensePolynomial<Self>)> {
+        let den = Self::poly_public(den);
+        let (q_polys, r_polys): (Vec<_>, Vec<_>) = (0..prss::layout().slots())
+            .map(|k| Self::poly_slot(&num, k).divide_with_q_and_r(&den).unwrap())
+            .unzip();
+        Some((Self::poly_unslot(q_polys), Self::poly_unslot(r_polys)))
+    }
+}
+
+#[derive(Derivative)]
+#[derivative(
+    Clone(bound = "T: Clone"),
+    Copy(bound = "T: Copy"),
+    PartialEq(bound = "T: PartialEq"),
+    Eq(bound = "T: Eq"),
+    PartialOrd(bound = "T: PartialOrd"),
+    Ord(bound = "T: Ord"),
+    Hash(bound = "T: Hash")
+)]
+pub struct RssGroupShare<T, M> {
+    /// How many of `vals` we hold.
+    pub len: usize,
+    /// Our shares, in the order of their sets, then unused space.
+    pub vals: [T; MAX_SHARES],
+    _phants: PhantomData<M>,
+}
+
+impl<G: Group, M> RssGroupShare<G, M> {
+    fn zero() -> Self {
+        Self::from_slots(Vec::new())
+    }
+
+    /// The share with slots `slots`, padded to this session's number of slots.
+    fn from_slots(slots: Vec<G>) -> Self {
+        let mut vals = [G::zero(); MAX_SHARES];
+        vals[..slots.len()].copy_from_slice(&slots);
+        Self {
+            len: prss::layout().slots(),
+            vals,
+            _phants: PhantomData::default(),
+        }
+    }
+
+    fn slots(&self) -> &[G] {
+        &self.vals[..self.len]
+    }
+}
+
+impl<G: Group, M> ShareConversion for RssGroupShare<G, M> {
+    type Target = AdditiveGroupShare<G, M>;
+
+    fn share_conversion(self) -> Self::Target {
+        let layout = prss::layout();
+        AdditiveGroupShare {
+            val: layout
+                .owned
+                .iter()
+                .fold(G::zero(), |acc, slot| acc + self.vals[*slot]),
+            _phants: PhantomData::default(),
+        }
+    }
+}
+
+impl<G: Group, M> Reveal for RssGroupShare<G, M> {
+    type Base = G;
+
+    fn try_reveal(self) -> Result<G, MpcNetError> {
+        let ours = vec![self.slots().to_vec()];
+        Ok(open_slots(ours, G::zero(), |a, b| a + b)?.pop().unwrap())
+    }
+
+    fn reveal_to(self, to: usize) -> Option<G> {
+        let ours = vec![self.slots().to_vec()];
+        open_slots_to(ours, to, G::zero(), |a, b| a + b)?.pop()
+    }
+
+    fn from_public(f: G) -> Self {
+        let mut s = Self::zero();
+        if let Some(slot) = prss::layout().first_slot() {
+            s.vals[slot] = f;
+        }
+        s
+    }
+
+    /// Reshare `f` as a product would be.
+    fn from_add_shared(f: Self::Base) -> Self {
+        Self::from_slots(prss::reshare(vec![f]).pop().unwrap())
+    }
+
+    fn unwrap_as_public_vec(self) -> Vec<G> {
+        self.slots().to_vec()
+    }
+
+    fn from_add_shared_vec(vals: Vec<G>) -> Self {
+        Self::from_slots(vals)
+    }
+
+    fn unwrap_as_public(self) -> G {
+        unimplemented!()
+    }
+
+    fn king_share<R: Rng>(f: Self::Base, rng: &mut R) -> Self {
+        Self::king_share_batch(vec![f], rng).pop().unwrap()
+    }
+
+    fn king_share_batch<R: Rng>(f: Vec<Self::Base>, _rng: &mut R) -> Vec<Self> {
+        king_share_slots(f).into_iter().map(Self::from_slots).collect()
+    }
+
+    fn init_protocol() {
+        prss::init_prss()
+    }
+
+    fn deinit_protocol() {
+        prss::deinit_prss()
+    }
+}
+
+impl<G: Group, M: Msm<G, G::ScalarField>> GroupShare<G> for RssGroupShare<G, M> {
+    type FieldShare = RssFieldShare<G::ScalarField>;
+
+    fn map_homo<G2: Group, S2: GroupShare<G2>, Fun: Fn(G) -> G2>(self, f: Fun) -> S2 {
+        let vals = self.unwrap_as_public_vec().into_iter().map(f).collect();
+        S2::from_add_shared_vec(vals)
+    }
+
+    fn batch_open(selfs: impl IntoIterator<Item = Self>) -> Vec<G> {
+        let ours = selfs.into_iter().map(|s| s.slots().to_vec()).collect();
+        open_slots(ours, G::zero(), |a, b| a + b).or_abort()
+    }
+
+    fn add(&mut self, other: &Self) -> &mut Self {
+        for (a, b) in self.vals.iter_mut().zip(other.slots()) {
+            *a += b;
+        }
+        self
+    }
+
+    fn sub(&mut self, other: &Self) -> &mut Self {
+        for (a, b) in self.vals.iter_mut().zip(other.slots()) {
+            *a -= b;
+        }
+        self
+    }
+
+    fn scale_pub_scalar(&mut self, scalar: &G::ScalarField) -> &mut Self {
+        for a in self.vals[..self.len].iter_mut() {
+            *a *= *scalar;
+        }
+        self
+    }
+
+    fn scale_pub_group(base: G, scalar: &Self::FieldShare) -> Self {
+        Self::from_slots(
+            scalar
+                .slots()
+                .iter()
+                .map(|s| {
+                    let mut b = base;
+                    b *= *s;
+                    b
+                })
+                .collect(),
+        )
+    }
+
+    fn shift(&mut self, other: &G) -> &mut Self {
+        if let Some(slot) = prss::layout().first_slot() {
+            self.vals[slot] += other;
+        }
+        self
+    }
+
+    /// As for [`RssFieldShare::batch_mul`].
+    fn scale<S: BeaverSource<Self, Self::FieldShare, Self>>(
+        self,
+        other: Self::FieldShare,
+        _source: &mut S,
+    ) -> Self {
+        let layout = prss::layout();
+        let z = layout.products.iter().fold(G::zero(), |acc, (a, b)| {
+            let mut p = self.vals[*a];
+            p *= other.vals[*b];
+            acc + p
+        });
+        Self::from_slots(prss::reshare(vec![z]).pop().unwrap())
+    }
+
+    fn multi_scale_pub_group(bases: &[G], scalars: &[Self::FieldShare]) -> Self {
+        Self::from_slots(
+            (0..prss::layout().slots())
+                .map(|k| {
+                    let scalars: Vec<G::ScalarField> = scalars.iter().map(|s| s.vals[k]).collect();
+                    M::msm(bases, &scalars)
+                })
+                .collect(),
+        )
+    }
+}
+
+/// Encodings that write only the slots in use.
+macro_rules! impl_slot_encoding {
+    ([$($gen:tt)*], $ty:ty, $blank:ident, [$($phant:ident),*]) => {
+        impl<$($gen)*> ToBytes for $ty {
+            fn write<W: Write>(&self, writer: W) -> io::Result<()> {
+                CanonicalSerialize::serialize(self, writer)
+                    .map_err($crate::share::encoding::ser_err_to_io)
+            }
+        }
+        impl<$($gen)*> FromBytes for $ty {
+            fn read<R: Read>(reader: R) -> io::Result<Self> {
+                <Self as CanonicalDeserialize>::deserialize(reader)
+                    .map_err($crate::share::encoding::ser_err_to_io)
+            }
+        }
+        impl<$($gen)*> CanonicalSerialize for $ty {
+            fn serialize<W: Write>(&self, mut writer: W) -> Result<(), SerializationError> {
+                $crate::share::encoding::write_version(&mut writer)?;
+                CanonicalSerialize::serialize(&(self.len as u8), &mut writer)?;
+                for val in &self.vals[..self.len] {
+                    CanonicalSerialize::serialize(val, &mut writer)?;
+                }
+                Ok(())
+            }
+            fn serialized_size(&self) -> usize {
+                2 + self.vals[..self.len]
+                    .iter()
+                    .map(CanonicalSerialize::serialized_size)
+                    .sum::<usize>()
+            }
+        }
+        impl<$($gen)*> CanonicalSerializeWithFlags for $ty {
+            fn serialize_with_flags<W: Write, Fl: Flags>(
+                &self,
+                mut writer: W,
+                flags: Fl,
+            ) -> Result<(), SerializationError> {
+                CanonicalSerialize::serialize(self, &mut writer)?;
+                $crate::share::encoding::write_flags(&mut writer, flags)
+            }
+
+            fn serialized_size_with_flags<Fl: Flags>(&self) -> usize {
+                CanonicalSerialize::serialized_size(self) + 1
+            }
+        }
+        impl<$($gen)*> CanonicalDeserialize for $ty {
+            fn deserialize<R: Read>(mut reader: R) -> Result<Self, SerializationError> {
+                $crate::share::encoding::read_version(&mut reader)?;
+                let len = <u8 as CanonicalDeserialize>::deserialize(&mut reader)? as usize;
+                if len > MAX_SHARES {
+                    return Err(SerializationError::InvalidData);
+                }
+                let mut vals = [T::$blank(); MAX_SHARES];
+                for val in vals[..len].iter_mut() {
+                    *val = CanonicalDeserialize::deserialize(&mut reader)?;
+                }
+                Ok(Self {
+                    len,
+                    vals,
+                    $($phant: PhantomData::default(),)*
+                })
+            }
+        }
+        impl<$($gen)*> CanonicalDeserializeWithFlags for $ty {
+            fn deserialize_with_flags<R: Read, Fl: Flags>(
+                mut reader: R,
+            ) -> Result<(Self, Fl), SerializationError> {
+                let s = <Self as CanonicalDeserialize>::deserialize(&mut reader)?;
+                let flags = $crate::share::encoding::read_flags(&mut reader)?;
+                Ok((s, flags))
+            }
+        }
+    };
+}
+
+/// Writes the slots in use, separated by `sep`.
+fn fmt_slots<T>(
+    f: &mut Formatter<'_>,
+    vals: &[T],
+    sep: &str,
+    show: impl Fn(&T, &mut Formatter<'_>) -> fmt::Result,
+) -> fmt::Result {
+    for (i, val) in vals.iter().enumerate() {
+        if i > 0 {
+            write!(f, "{}", sep)?;
+        }
+        show(val, f)?;
+    }
+    Ok(())
+}
+
+macro_rules! impl_basics {
+    ($share:ident, $bound:ident, $blank:ident) => {
+        impl<T: $bound> Display for $share<T> {
+            fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+                fmt_slots(f, &self.vals[..self.len], ",", |v, f| write!(f, "{}", v))
+            }
+        }
+        impl<T: $bound> Debug for $share<T> {
+            fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+                fmt_slots(f, &self.vals[..self.len], ", ", |v, f| write!(f, "{:?}", v))
+            }
+        }
+        impl_slot_encoding!([T: $bound], $share<T>, $blank, []);
+        impl<T: $bound> UniformRand for $share<T> {
+            fn rand<R: Rng + ?Sized>(_rng: &mut R) -> Self {
+                Self::from_slots(prss::rand_shares::<T>(1).pop().unwrap())
+            }
+        }
+    };
+}
+macro_rules! impl_basics_2_param {
+    ($share:ident, $bound:ident) => {
+        impl<T: $bound, M> Display for $share<T, M> {
+            fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+                fmt_slots(f, &self.vals[..self.len], ", ", |v, f| write!(f, "{}", v))
+            }
+        }
+        impl<T: $bound, M> Debug for $share<T, M> {
+            fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+                fmt_slots(f, &self.vals[..self.len], ", ", |v, f| write!(f, "{:?}", v))
+            }
+        }
+        impl_slot_encoding!([T: $bound, M], $share<T, M>, zero, [_phants]);
+        impl<T: $bound, M> UniformRand for $share<T, M> {
+            fn rand<R: Rng + ?Sized>(_rng: &mut R) -> Self {
+                Self::from_slots(prss::rand_shares::<T>(1).pop().unwrap())
+            }
+        }
+    };
+}
+
+impl_basics!(RssFieldShare, Field, zero);
+impl_basics_2_param!(RssGroupShare, Group);
+
+#[derive(Debug, Derivative)]
+#[derivative(
+    Default(bound = ""),
+    Clone(bound = ""),
+    Copy(bound = ""),
+    PartialEq(bound = "F: PartialEq"),
+    Eq(bound = "F: Eq"),
+    Hash(bound = "F: Hash")
+)]
+pub struct RssExtFieldShare<F: Field>(pub PhantomData<F>);
+
+impl<F: Field> ExtFieldShare<F> for RssExtFieldShare<F> {
+    type Ext = RssFieldShare<F>;
+    type Base = RssFieldShare<F::BasePrimeField>;
+}
+
+/// A multiplicative replicated share: the value is the product of one share per set.
+#[derive(Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct RssMulFieldShare<T> {
+    /// How many of `vals` we hold.
+    pub len: usize,
+    /// Our shares, in the order of their sets, then unused space.
+    pub vals: [T; MAX_SHARES],
+}
+
+impl<F: Field> RssMulFieldShare<F> {
+    fn from_slots(slots: Vec<F>) -> Self {
+        let mut vals = [F::one(); MAX_SHARES];
+        vals[..slots.len()].copy_from_slice(&slots);
+        Self {
+            len: prss::layout().slots(),
+            vals,
+        }
+    }
+}
+
+impl<F: Field> Reveal for RssMulFieldShare<F> {
+    type Base = F;
+
+    fn try_reveal(self) -> Result<F, MpcNetError> {
+        let ours = vec![self.vals[..self.len].to_vec()];
+        Ok(open_slots(ours, F::one(), |a, b| a * b)?.pop().unwrap())
+    }
+
+    fn from_add_shared(_f: Self::Base) -> Self {
+        unimplemented!()
+    }
+
+    fn from_public(f: F) -> Self {
+        let mut s = Self::from_slots(Vec::new());
+        if let Some(slot) = prss::layout().first_slot() {
+            s.vals[slot] = f;
+        }
+        s
+    }
+
+    fn unwrap_as_public(self) -> F {
+        unimplemented!()
+    }
+}
+
+impl<F: Field> FieldShare<F> for RssMulFieldShare<F> {
+    fn map_homo<FF: Field, SS: FieldShare<FF>, Fun: Fn(F) -> FF>(self, _f: Fun) -> SS {
+        unimplemented!()
+    }
+
+    fn batch_open(selfs: impl IntoIterator<Item = Self>) -> Vec<F> {
+        let ours = selfs
+            .into_iter()
+            .map(|s| s.vals[..s.len].to_vec())
+            .collect();
+        open_slots(ours, F::one(), |a, b| a * b).or_abort()
+    }
+
+    fn add(&mut self, _other: &Self) -> &mut Self {
+        unimplemented!("add for RssMulFieldShare")
+    }
+
+    fn scale(&mut self, other: &F) -> &mut Self {
+        if let Some(slot) = prss::layout().first_slot() {
+            self.vals[slot] *= other;
+        }
+        self
+    }
+
+    fn shift(&mut self, _other: &F) -> &mut Self {
+        unimplemented!("shift for RssMulFieldShare")
+    }
+
+    fn mul<S: BeaverSource<Self, Self, Self>>(mut self, other: Self, _source: &mut S) -> Self {
+        for (a, b) in self.vals.iter_mut().zip(other.vals.iter()) {
+            *a *= b;
+        }
+        self
+    }
+
+    fn batch_mul<S: BeaverSource<Self, Self, Self>>(
+        xs: Vec<Self>,
+        ys: Vec<Self>,
+        source: &mut S,
+    ) -> Vec<Self> {
+        xs.into_iter()
+            .zip(ys)
+            .map(|(x, y)| x.mul(y, source))
+            .collect()
+    }
+
+    fn inv<S: BeaverSource<Self, Self, Self>>(mut self, _source: &mut S) -> Self {
+        for a in self.vals[..self.len].iter_mut() {
+            *a = a.inverse().unwrap();
+        }
+        self
+    }
+
+    fn batch_inv<S: BeaverSource<Self, Self, Self>>(xs: Vec<Self>, source: &mut S) -> Vec<Self> {
+        xs.into_iter().map(|x| x.inv(source)).collect()
+    }
+}
+
+#[derive(Debug, Derivative)]
+#[derivative(
+    Default(bound = ""),
+    Clone(bound = ""),
+    Copy(bound = ""),
+    PartialEq(bound = "F: PartialEq"),
+    Eq(bound = "F: Eq"),
+    Hash(bound = "F: Hash")
+)]
+pub struct RssMulExtFieldShare<F: Field>(pub PhantomData<F>);
+
+impl<F: Field> ExtFieldShare<F> for RssMulExtFieldShare<F> {
+    type Ext = RssMulFieldShare<F>;
+    type Base = RssMulFieldShare<F::BasePrimeField>;
+}
+
+impl_basics!(RssMulFieldShare, Field, one);
+
+macro_rules! groups_share {
+    ($struct_name:ident, $affine:ident, $proj:ident) => {
+        pub struct $struct_name<E: PairingEngine>(pub PhantomData<E>);
+
+        impl<E: PairingEngine> AffProjShare<E::Fr, E::$affine, E::$proj> for $struct_name<E> {
+            type FrShare = RssFieldShare<E::Fr>;
+            type AffineShare = RssGroupShare<E::$affine, crate::msm::AffineMsm<E::$affine>>;
+            type ProjectiveShare = RssGroupShare<E::$proj, crate::msm::ProjectiveMsm<E::$proj>>;
+
+            fn sh_aff_to_proj(g: Self::AffineShare) -> Self::ProjectiveShare {
+                g.map_homo(|s| s.into())
+            }
+
+            fn sh_proj_to_aff(g: Self::ProjectiveShare) -> Self::AffineShare {
+                g.map_homo(|s| s.into())
+            }
+
+            fn add_sh_proj_sh_aff(
+                mut a: Self::ProjectiveShare,
+                o: &Self::AffineShare,
+            ) -> Self::ProjectiveShare {
+                for (a, o) in a.vals.iter_mut().zip(o.slots()) {
+                    a.add_assign_mixed(o);
+                }
+                a
+            }
+            fn add_sh_proj_pub_aff(
+                mut a: Self::ProjectiveShare,
+                o: &E::$affine,
+            ) -> Self::ProjectiveShare {
+                if let Some(slot) = prss::layout().first_slot() {
+                    a.vals[slot].add_assign_mixed(&o);
+                }
+                a
+            }
+            fn add_pub_proj_sh_aff(_a: &E::$proj, _o: Self::AffineShare) -> Self::ProjectiveShare {
+                unimplemented!()
+            }
+        }
+    };
+}
+
+groups_share!(RssG1Share, G1Affine, G1Projective);
+groups_share!(RssG2Share, G2Affine, G2Projective);
+
+#[derive(Debug, Derivative)]
+#[derivative(
+    Default(bound = ""),
+    Clone(bound = ""),
+    Copy(bound = ""),
+    PartialEq(bound = "E::G1Affine: PartialEq"),
+    Eq(bound = "E::G1Affine: Eq"),
+    Hash(bound = "E::G1Affine: Hash")
+)]
+pub struct RssPairingShare<E: PairingEngine>(pub PhantomData<E>);
+
+impl<E: PairingEngine> PairingShare<E> for RssPairingShare<E> {
+    type FrShare = RssFieldShare<E::Fr>;
+    type FqShare = RssFieldShare<E::Fq>;
+    type FqeShare = RssExtFieldShare<E::Fqe>;
+    // Not a typo. We want a multiplicative subgroup.
+    type FqkShare = RssMulExtFieldShare<E::Fqk>;
+    type G1AffineShare = RssGroupShare<E::G1Affine, crate::msm::AffineMsm<E::G1Affine>>;
+    type G2AffineShare = RssGroupShare<E::G2Affine, crate::msm::AffineMsm<E::G2Affine>>;
+    type G1ProjectiveShare =
+        RssGroupShare<E::G1Projective, crate::msm::ProjectiveMsm<E::G1Projective>>;
+    type G2ProjectiveShare =
+        RssGroupShare<E::G2Projective, crate::msm::ProjectiveMsm<E::G2Projective>>;
+    type G1 = RssG1Share<E>;
+    type G2 = RssG2Share<E>;
+
+    fn miller_loop_sh_pub(a: &Self::G1AffineShare, b: &E::G2Prepared) -> RssMulFieldShare<E::Fqk> {
+        RssMulFieldShare::from_slots(
+            a.slots()
+                .iter()
+                .map(|a| miller_loop_one::<E>((*a).into(), b.clone()))
+                .collect(),
+        )
+    }
+    fn miller_loop_pub_sh(a: &E::G1Prepared, b: &Self::G2AffineShare) -> RssMulFieldShare<E::Fqk> {
+        RssMulFieldShare::from_slots(
+            b.slots()
+                .iter()
+                .map(|b| miller_loop_one::<E>(a.clone(), (*b).into()))
+                .collect(),
+        )
+    }
+    fn final_exp_sh(f: RssMulFieldShare<E::Fqk>) -> RssMulFieldShare<E::Fqk> {
+        RssMulFieldShare::from_slots(
+            f.vals[..f.len]
+                .iter()
+                .map(|f| E::final_exponentiation(f).unwrap())
+                .collect(),
+        )
+    }
+    fn pow_pub_base(base: &E::Fqk, e: &Self::FrShare) -> RssMulFieldShare<E::Fqk> {
+        RssMulFieldShare::from_slots(
+            e.slots()
+                .iter()
+                .map(|e| base.pow(&e.into_repr()))
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MpcField;
+    use ark_bls12_377::Bls12_377 as E;
+    use ark_std::test_rng;
+
+    type Fr = <E as PairingEngine>::Fr;
+    type MpcFr = MpcField<Fr, RssFieldShare<Fr>>;
+
+    #[test]
+    fn multiplies_for_each_threshold() {
+        for n in 3..=7 {
+            for t in 1..=(n - 1) / 2 {
+                Net::simulate(n, move || {
+                    prss::set_threshold(t);
+                    MpcFr::init_protocol();
+                    let rng = &mut test_rng();
+                    let xs: Vec<Fr> = (0..3).map(|_| Fr::rand(rng)).collect();
+                    let shared = MpcFr::king_share_batch(xs.clone(), rng);
+                    let product = shared[0] * shared[1] * shared[2] + shared[0];
+                    assert_eq!(product.reveal(), xs[0] * xs[1] * xs[2] + xs[0]);
+                    let random = MpcFr::rand(rng);
+                    assert_eq!((random * shared[1]).reveal(), random.reveal() * xs[1]);
+                    MpcFr::deinit_protocol();
+                });
+            }
+        }
+    }
+
+    #[test]
+    fn inverts_without_preprocessing() {
+        Net::simulate(3, || {
+            MpcFr::init_protocol();
+            let rng = &mut test_rng();
+            let xs: Vec<Fr> = (0..3).map(|_| Fr::rand(rng)).collect();
+            let shared = MpcFr::king_share_batch(xs.clone(), rng);
+            let inverses: Vec<Fr> = shared.iter().map(|x| x.inverse().unwrap().reveal()).collect();
+            let expected: Vec<Fr> = xs.iter().map(|x| x.inverse().unwrap()).collect();
+            assert_eq!(inverses, expected);
+            MpcFr::deinit_protocol();
+        });
+    }
+}
@@ -1,3 +1,4 @@
+pub mod prss;
 pub mod share;
 pub use share::*;
 pub mod share_conversion;
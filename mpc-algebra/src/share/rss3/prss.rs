@@ -0,0 +1,101 @@
+//! Pseudo-random secret sharing (PRSS) for three-party replicated sharing.
+//!
+//! At setup, each party `i` samples a PRG seed `k_i` and passes it to party `i + 1`, so party `i`
+//! holds `k_i` and `k_{i - 1}`. Both holders of a seed draw from its PRG in lockstep, so from
+//! then on the parties get correlated randomness without talking:
+//!
+//! * a random sharing: if `r_j` is the next output of `k_j`'s PRG, party `i` holds
+//!   `(r_i, r_{i - 1})`, a replicated sharing of `r_0 + r_1 + r_2`;
+//! * a zero sharing: party `i` holds `r_i - r_{i - 1}`, and these sum to zero.
+//!
+//! Every party must draw the same amount of randomness, in the same order.
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use ark_ff::UniformRand;
+
+use std::ops::Sub;
+
+use crate::channel::MpcSerNet;
+use mpc_net::{MpcMultiNet as Net, MpcNet, OrAbort};
+
+struct Prss {
+    /// The PRG for our own seed, shared with the next party.
+    own: StdRng,
+    /// The PRG for the previous party's seed.
+    prev: StdRng,
+}
+
+fn setup() -> Prss {
+    assert_eq!(
+        Net::n_parties(),
+        3,
+        "RSS3 is for three parties; use rss for more"
+    );
+    let mut seed = <StdRng as SeedableRng>::Seed::default();
+    rand::thread_rng().fill(&mut seed);
+    let prev_seed: Vec<u8> = Net::pass_to_next(&seed.to_vec()).or_abort();
+    let mut prev = <StdRng as SeedableRng>::Seed::default();
+    prev.copy_from_slice(&prev_seed);
+    Prss {
+        own: StdRng::from_seed(seed),
+        prev: StdRng::from_seed(prev),
+    }
+}
+
+/// The current session's PRSS, set up on first use.
+fn with_prss<R>(f: impl FnOnce(&mut Prss) -> R) -> R {
+    Net::with_state(setup, f)
+}
+
+/// Exchange the PRSS seeds, if they have not been exchanged yet.
+pub fn init_prss() {
+    with_prss(|_| ());
+}
+
+/// Forget the PRSS seeds.
+pub fn deinit_prss() {
+    Net::take_state::<Prss>();
+}
+
+/// Our half of `n` random replicated sharings: `(r_i, r_{i - 1})` for each.
+pub fn rand_pairs<T: UniformRand>(n: usize) -> Vec<(T, T)> {
+    with_prss(|p| {
+        (0..n)
+            .map(|_| (T::rand(&mut p.own), T::rand(&mut p.prev)))
+            .collect()
+    })
+}
+
+/// Our share of each of `n` additive sharings of zero.
+pub fn zero_shares<T: UniformRand + Sub<Output = T>>(n: usize) -> Vec<T> {
+    rand_pairs::<T>(n).into_iter().map(|(a, b)| a - b).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bls12_377::Fr;
+
+    #[test]
+    fn prss_shares_zero() {
+        let draws = Net::simulate(3, || {
+            let pairs = rand_pairs::<Fr>(4);
+            let zeros = zero_shares::<Fr>(4);
+            deinit_prss();
+            (pairs, zeros)
+        });
+        for (id, (pairs, _)) in draws.iter().enumerate() {
+            // Party `i` draws from its own seed and from party `i - 1`'s.
+            let (prev_pairs, _) = &draws[(id + 2) % 3];
+            for (pair, prev_pair) in pairs.iter().zip(prev_pairs) {
+                assert_eq!(pair.1, prev_pair.0);
+            }
+        }
+        for k in 0..4 {
+            let sum: Fr = draws.iter().map(|(_, zeros)| zeros[k]).sum();
+            assert_eq!(sum, Fr::from(0u8));
+            assert_ne!(draws[0].1[k], Fr::from(0u8));
+        }
+    }
+}
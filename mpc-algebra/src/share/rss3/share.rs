@@ -18,16 +18,19 @@ use std::fmt::{self, Debug, Display, Formatter};
 use std::hash::Hash;
 use std::io::{self, Read, Write};
 use std::marker::PhantomData;
+use std::ops::Sub;
 
-use mpc_net::{MpcNet, MpcMultiNet as Net};
+use mpc_net::{MpcNet, MpcMultiNet as Net, MpcNetError, OrAbort};
 use crate::channel::MpcSerNet;
 
 use crate::share::field::{
     DenseOrSparsePolynomial, DensePolynomial, ExtFieldShare, FieldShare, SparsePolynomial,
 };
 use crate::share::group::GroupShare;
-use crate::share::pairing::{AffProjShare, PairingShare};
+use crate::share::pairing::{miller_loop_one, AffProjShare, PairingShare};
 use crate::share::BeaverSource;
+use super::prss;
+use crate::client::{self, ClientShare};
 use crate::msm::*;
 use crate::Reveal;
 use crate::share::{
@@ -39,7 +42,6 @@ use crate::share::{
 };
 
 use log::debug;
-use crate::wire::DummyFieldTripleSource;
 
 #[derive(Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct RSS3FieldShare<T> {
@@ -130,16 +132,28 @@ impl<F: Field> Reveal for RSS3FieldShare<F> {
     type Base = F;
 
     /// Reveal shared data, yielding plain data.
-    fn reveal(self) -> F {
-        let shares_vec: Vec<Vec<F>> = Net::broadcast(&vec![self.val0, self.val1]);
+    fn try_reveal(self) -> Result<F, MpcNetError> {
+        let shares_vec: Vec<Vec<F>> = Net::broadcast(&vec![self.val0, self.val1])?;
         let mut res0 = F::zero();
         let mut res1 = F::zero();
         for party_id in 0..3 {
             res0 += shares_vec[party_id][0];
             res1 += shares_vec[party_id][1];
         }
-        assert_eq!(res0, res1);
-        res0
+        if res0 != res1 {
+            return Err(MpcNetError::InconsistentShares);
+        }
+        Ok(res0)
+    }
+
+    fn reveal_to(self, to: usize) -> Option<F> {
+        let shares_vec: Vec<(F, F)> = Net::send_to(to, &(self.val0, self.val1)).or_abort()?;
+        let res0: F = shares_vec.iter().map(|s| s.0).sum();
+        let res1: F = shares_vec.iter().map(|s| s.1).sum();
+        if res0 != res1 {
+            MpcNetError::InconsistentShares.abort();
+        }
+        Some(res0)
     }
 
     /// Construct a share of the sum of the `b` over all machines in the protocol.
@@ -168,57 +182,94 @@ impl<F: Field> Reveal for RSS3FieldShare<F> {
     }
 
     fn king_share<R: Rng>(f: Self::Base, rng: &mut R) -> Self {
-        let my_share = Net::recv_from_king( 
-            if Net::am_king() { 
-                let r0 = F::rand(rng);
-                let r1 = F::rand(rng);
-                let r2 = f - r0 - r1;
-                let shares = vec![vec![r0, r2],
-                                  vec![r1, r0],
-                                  vec![r2, r1]];
-                Some(shares)
-            } else {
-                None 
-        });
-        Self {
-            val0: my_share[0],
-            val1: my_share[1],
-        }
-    }
-
-    fn king_share_batch<R: Rng>(f: Vec<Self::Base>, rng: &mut R) -> Vec<Self> {
-        let my_share_batch = Net::recv_from_king(
-            if Net::am_king() {
-                let mut share_batches0 = vec![];
-                let mut share_batches1 = vec![];
-                let mut share_batches2 = vec![];
-                f.into_iter()
-                    .for_each(|x| {
-                        let r0 = F::rand(rng);
-                        let r1 = F::rand(rng);
-                        let r2 = x - r0 - r1;
-                        share_batches0.push(vec![r0, r2]);
-                        share_batches1.push(vec![r1, r0]);
-                        share_batches2.push(vec![r2, r1]);
-                    });
-                Some(vec![share_batches0, share_batches1, share_batches2])
-            } else {
-                None
-        });
-        my_share_batch.into_iter().map(|v| {
-            Self {
-                val0: v[0],
-                val1: v[1],
-            }
-        }).collect()
+        Self::king_share_batch(vec![f], rng).pop().unwrap()
+    }
+
+    /// The king holds two of the three components of a PRSS random sharing, so it only needs to
+    /// send the third, `x - r_0 - r_2`, to the two parties that hold it.
+    fn king_share_batch<R: Rng>(f: Vec<Self::Base>, _rng: &mut R) -> Vec<Self> {
+        let (pairs, diffs) = king_share_diffs(f);
+        pairs
+            .into_iter()
+            .zip(diffs)
+            .map(|((r_own, r_prev), d)| match Net::party_id() {
+                0 => Self { val0: r_own, val1: r_prev },
+                1 => Self { val0: d, val1: r_prev },
+                _ => Self { val0: r_own, val1: d },
+            })
+            .collect()
+    }
+
+    fn init_protocol() {
+        prss::init_prss()
+    }
+
+    fn deinit_protocol() {
+        prss::deinit_prss()
     }
 }
 
+/// The client splits `x` into `x_0 + x_1 + x_2`, and party `i` gets `(x_i, x_{i - 1})`.
+impl<F: Field> ClientShare for RSS3FieldShare<F> {
+    type Dealt = Self;
+
+    fn deal<R: Rng>(values: &[F], n: usize, _threshold: Option<usize>, rng: &mut R) -> Vec<Vec<Self>> {
+        assert_eq!(n, 3, "RSS3 is for three parties; use rss for more");
+        let parts = client::add_split(values, 3, rng);
+        (0..3)
+            .map(|i| {
+                (0..values.len())
+                    .map(|j| Self {
+                        val0: parts[i][j],
+                        val1: parts[(i + 2) % 3][j],
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+    fn from_dealt(dealt: Vec<Self>) -> Result<Vec<Self>, MpcNetError> {
+        Ok(dealt)
+    }
+
+    type Keys = ();
+
+    fn output_keys() {}
+    fn use_output_keys(_keys: ()) {}
+}
+
+/// Draws a PRSS random sharing for each of `f` (which only the king needs to know), and has the
+/// king send `x - r_0 - r_2` for each `x` to the other parties.
+///
+/// Returns our half of each random sharing, and the differences.
+fn king_share_diffs<T>(f: Vec<T>) -> (Vec<(T, T)>, Vec<T>)
+where
+    T: UniformRand + Copy + Sub<Output = T> + CanonicalSerialize + CanonicalDeserialize,
+{
+    let king_pairs: Option<Vec<(T, T)>> = if Net::am_king() {
+        Some(prss::rand_pairs(f.len()))
+    } else {
+        None
+    };
+    let diffs: Vec<T> = Net::recv_from_king(king_pairs.as_ref().map(|pairs| {
+        let diffs: Vec<T> = f
+            .into_iter()
+            .zip(pairs)
+            .map(|(x, (r0, r2))| x - *r0 - *r2)
+            .collect();
+        vec![diffs; 3]
+    })).or_abort();
+    let pairs = king_pairs.unwrap_or_else(|| prss::rand_pairs(diffs.len()));
+    (pairs, diffs)
+}
+
 impl<F: Field> FieldShare<F> for RSS3FieldShare<F> {
-    
+    const USES_TRIPLES: bool = false;
+
     fn mul<S: BeaverSource<Self, Self, Self>>(self, other: Self, _source: &mut S) -> Self {
-        let z0 = self.val0 * (other.val0 + other.val1) + other.val0 * self.val1;
-        let z1 = Net::pass_to_next(&vec![z0])[0];
+        let z0 = self.val0 * (other.val0 + other.val1)
+            + other.val0 * self.val1
+            + prss::zero_shares::<F>(1)[0];
+        let z1 = Net::pass_to_next(&vec![z0]).or_abort()[0];
         Self {
             val0: z0,
             val1: z1,
@@ -230,13 +281,15 @@ impl<F: Field> FieldShare<F> for RSS3FieldShare<F> {
         ys: Vec<Self>,
         _source: &mut S,
     ) -> Vec<Self> {
+        let zeros = prss::zero_shares::<F>(xs.len());
         let z0s: Vec<F> = xs.into_iter()
                             .zip(ys.into_iter())
-                            .map(|(x, y)| {
-                                x.val0 * (y.val0 + y.val1) + y.val0 * x.val1
+                            .zip(zeros)
+                            .map(|((x, y), zero)| {
+                                x.val0 * (y.val0 + y.val1) + y.val0 * x.val1 + zero
                             }).collect();
 
-        let z1s = Net::pass_to_next(&z0s);
+        let z1s = Net::pass_to_next(&z0s).or_abort();
 
         z0s.into_iter()
             .zip(z1s.into_iter())
@@ -250,7 +303,7 @@ impl<F: Field> FieldShare<F> for RSS3FieldShare<F> {
     
     fn batch_open(selfs: impl IntoIterator<Item = Self>) -> Vec<F> { // Rewrite the function
         let self_vec: Vec<Vec<F>> = selfs.into_iter().map(|s| vec![s.val0, s.val1] ).collect();
-        let all_vals = Net::broadcast(&self_vec);
+        let all_vals = Net::broadcast(&self_vec).or_abort();
         let len = all_vals[0].len();
         let mut res = vec![F::zero(); len];
         for i in 0..len {
@@ -260,7 +313,9 @@ impl<F: Field> FieldShare<F> for RSS3FieldShare<F> {
                 tmp0 += all_vals[party_id][i][0];
                 tmp1 += all_vals[party_id][i][1];
             }
-            assert_eq!(tmp0, tmp1);
+            if tmp0 != tmp1 {
+                MpcNetError::InconsistentShares.abort();
+            }
             res[i] = tmp0;  
         }
         res
@@ -355,16 +410,28 @@ impl<G: Group, M> ShareConversion for RSS3GroupShare<G, M> {
 impl<G: Group, M> Reveal for RSS3GroupShare<G, M> {
     type Base = G;
 
-    fn reveal(self) -> G {
-        let shares_vec: Vec<Vec<G>> = Net::broadcast(&vec![self.val0, self.val1]);
+    fn try_reveal(self) -> Result<G, MpcNetError> {
+        let shares_vec: Vec<Vec<G>> = Net::broadcast(&vec![self.val0, self.val1])?;
         let mut res0 = G::zero();
         let mut res1 = G::zero();
         for party_id in 0..3 {
             res0 += shares_vec[party_id][0];
             res1 += shares_vec[party_id][1];
         }
-        assert_eq!(res0, res1);
-        res0
+        if res0 != res1 {
+            return Err(MpcNetError::InconsistentShares);
+        }
+        Ok(res0)
+    }
+
+    fn reveal_to(self, to: usize) -> Option<G> {
+        let shares_vec: Vec<(G, G)> = Net::send_to(to, &(self.val0, self.val1)).or_abort()?;
+        let res0: G = shares_vec.iter().map(|s| s.0).sum();
+        let res1: G = shares_vec.iter().map(|s| s.1).sum();
+        if res0 != res1 {
+            MpcNetError::InconsistentShares.abort();
+        }
+        Some(res0)
     }
 
     fn from_public(f: G) -> Self {
@@ -397,51 +464,36 @@ impl<G: Group, M> Reveal for RSS3GroupShare<G, M> {
     }
 
     fn king_share<R: Rng>(f: Self::Base, rng: &mut R) -> Self {
-        let my_share = Net::recv_from_king( 
-            if Net::am_king() { 
-                let r0 = G::rand(rng);
-                let r1 = G::rand(rng);
-                let r2 = f - r0 - r1;
-                let shares = vec![vec![r0, r2],
-                                  vec![r1, r0],
-                                  vec![r2, r1]];
-                Some(shares)
-            } else {
-                None 
-        });
-        Self {
-            val0: my_share[0],
-            val1: my_share[1],
-            _phants: PhantomData::default(),
-        }
+        Self::king_share_batch(vec![f], rng).pop().unwrap()
+    }
+
+    /// As for [`RSS3FieldShare`].
+    fn king_share_batch<R: Rng>(f: Vec<Self::Base>, _rng: &mut R) -> Vec<Self> {
+        let (pairs, diffs) = king_share_diffs(f);
+        pairs
+            .into_iter()
+            .zip(diffs)
+            .map(|((r_own, r_prev), d)| {
+                let (val0, val1) = match Net::party_id() {
+                    0 => (r_own, r_prev),
+                    1 => (d, r_prev),
+                    _ => (r_own, d),
+                };
+                Self {
+                    val0,
+                    val1,
+                    _phants: PhantomData::default(),
+                }
+            })
+            .collect()
     }
 
-    fn king_share_batch<R: Rng>(f: Vec<Self::Base>, rng: &mut R) -> Vec<Self> {
-        let my_share_batch = Net::recv_from_king(
-            if Net::am_king() {
-                let mut share_batches0 = vec![];
-                let mut share_batches1 = vec![];
-                let mut share_batches2 = vec![];
-                f.into_iter()
-                    .for_each(|x| {
-                        let r0 = G::rand(rng);
-                        let r1 = G::rand(rng);
-                        let r2 = x - r0 - r1;
-                        share_batches0.push(vec![r0, r2]);
-                        share_batches1.push(vec![r1, r0]);
-                        share_batches2.push(vec![r2, r1]);
-                    });
-                Some(vec![share_batches0, share_batches1, share_batches2])
-            } else {
-                None
-        });
-        my_share_batch.into_iter().map(|v| {
-            Self {
-                val0: v[0],
-                val1: v[1],
-                _phants: PhantomData::default(),
-            }
-        }).collect()
+    fn init_protocol() {
+        prss::init_prss()
+    }
+
+    fn deinit_protocol() {
+        prss::deinit_prss()
     }
 }
 
@@ -458,7 +510,7 @@ impl<G: Group, M: Msm<G, G::ScalarField>> GroupShare<G> for RSS3GroupShare<G, M>
 
     fn batch_open(selfs: impl IntoIterator<Item = Self>) -> Vec<G> {
         let self_vec: Vec<Vec<G>> = selfs.into_iter().map(|s| vec![s.val0, s.val1] ).collect();
-        let all_vals = Net::broadcast(&self_vec);
+        let all_vals = Net::broadcast(&self_vec).or_abort();
         let len = all_vals[0].len();
         let mut res = vec![G::zero(); len];
         for i in 0..len {
@@ -468,7 +520,9 @@ impl<G: Group, M: Msm<G, G::ScalarField>> GroupShare<G> for RSS3GroupShare<G, M>
                 tmp0 += all_vals[party_id][i][0];
                 tmp1 += all_vals[party_id][i][1];
             }
-            assert_eq!(tmp0, tmp1);
+            if tmp0 != tmp1 {
+                MpcNetError::InconsistentShares.abort();
+            }
             res[i] = tmp0;  
         }
         res
@@ -521,8 +575,8 @@ impl<G: Group, M: Msm<G, G::ScalarField>> GroupShare<G> for RSS3GroupShare<G, M>
         tmp0 *= other.val0 + other.val1;
         let mut tmp1 = self.val1;
         tmp1 *= other.val0;
-        let z0 = tmp0 + tmp1;
-        let z1 = Net::pass_to_next(&vec![z0])[0];
+        let z0 = tmp0 + tmp1 + prss::zero_shares::<G>(1)[0];
+        let z1 = Net::pass_to_next(&vec![z0]).or_abort()[0];
         Self {
             val0: z0,
             val1: z1,
@@ -553,60 +607,11 @@ macro_rules! impl_basics {
                 write!(f, "{:?}, {:?}", self.val0, self.val1)
             }
         }
-        impl<T: $bound> ToBytes for $share<T> {
-            fn write<W: Write>(&self, _writer: W) -> io::Result<()> {
-                unimplemented!("write")
-            }
-        }
-        impl<T: $bound> FromBytes for $share<T> {
-            fn read<R: Read>(_reader: R) -> io::Result<Self> {
-                unimplemented!("read")
-            }
-        }
-        impl<T: $bound> CanonicalSerialize for $share<T> {
-            fn serialize<W: Write>(&self, _writer: W) -> Result<(), SerializationError> {
-                unimplemented!("serialize")
-            }
-            fn serialized_size(&self) -> usize {
-                unimplemented!("serialized_size")
-            }
-        }
-        impl<T: $bound> CanonicalSerializeWithFlags for $share<T> {
-            fn serialize_with_flags<W: Write, F: Flags>(
-                &self,
-                _writer: W,
-                _flags: F,
-            ) -> Result<(), SerializationError> {
-                unimplemented!("serialize_with_flags")
-            }
-
-            fn serialized_size_with_flags<F: Flags>(&self) -> usize {
-                unimplemented!("serialized_size_with_flags")
-            }
-        }
-        impl<T: $bound> CanonicalDeserialize for $share<T> {
-            fn deserialize<R: Read>(_reader: R) -> Result<Self, SerializationError> {
-                unimplemented!("deserialize")
-            }
-        }
-        impl<T: $bound> CanonicalDeserializeWithFlags for $share<T> {
-            fn deserialize_with_flags<R: Read, F: Flags>(
-                _reader: R,
-            ) -> Result<(Self, F), SerializationError> {
-                unimplemented!("deserialize_with_flags")
-            }
-        }
+        impl_share_encoding!([T: $bound], $share<T>, [val0, val1], []);
         impl<T: $bound> UniformRand for $share<T> {
-            fn rand<R: Rng + ?Sized>(rng: &mut R) -> Self { 
-                let r0 = <T as UniformRand>::rand(rng);
-                let r1 = <T as UniformRand>::rand(rng);
-                let r2 = <T as UniformRand>::rand(rng);
-                match Net::party_id() {
-                    0 => Self { val0: r0, val1: r2 },
-                    1 => Self { val0: r1, val1: r0 },
-                    2 => Self { val0: r2, val1: r1 },
-                    _ => Self::default(),
-                }
+            fn rand<R: Rng + ?Sized>(_rng: &mut R) -> Self {
+                let (val0, val1) = prss::rand_pairs::<T>(1)[0];
+                Self { val0, val1 }
             }
         }
     };
@@ -623,65 +628,14 @@ macro_rules! impl_basics_2_param {
                 write!(f, "{:?}, {:?}", self.val0, self.val1)
             }
         }
-        impl<T: $bound, M> ToBytes for $share<T, M> {
-            fn write<W: Write>(&self, _writer: W) -> io::Result<()> {
-                unimplemented!("write")
-            }
-        }
-        impl<T: $bound, M> FromBytes for $share<T, M> {
-            fn read<R: Read>(_reader: R) -> io::Result<Self> {
-                unimplemented!("read")
-            }
-        }
-        impl<T: $bound, M> CanonicalSerialize for $share<T, M> {
-            fn serialize<W: Write>(&self, _writer: W) -> Result<(), SerializationError> {
-                unimplemented!("serialize")
-            }
-            fn serialized_size(&self) -> usize {
-                unimplemented!("serialized_size")
-            }
-        }
-        impl<T: $bound, M> CanonicalSerializeWithFlags for $share<T, M> {
-            fn serialize_with_flags<W: Write, F: Flags>(
-                &self,
-                _writer: W,
-                _flags: F,
-            ) -> Result<(), SerializationError> {
-                unimplemented!("serialize_with_flags")
-            }
-
-            fn serialized_size_with_flags<F: Flags>(&self) -> usize {
-                unimplemented!("serialized_size_with_flags")
-            }
-        }
-        impl<T: $bound, M> CanonicalDeserialize for $share<T, M> {
-            fn deserialize<R: Read>(_reader: R) -> Result<Self, SerializationError> {
-                unimplemented!("deserialize")
-            }
-        }
-        impl<T: $bound, M> CanonicalDeserializeWithFlags for $share<T, M> {
-            fn deserialize_with_flags<R: Read, F: Flags>(
-                _reader: R,
-            ) -> Result<(Self, F), SerializationError> {
-                unimplemented!("deserialize_with_flags")
-            }
-        }
+        impl_share_encoding!([T: $bound, M], $share<T, M>, [val0, val1], [_phants]);
         impl<T: $bound, M> UniformRand for $share<T, M> {
-            fn rand<R: Rng + ?Sized>(rng: &mut R) -> Self {
-                // Self {
-                //     val0: <T as UniformRand>::rand(rng),
-                //     val1: <T as UniformRand>::rand(rng),
-                //     _phants: PhantomData::default(),
-                // }
-                // }
-                let r0 = <T as UniformRand>::rand(rng);
-                let r1 = <T as UniformRand>::rand(rng);
-                let r2 = <T as UniformRand>::rand(rng);
-                match Net::party_id() {
-                    0 => Self { val0: r0, val1: r2, _phants: PhantomData::default(), },
-                    1 => Self { val0: r1, val1: r0, _phants: PhantomData::default(), },
-                    2 => Self { val0: r2, val1: r1, _phants: PhantomData::default(), },
-                    _ => Self::default(),
+            fn rand<R: Rng + ?Sized>(_rng: &mut R) -> Self {
+                let (val0, val1) = prss::rand_pairs::<T>(1)[0];
+                Self {
+                    val0,
+                    val1,
+                    _phants: PhantomData::default(),
                 }
             }
         }
@@ -722,16 +676,18 @@ impl<F: Field> MulFieldShare<F> {
 impl<F: Field> Reveal for MulFieldShare<F> {
     type Base = F;
 
-    fn reveal(self) -> F {
-        let shares_vec: Vec<Vec<F>> = Net::broadcast(&vec![self.val0, self.val1]);
+    fn try_reveal(self) -> Result<F, MpcNetError> {
+        let shares_vec: Vec<Vec<F>> = Net::broadcast(&vec![self.val0, self.val1])?;
         let mut res0 = F::one();
         let mut res1 = F::one();
         for party_id in 0..3 {
             res0 *= shares_vec[party_id][0];
             res1 *= shares_vec[party_id][1];
         }
-        assert_eq!(res0, res1);
-        res0
+        if res0 != res1 {
+            return Err(MpcNetError::InconsistentShares);
+        }
+        Ok(res0)
     }
 
     fn from_add_shared(f: Self::Base) -> Self {
@@ -758,7 +714,7 @@ impl<F: Field> FieldShare<F> for MulFieldShare<F> {
     }
     fn batch_open(selfs: impl IntoIterator<Item = Self>) -> Vec<F> {
         let self_vec: Vec<Vec<F>> = selfs.into_iter().map(|s| vec![s.val0, s.val1] ).collect();
-        let all_vals = Net::broadcast(&self_vec);
+        let all_vals = Net::broadcast(&self_vec).or_abort();
         let len = all_vals[0].len();
         let mut res = vec![F::one(); len];
         for i in 0..len {
@@ -768,7 +724,9 @@ impl<F: Field> FieldShare<F> for MulFieldShare<F> {
                 tmp0 *= all_vals[party_id][i][0];
                 tmp1 *= all_vals[party_id][i][1];
             }
-            assert_eq!(tmp0, tmp1);
+            if tmp0 != tmp1 {
+                MpcNetError::InconsistentShares.abort();
+            }
             res[i] = tmp0;  
         }
         res
@@ -911,4 +869,29 @@ impl<E: PairingEngine> PairingShare<E> for RSS3PairingShare<E> {
         RSS3GroupShare<E::G2Projective, crate::msm::ProjectiveMsm<E::G2Projective>>;
     type G1 = RSS3G1Share<E>;
     type G2 = RSS3G2Share<E>;
+
+    fn miller_loop_sh_pub(a: &Self::G1AffineShare, b: &E::G2Prepared) -> MulFieldShare<E::Fqk> {
+        MulFieldShare {
+            val0: miller_loop_one::<E>(a.val0.into(), b.clone()),
+            val1: miller_loop_one::<E>(a.val1.into(), b.clone()),
+        }
+    }
+    fn miller_loop_pub_sh(a: &E::G1Prepared, b: &Self::G2AffineShare) -> MulFieldShare<E::Fqk> {
+        MulFieldShare {
+            val0: miller_loop_one::<E>(a.clone(), b.val0.into()),
+            val1: miller_loop_one::<E>(a.clone(), b.val1.into()),
+        }
+    }
+    fn final_exp_sh(f: MulFieldShare<E::Fqk>) -> MulFieldShare<E::Fqk> {
+        MulFieldShare {
+            val0: E::final_exponentiation(&f.val0).unwrap(),
+            val1: E::final_exponentiation(&f.val1).unwrap(),
+        }
+    }
+    fn pow_pub_base(base: &E::Fqk, e: &Self::FrShare) -> MulFieldShare<E::Fqk> {
+        MulFieldShare {
+            val0: base.pow(&e.val0.into_repr()),
+            val1: base.pow(&e.val1.into_repr()),
+        }
+    }
 }
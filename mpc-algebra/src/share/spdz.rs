@@ -1,6 +1,7 @@
 #![macro_use]
 use derivative::Derivative;
-use rand::Rng;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 
 use ark_ec::{group::Group, AffineCurve, PairingEngine, ProjectiveCurve};
 use ark_ff::bytes::{FromBytes, ToBytes};
@@ -16,34 +17,69 @@ use std::hash::Hash;
 use std::io::{self, Read, Write};
 use std::marker::PhantomData;
 
-use mpc_net::{MpcNet, MpcMultiNet as Net};
-use crate::channel::{can_cheat, MpcSerNet};
+use mpc_net::{MpcNet, MpcMultiNet as Net, MpcNetError, OrAbort};
+use crate::channel::MpcSerNet;
 
-use super::add::{AdditiveFieldShare, AdditiveGroupShare, MulFieldShare};
+use super::add::{AdditiveFieldShare, AdditiveGroupShare, AdditivePairingShare, MulFieldShare};
 use super::field::{DenseOrSparsePolynomial, DensePolynomial, ExtFieldShare, FieldShare};
 use super::group::GroupShare;
 use super::msm::*;
 use super::pairing::{AffProjShare, PairingShare};
 use super::{BeaverSource, PanicBeaverSource};
+use crate::client::{self, ClientShare};
+use crate::preprocessing::{self, DealtFieldShare};
 use crate::Reveal;
 
+/// This party's share of the MAC key for one field.
+struct MacKey<F>(F);
+
+/// The current session's MAC key for `F`.
+fn with_mac_key<F: Field, R>(f: impl FnOnce(&mut MacKey<F>) -> R) -> R {
+    Net::with_state(|| MacKey(F::rand(&mut rand::thread_rng())), f)
+}
+
+/// Sample this party's share of the MAC key for `F`, if it does not have one yet.
+///
+/// The MAC key is the sum of all parties' shares, and no party learns it. Values are
+/// authenticated under it with input masks from the preprocessing (see
+/// [`take_input_masks`](preprocessing::take_input_masks)).
+pub fn init_mac_key<F: Field>() {
+    with_mac_key::<F, _>(|_| ());
+}
+
+/// Forget this party's share of the MAC key for `F`, ending the session.
+pub fn deinit_mac_key<F: Field>() {
+    Net::take_state::<MacKey<F>>();
+}
+
+/// Adopt `share` as this party's share of the MAC key for `F`.
+///
+/// Used to resume a session whose authenticated shares were stored, e.g. preprocessing.
+pub fn set_mac_share<F: Field>(share: F) {
+    Net::with_state(|| MacKey(share), |k| k.0 = share);
+}
+
+/// This party's share of the MAC key for `F`.
 #[inline]
 pub fn mac_share<F: Field>() -> F {
-    if Net::am_king() {
-        F::one()
-    } else {
-        F::zero()
-    }
+    with_mac_key::<F, _>(|k| k.0)
 }
 
-#[inline]
-/// A huge cheat. Useful for importing shares.
-pub fn mac<F: Field>() -> F {
-    if can_cheat() {
-        F::one()
-    } else {
-        panic!("Attempted to grab the MAC secret while cheating was not allowed")
-    }
+/// Whether this session stands in for the parties at a client, which has no key shares (see
+/// [`ClientShare::use_output_keys`]), so opens without checking MACs.
+struct AtClient(bool);
+
+/// Whether opening checks MACs in this session.
+fn checks_macs() -> bool {
+    !Net::with_state(|| AtClient(false), |c| c.0)
+}
+
+/// `n` of party `owner`'s input masks: authenticated shares of random values that only `owner`
+/// knows, and their values at `owner`. To authenticate `x`, `owner` opens `x - r`, and every party
+/// shifts its share of `[r]` by it.
+fn input_masks<F: Field>(owner: usize, n: usize) -> (Vec<SpdzFieldShare<F>>, Option<Vec<F>>) {
+    preprocessing::take_input_masks::<F, SpdzFieldShare<F>>(owner, n)
+        .unwrap_or_else(|e| panic!("{}", e))
 }
 
 #[derive(Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
@@ -64,52 +100,11 @@ macro_rules! impl_basics_spdz {
                 write!(f, "{:?}", self.sh)
             }
         }
-        impl<T: $bound> ToBytes for $share<T> {
-            fn write<W: Write>(&self, _writer: W) -> io::Result<()> {
-                unimplemented!("write")
-            }
-        }
-        impl<T: $bound> FromBytes for $share<T> {
-            fn read<R: Read>(_reader: R) -> io::Result<Self> {
-                unimplemented!("read")
-            }
-        }
-        impl<T: $bound> CanonicalSerialize for $share<T> {
-            fn serialize<W: Write>(&self, _writer: W) -> Result<(), SerializationError> {
-                unimplemented!("serialize")
-            }
-            fn serialized_size(&self) -> usize {
-                unimplemented!("serialized_size")
-            }
-        }
-        impl<T: $bound> CanonicalSerializeWithFlags for $share<T> {
-            fn serialize_with_flags<W: Write, F: Flags>(
-                &self,
-                _writer: W,
-                _flags: F,
-            ) -> Result<(), SerializationError> {
-                unimplemented!("serialize_with_flags")
-            }
-
-            fn serialized_size_with_flags<F: Flags>(&self) -> usize {
-                unimplemented!("serialized_size_with_flags")
-            }
-        }
-        impl<T: $bound> CanonicalDeserialize for $share<T> {
-            fn deserialize<R: Read>(_reader: R) -> Result<Self, SerializationError> {
-                unimplemented!("deserialize")
-            }
-        }
-        impl<T: $bound> CanonicalDeserializeWithFlags for $share<T> {
-            fn deserialize_with_flags<R: Read, F: Flags>(
-                _reader: R,
-            ) -> Result<(Self, F), SerializationError> {
-                unimplemented!("deserialize_with_flags")
-            }
-        }
+        impl_share_encoding!([T: $bound], $share<T>, [sh, mac], []);
         impl<T: $bound> UniformRand for $share<T> {
-            fn rand<R: Rng + ?Sized>(rng: &mut R) -> Self {
-                Self::from_add_shared(<T as UniformRand>::rand(rng))
+            fn rand<R: Rng + ?Sized>(_rng: &mut R) -> Self {
+                crate::preprocessing::take_rand_or_dummy::<T, Self>()
+                    .unwrap_or_else(|e| panic!("{}", e))
             }
         }
     };
@@ -119,15 +114,29 @@ impl_basics_spdz!(SpdzFieldShare, Field);
 impl<F: Field> Reveal for SpdzFieldShare<F> {
     type Base = F;
 
-    fn reveal(self) -> F {
-        let vals: Vec<F> = Net::broadcast(&self.sh.val);
+    fn try_reveal(self) -> Result<F, MpcNetError> {
+        let vals: Vec<F> = Net::broadcast(&self.sh.val)?;
         // _Pragmatic MPC_ 6.6.2
         let x: F = vals.iter().sum();
+        if !checks_macs() {
+            return Ok(x);
+        }
         let dx_t: F = mac_share::<F>() * x - self.mac.val;
-        let all_dx_ts: Vec<F> = Net::atomic_broadcast(&dx_t);
+        let all_dx_ts: Vec<F> = Net::atomic_broadcast(&dx_t)?;
         let sum: F = all_dx_ts.iter().sum();
-        assert!(sum.is_zero());
-        x
+        if !sum.is_zero() {
+            return Err(MpcNetError::MacFailure);
+        }
+        Ok(x)
+    }
+    /// The parties open `x - r` for one of `to`'s input masks `r`, checking its MAC, and `to` adds
+    /// `r` back. So no party learns anything about the MAC key.
+    fn reveal_to(self, to: usize) -> Option<F> {
+        let (mut mask, r) = input_masks::<F>(to, 1);
+        let mut masked = self;
+        masked.sub(&mask.pop().unwrap());
+        let masked = masked.try_reveal().or_abort();
+        r.map(|r| masked + r[0])
     }
     fn from_public(f: F) -> Self {
         Self {
@@ -136,38 +145,128 @@ impl<F: Field> Reveal for SpdzFieldShare<F> {
         }
     }
     fn from_add_shared(f: F) -> Self {
-        Self {
-            sh: Reveal::from_add_shared(f),
-            mac: Reveal::from_add_shared(f * mac::<F>()),
-        }
+        Self::from_add_shared_batch(vec![f]).pop().unwrap()
+    }
+    /// Each party `j` opens `x_j - r_j` for one of its input masks `r_j`, all in one round, and
+    /// `[x] = sum_j ([r_j] + x_j - r_j)`.
+    fn from_add_shared_batch(xs: Vec<F>) -> Vec<Self> {
+        let n = xs.len();
+        let (masks, rs): (Vec<Vec<Self>>, Vec<Option<Vec<F>>>) =
+            (0..Net::n_parties()).map(|owner| input_masks::<F>(owner, n)).unzip();
+        let ours = rs[Net::party_id()].as_ref().unwrap();
+        let diffs: Vec<F> = xs.iter().zip(ours).map(|(x, r)| *x - r).collect();
+        let all_diffs: Vec<Vec<F>> = Net::broadcast(&diffs).or_abort();
+        (0..n)
+            .map(|i| {
+                let mut s = masks[0][i];
+                for m in &masks[1..] {
+                    s.add(&m[i]);
+                }
+                s.shift(&all_diffs.iter().map(|d| &d[i]).sum());
+                s
+            })
+            .collect()
     }
     fn king_share<R: Rng>(f: Self::Base, rng: &mut R) -> Self {
-        let mut r: Vec<F> = (0..(Net::n_parties()-1)).map(|_| F::rand(rng)).collect();
-        let sum_r: F = r.iter().sum();
-        r.push(f - sum_r);
-        Self::from_add_shared(Net::recv_from_king( if Net::am_king() { Some(r) } else { None }))
-    }
-    fn king_share_batch<R: Rng>(f: Vec<Self::Base>, rng: &mut R) -> Vec<Self> {
-        let mut rs: Vec<Vec<Self::Base>> =
-            (0..(Net::n_parties()-1)).map(|_| {
-            (0..f.len()).map(|_| {
-                F::rand(rng)
-            }).collect()
-        }).collect();
-        let final_shares: Vec<Self::Base> = (0..rs[0].len()).map(|i| {
-            f[i] - &rs.iter().map(|r| &r[i]).sum()
-        }).collect();
-        rs.push(final_shares);
-        Net::recv_from_king(if Net::am_king() { Some(rs) } else {None}).into_iter().map(Self::from_add_shared).collect()
+        Self::king_share_batch(vec![f], rng).pop().unwrap()
+    }
+    /// The king opens `x - r` for its input masks `r`.
+    fn king_share_batch<R: Rng>(f: Vec<Self::Base>, _rng: &mut R) -> Vec<Self> {
+        let king = if Net::am_king() {
+            Some(input_masks::<F>(0, f.len()))
+        } else {
+            None
+        };
+        let diffs: Vec<F> = Net::recv_from_king(king.as_ref().map(|(_, rs)| {
+            let diffs: Vec<F> = f.iter().zip(rs.as_ref().unwrap()).map(|(x, r)| *x - r).collect();
+            vec![diffs; Net::n_parties()]
+        }))
+        .or_abort();
+        let masks = match king {
+            Some((masks, _)) => masks,
+            None => input_masks::<F>(0, diffs.len()).0,
+        };
+        masks
+            .into_iter()
+            .zip(diffs)
+            .map(|(mut s, d)| {
+                s.shift(&d);
+                s
+            })
+            .collect()
+    }
+    fn init_protocol() {
+        init_mac_key::<F>()
+    }
+    fn deinit_protocol() {
+        deinit_mac_key::<F>()
+    }
+}
+
+/// The client cannot compute MACs without the MAC key, so it deals additive shares, and the
+/// parties authenticate them with [`Reveal::from_add_shared_batch`].
+impl<F: Field> ClientShare for SpdzFieldShare<F> {
+    type Dealt = F;
+
+    fn deal<R: Rng>(values: &[F], n: usize, _threshold: Option<usize>, rng: &mut R) -> Vec<Vec<F>> {
+        client::add_split(values, n, rng)
+    }
+    fn from_dealt(dealt: Vec<F>) -> Result<Vec<Self>, MpcNetError> {
+        Ok(Self::from_add_shared_batch(dealt))
+    }
+
+    /// The parties send the client no key shares, since any party that learns the MAC key can
+    /// forge. So the client sums the shares of its outputs without checking their MACs, and must
+    /// check the outputs itself, as delegation does by verifying the proof.
+    type Keys = ();
+
+    fn output_keys() {}
+    fn use_output_keys(_keys: ()) {
+        Net::with_state(|| AtClient(true), |c| c.0 = true);
+    }
+}
+
+/// The dealer knows every key share, so it computes the MACs itself.
+impl<F: Field> DealtFieldShare<F> for SpdzFieldShare<F> {
+    fn deal_mac_key_shares<R: Rng>(n_parties: usize, rng: &mut R) -> Option<Vec<F>> {
+        Some((0..n_parties).map(|_| F::rand(rng)).collect())
+    }
+    fn deal_shares<R: Rng>(
+        values: &[F],
+        n_parties: usize,
+        keys: Option<&[F]>,
+        rng: &mut R,
+    ) -> Vec<Vec<Self>> {
+        let key: F = keys.expect("SPDZ shares are dealt with MAC key shares").iter().sum();
+        let macs: Vec<F> = values.iter().map(|v| key * v).collect();
+        client::add_split(values, n_parties, rng)
+            .into_iter()
+            .zip(client::add_split(&macs, n_parties, rng))
+            .map(|(shs, macs)| {
+                shs.into_iter()
+                    .zip(macs)
+                    .map(|(sh, mac)| Self {
+                        sh: AdditiveFieldShare::from_add_shared(sh),
+                        mac: AdditiveFieldShare::from_add_shared(mac),
+                    })
+                    .collect()
+            })
+            .collect()
     }
 }
 
 impl<F: Field> FieldShare<F> for SpdzFieldShare<F> {
+    fn mac_key_share() -> Option<F> {
+        Some(mac_share::<F>())
+    }
+    fn set_mac_key_share(share: F) {
+        set_mac_share::<F>(share)
+    }
     fn batch_open(selfs: impl IntoIterator<Item = Self>) -> Vec<F> {
         let (s_vals, macs): (Vec<F>, Vec<F>) =
             selfs.into_iter().map(|s| (s.sh.val, s.mac.val)).unzip();
         let n = s_vals.len();
-        let all_vals = Net::broadcast(&s_vals);
+        let all_vals = Net::broadcast(&s_vals).or_abort();
         let vals: Vec<F> =
             (0..n).map(|i| all_vals.iter().map(|v| &v[i]).sum()).collect();
         let dx_ts: Vec<F> =
@@ -176,10 +275,12 @@ impl<F: Field> FieldShare<F> for SpdzFieldShare<F> {
             .zip(vals.iter())
             .map(|(mac, val)| mac_share::<F>() * val - mac)
             .collect();
-        let all_dx_ts: Vec<Vec<F>> = Net::atomic_broadcast(&dx_ts);
+        let all_dx_ts: Vec<Vec<F>> = Net::atomic_broadcast(&dx_ts).or_abort();
         for i in 0..n {
             let sum: F = all_dx_ts.iter().map(|dx_ts| &dx_ts[i]).sum();
-            assert!(sum.is_zero());
+            if !sum.is_zero() {
+                MpcNetError::MacFailure.abort();
+            }
         }
         vals
     }
@@ -227,16 +328,21 @@ impl<F: Field> FieldShare<F> for SpdzFieldShare<F> {
         };
         let (q_sh, r_sh) = AdditiveFieldShare::univariate_div_qr(num_sh, den.clone()).unwrap();
         let (q_mac, r_mac) = AdditiveFieldShare::univariate_div_qr(num_mac, den).unwrap();
-        Some((
-            q_sh.into_iter()
-                .zip(q_mac)
-                .map(|(sh, mac)| Self { sh, mac })
-                .collect(),
-            r_sh.into_iter()
-                .zip(r_mac)
+        // The division trims leading zero coefficients, and the value and MAC shares can have
+        // different leading zeros (e.g. only the king holds a public shift). Re-align them.
+        let zip = |sh: DensePolynomial<AdditiveFieldShare<F>>,
+                   mac: DensePolynomial<AdditiveFieldShare<F>>|
+         -> DensePolynomial<Self> {
+            let n = sh.len().max(mac.len());
+            let zero = AdditiveFieldShare::from_add_shared(F::zero());
+            sh.into_iter()
+                .chain(std::iter::repeat(zero))
+                .zip(mac.into_iter().chain(std::iter::repeat(zero)))
+                .take(n)
                 .map(|(sh, mac)| Self { sh, mac })
-                .collect(),
-        ))
+                .collect()
+        };
+        Some((zip(q_sh, q_mac), zip(r_sh, r_mac)))
     }
 }
 
@@ -259,19 +365,35 @@ pub struct SpdzGroupShare<T, M> {
 impl<G: Group, M> Reveal for SpdzGroupShare<G, M> {
     type Base = G;
 
-    fn reveal(self) -> G {
-        let vals: Vec<G> = Net::broadcast(&self.sh.val);
+    fn try_reveal(self) -> Result<G, MpcNetError> {
+        let vals: Vec<G> = Net::broadcast(&self.sh.val)?;
         // _Pragmatic MPC_ 6.6.2
         let x: G = vals.iter().sum();
+        if !checks_macs() {
+            return Ok(x);
+        }
         let dx_t: G = {
             let mut t = x.clone();
             t *= mac_share::<G::ScalarField>();
             t - self.mac.val
         };
-        let all_dx_ts: Vec<G> = Net::atomic_broadcast(&dx_t);
+        let all_dx_ts: Vec<G> = Net::atomic_broadcast(&dx_t)?;
         let sum: G = all_dx_ts.iter().sum();
-        assert!(sum.is_zero());
-        x
+        if !sum.is_zero() {
+            return Err(MpcNetError::MacFailure);
+        }
+        Ok(x)
+    }
+    /// As for [`SpdzFieldShare`], with the mask `r P` (see [`SpdzGroupShare::masks`]).
+    fn reveal_to(self, to: usize) -> Option<G> {
+        let (mut mask, r) = Self::masks(to, 1);
+        let mask = mask.pop().unwrap();
+        let masked = Self {
+            sh: AdditiveGroupShare::from_add_shared(self.sh.val - mask.sh.val),
+            mac: AdditiveGroupShare::from_add_shared(self.mac.val - mask.mac.val),
+        };
+        let masked = masked.try_reveal().or_abort();
+        r.map(|r| masked + r[0])
     }
     fn from_public(f: G) -> Self {
         Self {
@@ -284,37 +406,86 @@ impl<G: Group, M> Reveal for SpdzGroupShare<G, M> {
         }
     }
     fn from_add_shared(f: G) -> Self {
-        Self {
-            sh: Reveal::from_add_shared(f),
-            mac: Reveal::from_add_shared({
-                let mut t = f;
-                t *= mac::<G::ScalarField>();
-                t
-            }),
-        }
+        Self::from_add_shared_batch(vec![f]).pop().unwrap()
+    }
+    /// As for [`SpdzFieldShare`], with the masks `r P` (see [`SpdzGroupShare::masks`]).
+    fn from_add_shared_batch(xs: Vec<G>) -> Vec<Self> {
+        let n = xs.len();
+        let (masks, rs): (Vec<Vec<Self>>, Vec<Option<Vec<G>>>) =
+            (0..Net::n_parties()).map(|owner| Self::masks(owner, n)).unzip();
+        let ours = rs[Net::party_id()].as_ref().unwrap();
+        let diffs: Vec<G> = xs.iter().zip(ours).map(|(x, r)| *x - r).collect();
+        let all_diffs: Vec<Vec<G>> = Net::broadcast(&diffs).or_abort();
+        (0..n)
+            .map(|i| {
+                let mut s = masks[0][i];
+                for m in &masks[1..] {
+                    s.sh.val += m[i].sh.val;
+                    s.mac.val += m[i].mac.val;
+                }
+                s.shifted(all_diffs.iter().map(|d| &d[i]).sum())
+            })
+            .collect()
     }
     fn king_share<R: Rng>(f: Self::Base, rng: &mut R) -> Self {
-        let mut r: Vec<G> = (0..(Net::n_parties()-1)).map(|_| G::rand(rng)).collect();
-        let sum_r: G = r.iter().sum();
-        r.push(f - sum_r);
-        Self::from_add_shared(Net::recv_from_king( if Net::am_king() { Some(r) } else { None }))
-    }
-    fn king_share_batch<R: Rng>(f: Vec<Self::Base>, rng: &mut R) -> Vec<Self> {
-        let mut rs: Vec<Vec<Self::Base>> =
-            (0..(Net::n_parties()-1)).map(|_| {
-            (0..f.len()).map(|_| {
-                Self::Base::rand(rng)
-            }).collect()
-        }).collect();
-        let final_shares: Vec<Self::Base> = (0..rs[0].len()).map(|i| {
-            f[i] - &rs.iter().map(|r| &r[i]).sum()
-        }).collect();
-        rs.push(final_shares);
-        Net::recv_from_king(if Net::am_king() { Some(rs) } else {None}).into_iter().map(Self::from_add_shared).collect()
+        Self::king_share_batch(vec![f], rng).pop().unwrap()
+    }
+    /// As for [`SpdzFieldShare`], with the masks `r P` (see [`SpdzGroupShare::masks`]).
+    fn king_share_batch<R: Rng>(f: Vec<Self::Base>, _rng: &mut R) -> Vec<Self> {
+        let king = if Net::am_king() {
+            Some(Self::masks(0, f.len()))
+        } else {
+            None
+        };
+        let diffs: Vec<G> = Net::recv_from_king(king.as_ref().map(|(_, rs)| {
+            let diffs: Vec<G> = f.iter().zip(rs.as_ref().unwrap()).map(|(x, r)| *x - r).collect();
+            vec![diffs; Net::n_parties()]
+        }))
+        .or_abort();
+        let masks = match king {
+            Some((masks, _)) => masks,
+            None => Self::masks(0, diffs.len()).0,
+        };
+        masks.into_iter().zip(diffs).map(|(s, d)| s.shifted(d)).collect()
+    }
+    fn init_protocol() {
+        init_mac_key::<G::ScalarField>()
+    }
+    fn deinit_protocol() {
+        deinit_mac_key::<G::ScalarField>()
+    }
+}
+
+impl<G: Group, M> SpdzGroupShare<G, M> {
+    /// `n` of party `owner`'s input masks for group elements: `r P` for its field input masks `r`
+    /// and a fixed, public base `P`, and their values at `owner`.
+    ///
+    /// These only hide elements of the subgroup generated by `P`: for the prime-order curve groups
+    /// we share, that is the whole group.
+    fn masks(owner: usize, n: usize) -> (Vec<Self>, Option<Vec<G>>) {
+        let base = G::rand(&mut StdRng::seed_from_u64(0));
+        let (masks, rs) = input_masks::<G::ScalarField>(owner, n);
+        let masks = masks
+            .into_iter()
+            .map(|r| Self {
+                sh: AdditiveGroupShare::from_add_shared(base.mul(&r.sh.val)),
+                mac: AdditiveGroupShare::from_add_shared(base.mul(&r.mac.val)),
+            })
+            .collect();
+        (masks, rs.map(|rs| rs.iter().map(|r| base.mul(r)).collect()))
+    }
+
+    /// This share, shifted by the public `d`.
+    fn shifted(mut self, d: G) -> Self {
+        if Net::am_king() {
+            self.sh.val += d;
+        }
+        self.mac.val += d.mul(&mac_share::<G::ScalarField>());
+        self
     }
 }
 macro_rules! impl_spdz_basics_2_param {
-    ($share:ident, $bound:ident) => {
+    ($share:ident, $bound:ident $(, $phant:ident)*) => {
         impl<T: $bound, M> Display for $share<T, M> {
             fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
                 write!(f, "{}", self.sh.val)
@@ -325,49 +496,7 @@ macro_rules! impl_spdz_basics_2_param {
                 write!(f, "{:?}", self.sh.val)
             }
         }
-        impl<T: $bound, M> ToBytes for $share<T, M> {
-            fn write<W: Write>(&self, _writer: W) -> io::Result<()> {
-                unimplemented!("write")
-            }
-        }
-        impl<T: $bound, M> FromBytes for $share<T, M> {
-            fn read<R: Read>(_reader: R) -> io::Result<Self> {
-                unimplemented!("read")
-            }
-        }
-        impl<T: $bound, M> CanonicalSerialize for $share<T, M> {
-            fn serialize<W: Write>(&self, _writer: W) -> Result<(), SerializationError> {
-                unimplemented!("serialize")
-            }
-            fn serialized_size(&self) -> usize {
-                unimplemented!("serialized_size")
-            }
-        }
-        impl<T: $bound, M> CanonicalSerializeWithFlags for $share<T, M> {
-            fn serialize_with_flags<W: Write, F: Flags>(
-                &self,
-                _writer: W,
-                _flags: F,
-            ) -> Result<(), SerializationError> {
-                unimplemented!("serialize_with_flags")
-            }
-
-            fn serialized_size_with_flags<F: Flags>(&self) -> usize {
-                unimplemented!("serialized_size_with_flags")
-            }
-        }
-        impl<T: $bound, M> CanonicalDeserialize for $share<T, M> {
-            fn deserialize<R: Read>(_reader: R) -> Result<Self, SerializationError> {
-                unimplemented!("deserialize")
-            }
-        }
-        impl<T: $bound, M> CanonicalDeserializeWithFlags for $share<T, M> {
-            fn deserialize_with_flags<R: Read, F: Flags>(
-                _reader: R,
-            ) -> Result<(Self, F), SerializationError> {
-                unimplemented!("deserialize_with_flags")
-            }
-        }
+        impl_share_encoding!([T: $bound, M], $share<T, M>, [sh, mac], [$($phant),*]);
         impl<T: $bound, M> UniformRand for $share<T, M> {
             fn rand<R: Rng + ?Sized>(_rng: &mut R) -> Self {
                 todo!()
@@ -386,7 +515,7 @@ impl<G: Group, M: Msm<G, G::ScalarField>> GroupShare<G> for SpdzGroupShare<G, M>
         let (s_vals, macs): (Vec<G>, Vec<G>) =
             selfs.into_iter().map(|s| (s.sh.val, s.mac.val)).unzip();
         let n = s_vals.len();
-        let all_vals = Net::broadcast(&s_vals);
+        let all_vals = Net::broadcast(&s_vals).or_abort();
         let vals: Vec<G> =
             (0..n).map(|i| all_vals.iter().map(|v| &v[i]).sum()).collect();
         let dx_ts: Vec<G> =
@@ -395,10 +524,12 @@ impl<G: Group, M: Msm<G, G::ScalarField>> GroupShare<G> for SpdzGroupShare<G, M>
             .zip(vals.iter())
             .map(|(mac, val)| val.mul(&mac_share::<G::ScalarField>()) - mac)
             .collect();
-        let all_dx_ts: Vec<Vec<G>> = Net::atomic_broadcast(&dx_ts);
+        let all_dx_ts: Vec<Vec<G>> = Net::atomic_broadcast(&dx_ts).or_abort();
         for i in 0..n {
             let sum: G = all_dx_ts.iter().map(|dx_ts| &dx_ts[i]).sum();
-            assert!(sum.is_zero());
+            if !sum.is_zero() {
+                MpcNetError::MacFailure.abort();
+            }
         }
         vals
     }
@@ -439,7 +570,7 @@ impl<G: Group, M: Msm<G, G::ScalarField>> GroupShare<G> for SpdzGroupShare<G, M>
 
     fn multi_scale_pub_group(bases: &[G], scalars: &[Self::FieldShare]) -> Self {
         let shares: Vec<G::ScalarField> = scalars.into_iter().map(|s| s.sh.val.clone()).collect();
-        let macs: Vec<G::ScalarField> = scalars.into_iter().map(|s| s.sh.val.clone()).collect();
+        let macs: Vec<G::ScalarField> = scalars.into_iter().map(|s| s.mac.val.clone()).collect();
         let sh = AdditiveGroupShare::from_add_shared(M::msm(bases, &shares));
         let mac = AdditiveGroupShare::from_add_shared(M::msm(bases, &macs));
         Self { sh, mac }
@@ -461,20 +592,22 @@ pub struct SpdzMulFieldShare<T, S> {
     mac: MulFieldShare<T>,
     _phants: PhantomData<S>,
 }
-impl_spdz_basics_2_param!(SpdzMulFieldShare, Field);
+impl_spdz_basics_2_param!(SpdzMulFieldShare, Field, _phants);
 
 impl<F: Field, S: PrimeField> Reveal for SpdzMulFieldShare<F, S> {
     type Base = F;
 
-    fn reveal(self) -> F {
-        let vals: Vec<F> = Net::broadcast(&self.sh.val);
+    fn try_reveal(self) -> Result<F, MpcNetError> {
+        let vals: Vec<F> = Net::broadcast(&self.sh.val)?;
         // _Pragmatic MPC_ 6.6.2
         let x: F = vals.iter().product();
         let dx_t: F = x.pow(&mac_share::<S>().into_repr()) / self.mac.val;
-        let all_dx_ts: Vec<F> = Net::atomic_broadcast(&dx_t);
+        let all_dx_ts: Vec<F> = Net::atomic_broadcast(&dx_t)?;
         let prod: F = all_dx_ts.iter().product();
-        assert!(prod.is_one());
-        x
+        if !prod.is_one() {
+            return Err(MpcNetError::MacFailure);
+        }
+        Ok(x)
     }
     fn from_public(f: F) -> Self {
         Self {
@@ -483,12 +616,21 @@ impl<F: Field, S: PrimeField> Reveal for SpdzMulFieldShare<F, S> {
             _phants: PhantomData::default(),
         }
     }
+    /// Authenticating multiplicative shares would need masks in `F`, which the preprocessing does
+    /// not deal. So this is only supported with dummy preprocessing, where the parties open their
+    /// shares.
     fn from_add_shared(f: F) -> Self {
-        Self {
-            sh: Reveal::from_add_shared(f),
-            mac: Reveal::from_add_shared(f.pow(&mac::<S>().into_repr())),
-            _phants: PhantomData::default(),
+        if !preprocessing::uses_dummy() {
+            panic!("SpdzMulFieldShare inputs need dummy preprocessing: no masks are dealt for them");
         }
+        let prod: F = Net::broadcast(&f).or_abort().iter().product();
+        Self::from_public(prod)
+    }
+    fn init_protocol() {
+        init_mac_key::<S>()
+    }
+    fn deinit_protocol() {
+        deinit_mac_key::<S>()
     }
 }
 
@@ -498,10 +640,8 @@ impl<F: Field, S: PrimeField> FieldShare<F> for SpdzMulFieldShare<F, S> {
     }
 
     fn scale(&mut self, other: &F) -> &mut Self {
-        if Net::am_king() {
-            self.sh.scale(other);
-        }
-        self.mac.scale(&other.pow(&mac_share::<S>().into_repr()));
+        self.sh.scale(other);
+        self.mac.val *= other.pow(&mac_share::<S>().into_repr());
         self
     }
 
@@ -510,9 +650,11 @@ impl<F: Field, S: PrimeField> FieldShare<F> for SpdzMulFieldShare<F, S> {
     }
 
     fn mul<S2: BeaverSource<Self, Self, Self>>(self, other: Self, _source: &mut S2) -> Self {
-        self.sh.mul(other.sh, &mut PanicBeaverSource::default());
-        self.mac.mul(other.mac, &mut PanicBeaverSource::default());
-        self
+        Self {
+            sh: self.sh.mul(other.sh, &mut PanicBeaverSource::default()),
+            mac: self.mac.mul(other.mac, &mut PanicBeaverSource::default()),
+            _phants: PhantomData::default(),
+        }
     }
 
     fn batch_mul<S2: BeaverSource<Self, Self, Self>>(
@@ -521,8 +663,7 @@ impl<F: Field, S: PrimeField> FieldShare<F> for SpdzMulFieldShare<F, S> {
         _source: &mut S2,
     ) -> Vec<Self> {
         for (x, y) in xs.iter_mut().zip(ys.iter()) {
-            x.sh.mul(y.sh, &mut PanicBeaverSource::default());
-            x.mac.mul(y.mac, &mut PanicBeaverSource::default());
+            *x = x.mul(*y, &mut PanicBeaverSource::default());
         }
         xs
     }
@@ -648,4 +789,41 @@ impl<E: PairingEngine> PairingShare<E> for SpdzPairingShare<E> {
         SpdzGroupShare<E::G2Projective, ProjectiveMsm<E::G2Projective>>;
     type G1 = SpdzG1Share<E>;
     type G2 = SpdzG2Share<E>;
+
+    // Pairings with a public point map the value and MAC shares alike: `e(alpha * a, b)` is
+    // `e(a, b)^alpha`, which is the multiplicative MAC.
+    fn miller_loop_sh_pub(
+        a: &Self::G1AffineShare,
+        b: &E::G2Prepared,
+    ) -> SpdzMulFieldShare<E::Fqk, E::Fr> {
+        SpdzMulFieldShare {
+            sh: AdditivePairingShare::<E>::miller_loop_sh_pub(&a.sh, b),
+            mac: AdditivePairingShare::<E>::miller_loop_sh_pub(&a.mac, b),
+            _phants: PhantomData::default(),
+        }
+    }
+    fn miller_loop_pub_sh(
+        a: &E::G1Prepared,
+        b: &Self::G2AffineShare,
+    ) -> SpdzMulFieldShare<E::Fqk, E::Fr> {
+        SpdzMulFieldShare {
+            sh: AdditivePairingShare::<E>::miller_loop_pub_sh(a, &b.sh),
+            mac: AdditivePairingShare::<E>::miller_loop_pub_sh(a, &b.mac),
+            _phants: PhantomData::default(),
+        }
+    }
+    fn final_exp_sh(f: SpdzMulFieldShare<E::Fqk, E::Fr>) -> SpdzMulFieldShare<E::Fqk, E::Fr> {
+        SpdzMulFieldShare {
+            sh: AdditivePairingShare::<E>::final_exp_sh(f.sh),
+            mac: AdditivePairingShare::<E>::final_exp_sh(f.mac),
+            _phants: PhantomData::default(),
+        }
+    }
+    fn pow_pub_base(base: &E::Fqk, e: &Self::FrShare) -> SpdzMulFieldShare<E::Fqk, E::Fr> {
+        SpdzMulFieldShare {
+            sh: AdditivePairingShare::<E>::pow_pub_base(base, &e.sh),
+            mac: AdditivePairingShare::<E>::pow_pub_base(base, &e.mac),
+            _phants: PhantomData::default(),
+        }
+    }
 }
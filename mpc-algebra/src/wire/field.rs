@@ -18,9 +18,11 @@ use std::iter::{Product, Sum};
 use std::marker::PhantomData;
 use std::ops::*;
 
+use super::super::share::bits;
 use super::super::share::field::FieldShare;
 use super::super::share::BeaverSource;
-use crate::Reveal;
+use crate::preprocessing::PreprocessedFieldTripleSource;
+use crate::{MpcNetError, Reveal};
 use mpc_net::{MpcNet, MpcMultiNet as Net};
 use ark_std::test_rng;
 use crate::share::{
@@ -53,36 +55,16 @@ impl<T: Field, S: FieldShare<T>> BeaverSource<S, S, S> for DummyFieldTripleSourc
     #[inline]
     fn triple(&mut self) -> (S, S, S) {
         (
-            S::from_add_shared(if Net::am_king() {
-                T::one()
-            } else {
-                T::zero()
-            }),
-            S::from_add_shared(if Net::am_king() {
-                T::one()
-            } else {
-                T::zero()
-            }),
-            S::from_add_shared(if Net::am_king() {
-                T::one()
-            } else {
-                T::zero()
-            }),
+            S::from_public(T::one()),
+            S::from_public(T::one()),
+            S::from_public(T::one()),
         )
     }
     #[inline]
     fn inv_pair(&mut self) -> (S, S) {
         (
-            S::from_add_shared(if Net::am_king() {
-                T::one()
-            } else {
-                T::zero()
-            }),
-            S::from_add_shared(if Net::am_king() {
-                T::one()
-            } else {
-                T::zero()
-            }),
+            S::from_public(T::one()),
+            S::from_public(T::one()),
         )
     }
 }
@@ -149,7 +131,7 @@ impl<T: Field, S: FieldShare<T>> MpcField<T, S> {
         match self {
             Self::Public(x) => x.inverse().map(MpcField::Public),
             Self::Shared(x) => Some(MpcField::Shared(
-                x.inv(&mut DummyFieldTripleSource::default()),
+                x.inv(&mut PreprocessedFieldTripleSource::default()),
             )),
         }
     }
@@ -192,7 +174,7 @@ impl<'a, T: Field, S: FieldShare<T>> MulAssign<&'a MpcField<T, S>> for MpcField<
                     x.scale(y);
                 }
                 MpcField::Shared(y) => {
-                    let t = x.mul(*y, &mut DummyFieldTripleSource::default());
+                    let t = x.mul(*y, &mut PreprocessedFieldTripleSource::default());
                     *self = MpcField::Shared(t);
                 }
             },
@@ -229,7 +211,7 @@ impl<'a, T: Field, S: FieldShare<T>> DivAssign<&'a MpcField<T, S>> for MpcField<
                     *x /= y;
                 }
                 MpcField::Shared(y) => {
-                    let mut t = y.inv(&mut DummyFieldTripleSource::default());
+                    let mut t = y.inv(&mut PreprocessedFieldTripleSource::default());
                     t.scale(&x);
                     *self = MpcField::Shared(t);
                 }
@@ -239,7 +221,7 @@ impl<'a, T: Field, S: FieldShare<T>> DivAssign<&'a MpcField<T, S>> for MpcField<
                     x.scale(&y.inverse().unwrap());
                 }
                 MpcField::Shared(y) => {
-                    let src = &mut DummyFieldTripleSource::default();
+                    let src = &mut PreprocessedFieldTripleSource::default();
                     *x = x.div(*y, src);
                 }
             },
@@ -315,13 +297,20 @@ impl<T: Field, S: FieldShare<T>> MpcWire for MpcField<T, S> {
 impl<T: Field, S: FieldShare<T>> Reveal for MpcField<T, S> {
     type Base = T;
     #[inline]
-    fn reveal(self) -> Self::Base {
+    fn try_reveal(self) -> Result<Self::Base, MpcNetError> {
         let result = match self {
-            Self::Shared(s) => s.reveal(),
+            Self::Shared(s) => s.try_reveal()?,
             Self::Public(s) => s,
         };
         super::macros::check_eq(result.clone());
-        result
+        Ok(result)
+    }
+    #[inline]
+    fn reveal_to(self, to: usize) -> Option<Self::Base> {
+        match self {
+            Self::Shared(s) => s.reveal_to(to),
+            Self::Public(s) => crate::only_to(to, s),
+        }
     }
     #[inline]
     fn from_public(b: Self::Base) -> Self {
@@ -332,6 +321,10 @@ impl<T: Field, S: FieldShare<T>> Reveal for MpcField<T, S> {
         MpcField::Shared(S::from_add_shared(b))
     }
     #[inline]
+    fn from_add_shared_batch(bs: Vec<Self::Base>) -> Vec<Self> {
+        S::from_add_shared_batch(bs).into_iter().map(Self::Shared).collect()
+    }
+    #[inline]
     fn unwrap_as_public(self) -> Self::Base {
         match self {
             Self::Shared(s) => s.unwrap_as_public(),
@@ -388,6 +381,10 @@ impl<T: PrimeField, S: FieldShare<T>> std::str::FromStr for MpcField<T, S> {
 impl<F: PrimeField, S: FieldShare<F>> Field for MpcField<F, S> {
     type BasePrimeField = Self;
     #[inline]
+    fn characteristic<'a>() -> &'a [u64] {
+        F::characteristic()
+    }
+    #[inline]
     fn extension_degree() -> u64 {
         unimplemented!("extension_degree")
     }
@@ -464,7 +461,7 @@ impl<F: PrimeField, S: FieldShare<F>> Field for MpcField<F, S> {
                     Self::Public(_) => unreachable!(),
                 })
                 .collect();
-            let nshares = S::batch_mul(sshares, oshares, &mut DummyFieldTripleSource::default());
+            let nshares = S::batch_mul(sshares, oshares, &mut PreprocessedFieldTripleSource::default());
             for (self_, new) in selfs.iter_mut().zip(nshares.into_iter()) {
                 *self_ = Self::Shared(new);
             }
@@ -500,7 +497,7 @@ impl<F: PrimeField, S: FieldShare<F>> Field for MpcField<F, S> {
                     Self::Public(_) => unreachable!(),
                 })
                 .collect();
-            let nshares = S::batch_div(sshares, oshares, &mut DummyFieldTripleSource::default());
+            let nshares = S::batch_div(sshares, oshares, &mut PreprocessedFieldTripleSource::default());
             for (self_, new) in selfs.iter_mut().zip(nshares.into_iter()) {
                 *self_ = Self::Shared(new);
             }
@@ -525,7 +522,7 @@ impl<F: PrimeField, S: FieldShare<F>> Field for MpcField<F, S> {
                 })
                 .collect();
             for (self_, new) in selfs.iter_mut().zip(
-                S::partial_products(sshares, &mut DummyFieldTripleSource::default()).into_iter(),
+                S::partial_products(sshares, &mut PreprocessedFieldTripleSource::default()).into_iter(),
             ) {
                 *self_ = Self::Shared(new);
             }
@@ -600,30 +597,82 @@ impl<F: PrimeField, S: FieldShare<F>> PrimeField for MpcField<F, S> {
     type Params = F::Params;
     type BigInt = F::BigInt;
     #[inline]
-    fn from_repr(_r: <Self as PrimeField>::BigInt) -> Option<Self> {
-        unimplemented!("No BigInt reprs for shared fields! (from_repr)")
-        //F::from_repr(r).map(|v| Self::from_public(v))
+    fn from_repr(r: <Self as PrimeField>::BigInt) -> Option<Self> {
+        F::from_repr(r).map(Self::from_public)
     }
-    // We're assuming that into_repr is linear
     #[inline]
     fn into_repr(&self) -> <Self as PrimeField>::BigInt {
-        unimplemented!("No BigInt reprs for shared fields! (into_repr)")
-        //self.unwrap_as_public().into_repr()
+        match self {
+            Self::Public(x) => x.into_repr(),
+            Self::Shared(_) => {
+                panic!("No BigInt reprs for shared fields! Use gadgets::BitDecomposition instead.")
+            }
+        }
+    }
+}
+
+impl<F: PrimeField, S: FieldShare<F>> MpcField<F, S> {
+    /// A share of a random `r` that is not zero, and a share of `1 / r`.
+    ///
+    /// We open `r * s` for another random `s`, and resample both until it is non-zero. Since `s`
+    /// is uniform, the opened value is too, so it says nothing about `r`.
+    fn rand_nonzero_with_inverse() -> (S, S) {
+        let src = &mut PreprocessedFieldTripleSource::default();
+        let rng = &mut rand::thread_rng();
+        loop {
+            let r = S::rand(rng);
+            let s = S::rand(rng);
+            let rs = r.mul(s, src).open();
+            if let Some(rs_inv) = rs.inverse() {
+                let mut r_inv = s;
+                r_inv.scale(&rs_inv);
+                break (r, r_inv);
+            }
+        }
+    }
+
+    /// Open `x * r^2` for a random non-zero `r`, returning it and a share of `1 / r`.
+    fn open_masked_square(x: &S) -> (F, S) {
+        let src = &mut PreprocessedFieldTripleSource::default();
+        let (r, r_inv) = Self::rand_nonzero_with_inverse();
+        (x.mul(r.mul(r, src), src).open(), r_inv)
     }
 }
 
+/// For a shared `x`, we open `x * r^2` for a random non-zero shared `r`: it has the same Legendre
+/// symbol as `x`, and dividing its square root by `r` gives a square root of `x`.
+///
+/// This leaks the Legendre symbol of `x` to every party: whether it is zero, a non-zero square, or
+/// a non-square. That is all it leaks, since for a given symbol the opened value is uniform.
+/// `legendre` outputs the symbol anyway, but note that `sqrt` leaks whether `x` is zero too.
 impl<F: PrimeField, S: FieldShare<F>> SquareRootField for MpcField<F, S> {
     #[inline]
     fn legendre(&self) -> ark_ff::LegendreSymbol {
-        todo!()
+        match self {
+            Self::Public(x) => bits::legendre(*x),
+            Self::Shared(x) => bits::legendre(Self::open_masked_square(x).0),
+        }
     }
     #[inline]
     fn sqrt(&self) -> Option<Self> {
-        todo!()
+        match self {
+            Self::Public(x) => bits::sqrt(*x).map(Self::Public),
+            Self::Shared(x) => {
+                let (masked, r_inv) = Self::open_masked_square(x);
+                bits::sqrt(masked).map(|y| {
+                    let mut root = r_inv;
+                    root.scale(&y);
+                    Self::Shared(root)
+                })
+            }
+        }
     }
     #[inline]
     fn sqrt_in_place(&mut self) -> Option<&mut Self> {
-        todo!()
+        self.sqrt().map(|s| {
+            *self = s;
+            self
+        })
     }
 }
 
@@ -632,7 +681,7 @@ mod poly_impl {
     use crate::struct_share_conversion_simp_impl;
     use crate::share::*;
     use crate::wire::*;
-    use crate::Reveal;
+    use crate::{MpcNetError, Reveal};
     use ark_ff::PrimeField;
     use ark_poly::domain::{EvaluationDomain, GeneralEvaluationDomain};
     use ark_poly::evaluations::univariate::Evaluations;
@@ -646,11 +695,11 @@ mod poly_impl {
     impl<F: PrimeField, S: FieldShare<F>> Reveal for Evaluations<MpcField<F, S>> {
         type Base = Evaluations<F>;
 
-        fn reveal(self) -> Self::Base {
-            Evaluations::from_vec_and_domain(
-                self.evals.reveal(),
+        fn try_reveal(self) -> Result<Self::Base, MpcNetError> {
+            Ok(Evaluations::from_vec_and_domain(
+                self.evals.try_reveal()?,
                 GeneralEvaluationDomain::new(self.domain.size()).unwrap(),
-            )
+            ))
         }
 
         fn from_add_shared(b: Self::Base) -> Self {
@@ -686,3 +735,33 @@ mod poly_impl {
     //     }
     // }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::share::rss::RssFieldShare;
+    use ark_bls12_377::Fr;
+    use ark_poly::{univariate::DensePolynomial, UVPolynomial};
+
+    type MpcFr = MpcField<Fr, RssFieldShare<Fr>>;
+
+    #[test]
+    fn fft_products_of_shared_polynomials_have_product_length() {
+        Net::simulate(5, || {
+            MpcFr::init_protocol();
+            let rng = &mut test_rng();
+            let a = DensePolynomial::<Fr>::rand(5, rng);
+            let b = DensePolynomial::<Fr>::rand(9, rng);
+            let share = |p: &DensePolynomial<Fr>, rng: &mut _| DensePolynomial {
+                coeffs: MpcFr::king_share_batch(p.coeffs.clone(), rng),
+            };
+            let (shared_a, shared_b) = (share(&a, rng), share(&b, rng));
+            // Shared coefficients never look zero, so only the truncation trims the FFT padding.
+            let product = &shared_a * &shared_b;
+            assert_eq!(product.coeffs.len(), a.coeffs.len() + b.coeffs.len() - 1);
+            let revealed: Vec<Fr> = product.coeffs.into_iter().map(|c| c.reveal()).collect();
+            assert_eq!(revealed, (&a * &b).coeffs);
+            MpcFr::deinit_protocol();
+        });
+    }
+}
@@ -28,7 +28,7 @@ use super::super::share::{
 use crate::msm::*;
 use super::field::MpcField;
 use mpc_net::{MpcNet, MpcMultiNet as Net};
-use crate::Reveal;
+use crate::{MpcNetError, Reveal};
 use ark_std::test_rng;
 
 #[derive(Clone, Copy, Hash, Debug, PartialEq, Eq, PartialOrd, Ord)]
@@ -52,28 +52,16 @@ impl<T: Group, S: GroupShare<T>> BeaverSource<S, S::FieldShare, S>
     #[inline]
     fn triple(&mut self) -> (S, S::FieldShare, S) {
         (
-            S::from_add_shared(T::zero()),
-            <S::FieldShare as Reveal>::from_add_shared(if Net::am_king() {
-                T::ScalarField::one()
-            } else {
-                T::ScalarField::zero()
-            }),
-            S::from_add_shared(T::zero()),
+            S::from_public(T::zero()),
+            <S::FieldShare as Reveal>::from_public(T::ScalarField::one()),
+            S::from_public(T::zero()),
         )
     }
     #[inline]
     fn inv_pair(&mut self) -> (S::FieldShare, S::FieldShare) {
         (
-            <S::FieldShare as Reveal>::from_add_shared(if Net::am_king() {
-                T::ScalarField::one()
-            } else {
-                T::ScalarField::zero()
-            }),
-            <S::FieldShare as Reveal>::from_add_shared(if Net::am_king() {
-                T::ScalarField::one()
-            } else {
-                T::ScalarField::zero()
-            }),
+            <S::FieldShare as Reveal>::from_public(T::ScalarField::one()),
+            <S::FieldShare as Reveal>::from_public(T::ScalarField::one()),
         )
     }
 }
@@ -166,13 +154,20 @@ impl<T: Group, S: GroupShare<T>> MpcWire for MpcGroup<T, S> {
 impl<T: Group, S: GroupShare<T>> Reveal for MpcGroup<T, S> {
     type Base = T;
     #[inline]
-    fn reveal(self) -> Self::Base {
+    fn try_reveal(self) -> Result<Self::Base, MpcNetError> {
         let result = match self {
-            Self::Shared(s) => s.reveal(),
+            Self::Shared(s) => s.try_reveal()?,
             Self::Public(s) => s,
         };
         super::macros::check_eq(result.clone());
-        result
+        Ok(result)
+    }
+    #[inline]
+    fn reveal_to(self, to: usize) -> Option<Self::Base> {
+        match self {
+            Self::Shared(s) => s.reveal_to(to),
+            Self::Public(s) => crate::only_to(to, s),
+        }
     }
     #[inline]
     fn from_public(b: Self::Base) -> Self {
@@ -183,6 +178,10 @@ impl<T: Group, S: GroupShare<T>> Reveal for MpcGroup<T, S> {
         Self::Shared(S::from_add_shared(b))
     }
     #[inline]
+    fn from_add_shared_batch(bs: Vec<Self::Base>) -> Vec<Self> {
+        S::from_add_shared_batch(bs).into_iter().map(Self::Shared).collect()
+    }
+    #[inline]
     fn unwrap_as_public(self) -> Self::Base {
         match self {
             Self::Shared(s) => s.unwrap_as_public(),
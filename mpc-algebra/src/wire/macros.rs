@@ -3,7 +3,7 @@
 use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
 
 use crate::channel::{self, MpcSerNet};
-use mpc_net::MpcNet;
+use mpc_net::{MpcNet, OrAbort};
 
 use std::fmt::Display;
 
@@ -13,7 +13,7 @@ pub fn check_eq<T: CanonicalSerialize + CanonicalDeserialize + Clone + Eq + Disp
     debug_assert!({
         use log::debug;
         if mpc_net::two::is_init() {
-            let other = channel::exchange(&t);
+            let other = channel::exchange(&t).or_abort();
             if t == other {
                 debug!("Consistency check passed");
                 true
@@ -23,7 +23,7 @@ pub fn check_eq<T: CanonicalSerialize + CanonicalDeserialize + Clone + Eq + Disp
             }
         } else {
             // debug!("Consistency check");
-            let others = mpc_net::MpcMultiNet::broadcast(&t);
+            let others = mpc_net::MpcMultiNet::broadcast(&t).or_abort();
             let mut result = true;
             for (i, other_t) in others.iter().enumerate() {
                 if &t != other_t {
@@ -72,57 +72,78 @@ macro_rules! impl_basics_2 {
                 }
             }
         }
+        // NB: ToBytes feeds Fiat-Shamir transcripts, so public values are written exactly as the
+        // underlying type writes them, with no tag. Shared values are rejected, so that FromBytes
+        // reads back everything ToBytes writes; use CanonicalSerialize/CanonicalDeserialize to
+        // persist shared values.
         impl<T: $bound, S: $share<T>> ToBytes for $wrap<T, S> {
             fn write<W: Write>(&self, writer: W) -> io::Result<()> {
                 match self {
                     Self::Public(v) => v.write(writer),
-                    Self::Shared(_) => unimplemented!("write share: {}", self),
+                    Self::Shared(_) => Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "shared values have no ToBytes encoding; use CanonicalSerialize",
+                    )),
                 }
             }
         }
         impl<T: $bound, S: $share<T>> FromBytes for $wrap<T, S> {
-            fn read<R: Read>(_reader: R) -> io::Result<Self> {
-                unimplemented!("read")
+            fn read<R: Read>(reader: R) -> io::Result<Self> {
+                T::read(reader).map(Self::Public)
             }
         }
+        // The canonical encoding is a tag byte (0 for public, 1 for shared) followed by the value.
         impl<T: $bound, S: $share<T>> CanonicalSerialize for $wrap<T, S> {
-            fn serialize<W: Write>(&self, writer: W) -> Result<(), SerializationError> {
+            fn serialize<W: Write>(&self, mut writer: W) -> Result<(), SerializationError> {
                 match self {
-                    Self::Public(v) => v.serialize(writer),
-                    Self::Shared(_) => unimplemented!("serialize share: {}", self),
+                    Self::Public(v) => {
+                        0u8.serialize(&mut writer)?;
+                        v.serialize(writer)
+                    }
+                    Self::Shared(s) => {
+                        1u8.serialize(&mut writer)?;
+                        s.serialize(writer)
+                    }
                 }
             }
             fn serialized_size(&self) -> usize {
-                match self {
+                1 + match self {
                     Self::Public(v) => v.serialized_size(),
-                    Self::Shared(_) => unimplemented!("serialized_size share: {}", self),
+                    Self::Shared(s) => s.serialized_size(),
                 }
             }
         }
-        // NB: CanonicalSerializeWithFlags is unimplemented for Group.
+        // NB: CanonicalSerializeWithFlags is unimplemented for Group, so flags get their own byte.
         impl<T: $bound, S: $share<T>> CanonicalSerializeWithFlags for $wrap<T, S> {
             fn serialize_with_flags<W: Write, F: Flags>(
                 &self,
-                _writer: W,
-                _flags: F,
+                mut writer: W,
+                flags: F,
             ) -> Result<(), SerializationError> {
-                unimplemented!("serialize_with_flags")
+                self.serialize(&mut writer)?;
+                crate::share::encoding::write_flags(writer, flags)
             }
 
             fn serialized_size_with_flags<F: Flags>(&self) -> usize {
-                unimplemented!("serialized_size_with_flags")
+                self.serialized_size() + 1
             }
         }
         impl<T: $bound, S: $share<T>> CanonicalDeserialize for $wrap<T, S> {
-            fn deserialize<R: Read>(_reader: R) -> Result<Self, SerializationError> {
-                unimplemented!("deserialize")
+            fn deserialize<R: Read>(mut reader: R) -> Result<Self, SerializationError> {
+                match u8::deserialize(&mut reader)? {
+                    0 => Ok(Self::Public(T::deserialize(reader)?)),
+                    1 => Ok(Self::Shared(S::deserialize(reader)?)),
+                    _ => Err(SerializationError::InvalidData),
+                }
             }
         }
         impl<T: $bound, S: $share<T>> CanonicalDeserializeWithFlags for $wrap<T, S> {
             fn deserialize_with_flags<R: Read, F: Flags>(
-                _reader: R,
+                mut reader: R,
             ) -> Result<(Self, F), SerializationError> {
-                unimplemented!("deserialize_with_flags")
+                let v = Self::deserialize(&mut reader)?;
+                let flags = crate::share::encoding::read_flags(reader)?;
+                Ok((v, flags))
             }
         }
         impl<T: $bound, S: $share<T>> UniformRand for $wrap<T, S> {
@@ -22,14 +22,10 @@ use zeroize::Zeroize;
 
 use mpc_trait::MpcWire;
 
-use super::super::share::field::ExtFieldShare;
+use super::super::share::field::{ExtFieldShare, FieldShare};
 use super::super::share::group::GroupShare;
-use super::super::share::pairing::{AffProjShare, PairingShare};
-use super::super::share::BeaverSource;
-use super::super::share::{
-    AdditiveGroupShare,
-    RSS3GroupShare,
-};
+use super::super::share::pairing::{miller_loop_one, AffProjShare, FqkExtShare, PairingShare};
+use super::super::share::PanicBeaverSource;
 use crate::{
     RSS3PairingShare,
     AdditivePairingShare,
@@ -37,43 +33,12 @@ use crate::{
 
 use super::field::MpcField;
 use super::group::MpcGroup;
-use crate::Reveal;
+use crate::preprocessing::PreprocessedFieldTripleSource;
+use crate::{MpcNetError, Reveal};
 use crate::ShareConversion;
 
 use log::debug;
 
-#[derive(Derivative)]
-#[derivative(Default(bound = ""), Clone(bound = ""), Copy(bound = ""))]
-pub struct DummyPairingTripleSource<E, S> {
-    _phants: PhantomData<(E, S)>,
-}
-
-impl<E: PairingEngine, S: PairingShare<E>>
-    BeaverSource<MpcG1Projective<E, S>, MpcG2Projective<E, S>, MpcExtField<E::Fqk, S::FqkShare>>
-    for DummyPairingTripleSource<E, S>
-{
-    #[inline]
-    fn triple(
-        &mut self,
-    ) -> (
-        MpcG1Projective<E, S>,
-        MpcG2Projective<E, S>,
-        MpcExtField<E::Fqk, S::FqkShare>,
-    ) {
-        let g1 = E::G1Projective::zero();
-        let g2 = E::G2Projective::zero();
-        (
-            MpcG1Projective::from_add_shared(g1.clone()),
-            MpcG2Projective::from_add_shared(g2.clone()),
-            MpcExtField::from_add_shared(E::pairing(g1, g2)),
-        )
-    }
-    #[inline]
-    fn inv_pair(&mut self) -> (MpcG2Projective<E, S>, MpcG2Projective<E, S>) {
-        unimplemented!("No inverses from Pairing triple source")
-    }
-}
-
 #[derive(Debug, Derivative)]
 #[derivative(
     Clone(bound = ""),
@@ -113,6 +78,9 @@ pub struct MpcG1Projective<E: PairingEngine, PS: PairingShare<E>> {
 #[derivative(Clone(bound = ""), Default(bound = "E::G1Prepared: Default"))]
 pub struct MpcG1Prep<E: PairingEngine, PS: PairingShare<E>> {
     pub val: E::G1Prepared,
+    /// The point itself, if it is shared. Shared points cannot be prepared, so `val` is then
+    /// unused.
+    pub shared: Option<PS::G1AffineShare>,
     pub _phants: PhantomData<(E, PS)>,
 }
 
@@ -154,6 +122,9 @@ pub struct MpcG2Projective<E: PairingEngine, PS: PairingShare<E>> {
 #[derivative(Clone(bound = ""), Default(bound = "E::G1Prepared: Default"))]
 pub struct MpcG2Prep<E: PairingEngine, PS: PairingShare<E>> {
     pub val: E::G2Prepared,
+    /// The point itself, if it is shared. Shared points cannot be prepared, so `val` is then
+    /// unused.
+    pub shared: Option<PS::G2AffineShare>,
     pub _phants: PhantomData<(E, PS)>,
 }
 
@@ -163,6 +134,7 @@ impl<E: PairingEngine> ShareConversion for MpcG2Prep<E, RSS3PairingShare<E>> {
     fn share_conversion(self) -> Self::Target {
         Self::Target {
             val: self.val,
+            shared: self.shared.map(|s| s.share_conversion()),
             _phants: PhantomData::<(E, AdditivePairingShare::<E>)>,
         }
     }
@@ -209,28 +181,64 @@ impl<E: PairingEngine, PS: PairingShare<E>> PairingEngine for MpcPairingEngine<E
     type G2Prepared = MpcG2Prep<E, PS>;
     type Fqk = MpcExtField<E::Fqk, PS::FqkShare>;
 
-    fn miller_loop<'a, I>(_i: I) -> Self::Fqk
+    /// Panics if any operand is shared: our shares of a shared Miller loop are masked, and only
+    /// hold the right value after final exponentiation, so revealing them would be wrong and
+    /// could leak the mask. Use [`PairingEngine::product_of_pairings`] instead, which does both.
+    fn miller_loop<'a, I>(i: I) -> Self::Fqk
     where
         I: IntoIterator<Item = &'a (Self::G1Prepared, Self::G2Prepared)>,
     {
-        unimplemented!("miller_loop")
-        // <Bls12_377 as PairingEngine>::miller_loop(i)
+        let public: Vec<_> = i
+            .into_iter()
+            .map(|(p, q)| {
+                assert!(
+                    p.shared.is_none() && q.shared.is_none(),
+                    "The Miller loop of shared points is only right after final exponentiation; \
+                     use product_of_pairings"
+                );
+                (p.val.clone(), q.val.clone())
+            })
+            .collect();
+        MpcExtField::from_public(E::miller_loop(&public))
     }
 
-    fn final_exponentiation(_f: &Self::Fqk) -> Option<Self::Fqk> {
-        unimplemented!("final_exponentiation")
-        // <Bls12_377 as PairingEngine>::final_exponentiation(f)
+    fn final_exponentiation(f: &Self::Fqk) -> Option<Self::Fqk> {
+        match f.val {
+            MpcField::Public(f) => E::final_exponentiation(&f).map(MpcExtField::from_public),
+            MpcField::Shared(f) => Some(MpcExtField::wrap(MpcField::Shared(PS::final_exp_sh(f)))),
+        }
     }
 
-    /// Computes a product of pairings.
+    /// Computes a product of pairings, whose operands may be shared.
     #[must_use]
-    fn product_of_pairings<'a, I>(_i: I) -> Self::Fqk
+    fn product_of_pairings<'a, I>(i: I) -> Self::Fqk
     where
         I: IntoIterator<Item = &'a (Self::G1Prepared, Self::G2Prepared)>,
     {
-        // TODO: MPC!
-        // <Bls12_377 as PairingEngine>::product_of_pairings(i)
-        unimplemented!("pairing product")
+        let mut public = Vec::new();
+        let mut shared = Vec::new();
+        let mut sh_g1 = Vec::new();
+        let mut sh_g2 = Vec::new();
+        for (p, q) in i {
+            match (&p.shared, &q.shared) {
+                (None, None) => public.push((p.val.clone(), q.val.clone())),
+                (Some(a), None) => shared.push(PS::miller_loop_sh_pub(a, &q.val)),
+                (None, Some(b)) => shared.push(PS::miller_loop_pub_sh(&p.val, b)),
+                (Some(a), Some(b)) => {
+                    sh_g1.push(*a);
+                    sh_g2.push(*b);
+                }
+            }
+        }
+        if !sh_g1.is_empty() {
+            shared.extend(miller_loop_sh_sh::<E, PS>(sh_g1, sh_g2));
+        }
+        // The Miller loop, which never leaves this function while it is shared.
+        let mut f = MpcField::Public(E::miller_loop(&public));
+        for s in shared {
+            f *= &MpcField::Shared(s);
+        }
+        Self::final_exponentiation(&MpcExtField::wrap(f)).unwrap()
     }
 
     /// Performs multiple pairing operations
@@ -240,39 +248,64 @@ impl<E: PairingEngine, PS: PairingShare<E>> PairingEngine for MpcPairingEngine<E
         G1: Into<Self::G1Affine>,
         G2: Into<Self::G2Affine>,
     {
-        let a: Self::G1Affine = p.into();
-        let b: Self::G2Affine = q.into();
-        let a: Self::G1Projective = a.into();
-        let b: Self::G2Projective = b.into();
-        if a.is_shared() && b.is_shared() {
-            let source = &mut DummyPairingTripleSource::default();
-            // x * y = z
-            let (x, y, z) = source.triple();
-            // x + a
-            let xa = (a + x).reveal();
-            // y + b
-            let yb = (b + y).reveal();
-            let xayb: MpcExtField<E::Fqk, PS::FqkShare> =
-                MpcExtField::wrap(MpcField::Public(E::pairing(xa, yb)));
-            let xay: MpcExtField<E::Fqk, PS::FqkShare> = MpcExtField::wrap(MpcField::Shared(
-                <PS::FqkShare as ExtFieldShare<E::Fqk>>::Ext::from_add_shared(E::pairing(
-                    xa,
-                    y.unwrap_as_public(),
-                )),
-            ));
-            let xyb: MpcExtField<E::Fqk, PS::FqkShare> = MpcExtField::wrap(MpcField::Shared(
-                <PS::FqkShare as ExtFieldShare<E::Fqk>>::Ext::from_add_shared(E::pairing(
-                    x.unwrap_as_public(),
-                    yb,
-                )),
-            ));
-            z / xay / xyb * xayb
-        } else {
-            MpcExtField::wrap(MpcField::Public(E::pairing(a.reveal(), b.reveal())))
-        }
+        let a: Self::G1Prepared = p.into().into();
+        let b: Self::G2Prepared = q.into().into();
+        Self::product_of_pairings(&[(a, b)])
     }
 }
 
+/// Our shares of the Miller loops of pairs of shared points `(a_i, b_i)`.
+///
+/// Each pair is masked with a random pairing triple: `x = r * g1` and `y = s * g2` for random
+/// shared `r` and `s`, and `z = ML(g1, g2)^(rs)`, whose final exponentiation is `e(x, y)`. With
+/// `A = a + x` and `B = b + y` opened, `e(a, b) = e(A, B) * e(A, -y) * e(-x, B) * e(x, y)`.
+fn miller_loop_sh_sh<E: PairingEngine, PS: PairingShare<E>>(
+    a: Vec<PS::G1AffineShare>,
+    b: Vec<PS::G2AffineShare>,
+) -> Vec<FqkExtShare<E, PS>> {
+    let rng = &mut rand::thread_rng();
+    let g1 = E::G1Affine::prime_subgroup_generator();
+    let g2 = E::G2Affine::prime_subgroup_generator();
+    let base = miller_loop_one::<E>(g1.into(), g2.into());
+    let r: Vec<PS::FrShare> = (0..a.len()).map(|_| PS::FrShare::rand(rng)).collect();
+    let s: Vec<PS::FrShare> = (0..a.len()).map(|_| PS::FrShare::rand(rng)).collect();
+    let xs: Vec<PS::G1AffineShare> = r
+        .iter()
+        .map(|r| PS::G1AffineShare::scale_pub_group(g1, r))
+        .collect();
+    let ys: Vec<PS::G2AffineShare> = s
+        .iter()
+        .map(|s| PS::G2AffineShare::scale_pub_group(g2, s))
+        .collect();
+    let rs = PS::FrShare::batch_mul(r, s, &mut PreprocessedFieldTripleSource::default());
+    let big_a = PS::G1AffineShare::batch_open(a.into_iter().zip(&xs).map(|(mut a, x)| {
+        a.add(x);
+        a
+    }));
+    let big_b = PS::G2AffineShare::batch_open(b.into_iter().zip(&ys).map(|(mut b, y)| {
+        b.add(y);
+        b
+    }));
+    let src = &mut PanicBeaverSource::default();
+    big_a
+        .into_iter()
+        .zip(big_b)
+        .zip(xs.into_iter().zip(ys))
+        .zip(rs.iter())
+        .map(|(((big_a, big_b), (mut x, mut y)), rs)| {
+            let big_a: E::G1Prepared = big_a.into();
+            let big_b: E::G2Prepared = big_b.into();
+            x.neg();
+            y.neg();
+            let mut f = PS::pow_pub_base(&base, rs)
+                .mul(PS::miller_loop_pub_sh(&big_a, &y), src)
+                .mul(PS::miller_loop_sh_pub(&x, &big_b), src);
+            f.scale(&miller_loop_one::<E>(big_a, big_b));
+            f
+        })
+        .collect()
+}
+
 macro_rules! impl_pairing_mpc_wrapper {
     ($wrapped:ident, $bound1:ident, $bound2:ident, $base:ident, $share:ident, $wrap:ident) => {
         impl<E: $bound1, PS: $bound2<E>> Display for $wrap<E, PS> {
@@ -286,8 +319,8 @@ macro_rules! impl_pairing_mpc_wrapper {
             }
         }
         impl<E: $bound1, PS: $bound2<E>> FromBytes for $wrap<E, PS> {
-            fn read<R: Read>(_reader: R) -> io::Result<Self> {
-                unimplemented!("read")
+            fn read<R: Read>(reader: R) -> io::Result<Self> {
+                $wrapped::read(reader).map(|val| Self { val })
             }
         }
         impl<E: $bound1, PS: $bound2<E>> CanonicalSerialize for $wrap<E, PS> {
@@ -312,15 +345,15 @@ macro_rules! impl_pairing_mpc_wrapper {
             }
         }
         impl<E: $bound1, PS: $bound2<E>> CanonicalDeserialize for $wrap<E, PS> {
-            fn deserialize<R: Read>(_reader: R) -> Result<Self, SerializationError> {
-                unimplemented!("deserialize")
+            fn deserialize<R: Read>(reader: R) -> Result<Self, SerializationError> {
+                $wrapped::deserialize(reader).map(|val| Self { val })
             }
         }
         impl<E: $bound1, PS: $bound2<E>> CanonicalDeserializeWithFlags for $wrap<E, PS> {
             fn deserialize_with_flags<R: Read, F: Flags>(
-                _reader: R,
+                reader: R,
             ) -> Result<(Self, F), SerializationError> {
-                unimplemented!("deserialize_with_flags")
+                $wrapped::deserialize_with_flags(reader).map(|(val, f)| (Self { val }, f))
             }
         }
         impl<E: $bound1, PS: $bound2<E>> UniformRand for $wrap<E, PS> {
@@ -464,8 +497,8 @@ macro_rules! impl_ext_field_wrapper {
         impl<E: Field, PS: ExtFieldShare<E>> Reveal for $wrap<E, PS> {
             type Base = E;
             #[inline]
-            fn reveal(self) -> E {
-                self.val.reveal()
+            fn try_reveal(self) -> Result<E, MpcNetError> {
+                self.val.try_reveal()
             }
             #[inline]
             fn from_public(t: E) -> Self {
@@ -604,8 +637,12 @@ macro_rules! impl_pairing_curve_wrapper {
         impl<E: $bound1, PS: $bound2<E>> Reveal for $wrap<E, PS> {
             type Base = E::$base;
             #[inline]
-            fn reveal(self) -> Self::Base {
-                self.val.reveal()
+            fn try_reveal(self) -> Result<Self::Base, MpcNetError> {
+                self.val.try_reveal()
+            }
+            #[inline]
+            fn reveal_to(self, to: usize) -> Option<Self::Base> {
+                self.val.reveal_to(to)
             }
             #[inline]
             fn from_public(t: Self::Base) -> Self {
@@ -732,27 +769,41 @@ macro_rules! impl_aff_proj {
         }
 
         impl<E: PairingEngine, PS: PairingShare<E>> From<$w_aff<E, PS>> for $w_prep<E, PS> {
-            fn from(_o: $w_aff<E, PS>) -> Self {
-                unimplemented!("Prepared curves")
+            fn from(o: $w_aff<E, PS>) -> Self {
+                match o.val {
+                    MpcGroup::Public(g) => Self::from_public(g.into()),
+                    MpcGroup::Shared(g) => Self {
+                        val: Default::default(),
+                        shared: Some(g),
+                        _phants: PhantomData::default(),
+                    },
+                }
             }
         }
 
         impl<E: PairingEngine, PS: PairingShare<E>> ToBytes for $w_prep<E, PS> {
-            fn write<W: Write>(&self, _writer: W) -> io::Result<()> {
-                unimplemented!("Prepared curves")
+            fn write<W: Write>(&self, writer: W) -> io::Result<()> {
+                match &self.shared {
+                    Some(g) => g.write(writer),
+                    None => self.val.write(writer),
+                }
             }
         }
 
         impl<E: PairingEngine, PS: PairingShare<E>> Reveal for $w_prep<E, PS> {
             type Base = E::$prep;
             #[inline]
-            fn reveal(self) -> E::$prep {
-                self.val
+            fn try_reveal(self) -> Result<E::$prep, MpcNetError> {
+                Ok(match self.shared {
+                    Some(g) => g.try_reveal()?.into(),
+                    None => self.val,
+                })
             }
             #[inline]
             fn from_public(g: E::$prep) -> Self {
                 Self {
                     val: g,
+                    shared: None,
                     _phants: PhantomData::default(),
                 }
             }
@@ -928,3 +979,64 @@ impl_aff_proj!(
     G2AffineShare,
     G2ProjectiveShare
 );
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::share::pairing::simulate;
+    use crate::share::{add::AdditivePairingShare, gsz20::GszPairingShare, rss::RssPairingShare};
+    use crate::share::{rss3::RSS3PairingShare, spdz::SpdzPairingShare};
+    use ark_bls12_377::Bls12_377 as E;
+    use ark_std::test_rng;
+
+    type Fr = <E as PairingEngine>::Fr;
+
+    /// A product of pairings with shared points on either side, and on both. The Miller loop alone,
+    /// whose shared results would be masked, refuses shared points.
+    fn pairing_products<S: PairingShare<E>>() {
+        type P<S> = MpcPairingEngine<E, S>;
+        let rng = &mut test_rng();
+        let xs: Vec<Fr> = (0..4).map(|_| Fr::rand(rng)).collect();
+        let (g1, g2) = (
+            <E as PairingEngine>::G1Affine::prime_subgroup_generator(),
+            <E as PairingEngine>::G2Affine::prime_subgroup_generator(),
+        );
+        let expected = E::pairing(
+            AffineCurve::mul(&g1, xs[0] + xs[1] + xs[2] * xs[3] + Fr::from(1u8)),
+            g2,
+        );
+
+        let shared = MpcField::<Fr, S::FrShare>::king_share_batch(xs, rng);
+        let mpc_g1 = <P<S> as PairingEngine>::G1Projective::from_public(g1.into_projective());
+        let mpc_g2 = <P<S> as PairingEngine>::G2Projective::from_public(g2.into_projective());
+        let prep1 = |g: <P<S> as PairingEngine>::G1Projective| -> <P<S> as PairingEngine>::G1Prepared {
+            g.into_affine().into()
+        };
+        let prep2 = |g: <P<S> as PairingEngine>::G2Projective| -> <P<S> as PairingEngine>::G2Prepared {
+            g.into_affine().into()
+        };
+        let pairs = vec![
+            (prep1(mpc_g1 * shared[0]), prep2(mpc_g2)),
+            (prep1(mpc_g1), prep2(mpc_g2 * shared[1])),
+            (prep1(mpc_g1 * shared[2]), prep2(mpc_g2 * shared[3])),
+            (prep1(mpc_g1), prep2(mpc_g2)),
+        ];
+        let product = P::<S>::product_of_pairings(&pairs);
+        assert_eq!(product.reveal(), expected);
+        // The Miller loop alone is only defined on public points.
+        let looped = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| P::<S>::miller_loop(&pairs)));
+        assert!(looped.is_err());
+        let public = vec![(prep1(mpc_g1), prep2(mpc_g2))];
+        let looped = P::<S>::final_exponentiation(&P::<S>::miller_loop(&public)).unwrap();
+        assert_eq!(looped.reveal(), E::pairing(g1, g2));
+    }
+
+    #[test]
+    fn pairing_products_for_each_scheme() {
+        simulate::<E, AdditivePairingShare<E>>(3, pairing_products::<AdditivePairingShare<E>>);
+        simulate::<E, SpdzPairingShare<E>>(3, pairing_products::<SpdzPairingShare<E>>);
+        simulate::<E, GszPairingShare<E>>(3, pairing_products::<GszPairingShare<E>>);
+        simulate::<E, RSS3PairingShare<E>>(3, pairing_products::<RSS3PairingShare<E>>);
+        simulate::<E, RssPairingShare<E>>(3, pairing_products::<RssPairingShare<E>>);
+    }
+}
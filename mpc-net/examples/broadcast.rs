@@ -1,8 +1,9 @@
 use log::debug;
-use mpc_net::multi as net;
-use mpc_algebra::share::gs20;
+use mpc_net::shape::{parse_bandwidth, parse_duration};
+use mpc_net::{LinkShape, MpcMultiNet as Net, MpcNet, MpcNetError, Topology, Transport};
 
 use std::path::PathBuf;
+use std::time::{Duration, Instant};
 use structopt::StructOpt;
 
 #[derive(Debug, StructOpt)]
@@ -14,18 +15,86 @@ struct Opt {
     /// Input file
     #[structopt(parse(from_os_str))]
     input: PathBuf,
+
+    /// Secret key, to connect over Noise (see the keygen example)
+    #[structopt(long, parse(from_os_str))]
+    key: Option<PathBuf>,
+
+    /// Number of concurrent sessions to run
+    #[structopt(long, default_value = "4")]
+    sessions: u64,
+
+    /// Emulated latency of every link, like 40ms (replaces the hosts file's conditions)
+    #[structopt(long, default_value = "0ms", parse(try_from_str = parse_duration))]
+    latency: Duration,
+
+    /// Emulated jitter of every link, like 5ms
+    #[structopt(long, default_value = "0ms", parse(try_from_str = parse_duration))]
+    jitter: Duration,
+
+    /// Emulated bandwidth of every link, like 100mbit
+    #[structopt(long, parse(try_from_str = parse_bandwidth))]
+    bandwidth: Option<u64>,
+
+    /// How the parties connect: mesh (everyone to everyone) or star (everyone through party 0)
+    #[structopt(long, default_value = "mesh")]
+    topology: Topology,
 }
 
-fn main() {
+fn main() -> Result<(), MpcNetError> {
     env_logger::builder().format_timestamp(None).format_module_path(false).init();
     debug!("Start");
     let opt = Opt::from_args();
     println!("{:?}", opt);
-    multi::init_from_path(opt.input.to_str().unwrap(), opt.id);
-    let all = multi::broadcast(&[opt.id as u8]);
+    Net::init_with_topology(
+        opt.input.to_str().unwrap(),
+        opt.id,
+        Transport::from_secret_key(opt.key.clone()),
+        opt.topology,
+    )?;
+    let shape = LinkShape {
+        latency: opt.latency,
+        jitter: opt.jitter,
+        bandwidth: opt.bandwidth,
+    };
+    if shape != LinkShape::default() {
+        Net::shape_links(shape);
+    }
+    let all = Net::broadcast_bytes(&[opt.id as u8])?;
     println!("{:?}", all);
-    let r = multi::send_to_king(&[opt.id as u8]);
-    let all = multi::recv_from_king(r);
+    let r = Net::send_bytes_to_king(&[opt.id as u8])?;
+    let all = Net::recv_bytes_from_king(r.map(|r| vec![r.concat(); Net::n_parties()]))?;
     println!("{:?}", all);
-    multi::uninit();
+
+    let start = Instant::now();
+    Net::broadcast_bytes(&vec![0u8; 1 << 20])?;
+    println!("1 MiB broadcast in {:?}", start.elapsed());
+
+    // Sessions run side by side over the same connections, without mixing their messages.
+    let start = Instant::now();
+    let threads: Vec<_> = (1..=opt.sessions)
+        .map(|s| {
+            let id = opt.id;
+            std::thread::spawn(move || {
+                let session = Net::session(s);
+                let r = session.enter(|| {
+                    for round in 0..100u8 {
+                        let all = Net::broadcast_bytes(&[s as u8, id as u8, round])?;
+                        for (i, b) in all.into_iter().enumerate() {
+                            assert_eq!(b, vec![s as u8, i as u8, round]);
+                        }
+                    }
+                    Ok(session.stats().broadcasts)
+                });
+                session.end();
+                r
+            })
+        })
+        .collect();
+    for t in threads {
+        assert_eq!(t.join().unwrap()?, 100);
+    }
+    println!("{} sessions done in {:?}", opt.sessions, start.elapsed());
+    Net::deinit();
+    Ok(())
 }
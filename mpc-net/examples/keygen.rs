@@ -0,0 +1,51 @@
+use mpc_net::noise::SecretKey;
+
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+/// Make a key pair for each party in a hosts file.
+///
+/// Writes each party's secret key to `OUT/<party>.key`, and to `OUT/hosts` a copy of the hosts
+/// file that lists the public keys. With `--client`, also makes a key pair for an input client:
+/// `OUT/client.key` and `OUT/client.pub`.
+#[derive(Debug, StructOpt)]
+#[structopt(name = "keygen")]
+struct Opt {
+    /// Hosts file
+    #[structopt(parse(from_os_str))]
+    hosts: PathBuf,
+
+    /// Output directory
+    #[structopt(parse(from_os_str))]
+    out: PathBuf,
+
+    /// Also make a key pair for a client that sends the parties its input
+    #[structopt(long)]
+    client: bool,
+}
+
+fn main() {
+    let opt = Opt::from_args();
+    std::fs::create_dir_all(&opt.out).unwrap();
+    let hosts = BufReader::new(File::open(&opt.hosts).expect("host configuration path"));
+    let mut lines = Vec::new();
+    for line in hosts.lines() {
+        let line = line.unwrap();
+        if let Some(addr) = line.split_whitespace().next() {
+            let key = SecretKey::generate();
+            let key_path = opt.out.join(format!("{}.key", lines.len()));
+            std::fs::write(&key_path, key.to_hex()).unwrap();
+            lines.push(format!("{} {}\n", addr, key.public_key()));
+        }
+    }
+    std::fs::write(opt.out.join("hosts"), lines.concat()).unwrap();
+    println!("Wrote keys for {} parties to {}", lines.len(), opt.out.display());
+    if opt.client {
+        let key = SecretKey::generate();
+        std::fs::write(opt.out.join("client.key"), key.to_hex()).unwrap();
+        std::fs::write(opt.out.join("client.pub"), key.public_key().to_string()).unwrap();
+        println!("Wrote the client's key to {}", opt.out.display());
+    }
+}
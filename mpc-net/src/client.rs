@@ -0,0 +1,243 @@
+//! Input from a client: someone outside the parties who holds a secret input, shares it, and
+//! sends each party its shares, so that no party (not even the king) sees the input. The client
+//! can also take an output the same way: each party sends it its shares, and only the client puts
+//! them together.
+//!
+//! Once the parties are connected to each other, each waits for the client at its own address in
+//! the hosts file, and the client contacts each party there, once for the input
+//! ([`send_to_parties`]) and once for the output ([`recv_from_parties`]).
+//!
+//! The client has a key pair of its own. It runs the Noise KK handshake with each party as the
+//! initiator, with the party's public key from the hosts file, and each party must know the
+//! client's public key. Then the client is authenticated, and the messages are encrypted; without
+//! that, a party could not tell the client from anyone else who contacts it first.
+use log::{debug, warn};
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::time::{Duration, Instant};
+
+use super::multi::{connect, disconnected, hosts, MAX_FRAME_LEN};
+use super::noise::{self, CipherState, PublicKey, SecretKey};
+use super::MpcNetError;
+
+/// How long a party waits for the client, and the client for the parties.
+pub const CLIENT_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// How long either end waits for the other in the middle of a message.
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The longest message between the client and a party.
+pub const MAX_MESSAGE_LEN: usize = MAX_FRAME_LEN;
+
+/// The address and public key (if the file lists keys) of each party in the hosts file at `path`.
+pub fn read_hosts(path: &str) -> Vec<(SocketAddr, Option<PublicKey>)> {
+    hosts(path)
+}
+
+/// Send `messages[i]` to party `i`, which is at `parties[i].0`.
+///
+/// Every connection is secured with Noise, with `key`, our secret key, so every party must have a
+/// public key. Waits up to [`CLIENT_TIMEOUT`] for each party to be listening.
+pub fn send_to_parties(
+    parties: &[(SocketAddr, Option<PublicKey>)],
+    messages: &[Vec<u8>],
+    key: &SecretKey,
+) -> Result<(), MpcNetError> {
+    assert_eq!(parties.len(), messages.len(), "one message per party");
+    let deadline = Instant::now() + CLIENT_TIMEOUT;
+    for (party, (addr_key, message)) in parties.iter().zip(messages).enumerate() {
+        debug!("Sending input to party {}", party);
+        let (mut stream, (send, _recv)) = connect_to_party(party, addr_key, key, deadline)?;
+        write_message(&mut stream, send, message)
+            .map_err(|e| disconnected(party, e))?;
+    }
+    Ok(())
+}
+
+/// Receive one message from each of `parties`, in party order, as [`send_to_parties`] connects.
+///
+/// A party that closes the connection before it answers is tried again: the connection may have
+/// reached the listener that the party used for the input, just as it stopped listening.
+pub fn recv_from_parties(
+    parties: &[(SocketAddr, Option<PublicKey>)],
+    key: &SecretKey,
+) -> Result<Vec<Vec<u8>>, MpcNetError> {
+    let deadline = Instant::now() + CLIENT_TIMEOUT;
+    parties
+        .iter()
+        .enumerate()
+        .map(|(party, (addr, public_key))| loop {
+            debug!("Receiving output from party {}", party);
+            let mut stream = connect(party, *addr, deadline)?;
+            stream.set_read_timeout(Some(READ_TIMEOUT)).unwrap();
+            let message = handshake_with_party(&mut stream, party, public_key, key)
+                .and_then(|(_send, recv)| read_message(&mut stream, recv));
+            match message {
+                Ok(message) => return Ok(message),
+                Err(e) if closed_early(&e) && Instant::now() < deadline => {
+                    std::thread::sleep(Duration::from_millis(10));
+                }
+                Err(e) => return Err(disconnected(party, e)),
+            }
+        })
+        .collect()
+}
+
+/// Wait for the client to contact us at `addr`, and receive its message.
+///
+/// The connection must be secured with Noise, with `keys`, our secret key and the client's public
+/// key; connections from anyone else fail the handshake and are ignored.
+pub fn recv_from_client(
+    addr: SocketAddr,
+    keys: (&SecretKey, &PublicKey),
+) -> Result<Vec<u8>, MpcNetError> {
+    let (mut stream, (_send, recv)) = accept_client(addr, keys)?;
+    read_message(&mut stream, recv).map_err(|e| MpcNetError::Client {
+        reason: e.to_string(),
+    })
+}
+
+/// Wait for the client to contact us at `addr`, as for [`recv_from_client`], and send it
+/// `message`.
+pub fn send_to_client(
+    addr: SocketAddr,
+    keys: (&SecretKey, &PublicKey),
+    message: &[u8],
+) -> Result<(), MpcNetError> {
+    let (mut stream, (send, _recv)) = accept_client(addr, keys)?;
+    write_message(&mut stream, send, message).map_err(|e| {
+        MpcNetError::Client {
+            reason: e.to_string(),
+        }
+    })
+}
+
+/// Connect to `party`, at `addr_key.0`, and run the handshake with its public key. Returns the
+/// sending and receiving ciphers.
+fn connect_to_party(
+    party: usize,
+    addr_key: &(SocketAddr, Option<PublicKey>),
+    key: &SecretKey,
+    deadline: Instant,
+) -> Result<(TcpStream, (CipherState, CipherState)), MpcNetError> {
+    let (addr, public_key) = addr_key;
+    let mut stream = connect(party, *addr, deadline)?;
+    stream.set_read_timeout(Some(READ_TIMEOUT)).unwrap();
+    let ciphers = handshake_with_party(&mut stream, party, public_key, key).map_err(|e| {
+        MpcNetError::Disconnected {
+            party,
+            reason: format!("handshake failed: {}", e),
+        }
+    })?;
+    Ok((stream, ciphers))
+}
+
+/// Run the handshake with `party`, whose public key is `public_key`.
+fn handshake_with_party(
+    stream: &mut TcpStream,
+    party: usize,
+    public_key: &Option<PublicKey>,
+    key: &SecretKey,
+) -> std::io::Result<(CipherState, CipherState)> {
+    let public_key = public_key
+        .unwrap_or_else(|| panic!("The hosts file does not list a key for party {}", party));
+    noise::handshake(stream, true, key, &public_key)
+}
+
+/// Whether `e` means that the other end closed the connection before sending anything.
+fn closed_early(e: &std::io::Error) -> bool {
+    use std::io::ErrorKind::*;
+    matches!(e.kind(), UnexpectedEof | ConnectionReset | ConnectionAborted | BrokenPipe)
+}
+
+/// Wait up to [`CLIENT_TIMEOUT`] for the client to connect to `addr`, ignoring connections that
+/// fail the handshake. Returns the sending and receiving ciphers.
+fn accept_client(
+    addr: SocketAddr,
+    (key, client_key): (&SecretKey, &PublicKey),
+) -> Result<(TcpStream, (CipherState, CipherState)), MpcNetError> {
+    let deadline = Instant::now() + CLIENT_TIMEOUT;
+    let not_listening = |e: std::io::Error| MpcNetError::Client {
+        reason: format!("could not listen on {}: {}", addr, e),
+    };
+    let listener = TcpListener::bind(addr).map_err(not_listening)?;
+    listener.set_nonblocking(true).map_err(not_listening)?;
+    loop {
+        let mut stream = match listener.accept() {
+            Ok((stream, _addr)) => stream,
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                if Instant::now() > deadline {
+                    return Err(MpcNetError::Client {
+                        reason: "timed out waiting for it".to_owned(),
+                    });
+                }
+                std::thread::sleep(Duration::from_millis(10));
+                continue;
+            }
+            Err(e) => return Err(not_listening(e)),
+        };
+        let handshake = stream
+            .set_nonblocking(false)
+            .and_then(|()| stream.set_read_timeout(Some(READ_TIMEOUT)))
+            .and_then(|()| noise::handshake(&mut stream, false, key, client_key));
+        match handshake {
+            Ok(ciphers) => return Ok((stream, ciphers)),
+            Err(e) => warn!("Ignoring a connection that is not from the client: {}", e),
+        }
+    }
+}
+
+/// Send one message: its length, then the encrypted bytes.
+fn write_message(stream: &mut TcpStream, mut cipher: CipherState, bytes: &[u8]) -> std::io::Result<()> {
+    if bytes.len() > MAX_MESSAGE_LEN {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("a message of {} bytes is over the limit of {}", bytes.len(), MAX_MESSAGE_LEN),
+        ));
+    }
+    let mut body = bytes.to_vec();
+    cipher.encrypt(&mut body);
+    stream.write_all(&(body.len() as u64).to_le_bytes())?;
+    stream.write_all(&body)?;
+    stream.flush()
+}
+
+fn read_message(stream: &mut TcpStream, mut cipher: CipherState) -> std::io::Result<Vec<u8>> {
+    let mut len = [0u8; 8];
+    stream.read_exact(&mut len)?;
+    let len = u64::from_le_bytes(len);
+    if len > noise::ciphertext_len(MAX_MESSAGE_LEN) as u64 {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("a message of {} bytes is over the limit", len),
+        ));
+    }
+    let mut body = vec![0u8; len as usize];
+    stream.read_exact(&mut body)?;
+    cipher.decrypt(&mut body)?;
+    Ok(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_over_the_limit_are_refused() {
+        let (party_key, client_key) = (SecretKey::generate(), SecretKey::generate());
+        let (party_public, client_public) = (party_key.public_key(), client_key.public_key());
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let client = std::thread::spawn(move || {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            noise::handshake(&mut stream, true, &client_key, &party_public).unwrap();
+            stream.write_all(&u64::MAX.to_le_bytes()).unwrap();
+            stream
+        });
+        let mut stream = listener.accept().unwrap().0;
+        let (_send, recv) = noise::handshake(&mut stream, false, &party_key, &client_public).unwrap();
+        let _client_stream = client.join().unwrap();
+        let e = read_message(&mut stream, recv).unwrap_err();
+        assert_eq!(e.kind(), std::io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,91 @@
+//! What can go wrong in the network, and how errors get out of code that cannot return them.
+use log::warn;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+
+/// A network operation failed, or another party deviated from the protocol.
+///
+/// Where we know which party is to blame, the error names it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MpcNetError {
+    /// `party` did not connect to us in time.
+    Timeout { party: usize },
+    /// Our connection to `party` closed or failed.
+    Disconnected { party: usize, reason: String },
+    /// `party` sent something that is not what the protocol expects.
+    Malformed { party: usize },
+    /// `party` opened a commitment to something other than what it committed to.
+    CommitmentMismatch { party: usize },
+    /// A MAC check failed: some party cheated, but the check cannot tell which.
+    MacFailure,
+    /// Opened shares do not agree: some party cheated, but the check cannot tell which.
+    InconsistentShares,
+    /// The client that provides our input (see [`client`](crate::client)) did not contact us in
+    /// time, or sent something other than shares.
+    Client { reason: String },
+}
+
+impl MpcNetError {
+    /// The party that caused the error, if we know.
+    pub fn party(&self) -> Option<usize> {
+        match self {
+            MpcNetError::Timeout { party }
+            | MpcNetError::Disconnected { party, .. }
+            | MpcNetError::Malformed { party }
+            | MpcNetError::CommitmentMismatch { party } => Some(*party),
+            MpcNetError::MacFailure
+            | MpcNetError::InconsistentShares
+            | MpcNetError::Client { .. } => None,
+        }
+    }
+
+    /// Abort the computation with this error. See [`OrAbort`].
+    pub fn abort(self) -> ! {
+        warn!("Aborting: {}", self);
+        panic::panic_any(self)
+    }
+}
+
+impl fmt::Display for MpcNetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MpcNetError::Timeout { party } => write!(f, "timed out waiting for party {}", party),
+            MpcNetError::Disconnected { party, reason } => {
+                write!(f, "lost connection to party {}: {}", party, reason)
+            }
+            MpcNetError::Malformed { party } => write!(f, "malformed message from party {}", party),
+            MpcNetError::CommitmentMismatch { party } => {
+                write!(f, "party {} opened a different value than it committed to", party)
+            }
+            MpcNetError::MacFailure => write!(f, "MAC check failed"),
+            MpcNetError::InconsistentShares => write!(f, "opened shares are inconsistent"),
+            MpcNetError::Client { reason } => write!(f, "input client: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for MpcNetError {}
+
+/// Unwrapping network results in code that cannot return them, such as field arithmetic.
+///
+/// An error unwinds the stack with the [`MpcNetError`] itself as the panic payload, so that
+/// [`catch`] (or [`MpcSession::run`](crate::MpcSession::run)) can turn it back into an error,
+/// and only the computation that failed is lost.
+pub trait OrAbort<T> {
+    fn or_abort(self) -> T;
+}
+
+impl<T> OrAbort<T> for Result<T, MpcNetError> {
+    #[inline]
+    fn or_abort(self) -> T {
+        self.unwrap_or_else(|e| e.abort())
+    }
+}
+
+/// Run `f`, returning the error that it aborted with, if any. Other panics carry on unwinding.
+pub fn catch<R>(f: impl FnOnce() -> R) -> Result<R, MpcNetError> {
+    panic::catch_unwind(AssertUnwindSafe(f)).or_else(|payload| match payload.downcast() {
+        Ok(e) => Err(*e),
+        Err(payload) => panic::resume_unwind(payload),
+    })
+}
@@ -1,26 +1,79 @@
+pub mod client;
+pub mod error;
 pub mod multi;
+pub mod noise;
+pub mod shape;
+pub mod stats;
 pub mod two;
 
+use std::path::PathBuf;
+
+pub use error::{catch, MpcNetError, OrAbort};
 pub use two::MpcTwoNet;
-pub use multi::MpcMultiNet;
+pub use multi::{MpcMultiNet, MpcSession};
+pub use shape::LinkShape;
+pub use stats::{phase, Phase, Stats};
 
+/// How the connections between parties are made.
 #[derive(Clone, Debug)]
-pub struct Stats {
-    pub bytes_sent: usize,
-    pub bytes_recv: usize,
-    pub broadcasts: usize,
-    pub to_king: usize,
-    pub from_king: usize,
+pub enum Transport {
+    /// Plain TCP, with no authentication or encryption.
+    Tcp,
+    /// TCP, authenticated and encrypted with the Noise KK handshake (see [noise]).
+    ///
+    /// The hosts file must list each party's public key after its address, and `secret_key` is
+    /// the file holding our secret key.
+    Noise { secret_key: PathBuf },
 }
 
-impl std::default::Default for Stats {
+impl Transport {
+    /// Noise if we were given a secret key, plain TCP otherwise.
+    pub fn from_secret_key(secret_key: Option<PathBuf>) -> Self {
+        match secret_key {
+            Some(secret_key) => Transport::Noise { secret_key },
+            None => Transport::Tcp,
+        }
+    }
+}
+
+impl std::default::Default for Transport {
     fn default() -> Self {
-        Self {
-            bytes_sent: 0,
-            bytes_recv: 0,
-            broadcasts: 0,
-            to_king: 0,
-            from_king: 0,
+        Transport::Tcp
+    }
+}
+
+/// Which parties are connected to each other.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Topology {
+    /// Every party is connected to every other.
+    #[default]
+    Mesh,
+    /// Every party is connected only to the king, who relays messages between the others. For
+    /// deployments where the parties can reach a coordinator but not each other.
+    ///
+    /// The API is the same, but a broadcast or an all-to-all takes two rounds, and the king's
+    /// [`Stats::relayed`] counts what it passes on. Messages relayed by the king are only as
+    /// trustworthy as the king.
+    Star,
+}
+
+impl Topology {
+    /// Whether parties `a` and `b` have a connection.
+    pub fn connects(&self, a: usize, b: usize) -> bool {
+        match self {
+            Topology::Mesh => true,
+            Topology::Star => a == 0 || b == 0,
+        }
+    }
+}
+
+impl std::str::FromStr for Topology {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "mesh" => Ok(Topology::Mesh),
+            "star" => Ok(Topology::Star),
+            _ => Err(format!("unknown topology {:?}: expected mesh or star", s)),
         }
     }
 }
@@ -37,27 +90,43 @@ pub trait MpcNet {
     fn party_id() -> usize;
     /// Initialize the network layer from a file.
     /// The file should contain one HOST:PORT setting per line, corresponding to the addresses of
-    /// the parties in increasing order.
+    /// the parties in increasing order. For [Transport::Noise], each address is followed by a
+    /// space and the party's public key. The line may end with options for emulating network
+    /// conditions (see [shape]).
     ///
-    /// Parties are zero-indexed.
-    fn init_from_file(path: &str, party_id: usize);
+    /// Parties are zero-indexed. Fails if a party does not connect in time, or (with Noise) does
+    /// not prove it holds its key.
+    fn init_from_file(path: &str, party_id: usize, transport: Transport) -> Result<(), MpcNetError>;
     /// Is the network layer initalized?
     fn is_init() -> bool;
     /// Uninitialize the network layer, closing all connections.
     fn deinit();
     /// Set statistics to zero.
     fn reset_stats();
-    /// Get statistics.
+    /// Get statistics, broken down by [phase].
     fn stats() -> Stats;
     /// All parties send bytes to each other.
-    fn broadcast_bytes(bytes: &[u8]) -> Vec<Vec<u8>>;
+    fn broadcast_bytes(bytes: &[u8]) -> Result<Vec<Vec<u8>>, MpcNetError>;
     /// All parties send bytes to the king.
-    fn send_bytes_to_king(bytes: &[u8]) -> Option<Vec<Vec<u8>>>;
+    fn send_bytes_to_king(bytes: &[u8]) -> Result<Option<Vec<Vec<u8>>>, MpcNetError>;
+    /// All parties send bytes to party `to`, which gets them all, in party order. The others get
+    /// `None`.
+    fn send_bytes_to(to: usize, bytes: &[u8]) -> Result<Option<Vec<Vec<u8>>>, MpcNetError>;
     /// All parties recv bytes from the king.
     /// Provide bytes iff you're the king!
-    fn recv_bytes_from_king(bytes: Option<Vec<Vec<u8>>>) -> Vec<u8>;
+    fn recv_bytes_from_king(bytes: Option<Vec<Vec<u8>>>) -> Result<Vec<u8>, MpcNetError>;
+
+    /// Send bytes to the next party around the ring of parties, and receive from the previous
+    /// one.
+    fn pass_to_next_bytes(bytes: &[u8]) -> Result<Vec<u8>, MpcNetError>;
+
+    /// Send bytes to each of the next `hops` parties around the ring of parties.
+    /// Returns the bytes from each of the previous `hops` parties, nearest first.
+    fn pass_around_bytes(bytes: &[u8], hops: usize) -> Result<Vec<Vec<u8>>, MpcNetError>;
 
-    fn pass_to_next_bytes(bytes: &[u8]) -> Vec<u8>;
+    /// Each party sends `bytes[j]` to party `j`.
+    /// Returns the bytes sent to us by each party, in party order.
+    fn all_to_all_bytes(bytes: Vec<Vec<u8>>) -> Result<Vec<Vec<u8>>, MpcNetError>;
 
     /// Everyone sends bytes to the king, who recieves those bytes, runs a computation on them, and
     /// redistributes the resulting bytes.
@@ -65,8 +134,11 @@ pub trait MpcNet {
     /// The king's computation is given by a function, `f`
     /// proceeds.
     #[inline]
-    fn king_compute(bytes: &[u8], f: impl Fn(Vec<Vec<u8>>) -> Vec<Vec<u8>>) -> Vec<u8> {
-        let king_response = Self::send_bytes_to_king(bytes).map(f);
+    fn king_compute(
+        bytes: &[u8],
+        f: impl Fn(Vec<Vec<u8>>) -> Vec<Vec<u8>>,
+    ) -> Result<Vec<u8>, MpcNetError> {
+        let king_response = Self::send_bytes_to_king(bytes)?.map(f);
         Self::recv_bytes_from_king(king_response)
     }
 }
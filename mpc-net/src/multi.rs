@@ -1,39 +1,91 @@
+//! A network of `n` parties, which can run several MPC sessions at once.
+//!
+//! There is one TCP connection per pair of parties, or with [`Topology::Star`], one between each
+//! party and the king, who relays everything else. Every message is framed with the id of the
+//! session it belongs to, and a reader thread per connection sorts incoming frames into
+//! per-session queues, so sessions do not block one another.
+//!
+//! With [`Transport::Noise`], each connection is authenticated and encrypted (see
+//! [`noise`](crate::noise)) before any frames are sent.
+//!
+//! Links can emulate a slower network, with the latency, jitter and bandwidth given in the hosts
+//! file or to [`MpcMultiNet::shape_links`] (see [`shape`](crate::shape)).
+//!
+//! For tests, [`MpcMultiNet::simulate`] runs all the parties as threads of one process, connected
+//! in memory instead.
+//!
+//! A session is an [`MpcSession`] handle. The static [`MpcMultiNet`] functions (and everything
+//! built on them) use the session entered on the current thread with [`MpcSession::enter`]. The
+//! thread that sets up the network, and each thread of [`MpcMultiNet::simulate`], starts out in
+//! the default session; on any other thread they panic until a session is entered.
+//!
+//! Code that talks to the other parties directly should rather take the handle and call its own
+//! methods (or, over serializable values, `mpc_algebra::channel::SessionSerNet`), which go to that
+//! session on any thread. Arithmetic on shared values still uses the entered session, as the
+//! field and group traits it implements have nowhere to pass a handle.
 use lazy_static::lazy_static;
-use log::debug;
-use rayon::prelude::*;
+use log::{debug, warn};
+use std::any::{Any, TypeId};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::convert::TryInto;
 use std::fs::File;
 use std::io::{BufRead, BufReader, Read, Write};
-use std::net::{SocketAddr, TcpListener, TcpStream};
-use std::sync::Mutex;
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::path::Path;
+use std::sync::{Arc, Condvar, Mutex, RwLock};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
 
 use ark_std::{end_timer, start_timer};
 
-use super::{MpcNet, Stats};
+use super::noise::{self, CipherState, PublicKey, SecretKey};
+use super::shape::{LinkShape, Pacer};
+use super::{MpcNet, MpcNetError, Stats, Topology, Transport};
+
+/// The session that the thread that sets up the network starts out in.
+pub const DEFAULT_SESSION: u64 = 0;
 
-#[macro_use]
 lazy_static! {
-    static ref CONNECTIONS: Mutex<Connections> = Mutex::new(Connections::default());
+    /// The network set up by [`MpcNet::init_from_file`].
+    static ref NETWORK: RwLock<Option<Arc<Network>>> = RwLock::new(None);
 }
 
-/// Macro for locking the FieldChannel singleton in the current scope.
-macro_rules! get_ch {
-    () => {
-        CONNECTIONS.lock().expect("Poisoned FieldChannel")
-    };
+thread_local! {
+    /// The session entered on this thread, if any.
+    static CURRENT: RefCell<Option<MpcSession>> = RefCell::new(None);
+}
+
+/// The network this thread uses: that of the entered session (which, for a simulated party, is
+/// its own network), or the one set up by [`MpcNet::init_from_file`].
+fn try_network() -> Option<Arc<Network>> {
+    CURRENT
+        .with(|c| c.borrow().as_ref().map(|s| s.net.clone()))
+        .or_else(|| NETWORK.read().unwrap().clone())
+}
+
+fn network() -> Arc<Network> {
+    try_network().expect("The network is not initialized")
 }
 
 #[derive(Debug)]
 struct Peer {
     id: usize,
     addr: SocketAddr,
+    /// From the hosts file, if it lists keys.
+    public_key: Option<PublicKey>,
+    /// The conditions on this party's connection, from the hosts file.
+    shape: LinkShape,
     stream: Option<TcpStream>,
+    /// Our sending and receiving ciphers, once we have done a handshake with this peer.
+    ciphers: Option<(CipherState, CipherState)>,
 }
 
+/// The parties' addresses and our raw connections to them, while connecting.
 #[derive(Default, Debug)]
 struct Connections {
     id: usize,
     peers: Vec<Peer>,
-    stats: Stats,
 }
 
 impl std::default::Default for Peer {
@@ -41,7 +93,10 @@ impl std::default::Default for Peer {
         Self {
             id: 0,
             addr: "127.0.0.1:8000".parse().unwrap(),
+            public_key: None,
+            shape: LinkShape::default(),
             stream: None,
+            ciphers: None,
         }
     }
 }
@@ -55,13 +110,28 @@ impl Connections {
             let line = line.unwrap();
             let trimmed = line.trim();
             if trimmed.len() > 0 {
-                let addr: SocketAddr = trimmed
+                let mut fields = trimmed.split_whitespace();
+                let addr = fields.next().unwrap();
+                let addr: SocketAddr = addr
                     .parse()
-                    .unwrap_or_else(|e| panic!("bad socket address: {}:\n{}", trimmed, e));
+                    .unwrap_or_else(|e| panic!("bad socket address: {}:\n{}", addr, e));
+                let mut fields = fields.peekable();
+                let public_key = fields
+                    .next_if(|f| !f.contains('='))
+                    .map(|k| k.parse().unwrap_or_else(|e| panic!("{}", e)));
+                let mut shape = LinkShape::default();
+                for option in fields {
+                    shape
+                        .set_option(option)
+                        .unwrap_or_else(|e| panic!("party {}: {}", peer_id, e));
+                }
                 let peer = Peer {
                     id: peer_id,
                     addr,
+                    public_key,
+                    shape,
                     stream: None,
+                    ciphers: None,
                 };
                 self.peers.push(peer);
                 peer_id += 1;
@@ -70,266 +140,1437 @@ impl Connections {
         assert!(id < self.peers.len());
         self.id = id;
     }
-    fn connect_to_all(&mut self) {
+    /// Connect to every peer that `topology` connects us to: we contact those after us, and those
+    /// before us contact us.
+    fn connect_to_all(&mut self, topology: Topology) -> Result<(), MpcNetError> {
         let timer = start_timer!(|| "Connecting");
-        let n = self.peers.len();
-        for from_id in 0..n {
-            for to_id in (from_id + 1)..n {
-                debug!("{} to {}", from_id, to_id);
-                if self.id == from_id {
-                    let to_addr = self.peers[to_id].addr;
-                    debug!("Contacting {}", to_id);
-                    let stream = loop {
-                        let mut ms_waited = 0;
-                        match TcpStream::connect(to_addr) {
-                            Ok(s) => break s,
-                            Err(e) => match e.kind() {
-                                std::io::ErrorKind::ConnectionRefused
-                                | std::io::ErrorKind::ConnectionReset => {
-                                    ms_waited += 10;
-                                    std::thread::sleep(std::time::Duration::from_millis(10));
-                                    if ms_waited % 3_000 == 0 {
-                                        debug!("Still waiting");
-                                    } else if ms_waited > 30_000 {
-                                        panic!("Could not find peer in 30s");
-                                    }
-                                }
-                                _ => {
-                                    panic!("Error during FieldChannel::new: {}", e);
-                                }
-                            },
-                        }
+        let deadline = Instant::now() + CONNECT_TIMEOUT;
+        let id = self.id;
+        let addr = self.peers[id].addr;
+        // Failing to listen is our own problem, not any peer's.
+        let not_listening = |e: std::io::Error| MpcNetError::Disconnected {
+            party: id,
+            reason: format!("could not listen on {}: {}", addr, e),
+        };
+        // Listen before contacting anyone, and until everyone has contacted us, so that nobody
+        // finds our port closed.
+        let listener = TcpListener::bind(addr).map_err(not_listening)?;
+        listener.set_nonblocking(true).map_err(not_listening)?;
+        for to_id in (id + 1..self.peers.len()).filter(|to| topology.connects(id, *to)) {
+            debug!("Contacting {}", to_id);
+            let mut stream = connect(to_id, self.peers[to_id].addr, deadline)?;
+            stream
+                .write_all(&(self.id as u64).to_le_bytes())
+                .map_err(|e| disconnected(to_id, e))?;
+            self.peers[to_id].stream = Some(stream);
+        }
+        // Those before us contact us in any order, and tell us who they are.
+        let expected = |i: usize| i < id && topology.connects(id, i);
+        while let Some(from_id) = (0..id).find(|i| expected(*i) && self.peers[*i].stream.is_none()) {
+            debug!("Awaiting {}", from_id);
+            let (mut stream, peer_addr) = match listener.accept() {
+                Ok(accepted) => accepted,
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                    if Instant::now() > deadline {
+                        return Err(MpcNetError::Timeout { party: from_id });
+                    }
+                    std::thread::sleep(Duration::from_millis(10));
+                    continue;
+                }
+                Err(e) => return Err(not_listening(e)),
+            };
+            // Until it says who it is, we do not know who is connecting, so a bad hello only
+            // loses that connection.
+            let from = match read_hello(&mut stream, deadline) {
+                Ok(from) => from,
+                Err(e) => {
+                    warn!("Ignoring a connection from {}: {}", peer_addr, e);
+                    continue;
+                }
+            };
+            if !expected(from) || self.peers[from].stream.is_some() {
+                warn!("Ignoring a connection that claims to be from party {}", from);
+                continue;
+            }
+            self.peers[from].stream = Some(stream);
+        }
+        end_timer!(timer);
+        Ok(())
+    }
+    /// Run a Noise handshake with every peer, as the initiator with those after us.
+    ///
+    /// Everyone does their handshakes in increasing order of peer, so the handshakes happen in
+    /// the same order everywhere and nobody waits on a peer that is waiting on someone else.
+    fn secure(&mut self, secret_key_path: &Path) -> Result<(), MpcNetError> {
+        let timer = start_timer!(|| "Handshakes");
+        let secret_key = SecretKey::from_file(secret_key_path);
+        let key_of = |peer: &Peer| {
+            peer.public_key
+                .unwrap_or_else(|| panic!("The hosts file does not list a key for party {}", peer.id))
+        };
+        assert!(
+            key_of(&self.peers[self.id]) == secret_key.public_key(),
+            "{} is not the secret key of party {}",
+            secret_key_path.display(),
+            self.id
+        );
+        let id = self.id;
+        for peer in self.peers.iter_mut().filter(|p| p.stream.is_some()) {
+            debug!("Handshake with {}", peer.id);
+            let public_key = key_of(peer);
+            let ciphers = noise::handshake(
+                peer.stream.as_mut().unwrap(),
+                id < peer.id,
+                &secret_key,
+                &public_key,
+            )
+            .map_err(|e| MpcNetError::Disconnected {
+                party: peer.id,
+                reason: format!("handshake failed: {}", e),
+            })?;
+            peer.ciphers = Some(ciphers);
+        }
+        end_timer!(timer);
+        Ok(())
+    }
+}
+
+/// The address and public key (if any) of each party in the hosts file at `path`.
+pub(crate) fn hosts(path: &str) -> Vec<(SocketAddr, Option<PublicKey>)> {
+    let mut connections = Connections::default();
+    connections.init_from_path(path, 0);
+    connections
+        .peers
+        .iter()
+        .map(|p| (p.addr, p.public_key))
+        .collect()
+}
+
+/// Read the id that a party that contacted us sends first, waiting no later than `deadline`.
+fn read_hello(stream: &mut TcpStream, deadline: Instant) -> std::io::Result<usize> {
+    let timeout = deadline
+        .saturating_duration_since(Instant::now())
+        .min(HELLO_TIMEOUT);
+    if timeout == Duration::ZERO {
+        return Err(std::io::ErrorKind::TimedOut.into());
+    }
+    stream.set_nonblocking(false)?;
+    stream.set_nodelay(true)?;
+    stream.set_read_timeout(Some(timeout))?;
+    let mut hello = [0u8; 8];
+    stream.read_exact(&mut hello)?;
+    stream.set_read_timeout(None)?;
+    Ok(u64::from_le_bytes(hello) as usize)
+}
+
+/// How long to wait for the other parties to connect.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long to wait for a connection to say who it is from. A party sends that as soon as it
+/// connects, and we hear from nobody else meanwhile.
+const HELLO_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Contact `party` at `addr`, retrying until `deadline` while it is not listening yet.
+pub(crate) fn connect(party: usize, addr: SocketAddr, deadline: Instant) -> Result<TcpStream, MpcNetError> {
+    let mut ms_waited = 0;
+    loop {
+        match TcpStream::connect(addr) {
+            Ok(stream) => {
+                stream.set_nodelay(true).map_err(|e| disconnected(party, e))?;
+                return Ok(stream);
+            }
+            Err(e) => match e.kind() {
+                std::io::ErrorKind::ConnectionRefused | std::io::ErrorKind::ConnectionReset => {
+                    if Instant::now() > deadline {
+                        return Err(MpcNetError::Timeout { party });
+                    }
+                    std::thread::sleep(Duration::from_millis(10));
+                    ms_waited += 10;
+                    if ms_waited % 3_000 == 0 {
+                        debug!("Still waiting for {}", party);
+                    }
+                }
+                _ => return Err(disconnected(party, e)),
+            },
+        }
+    }
+}
+
+pub(crate) fn disconnected(party: usize, e: std::io::Error) -> MpcNetError {
+    MpcNetError::Disconnected {
+        party,
+        reason: e.to_string(),
+    }
+}
+
+/// The sending half of a connection.
+struct Writer {
+    stream: TcpStream,
+    cipher: Option<CipherState>,
+}
+
+impl Writer {
+    /// Send a frame: its length, then the session and the bytes, encrypted if the connection is.
+    fn send_frame(&mut self, session: u64, bytes: &[u8]) -> std::io::Result<()> {
+        let mut body = Vec::with_capacity(noise::ciphertext_len(8 + bytes.len()));
+        body.extend_from_slice(&session.to_le_bytes());
+        body.extend_from_slice(bytes);
+        if let Some(cipher) = &mut self.cipher {
+            cipher.encrypt(&mut body);
+        }
+        let mut frame = Vec::with_capacity(8 + body.len());
+        frame.extend_from_slice(&(body.len() as u64).to_le_bytes());
+        frame.extend_from_slice(&body);
+        self.stream.write_all(&frame)
+    }
+}
+
+/// The longest message a party may send in one frame. A longer frame is malformed, so we never
+/// allocate more than this for one.
+pub const MAX_FRAME_LEN: usize = 1 << 28;
+
+/// The most bytes of frames from one party that may wait for one session to receive them.
+const MAX_BACKLOG: usize = 1 << 30;
+
+/// The most sessions that we have not started yet, which one party may have frames waiting for.
+const MAX_PENDING_SESSIONS: usize = 64;
+
+/// The receiving half of a connection.
+struct Reader {
+    stream: TcpStream,
+    cipher: Option<CipherState>,
+}
+
+impl Reader {
+    fn recv_frame(&mut self) -> std::io::Result<(u64, Vec<u8>)> {
+        let mut len = [0u8; 8];
+        self.stream.read_exact(&mut len)?;
+        let len = u64::from_le_bytes(len);
+        if len > noise::ciphertext_len(8 + MAX_FRAME_LEN) as u64 {
+            return Err(std::io::ErrorKind::InvalidData.into());
+        }
+        let mut body = vec![0u8; len as usize];
+        self.stream.read_exact(&mut body)?;
+        if let Some(cipher) = &mut self.cipher {
+            cipher.decrypt(&mut body)?;
+        }
+        if body.len() < 8 {
+            return Err(std::io::ErrorKind::InvalidData.into());
+        }
+        let bytes = body.split_off(8);
+        Ok((u64::from_le_bytes(body[..].try_into().unwrap()), bytes))
+    }
+}
+
+/// Frames, with the time each one arrives.
+#[derive(Default)]
+struct Queue {
+    frames: VecDeque<(Instant, Vec<u8>)>,
+    /// The total length of `frames`.
+    bytes: usize,
+}
+
+/// Frames that have arrived but not been received yet.
+#[derive(Default)]
+struct Inbox {
+    /// Keyed by sending party and session.
+    queues: HashMap<(usize, u64), Queue>,
+    /// For each sending party.
+    pacers: Vec<Pacer>,
+    /// Why each party's connection closed, if it has.
+    closed: Vec<Option<MpcNetError>>,
+    /// Sessions that we have started.
+    started: HashSet<u64>,
+    /// Sessions that have ended. Frames for them are dropped as they arrive.
+    ended: HashSet<u64>,
+}
+
+/// Where a party's incoming frames wait to be received.
+struct Mailbox {
+    inbox: Mutex<Inbox>,
+    arrived: Condvar,
+}
+
+impl Mailbox {
+    fn new(n: usize) -> Self {
+        Self {
+            inbox: Mutex::new(Inbox {
+                queues: HashMap::new(),
+                pacers: (0..n).map(|_| Pacer::default()).collect(),
+                closed: vec![None; n],
+                started: HashSet::new(),
+                ended: HashSet::new(),
+            }),
+            arrived: Condvar::new(),
+        }
+    }
+
+    /// Emulate `shape` on the link from `from`.
+    fn shape(&self, from: usize, shape: LinkShape) {
+        self.inbox.lock().unwrap().pacers[from].shape = shape;
+    }
+
+    /// Queue a frame from `from`.
+    ///
+    /// Fails if `from` has sent more than we are willing to hold for sessions that have not
+    /// received it; we then take nothing more from `from`.
+    fn deliver(&self, from: usize, session: u64, bytes: Vec<u8>) -> Result<(), MpcNetError> {
+        let mut inbox = self.inbox.lock().unwrap();
+        if inbox.closed[from].is_some() {
+            return Ok(());
+        }
+        if inbox.ended.contains(&session) {
+            debug!("Dropping a frame from {} for ended session {}", from, session);
+            return Ok(());
+        }
+        let pending = |inbox: &Inbox| {
+            inbox
+                .queues
+                .iter()
+                .filter(|((peer, s), q)| {
+                    *peer == from && !q.frames.is_empty() && !inbox.started.contains(s)
+                })
+                .count()
+        };
+        let queued = inbox.queues.get(&(from, session)).map_or(0, |q| q.bytes);
+        let new_session = queued == 0 && !inbox.started.contains(&session);
+        if queued + bytes.len() > MAX_BACKLOG
+            || (new_session && pending(&inbox) >= MAX_PENDING_SESSIONS)
+        {
+            warn!("Party {} sent too much for session {} before it was received", from, session);
+            let e = MpcNetError::Malformed { party: from };
+            inbox.closed[from] = Some(e.clone());
+            self.arrived.notify_all();
+            return Err(e);
+        }
+        // Count the frame header too.
+        let arrival = inbox.pacers[from].arrival(bytes.len() + 16);
+        let queue = inbox.queues.entry((from, session)).or_default();
+        queue.bytes += bytes.len();
+        queue.frames.push_back((arrival, bytes));
+        self.arrived.notify_all();
+        Ok(())
+    }
+
+    /// Note that `from` will send nothing more, and why.
+    fn close(&self, from: usize, error: MpcNetError) {
+        self.inbox.lock().unwrap().closed[from].get_or_insert(error);
+        self.arrived.notify_all();
+    }
+
+    /// Note that we have started `session`, so frames for it are expected.
+    fn start_session(&self, session: u64) {
+        self.inbox.lock().unwrap().started.insert(session);
+    }
+
+    fn recv(&self, from: usize, session: u64) -> Result<Vec<u8>, MpcNetError> {
+        let mut inbox = self.inbox.lock().unwrap();
+        loop {
+            let queue = inbox.queues.get_mut(&(from, session));
+            match queue.as_ref().and_then(|q| q.frames.front()).map(|(arrival, _)| *arrival) {
+                Some(arrival) => {
+                    let now = Instant::now();
+                    if arrival <= now {
+                        let queue = queue.unwrap();
+                        let bytes = queue.frames.pop_front().unwrap().1;
+                        queue.bytes -= bytes.len();
+                        return Ok(bytes);
+                    }
+                    inbox = self.arrived.wait_timeout(inbox, arrival - now).unwrap().0;
+                }
+                None => match &inbox.closed[from] {
+                    Some(e) => return Err(e.clone()),
+                    None => inbox = self.arrived.wait(inbox).unwrap(),
+                },
+            }
+        }
+    }
+
+    /// Drop the frames for `session` that were never received, and any that arrive later.
+    fn drop_session(&self, session: u64) {
+        let mut inbox = self.inbox.lock().unwrap();
+        inbox.ended.insert(session);
+        inbox.started.remove(&session);
+        inbox.queues.retain(|(peer, s), q| {
+            if *s == session && !q.frames.is_empty() {
+                warn!("Session {} ended with {} unread frames from {}", s, q.frames.len(), peer);
+            }
+            *s != session
+        });
+    }
+
+    fn has_ended(&self, session: u64) -> bool {
+        self.inbox.lock().unwrap().ended.contains(&session)
+    }
+}
+
+/// How we send to another party.
+enum Link {
+    /// Over a TCP connection, read on the other side by a reader thread.
+    Tcp(Mutex<Writer>),
+    /// Straight into their mailbox, when all parties are in this process.
+    Local(Arc<Mailbox>),
+}
+
+/// One party's view of the network: its links to the other parties, and its sessions.
+struct Network {
+    id: usize,
+    n: usize,
+    topology: Topology,
+    /// Our link to each party (`None` for ourselves, and those the topology does not connect us
+    /// to).
+    links: Vec<Option<Link>>,
+    mailbox: Arc<Mailbox>,
+    readers: Mutex<Vec<JoinHandle<()>>>,
+    sessions: Mutex<HashMap<u64, Arc<SessionData>>>,
+}
+
+impl Network {
+    fn new(id: usize, topology: Topology, links: Vec<Option<Link>>, mailbox: Arc<Mailbox>) -> Self {
+        let mut sessions = HashMap::new();
+        sessions.insert(DEFAULT_SESSION, Arc::new(SessionData::default()));
+        Self {
+            id,
+            n: links.len(),
+            topology,
+            links,
+            mailbox,
+            readers: Mutex::new(Vec::new()),
+            sessions: Mutex::new(sessions),
+        }
+    }
+
+    fn start(connections: Connections, topology: Topology) -> Arc<Self> {
+        let n = connections.peers.len();
+        let mailbox = Arc::new(Mailbox::new(n));
+        let our_shape = connections.peers[connections.id].shape;
+        let mut links = Vec::with_capacity(n);
+        let mut readers = Vec::with_capacity(n);
+        for peer in connections.peers {
+            mailbox.shape(peer.id, peer.shape.join(&our_shape));
+            match peer.stream {
+                Some(stream) => {
+                    let (send, recv) = match peer.ciphers {
+                        Some((send, recv)) => (Some(send), Some(recv)),
+                        None => (None, None),
+                    };
+                    let reader = Reader {
+                        stream: stream.try_clone().unwrap(),
+                        cipher: recv,
                     };
-                    stream.set_nodelay(true).unwrap();
-                    self.peers[to_id].stream = Some(stream);
-                } else if self.id == to_id {
-                    debug!("Awaiting {}", from_id);
-                    let listener = TcpListener::bind(self.peers[self.id].addr).unwrap();
-                    let (stream, _addr) = listener.accept().unwrap();
-                    stream.set_nodelay(true).unwrap();
-                    self.peers[from_id].stream = Some(stream);
+                    readers.push((peer.id, reader));
+                    links.push(Some(Link::Tcp(Mutex::new(Writer {
+                        stream,
+                        cipher: send,
+                    }))));
                 }
+                None => links.push(None),
             }
-            // Sender for next round waits for note from this sender to prevent race on receipt.
-            if from_id + 1 < n {
-                if self.id == from_id {
-                    self.peers[self.id + 1]
-                        .stream
-                        .as_mut()
-                        .unwrap()
-                        .write_all(&[0u8])
-                        .unwrap();
-                } else if self.id == from_id + 1 {
-                    self.peers[self.id - 1]
-                        .stream
-                        .as_mut()
-                        .unwrap()
-                        .read_exact(&mut [0u8])
-                        .unwrap();
+        }
+        let net = Arc::new(Self::new(connections.id, topology, links, mailbox.clone()));
+        let handles = readers
+            .into_iter()
+            .map(|(peer, reader)| {
+                let mailbox = mailbox.clone();
+                std::thread::Builder::new()
+                    .name(format!("mpc-net reader {}", peer))
+                    .spawn(move || read_loop(&mailbox, peer, reader))
+                    .unwrap()
+            })
+            .collect();
+        *net.readers.lock().unwrap() = handles;
+        net
+    }
+
+    /// `n` parties, connected to each other in memory.
+    fn local(n: usize, topology: Topology) -> Vec<Arc<Self>> {
+        let mailboxes: Vec<Arc<Mailbox>> = (0..n).map(|_| Arc::new(Mailbox::new(n))).collect();
+        (0..n)
+            .map(|id| {
+                let links = (0..n)
+                    .map(|peer| {
+                        (peer != id && topology.connects(id, peer))
+                            .then(|| Link::Local(mailboxes[peer].clone()))
+                    })
+                    .collect();
+                Arc::new(Self::new(id, topology, links, mailboxes[id].clone()))
+            })
+            .collect()
+    }
+
+    fn send(&self, peer: usize, session: u64, bytes: &[u8]) -> Result<(), MpcNetError> {
+        assert!(
+            bytes.len() <= MAX_FRAME_LEN,
+            "A message of {} bytes is over the limit of {}",
+            bytes.len(),
+            MAX_FRAME_LEN
+        );
+        let link = self.links[peer]
+            .as_ref()
+            .unwrap_or_else(|| panic!("Party {} has no link to party {}", self.id, peer));
+        match link {
+            Link::Tcp(writer) => writer
+                .lock()
+                .unwrap()
+                .send_frame(session, bytes)
+                .map_err(|e| disconnected(peer, e)),
+            Link::Local(mailbox) => {
+                // If they refuse it, they will report it; sending succeeded.
+                let _ = mailbox.deliver(self.id, session, bytes.to_vec());
+                Ok(())
+            }
+        }
+    }
+
+    fn recv(&self, peer: usize, session: u64) -> Result<Vec<u8>, MpcNetError> {
+        self.mailbox.recv(peer, session)
+    }
+
+    /// The session with id `id`, starting it if needed.
+    fn session(self: &Arc<Self>, id: u64) -> MpcSession {
+        assert!(
+            !self.mailbox.has_ended(id),
+            "Session {} has ended; session ids cannot be reused",
+            id
+        );
+        let data = self.sessions.lock().unwrap().entry(id).or_default().clone();
+        self.mailbox.start_session(id);
+        MpcSession {
+            id,
+            net: self.clone(),
+            data,
+        }
+    }
+
+    /// Forget a session's state and any frames for it that were never received (or are still in
+    /// flight).
+    fn end_session(&self, id: u64) {
+        self.sessions.lock().unwrap().remove(&id);
+        self.mailbox.drop_session(id);
+    }
+
+    fn shutdown(&self) {
+        for link in self.links.iter().flatten() {
+            match link {
+                Link::Tcp(writer) => {
+                    let _ = writer.lock().unwrap().stream.shutdown(Shutdown::Both);
                 }
+                Link::Local(mailbox) => mailbox.close(
+                    self.id,
+                    MpcNetError::Disconnected {
+                        party: self.id,
+                        reason: "connection closed".to_owned(),
+                    },
+                ),
             }
         }
-        // Do a round with the king, to be sure everyone is ready
-        let from_all = self.send_to_king(&[self.id as u8]);
-        self.recv_from_king(from_all);
-        for id in 0..n {
-            if id != self.id {
-                assert!(self.peers[id].stream.is_some());
+        for r in self.readers.lock().unwrap().drain(..) {
+            let _ = r.join();
+        }
+    }
+}
+
+/// Sort the frames from `peer` into `mailbox`, until the connection closes.
+fn read_loop(mailbox: &Mailbox, peer: usize, mut reader: Reader) {
+    let error = loop {
+        match reader.recv_frame() {
+            Ok((session, bytes)) => {
+                if let Err(e) = mailbox.deliver(peer, session, bytes) {
+                    break e;
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                break MpcNetError::Disconnected {
+                    party: peer,
+                    reason: "connection closed".to_owned(),
+                }
             }
+            Err(e) if e.kind() == std::io::ErrorKind::InvalidData => {
+                warn!("Bad frame from {}: {}", peer, e);
+                break MpcNetError::Malformed { party: peer };
+            }
+            Err(e) => break disconnected(peer, e),
         }
-        end_timer!(timer);
+    };
+    debug!("Connection to {} closed: {}", peer, error);
+    mailbox.close(peer, error);
+}
+
+/// What a session keeps between network calls.
+#[derive(Default)]
+struct SessionData {
+    stats: Mutex<Stats>,
+    /// Values of any type, keyed by type.
+    state: Mutex<HashMap<TypeId, Arc<Mutex<Box<dyn Any + Send>>>>>,
+}
+
+/// A handle to one MPC session over the network.
+///
+/// All parties must use the same id for a session. Sessions with different ids can run at the
+/// same time, from different threads; their messages are kept apart, and each has its own
+/// statistics and [state](Self::with_state).
+#[derive(Clone)]
+pub struct MpcSession {
+    id: u64,
+    net: Arc<Network>,
+    data: Arc<SessionData>,
+}
+
+impl std::fmt::Debug for MpcSession {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MpcSession").field("id", &self.id).finish()
+    }
+}
+
+impl MpcSession {
+    /// The session with id `id`, starting it if needed.
+    pub fn new(id: u64) -> Self {
+        network().session(id)
+    }
+
+    pub fn id(&self) -> u64 {
+        self.id
     }
-    fn am_king(&self) -> bool {
-        self.id == 0
+
+    /// Run `f` with this as the current session of this thread.
+    ///
+    /// The static [`MpcMultiNet`] functions called by `f` (on this thread) go to this session.
+    pub fn enter<R>(&self, f: impl FnOnce() -> R) -> R {
+        struct Restore(Option<MpcSession>);
+        impl Drop for Restore {
+            fn drop(&mut self) {
+                let prev = self.0.take();
+                CURRENT.with(|c| *c.borrow_mut() = prev);
+            }
+        }
+        let _restore = Restore(CURRENT.with(|c| c.borrow_mut().replace(self.clone())));
+        f()
     }
-    fn broadcast(&mut self, bytes_out: &[u8]) -> Vec<Vec<u8>> {
+
+    /// Like [`enter`](Self::enter), but if `f` aborts with an error (see
+    /// [`OrAbort`](crate::OrAbort)), return it rather than unwinding.
+    ///
+    /// Other sessions carry on. Frames of an aborted session may still be in flight, so end it
+    /// rather than use it again.
+    pub fn run<R>(&self, f: impl FnOnce() -> R) -> Result<R, MpcNetError> {
+        crate::catch(|| self.enter(f))
+    }
+
+    /// End the session, forgetting its state and any frames for it that were never received.
+    pub fn end(self) {
+        assert_ne!(self.id, DEFAULT_SESSION, "The default session cannot be ended");
+        self.net.end_session(self.id);
+    }
+
+    /// Run `f` on this session's value of type `T`, which `init` makes if there is none yet.
+    ///
+    /// This is how protocols keep per-session state (keys, seeds, preprocessing), so that
+    /// concurrent sessions do not share it. `f` must not ask for a value of the same type.
+    pub fn with_state<T: Any + Send, R>(
+        &self,
+        init: impl FnOnce() -> T,
+        f: impl FnOnce(&mut T) -> R,
+    ) -> R {
+        let existing = self.data.state.lock().unwrap().get(&TypeId::of::<T>()).cloned();
+        // `init` may talk to the other parties, so we do not hold the lock over it.
+        let cell = existing.unwrap_or_else(|| {
+            let value: Box<dyn Any + Send> = Box::new(init());
+            self.data
+                .state
+                .lock()
+                .unwrap()
+                .entry(TypeId::of::<T>())
+                .or_insert_with(|| Arc::new(Mutex::new(value)))
+                .clone()
+        });
+        let mut value = cell.lock().unwrap();
+        f(value.downcast_mut().unwrap())
+    }
+
+    /// Remove and return this session's value of type `T`, if there is one.
+    pub fn take_state<T: Any + Send>(&self) -> Option<T> {
+        let cell = self.data.state.lock().unwrap().remove(&TypeId::of::<T>())?;
+        let mut value = cell.lock().unwrap();
+        let value = std::mem::replace(&mut *value, Box::new(()));
+        Some(*value.downcast().unwrap())
+    }
+
+    #[inline]
+    pub fn party_id(&self) -> usize {
+        self.net.id
+    }
+
+    #[inline]
+    pub fn n_parties(&self) -> usize {
+        self.net.n
+    }
+
+    #[inline]
+    pub fn am_king(&self) -> bool {
+        self.party_id() == 0
+    }
+
+    pub fn reset_stats(&self) {
+        *self.data.stats.lock().unwrap() = Stats::default();
+    }
+
+    pub fn stats(&self) -> Stats {
+        self.data.stats.lock().unwrap().clone()
+    }
+
+    fn count(&self, f: impl Fn(&mut Stats)) {
+        self.data.stats.lock().unwrap().count(f)
+    }
+
+    /// All parties send bytes to each other.
+    pub fn broadcast_bytes(&self, bytes_out: &[u8]) -> Result<Vec<Vec<u8>>, MpcNetError> {
+        if self.net.topology == Topology::Star {
+            self.count(|stats| stats.broadcasts += 1);
+            return self.relay(vec![bytes_out.to_vec()], true);
+        }
         let timer = start_timer!(|| format!("Broadcast {}", bytes_out.len()));
+        let net = &self.net;
         let m = bytes_out.len();
-        let own_id = self.id;
-        self.stats.bytes_sent += (self.peers.len() - 1) * m;
-        self.stats.bytes_recv += (self.peers.len() - 1) * m;
-        self.stats.broadcasts += 1;
-        let r = self
-            .peers
-            .par_iter_mut()
-            .enumerate()
-            .map(|(id, peer)| {
-                let mut bytes_in = vec![0u8; m];
-                if id < own_id {
-                    let stream = peer.stream.as_mut().unwrap();
-                    stream.read_exact(&mut bytes_in[..]).unwrap();
-                    stream.write_all(bytes_out).unwrap();
-                } else if id == own_id {
-                    bytes_in.copy_from_slice(bytes_out);
+        self.count(|stats| {
+            stats.bytes_sent += (net.n - 1) * m;
+            stats.bytes_recv += (net.n - 1) * m;
+            stats.broadcasts += 1;
+            stats.rounds += 1;
+            stats.message(m);
+        });
+        for id in (0..net.n).filter(|id| *id != net.id) {
+            net.send(id, self.id, bytes_out)?;
+        }
+        let r = (0..net.n)
+            .map(|id| {
+                if id == net.id {
+                    Ok(bytes_out.to_vec())
                 } else {
-                    let stream = peer.stream.as_mut().unwrap();
-                    stream.write_all(bytes_out).unwrap();
-                    stream.read_exact(&mut bytes_in[..]).unwrap();
-                };
-                bytes_in
+                    net.recv(id, self.id)
+                }
             })
             .collect();
         end_timer!(timer);
         r
     }
-    fn send_to_king(&mut self, bytes_out: &[u8]) -> Option<Vec<Vec<u8>>> {
-        let timer = start_timer!(|| format!("To king {}", bytes_out.len()));
+
+    /// All parties send bytes to the king.
+    pub fn send_bytes_to_king(
+        &self,
+        bytes_out: &[u8],
+    ) -> Result<Option<Vec<Vec<u8>>>, MpcNetError> {
+        self.send_bytes_to(0, bytes_out)
+    }
+
+    /// All parties send bytes to party `to`, which gets them all, in party order.
+    ///
+    /// With [`Topology::Star`], what the others send a party other than the king goes through
+    /// the king, which sees it.
+    pub fn send_bytes_to(
+        &self,
+        to: usize,
+        bytes_out: &[u8],
+    ) -> Result<Option<Vec<Vec<u8>>>, MpcNetError> {
+        let net = &self.net;
+        assert!(to < net.n, "There is no party {}", to);
+        if net.topology == Topology::Star && to != 0 {
+            let mut out = vec![Vec::new(); net.n];
+            out[to] = bytes_out.to_vec();
+            let r = self.relay(out, false)?;
+            return Ok(if net.id == to { Some(r) } else { None });
+        }
+        let timer = start_timer!(|| format!("To party {} {}", to, bytes_out.len()));
         let m = bytes_out.len();
-        let own_id = self.id;
-        self.stats.to_king += 1;
-        let r = if self.am_king() {
-            self.stats.bytes_recv += (self.peers.len() - 1) * m;
+        self.count(|stats| {
+            if to == 0 {
+                stats.to_king += 1;
+            }
+            stats.rounds += 1;
+            stats.message(m);
+            if net.id == to {
+                stats.bytes_recv += (net.n - 1) * m;
+            } else {
+                stats.bytes_sent += m;
+            }
+        });
+        let r = if net.id == to {
             Some(
-                self.peers
-                    .par_iter_mut()
-                    .enumerate()
-                    .map(|(id, peer)| {
-                        let mut bytes_in = vec![0u8; m];
-                        if id == own_id {
-                            bytes_in.copy_from_slice(bytes_out);
+                (0..net.n)
+                    .map(|id| {
+                        if id == net.id {
+                            Ok(bytes_out.to_vec())
                         } else {
-                            let stream = peer.stream.as_mut().unwrap();
-                            stream.read_exact(&mut bytes_in[..]).unwrap();
-                        };
-                        bytes_in
+                            net.recv(id, self.id)
+                        }
                     })
-                    .collect(),
+                    .collect::<Result<_, _>>()?,
             )
         } else {
-            self.stats.bytes_sent += m;
-            self.peers[0]
-                .stream
-                .as_mut()
-                .unwrap()
-                .write_all(bytes_out)
-                .unwrap();
+            net.send(to, self.id, bytes_out)?;
             None
         };
         end_timer!(timer);
-        r
+        Ok(r)
     }
-    fn recv_from_king(&mut self, bytes_out: Option<Vec<Vec<u8>>>) -> Vec<u8> {
-        let own_id = self.id;
-        self.stats.from_king += 1;
-        if self.am_king() {
+
+    /// All parties recv bytes from the king.
+    /// Provide bytes iff you're the king!
+    pub fn recv_bytes_from_king(
+        &self,
+        bytes_out: Option<Vec<Vec<u8>>>,
+    ) -> Result<Vec<u8>, MpcNetError> {
+        let net = &self.net;
+        if net.id == 0 {
             let bytes_out = bytes_out.unwrap();
             let m = bytes_out[0].len();
             let timer = start_timer!(|| format!("From king {}", m));
-            let bytes_size = (m as u64).to_le_bytes();
-            self.stats.bytes_sent += (self.peers.len() - 1) * (m + 8);
-            self.peers
-                .par_iter_mut()
-                .enumerate()
-                .filter(|p| p.0 != own_id)
-                .for_each(|(id, peer)| {
-                    let stream = peer.stream.as_mut().unwrap();
-                    assert_eq!(bytes_out[id].len(), m);
-                    stream.write_all(&bytes_size).unwrap();
-                    stream.write_all(&bytes_out[id]).unwrap();
-                });
+            self.count(|stats| {
+                stats.from_king += 1;
+                stats.rounds += 1;
+                stats.bytes_sent += (net.n - 1) * (m + 8);
+                stats.message(m);
+            });
+            for id in (0..net.n).filter(|id| *id != net.id) {
+                assert_eq!(bytes_out[id].len(), m);
+                net.send(id, self.id, &bytes_out[id])?;
+            }
             end_timer!(timer);
-            bytes_out[own_id].clone()
+            Ok(bytes_out[net.id].clone())
         } else {
-            let stream = self.peers[0].stream.as_mut().unwrap();
-            let mut bytes_size = [0u8; 8];
-            stream.read_exact(&mut bytes_size).unwrap();
-            let m = u64::from_le_bytes(bytes_size) as usize;
-            self.stats.bytes_recv += m;
-            let mut bytes_in = vec![0u8; m];
-            stream.read_exact(&mut bytes_in).unwrap();
-            bytes_in
+            let bytes_in = net.recv(0, self.id)?;
+            self.count(|stats| {
+                stats.from_king += 1;
+                stats.rounds += 1;
+                stats.bytes_recv += bytes_in.len();
+                stats.message(bytes_in.len());
+            });
+            Ok(bytes_in)
         }
     }
-    fn uninit(&mut self) {
-        for p in &mut self.peers {
-            p.stream = None;
+
+    /// Everyone sends bytes to the king, who recieves those bytes, runs a computation on them,
+    /// and redistributes the resulting bytes.
+    pub fn king_compute(
+        &self,
+        bytes: &[u8],
+        f: impl Fn(Vec<Vec<u8>>) -> Vec<Vec<u8>>,
+    ) -> Result<Vec<u8>, MpcNetError> {
+        let king_response = self.send_bytes_to_king(bytes)?.map(f);
+        self.recv_bytes_from_king(king_response)
+    }
+
+    /// Each party sends `bytes[j]` to party `j`.
+    /// Returns the bytes sent to us by each party, in party order.
+    pub fn all_to_all_bytes(&self, bytes_out: Vec<Vec<u8>>) -> Result<Vec<Vec<u8>>, MpcNetError> {
+        if self.net.topology == Topology::Star {
+            assert_eq!(bytes_out.len(), self.net.n);
+            self.count(|stats| stats.all_to_all += 1);
+            return self.relay(bytes_out, false);
         }
+        let timer = start_timer!(|| format!("All to all {}", bytes_out[0].len()));
+        let net = &self.net;
+        assert_eq!(bytes_out.len(), net.n);
+        let mut sent = 0;
+        let mut largest_out = 0;
+        for (id, bytes) in bytes_out.iter().enumerate().filter(|(id, _)| *id != net.id) {
+            net.send(id, self.id, bytes)?;
+            sent += bytes.len() + 8;
+            largest_out = largest_out.max(bytes.len());
+        }
+        let mut bytes_out = bytes_out;
+        let r: Vec<Vec<u8>> = (0..net.n)
+            .map(|id| {
+                if id == net.id {
+                    Ok(std::mem::take(&mut bytes_out[id]))
+                } else {
+                    net.recv(id, self.id)
+                }
+            })
+            .collect::<Result<_, _>>()?;
+        let others = || r.iter().enumerate().filter(|(id, _)| *id != net.id);
+        let recv = others().map(|(_, bytes_in)| bytes_in.len() + 8).sum::<usize>();
+        let largest = others()
+            .map(|(_, bytes_in)| bytes_in.len())
+            .chain(std::iter::once(largest_out))
+            .max()
+            .unwrap_or(0);
+        self.count(|stats| {
+            stats.all_to_all += 1;
+            stats.rounds += 1;
+            stats.bytes_sent += sent;
+            stats.bytes_recv += recv;
+            stats.message(largest);
+        });
+        end_timer!(timer);
+        Ok(r)
+    }
+
+    /// Send bytes to the next party around the ring of parties, and receive from the previous one.
+    pub fn pass_to_next_bytes(&self, bytes_out: &[u8]) -> Result<Vec<u8>, MpcNetError> {
+        Ok(self.pass_around_bytes(bytes_out, 1)?.pop().unwrap())
     }
-    fn pass_to_next(&mut self, bytes_out: &[u8]) -> Vec<u8> { 
+
+    /// Send bytes to each of the next `hops` parties around the ring of parties.
+    /// Returns the bytes from each of the previous `hops` parties, nearest first.
+    pub fn pass_around_bytes(
+        &self,
+        bytes_out: &[u8],
+        hops: usize,
+    ) -> Result<Vec<Vec<u8>>, MpcNetError> {
+        let net = &self.net;
+        let n = net.n;
+        assert!(
+            hops > 0 && hops < n,
+            "Cannot pass {} hops around {} parties",
+            hops,
+            n
+        );
+        if net.topology == Topology::Star {
+            let mut out = vec![Vec::new(); n];
+            for h in 1..=hops {
+                out[(net.id + h) % n] = bytes_out.to_vec();
+            }
+            let mut bytes_in = self.relay(out, false)?;
+            return Ok((1..=hops)
+                .map(|h| std::mem::take(&mut bytes_in[(net.id + n - h) % n]))
+                .collect());
+        }
         let timer = start_timer!(|| format!("Pass around {}", bytes_out.len()));
         let m = bytes_out.len();
-        let own_id = self.id;
-        self.stats.bytes_sent += m;
-        self.stats.bytes_recv += m;
-        let mut bytes_in = vec![0u8; m];
-        self.peers
-            .iter_mut()
-            .enumerate()
-            .for_each(|(id, peer)| {
-                if id == (own_id + 1) % 3 {
-                    let stream = peer.stream.as_mut().unwrap();
-                    stream.write_all(bytes_out).unwrap();
-                } else if (id + 1) % 3 == own_id {
-                    let stream = peer.stream.as_mut().unwrap();
-                    stream.read_exact(&mut bytes_in[..]).unwrap();
-                };
+        for h in 1..=hops {
+            net.send((net.id + h) % n, self.id, bytes_out)?;
+        }
+        let bytes_in: Vec<Vec<u8>> = (1..=hops)
+            .map(|h| net.recv((net.id + n - h) % n, self.id))
+            .collect::<Result<_, _>>()?;
+        let recv = bytes_in.iter().map(|b| b.len()).sum::<usize>();
+        let largest = bytes_in.iter().map(|b| b.len()).fold(m, usize::max);
+        self.count(|stats| {
+            stats.bytes_sent += hops * m;
+            stats.bytes_recv += recv;
+            stats.rounds += 1;
+            stats.message(largest);
+        });
+        end_timer!(timer);
+        Ok(bytes_in)
+    }
+}
+
+/// Length-prefixed messages, one after another.
+fn pack(messages: &[impl AsRef<[u8]>]) -> Vec<u8> {
+    let len = messages.iter().map(|m| 8 + m.as_ref().len()).sum();
+    let mut bytes = Vec::with_capacity(len);
+    for m in messages {
+        bytes.extend_from_slice(&(m.as_ref().len() as u64).to_le_bytes());
+        bytes.extend_from_slice(m.as_ref());
+    }
+    bytes
+}
+
+/// The `n` messages that `party` [packed](pack) into `bytes`.
+fn unpack(party: usize, mut bytes: &[u8], n: usize) -> Result<Vec<Vec<u8>>, MpcNetError> {
+    let malformed = MpcNetError::Malformed { party };
+    let mut messages = Vec::with_capacity(n);
+    for _ in 0..n {
+        if bytes.len() < 8 {
+            return Err(malformed);
+        }
+        let (len, rest) = bytes.split_at(8);
+        let len = u64::from_le_bytes(len.try_into().unwrap());
+        if len > rest.len() as u64 {
+            return Err(malformed);
+        }
+        let (message, rest) = rest.split_at(len as usize);
+        messages.push(message.to_vec());
+        bytes = rest;
+    }
+    if !bytes.is_empty() {
+        return Err(malformed);
+    }
+    Ok(messages)
+}
+
+impl MpcSession {
+    /// Send messages to the other parties through the king, for [`Topology::Star`]: everyone
+    /// sends the king all their messages in one frame, then the king sends each party, in one
+    /// frame, the messages for it.
+    ///
+    /// If `to_all`, `bytes_out` is one message for everyone (a broadcast); otherwise it has a
+    /// message for each party. Returns the message from each party, as
+    /// [`all_to_all_bytes`](Self::all_to_all_bytes) does.
+    fn relay(&self, mut bytes_out: Vec<Vec<u8>>, to_all: bool) -> Result<Vec<Vec<u8>>, MpcNetError> {
+        let timer = start_timer!(|| format!("Relay {}", bytes_out[0].len()));
+        let net = &self.net;
+        let n = net.n;
+        let ours = if to_all { 0 } else { net.id };
+        let r = if net.id == 0 {
+            let mut recv = 0;
+            let mut largest = 0;
+            // What each party has for each party.
+            let mut rows = Vec::with_capacity(n);
+            rows.push(bytes_out);
+            for id in 1..n {
+                let frame = net.recv(id, self.id)?;
+                recv += frame.len();
+                largest = largest.max(frame.len());
+                rows.push(if to_all { vec![frame] } else { unpack(id, &frame, n)? });
+            }
+            let message = |from: usize, to: usize| -> &[u8] {
+                if to_all {
+                    &rows[from][0]
+                } else {
+                    &rows[from][to]
+                }
+            };
+            let mut sent = 0;
+            let mut relayed = 0;
+            for to in 1..n {
+                let theirs = (0..n).map(|from| if from == to { &[][..] } else { message(from, to) });
+                let frame = pack(&theirs.collect::<Vec<_>>());
+                net.send(to, self.id, &frame)?;
+                sent += frame.len();
+                largest = largest.max(frame.len());
+                relayed += (1..n)
+                    .filter(|from| *from != to)
+                    .map(|from| message(from, to).len())
+                    .sum::<usize>();
+            }
+            self.count(|stats| {
+                stats.rounds += 2;
+                stats.bytes_sent += sent;
+                stats.bytes_recv += recv;
+                stats.relayed += relayed;
+                stats.message(largest);
             });
+            (0..n).map(|from| message(from, 0).to_vec()).collect()
+        } else {
+            let frame = if to_all {
+                bytes_out[0].clone()
+            } else {
+                pack(&bytes_out)
+            };
+            net.send(0, self.id, &frame)?;
+            let reply = net.recv(0, self.id)?;
+            self.count(|stats| {
+                stats.rounds += 2;
+                stats.bytes_sent += frame.len();
+                stats.bytes_recv += reply.len();
+                stats.message(frame.len().max(reply.len()));
+            });
+            let mut r = unpack(0, &reply, n)?;
+            r[net.id] = std::mem::take(&mut bytes_out[ours]);
+            r
+        };
         end_timer!(timer);
-        bytes_in
+        Ok(r)
     }
 }
 
 pub struct MpcMultiNet;
 
+impl MpcMultiNet {
+    /// The session that network calls on this thread go to: the one entered with
+    /// [`MpcSession::enter`].
+    ///
+    /// Panics if no session has been entered on this thread. Code that is handed a session rather
+    /// than run in one can call the handle's own methods instead, which need no entered session.
+    pub fn current_session() -> MpcSession {
+        CURRENT.with(|c| c.borrow().clone()).unwrap_or_else(|| {
+            panic!(
+                "No MPC session has been entered on thread {:?}; use MpcSession::enter, or call \
+                 the session handle's own methods",
+                std::thread::current().name().unwrap_or("<unnamed>")
+            )
+        })
+    }
+
+    /// The session with id `id`, starting it if needed.
+    pub fn session(id: u64) -> MpcSession {
+        MpcSession::new(id)
+    }
+
+    /// Run `f` on the current session's value of type `T`. See [`MpcSession::with_state`].
+    pub fn with_state<T: Any + Send, R>(init: impl FnOnce() -> T, f: impl FnOnce(&mut T) -> R) -> R {
+        Self::current_session().with_state(init, f)
+    }
+
+    /// Remove and return the current session's value of type `T`, if there is one.
+    pub fn take_state<T: Any + Send>() -> Option<T> {
+        Self::current_session().take_state()
+    }
+
+    /// Like [`MpcNet::init_from_file`], but connecting the parties as `topology` says. All parties
+    /// must use the same topology.
+    pub fn init_with_topology(
+        path: &str,
+        party_id: usize,
+        transport: Transport,
+        topology: Topology,
+    ) -> Result<(), MpcNetError> {
+        let mut connections = Connections::default();
+        connections.init_from_path(path, party_id);
+        connections.connect_to_all(topology)?;
+        if let Transport::Noise { secret_key } = &transport {
+            connections.secure(secret_key)?;
+        }
+        let net = Network::start(connections, topology);
+        *NETWORK.write().unwrap() = Some(net.clone());
+        let session = net.session(DEFAULT_SESSION);
+        CURRENT.with(|c| *c.borrow_mut() = Some(session.clone()));
+        // Do a round with the king, to be sure everyone is ready
+        let from_all = session.send_bytes_to_king(&[party_id as u8])?;
+        session.recv_bytes_from_king(from_all)?;
+        Ok(())
+    }
+
+    /// Emulate `shape` on every link to us, in place of the conditions in the hosts file.
+    ///
+    /// This shapes the frames we receive, so every party should do the same.
+    pub fn shape_links(shape: LinkShape) {
+        let net = network();
+        for peer in (0..net.n).filter(|p| *p != net.id) {
+            net.mailbox.shape(peer, shape);
+        }
+    }
+
+    /// Run `f` as each of `n` parties, all in this process, and return their results in party
+    /// order.
+    ///
+    /// Each party is a thread, with its own network and sessions, and the parties are connected
+    /// in memory rather than over TCP; there is no need to call [`MpcNet::init_from_file`]. This
+    /// is how tests run a protocol end to end. If a party panics, the others lose their
+    /// connection to it, and the first panic (or abort, see [`OrAbort`](crate::OrAbort)) is
+    /// raised again here.
+    pub fn simulate<R, F>(n: usize, f: F) -> Vec<R>
+    where
+        R: Send + 'static,
+        F: Fn() -> R + Send + Sync + 'static,
+    {
+        Self::simulate_with(n, Topology::Mesh, f)
+    }
+
+    /// Like [`simulate`](Self::simulate), with the parties connected as `topology` says.
+    pub fn simulate_with<R, F>(n: usize, topology: Topology, f: F) -> Vec<R>
+    where
+        R: Send + 'static,
+        F: Fn() -> R + Send + Sync + 'static,
+    {
+        /// Closes our links when the party finishes, even by panicking, so nobody waits on us.
+        struct Shutdown(Arc<Network>);
+        impl Drop for Shutdown {
+            fn drop(&mut self) {
+                self.0.shutdown();
+                CURRENT.with(|c| *c.borrow_mut() = None);
+            }
+        }
+        let f = Arc::new(f);
+        let parties: Vec<_> = Network::local(n, topology)
+            .into_iter()
+            .map(|net| {
+                let f = f.clone();
+                std::thread::Builder::new()
+                    .name(format!("party {}", net.id))
+                    // Like a main thread's stack; the provers recurse deeply in debug builds.
+                    .stack_size(8 << 20)
+                    .spawn(move || {
+                        let session = net.session(DEFAULT_SESSION);
+                        CURRENT.with(|c| *c.borrow_mut() = Some(session));
+                        let _shutdown = Shutdown(net);
+                        f()
+                    })
+                    .unwrap()
+            })
+            .collect();
+        let results: Vec<_> = parties.into_iter().map(|p| p.join()).collect();
+        // A party that lost its connection is usually not the one at fault.
+        let lost_connection = |e: &Box<dyn Any + Send>| {
+            matches!(e.downcast_ref(), Some(MpcNetError::Disconnected { .. }))
+        };
+        if results.iter().any(|r| r.is_err()) {
+            let mut errors: Vec<_> = results
+                .into_iter()
+                .enumerate()
+                .filter_map(|(i, r)| Some(i).zip(r.err()))
+                .collect();
+            let i = errors.iter().position(|(_, e)| !lost_connection(e)).unwrap_or(0);
+            let (party, e) = errors.swap_remove(i);
+            match e.downcast::<MpcNetError>() {
+                Ok(e) => panic!("Party {} aborted: {}", party, e),
+                Err(e) => std::panic::resume_unwind(e),
+            }
+        }
+        results.into_iter().map(|r| r.unwrap()).collect()
+    }
+}
+
 impl MpcNet for MpcMultiNet {
     #[inline]
     fn party_id() -> usize {
-        get_ch!().id
+        try_network().map_or(0, |n| n.id)
     }
 
     #[inline]
     fn n_parties() -> usize {
-        get_ch!().peers.len()
+        try_network().map_or(0, |n| n.n)
     }
 
     #[inline]
-    fn init_from_file(path: &str, party_id: usize) {
-        let mut ch = get_ch!();
-        ch.init_from_path(path, party_id);
-        ch.connect_to_all();
+    fn init_from_file(
+        path: &str,
+        party_id: usize,
+        transport: Transport,
+    ) -> Result<(), MpcNetError> {
+        Self::init_with_topology(path, party_id, transport, Topology::Mesh)
     }
 
     #[inline]
     fn is_init() -> bool {
-        get_ch!()
-            .peers
-            .first()
-            .map(|p| p.stream.is_some())
-            .unwrap_or(false)
+        try_network().is_some()
     }
 
     #[inline]
     fn deinit() {
-        get_ch!().uninit()
+        CURRENT.with(|c| *c.borrow_mut() = None);
+        let net = NETWORK.write().unwrap().take();
+        if let Some(net) = net {
+            net.shutdown();
+        }
     }
 
     #[inline]
     fn reset_stats() {
-        get_ch!().stats = Stats::default();
+        Self::current_session().reset_stats()
     }
 
     #[inline]
     fn stats() -> crate::Stats {
-        get_ch!().stats.clone()
+        Self::current_session().stats()
+    }
+
+    #[inline]
+    fn broadcast_bytes(bytes: &[u8]) -> Result<Vec<Vec<u8>>, MpcNetError> {
+        Self::current_session().broadcast_bytes(bytes)
+    }
+
+    #[inline]
+    fn send_bytes_to_king(bytes: &[u8]) -> Result<Option<Vec<Vec<u8>>>, MpcNetError> {
+        Self::current_session().send_bytes_to_king(bytes)
+    }
+
+    #[inline]
+    fn send_bytes_to(to: usize, bytes: &[u8]) -> Result<Option<Vec<Vec<u8>>>, MpcNetError> {
+        Self::current_session().send_bytes_to(to, bytes)
     }
 
     #[inline]
-    fn broadcast_bytes(bytes: &[u8]) -> Vec<Vec<u8>> {
-        get_ch!().broadcast(bytes)
+    fn recv_bytes_from_king(bytes: Option<Vec<Vec<u8>>>) -> Result<Vec<u8>, MpcNetError> {
+        Self::current_session().recv_bytes_from_king(bytes)
     }
 
     #[inline]
-    fn send_bytes_to_king(bytes: &[u8]) -> Option<Vec<Vec<u8>>> {
-        get_ch!().send_to_king(bytes)
+    fn pass_to_next_bytes(bytes: &[u8]) -> Result<Vec<u8>, MpcNetError> {
+        Self::current_session().pass_to_next_bytes(bytes)
     }
 
     #[inline]
-    fn recv_bytes_from_king(bytes: Option<Vec<Vec<u8>>>) -> Vec<u8> {
-        get_ch!().recv_from_king(bytes)
+    fn pass_around_bytes(bytes: &[u8], hops: usize) -> Result<Vec<Vec<u8>>, MpcNetError> {
+        Self::current_session().pass_around_bytes(bytes, hops)
     }
 
     #[inline]
-    fn pass_to_next_bytes(bytes: &[u8]) -> Vec<u8> { 
-        get_ch!().pass_to_next(bytes)
+    fn all_to_all_bytes(bytes: Vec<Vec<u8>>) -> Result<Vec<Vec<u8>>, MpcNetError> {
+        Self::current_session().all_to_all_bytes(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_over_the_length_limit_are_malformed() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut sender = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let reader = Reader {
+            stream: listener.accept().unwrap().0,
+            cipher: None,
+        };
+        sender.write_all(&u64::MAX.to_le_bytes()).unwrap();
+        let mailbox = Mailbox::new(2);
+        read_loop(&mailbox, 1, reader);
+        assert_eq!(mailbox.recv(1, DEFAULT_SESSION), Err(MpcNetError::Malformed { party: 1 }));
+    }
+
+    fn free_addr() -> SocketAddr {
+        TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap()
+    }
+
+    #[test]
+    fn bad_hellos_are_ignored() {
+        let addrs = [free_addr(), free_addr()];
+        let connections = move |id: usize| Connections {
+            id,
+            peers: (0..2)
+                .map(|i| Peer {
+                    id: i,
+                    addr: addrs[i],
+                    ..Peer::default()
+                })
+                .collect(),
+        };
+        let party_1 = std::thread::spawn(move || {
+            let mut c = connections(1);
+            c.connect_to_all(Topology::Mesh).map(|()| c.peers[0].stream.is_some())
+        });
+        // Before party 0, someone that hangs up halfway through its hello, and someone claiming
+        // to be party 1.
+        let mut stray = connect(1, addrs[1], Instant::now() + CONNECT_TIMEOUT).unwrap();
+        stray.write_all(&[0; 3]).unwrap();
+        drop(stray);
+        let mut stray = connect(1, addrs[1], Instant::now() + CONNECT_TIMEOUT).unwrap();
+        stray.write_all(&1u64.to_le_bytes()).unwrap();
+        let mut c = connections(0);
+        c.connect_to_all(Topology::Mesh).unwrap();
+        assert_eq!(party_1.join().unwrap(), Ok(true));
+    }
+
+    #[test]
+    fn backlogs_are_limited() {
+        let mailbox = Mailbox::new(3);
+        mailbox.start_session(DEFAULT_SESSION);
+        // Party 1 may run ahead, into sessions we have not started, but only so far.
+        for session in 1..=MAX_PENDING_SESSIONS as u64 {
+            mailbox.deliver(1, session, vec![0]).unwrap();
+        }
+        let too_many = MAX_PENDING_SESSIONS as u64 + 1;
+        assert_eq!(
+            mailbox.deliver(1, too_many, vec![0]),
+            Err(MpcNetError::Malformed { party: 1 })
+        );
+        // Frames it sent before are still there, then the error.
+        assert_eq!(mailbox.recv(1, 1), Ok(vec![0]));
+        assert_eq!(mailbox.recv(1, 1), Err(MpcNetError::Malformed { party: 1 }));
+
+        // Party 2 may not queue more than `MAX_BACKLOG` bytes for one session.
+        for _ in 0..MAX_BACKLOG / MAX_FRAME_LEN {
+            mailbox.deliver(2, DEFAULT_SESSION, vec![0; MAX_FRAME_LEN]).unwrap();
+        }
+        assert_eq!(
+            mailbox.deliver(2, DEFAULT_SESSION, vec![0]),
+            Err(MpcNetError::Malformed { party: 2 })
+        );
+    }
+
+    #[test]
+    fn pass_around_the_ring() {
+        let n = 5;
+        for topology in [Topology::Mesh, Topology::Star] {
+            let received = MpcMultiNet::simulate_with(n, topology, move || {
+                let id = MpcMultiNet::party_id() as u8;
+                let next = MpcMultiNet::pass_to_next_bytes(&[id]).unwrap();
+                (next, MpcMultiNet::pass_around_bytes(&[id; 2], 3).unwrap())
+            });
+            for (id, (next, around)) in received.into_iter().enumerate() {
+                let prev = |h: usize| ((id + n - h) % n) as u8;
+                assert_eq!(next, vec![prev(1)]);
+                assert_eq!(around, (1..=3).map(|h| vec![prev(h); 2]).collect::<Vec<_>>());
+            }
+        }
+    }
+
+    #[test]
+    fn stats_count_each_phase_once() {
+        let stats = MpcMultiNet::simulate(3, || {
+            let session = MpcMultiNet::session(1);
+            session.broadcast_bytes(&[0; 10]).unwrap();
+            let outer = crate::phase("outer");
+            session.broadcast_bytes(&[0; 20]).unwrap();
+            {
+                let _inner = crate::phase("inner");
+                session.king_compute(&[0; 30], |bytes| bytes).unwrap();
+            }
+            session.broadcast_bytes(&[0; 5]).unwrap();
+            drop(outer);
+            let stats = session.stats();
+            session.end();
+            stats
+        });
+        for stats in stats {
+            assert_eq!((stats.broadcasts, stats.rounds, stats.max_message), (3, 5, 30));
+            let phases: Vec<_> = stats.phases.iter().map(|(name, s)| (&name[..], s.rounds)).collect();
+            assert_eq!(phases, vec![("outer", 2), ("outer/inner", 2)]);
+            assert_eq!(stats.phases[0].1.bytes_sent, 2 * 25);
+            assert_eq!(stats.phases[0].1.max_message, 20);
+        }
+    }
+
+    #[test]
+    fn star_relays_through_king() {
+        let n = 4;
+        let exchange = move || {
+            let id = MpcMultiNet::party_id();
+            let broadcast = MpcMultiNet::broadcast_bytes(&[id as u8; 3]).unwrap();
+            let out = (0..n).map(|to| vec![id as u8; to]).collect();
+            let all_to_all = MpcMultiNet::all_to_all_bytes(out).unwrap();
+            (broadcast, all_to_all, MpcMultiNet::stats())
+        };
+        let mesh = MpcMultiNet::simulate_with(n, Topology::Mesh, exchange);
+        let star = MpcMultiNet::simulate_with(n, Topology::Star, exchange);
+        for (id, (mesh, star)) in mesh.iter().zip(&star).enumerate() {
+            assert_eq!((&mesh.0, &mesh.1), (&star.0, &star.1));
+            assert_eq!(star.0, (0..n).map(|i| vec![i as u8; 3]).collect::<Vec<_>>());
+            assert_eq!(star.1, (0..n).map(|i| vec![i as u8; id]).collect::<Vec<_>>());
+            assert_eq!((mesh.2.rounds, star.2.rounds), (2, 4));
+            assert_eq!(mesh.2.relayed, 0);
+        }
+        // Of the king's broadcast, each of the 3 others gets the other 2's 3 bytes; of the
+        // all-to-all, party `to` gets `to` bytes from each of the other 2.
+        assert_eq!(star[0].2.relayed, 3 * 2 * 3 + (1 + 2 + 3) * 2);
+        assert!(star[1..].iter().all(|(_, _, stats)| stats.relayed == 0));
+    }
+
+    #[test]
+    fn network_calls_need_an_entered_session() {
+        let results = MpcMultiNet::simulate(2, || {
+            let session = MpcMultiNet::current_session();
+            // A new thread starts in no session, rather than in the default one.
+            let unentered = std::thread::spawn(|| {
+                std::panic::catch_unwind(|| MpcMultiNet::broadcast_bytes(&[1])).is_err()
+            })
+            .join()
+            .unwrap();
+            let entered = std::thread::spawn(move || {
+                session.enter(|| MpcMultiNet::broadcast_bytes(&[2]).unwrap())
+            })
+            .join()
+            .unwrap();
+            (unentered, entered)
+        });
+        for (unentered, entered) in results {
+            assert!(unentered);
+            assert_eq!(entered, vec![vec![2], vec![2]]);
+        }
+    }
+
+    #[test]
+    fn ended_sessions_drop_late_frames() {
+        MpcMultiNet::simulate(2, || {
+            let session = MpcMultiNet::session(1);
+            if MpcMultiNet::party_id() == 0 {
+                // Wait until party 1 has ended the session, then send to it anyway.
+                MpcMultiNet::recv_bytes_from_king(MpcMultiNet::send_bytes_to_king(&[]).unwrap())
+                    .unwrap();
+                session.send_bytes_to(1, &[0; 100]).unwrap();
+                session.end();
+            } else {
+                session.end();
+                MpcMultiNet::recv_bytes_from_king(MpcMultiNet::send_bytes_to_king(&[]).unwrap())
+                    .unwrap();
+            }
+            // Both parties agree that the id cannot be used again.
+            let reused = std::panic::catch_unwind(|| MpcMultiNet::session(1));
+            assert!(reused.is_err());
+            MpcMultiNet::broadcast_bytes(&[3]).unwrap();
+        });
     }
 }
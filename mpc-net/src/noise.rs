@@ -0,0 +1,302 @@
+//! Authenticated, encrypted channels between parties, set up with the Noise KK handshake.
+//!
+//! Every party has a static key pair, and every party knows everyone's public key (they are
+//! listed in the hosts file). Two parties run the KK handshake over their TCP connection, which
+//! authenticates both ends and agrees on a key for each direction; every frame after that is
+//! encrypted and authenticated.
+//!
+//! The handshake and the ciphers are the standard `Noise_KK_25519_ChaChaPoly_BLAKE2s` suite, from
+//! the `snow` crate. A Noise message holds at most 64 KiB, so a longer message is sent as several,
+//! and the first of them says how long the whole message is, so that one cut short is rejected.
+use snow::params::{DHChoice, NoiseParams};
+use snow::resolvers::{CryptoResolver, DefaultResolver};
+use snow::{Builder, HandshakeState, StatelessTransportState};
+
+use std::convert::TryInto;
+use std::fmt::{self, Debug, Display, Formatter};
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::Arc;
+
+const PROTOCOL_NAME: &str = "Noise_KK_25519_ChaChaPoly_BLAKE2s";
+const PROLOGUE: &[u8] = b"mpc-net";
+const KEY_LEN: usize = 32;
+/// The bytes that encryption adds to each Noise message.
+pub const TAG_LEN: usize = 16;
+/// The longest Noise message.
+const MAX_MSG_LEN: usize = 65535;
+/// The most plaintext that one Noise message holds.
+const CHUNK_LEN: usize = MAX_MSG_LEN - TAG_LEN;
+
+fn params() -> NoiseParams {
+    PROTOCOL_NAME.parse().unwrap()
+}
+
+/// A party's static secret key.
+#[derive(Clone)]
+pub struct SecretKey([u8; KEY_LEN]);
+
+/// A party's static public key: an X25519 public key.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct PublicKey([u8; KEY_LEN]);
+
+impl SecretKey {
+    /// A fresh random key.
+    pub fn generate() -> Self {
+        let keypair = Builder::new(params()).generate_keypair().unwrap();
+        Self(keypair.private[..].try_into().unwrap())
+    }
+
+    pub fn public_key(&self) -> PublicKey {
+        let mut dh = DefaultResolver.resolve_dh(&DHChoice::Curve25519).unwrap();
+        dh.set(&self.0);
+        PublicKey(dh.pubkey().try_into().unwrap())
+    }
+
+    pub fn to_hex(&self) -> String {
+        to_hex(&self.0)
+    }
+
+    pub fn from_hex(s: &str) -> Option<Self> {
+        let bytes = from_hex(s.trim())?;
+        Some(Self(bytes[..].try_into().ok()?))
+    }
+
+    /// Read a key written by [`SecretKey::to_hex`] from a file.
+    pub fn from_file(path: &Path) -> Self {
+        let hex = std::fs::read_to_string(path)
+            .unwrap_or_else(|e| panic!("Could not read secret key {}: {}", path.display(), e));
+        Self::from_hex(&hex)
+            .unwrap_or_else(|| panic!("{} does not hold a secret key", path.display()))
+    }
+}
+
+impl Debug for SecretKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "SecretKey(..)")
+    }
+}
+
+impl Display for PublicKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", to_hex(&self.0))
+    }
+}
+
+impl Debug for PublicKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "PublicKey({})", self)
+    }
+}
+
+impl FromStr for PublicKey {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        from_hex(s)
+            .and_then(|b| b[..].try_into().ok())
+            .map(Self)
+            .ok_or_else(|| format!("bad public key: {}", s))
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(s: &str) -> Option<Vec<u8>> {
+    if s.len() % 2 != 0 {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+fn noise_error(e: snow::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e.to_string())
+}
+
+/// How long a message of `len` bytes is once encrypted.
+pub fn ciphertext_len(len: usize) -> usize {
+    let len = 8 + len;
+    len + TAG_LEN * ((len + CHUNK_LEN - 1) / CHUNK_LEN)
+}
+
+/// One direction of an encrypted channel: the keys agreed in the handshake, and the nonce of
+/// the next Noise message.
+pub struct CipherState {
+    transport: Arc<StatelessTransportState>,
+    nonce: u64,
+}
+
+impl Debug for CipherState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CipherState").field("nonce", &self.nonce).finish()
+    }
+}
+
+impl CipherState {
+    fn next_nonce(&mut self) -> u64 {
+        assert!(self.nonce < u64::MAX, "Out of nonces");
+        self.nonce += 1;
+        self.nonce - 1
+    }
+
+    /// Encrypt `buf` in place.
+    pub fn encrypt(&mut self, buf: &mut Vec<u8>) {
+        let mut plaintext = Vec::with_capacity(8 + buf.len());
+        plaintext.extend_from_slice(&(buf.len() as u64).to_le_bytes());
+        plaintext.extend_from_slice(buf);
+        buf.clear();
+        buf.reserve(ciphertext_len(plaintext.len() - 8));
+        let mut msg = vec![0u8; MAX_MSG_LEN];
+        for chunk in plaintext.chunks(CHUNK_LEN) {
+            let nonce = self.next_nonce();
+            let n = self.transport.write_message(nonce, chunk, &mut msg).unwrap();
+            buf.extend_from_slice(&msg[..n]);
+        }
+    }
+
+    /// Check and decrypt `buf` in place.
+    pub fn decrypt(&mut self, buf: &mut Vec<u8>) -> io::Result<()> {
+        let mut plaintext = Vec::with_capacity(buf.len());
+        let mut chunk = vec![0u8; MAX_MSG_LEN];
+        for msg in buf.chunks(MAX_MSG_LEN) {
+            let nonce = self.next_nonce();
+            let n = self
+                .transport
+                .read_message(nonce, msg, &mut chunk)
+                .map_err(noise_error)?;
+            plaintext.extend_from_slice(&chunk[..n]);
+        }
+        if plaintext.len() < 8
+            || u64::from_le_bytes(plaintext[..8].try_into().unwrap()) != (plaintext.len() - 8) as u64
+        {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "message cut short"));
+        }
+        buf.clear();
+        buf.extend_from_slice(&plaintext[8..]);
+        Ok(())
+    }
+}
+
+/// Send the next handshake message, with its length.
+fn write_handshake<S: Write>(stream: &mut S, state: &mut HandshakeState) -> io::Result<()> {
+    let mut msg = vec![0u8; MAX_MSG_LEN];
+    let n = state.write_message(&[], &mut msg).map_err(noise_error)?;
+    stream.write_all(&(n as u16).to_le_bytes())?;
+    stream.write_all(&msg[..n])?;
+    stream.flush()
+}
+
+fn read_handshake<S: Read>(stream: &mut S, state: &mut HandshakeState) -> io::Result<()> {
+    let mut len = [0u8; 2];
+    stream.read_exact(&mut len)?;
+    let mut msg = vec![0u8; u16::from_le_bytes(len) as usize];
+    stream.read_exact(&mut msg)?;
+    let mut payload = vec![0u8; MAX_MSG_LEN];
+    state.read_message(&msg, &mut payload).map_err(noise_error)?;
+    Ok(())
+}
+
+/// Run the KK handshake over `stream`, with our static key `s` and the peer's public key `rs`.
+///
+/// Returns the cipher states for sending and receiving. Fails if the peer does not hold the
+/// secret key for `rs`, or if a message is tampered with.
+pub fn handshake<S: Read + Write>(
+    stream: &mut S,
+    initiator: bool,
+    s: &SecretKey,
+    rs: &PublicKey,
+) -> io::Result<(CipherState, CipherState)> {
+    let builder = Builder::new(params())
+        .local_private_key(&s.0)
+        .remote_public_key(&rs.0)
+        .prologue(PROLOGUE);
+    let mut state = if initiator {
+        builder.build_initiator()
+    } else {
+        builder.build_responder()
+    }
+    .map_err(noise_error)?;
+    if initiator {
+        write_handshake(stream, &mut state)?;
+        read_handshake(stream, &mut state)?;
+    } else {
+        read_handshake(stream, &mut state)?;
+        write_handshake(stream, &mut state)?;
+    }
+    let transport = Arc::new(state.into_stateless_transport_mode().map_err(noise_error)?);
+    let cipher = || CipherState {
+        transport: transport.clone(),
+        nonce: 0,
+    };
+    Ok((cipher(), cipher()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{TcpListener, TcpStream};
+
+    /// Handshake over TCP: `a` as the initiator, expecting `b_pub`; `b` expecting `a_pub`.
+    fn connect(
+        a: &SecretKey,
+        b_pub: PublicKey,
+        b: SecretKey,
+        a_pub: PublicKey,
+    ) -> (io::Result<(CipherState, CipherState)>, io::Result<(CipherState, CipherState)>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let responder = std::thread::spawn(move || {
+            let mut stream = listener.accept().unwrap().0;
+            handshake(&mut stream, false, &b, &a_pub)
+        });
+        let mut stream = TcpStream::connect(addr).unwrap();
+        let initiator = handshake(&mut stream, true, a, &b_pub);
+        (initiator, responder.join().unwrap())
+    }
+
+    #[test]
+    fn long_messages_round_trip() {
+        let (a, b) = (SecretKey::generate(), SecretKey::generate());
+        let (a_pub, b_pub) = (a.public_key(), b.public_key());
+        let (a_ciphers, b_ciphers) = connect(&a, b_pub, b, a_pub);
+        let (mut a_send, _) = a_ciphers.unwrap();
+        let (_, mut b_recv) = b_ciphers.unwrap();
+        for len in [0, 1, CHUNK_LEN - 8, CHUNK_LEN, 3 * CHUNK_LEN + 5] {
+            let message: Vec<u8> = (0..len).map(|i| i as u8).collect();
+            let mut buf = message.clone();
+            a_send.encrypt(&mut buf);
+            assert_eq!(buf.len(), ciphertext_len(len));
+            b_recv.decrypt(&mut buf).unwrap();
+            assert_eq!(buf, message);
+        }
+        // Dropping the last Noise message of a long one is caught.
+        let mut buf = vec![0u8; 2 * CHUNK_LEN];
+        a_send.encrypt(&mut buf);
+        buf.truncate(2 * MAX_MSG_LEN);
+        assert!(b_recv.decrypt(&mut buf).is_err());
+    }
+
+    #[test]
+    fn handshake_needs_the_expected_key() {
+        let (a, b, c) = (SecretKey::generate(), SecretKey::generate(), SecretKey::generate());
+        let (b_pub, c_pub) = (b.public_key(), c.public_key());
+        // `b` expects `c`, but `a` connects.
+        let (initiator, responder) = connect(&a, b_pub, b, c_pub);
+        assert!(responder.is_err());
+        assert!(initiator.is_err());
+    }
+
+    #[test]
+    fn keys_round_trip_through_hex() {
+        let key = SecretKey::generate();
+        let read = SecretKey::from_hex(&key.to_hex()).unwrap();
+        assert_eq!(read.public_key(), key.public_key());
+        let public = key.public_key();
+        assert_eq!(public.to_string().parse::<PublicKey>(), Ok(public));
+    }
+}
@@ -0,0 +1,135 @@
+//! Emulated network conditions: latency, jitter and bandwidth caps on the links between parties.
+//!
+//! Shaping happens where frames arrive. Each frame is held back until it would have arrived over
+//! a link with the given conditions, so shaping works the same over TCP and in
+//! [`MpcMultiNet::simulate`](crate::MpcMultiNet::simulate). Run the parties on one machine to
+//! measure how a protocol would do on a LAN or a WAN.
+//!
+//! The conditions come from the hosts file, where a party's line may end with options such as
+//! `latency=40ms jitter=5ms bandwidth=100mbit`. These describe that party's own connection, so a
+//! link's delays are the sum of its two ends' and its bandwidth is the lower of the two. A binary
+//! can also shape every link the same way with
+//! [`MpcMultiNet::shape_links`](crate::MpcMultiNet::shape_links).
+use rand::Rng;
+use std::time::{Duration, Instant};
+
+/// The conditions on a link.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LinkShape {
+    /// The one-way delay of every frame.
+    pub latency: Duration,
+    /// Each frame is delayed by a further random time, up to this.
+    pub jitter: Duration,
+    /// In bytes per second. Unlimited if `None`.
+    pub bandwidth: Option<u64>,
+}
+
+impl LinkShape {
+    /// The link between two parties, each with the given conditions on its own connection.
+    pub fn join(&self, other: &Self) -> Self {
+        Self {
+            latency: self.latency + other.latency,
+            jitter: self.jitter + other.jitter,
+            bandwidth: match (self.bandwidth, other.bandwidth) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            },
+        }
+    }
+
+    /// Set one `key=value` option of a hosts file line.
+    pub fn set_option(&mut self, option: &str) -> Result<(), String> {
+        let (key, value) = option
+            .split_once('=')
+            .ok_or_else(|| format!("expected key=value, not {:?}", option))?;
+        match key {
+            "latency" => self.latency = parse_duration(value)?,
+            "jitter" => self.jitter = parse_duration(value)?,
+            "bandwidth" => self.bandwidth = Some(parse_bandwidth(value)?),
+            _ => return Err(format!("unknown link option {:?}", key)),
+        }
+        Ok(())
+    }
+}
+
+/// Parse a duration such as `40ms`, `1.5s` or `250us`.
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let (number, unit) = split_unit(s);
+    let scale = match unit {
+        "s" => 1.0,
+        "ms" => 1e-3,
+        "us" => 1e-6,
+        _ => return Err(format!("{:?} needs a unit: s, ms or us", s)),
+    };
+    let number: f64 = number
+        .parse()
+        .map_err(|e| format!("bad duration {:?}: {}", s, e))?;
+    if !(number >= 0.0 && number.is_finite()) {
+        return Err(format!("bad duration {:?}", s));
+    }
+    Ok(Duration::from_secs_f64(number * scale))
+}
+
+/// Parse a bandwidth such as `100mbit` or `1gbit` (bits per second, as `tc` writes them), or
+/// `10MB` (bytes per second), into bytes per second.
+pub fn parse_bandwidth(s: &str) -> Result<u64, String> {
+    let (number, unit) = split_unit(s);
+    let bytes_per_unit = match unit {
+        "bit" => 1.0 / 8.0,
+        "kbit" => 1e3 / 8.0,
+        "mbit" => 1e6 / 8.0,
+        "gbit" => 1e9 / 8.0,
+        "B" => 1.0,
+        "KB" => 1e3,
+        "MB" => 1e6,
+        "GB" => 1e9,
+        _ => return Err(format!("{:?} needs a unit: kbit, mbit, gbit, KB, MB or GB", s)),
+    };
+    let number: f64 = number
+        .parse()
+        .map_err(|e| format!("bad bandwidth {:?}: {}", s, e))?;
+    let bytes = (number * bytes_per_unit).round();
+    if !(bytes >= 1.0 && bytes.is_finite()) {
+        return Err(format!("bad bandwidth {:?}", s));
+    }
+    Ok(bytes as u64)
+}
+
+fn split_unit(s: &str) -> (&str, &str) {
+    let i = s
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(s.len());
+    s.split_at(i)
+}
+
+/// Works out when the frames on one incoming link arrive.
+#[derive(Default)]
+pub(crate) struct Pacer {
+    pub(crate) shape: LinkShape,
+    /// When the link has finished carrying the frames so far.
+    free_at: Option<Instant>,
+    /// When the last frame arrives. Frames do not overtake each other, jitter or not.
+    last: Option<Instant>,
+}
+
+impl Pacer {
+    /// When a frame of `len` bytes, sent now, arrives.
+    pub(crate) fn arrival(&mut self, len: usize) -> Instant {
+        let now = Instant::now();
+        if self.shape == LinkShape::default() {
+            return now;
+        }
+        let mut sent = self.free_at.map_or(now, |t| t.max(now));
+        if let Some(bandwidth) = self.shape.bandwidth {
+            sent += Duration::from_secs_f64(len as f64 / bandwidth as f64);
+        }
+        self.free_at = Some(sent);
+        let mut arrival = sent + self.shape.latency;
+        if self.shape.jitter > Duration::from_secs(0) {
+            arrival += rand::thread_rng().gen_range(Duration::from_secs(0), self.shape.jitter);
+        }
+        let arrival = self.last.map_or(arrival, |t| t.max(arrival));
+        self.last = Some(arrival);
+        arrival
+    }
+}
@@ -0,0 +1,160 @@
+//! Communication statistics, in total and broken down by labelled phase.
+//!
+//! A phase is whatever code runs while a [`Phase`] guard from [`phase`] is alive on the thread
+//! that talks to the network:
+//! ```
+//! let _phase = mpc_net::phase("first round commit");
+//! // ... network calls here are counted under "first round commit" ...
+//! ```
+//! Phases nest. A phase started inside another is recorded as `outer/inner`, and its
+//! communication counts towards the inner phase only, so the phases never count a message twice.
+//! Communication outside of any phase shows up only in the totals.
+use std::cell::RefCell;
+use std::fmt::Write;
+
+thread_local! {
+    /// The phases open on this thread, innermost last.
+    static PHASES: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+/// While this lives, communication on this thread counts towards its phase. See [`phase`].
+#[must_use = "the phase ends when the guard is dropped"]
+pub struct Phase {
+    depth: usize,
+}
+
+impl Drop for Phase {
+    fn drop(&mut self) {
+        PHASES.with(|p| p.borrow_mut().truncate(self.depth));
+    }
+}
+
+/// Start the phase `name`, which lasts until the guard is dropped.
+pub fn phase(name: impl Into<String>) -> Phase {
+    PHASES.with(|p| {
+        let mut p = p.borrow_mut();
+        let depth = p.len();
+        p.push(name.into());
+        Phase { depth }
+    })
+}
+
+/// The name of this thread's innermost phase, with the phases around it, if any.
+fn current_phase() -> Option<String> {
+    PHASES.with(|p| {
+        let p = p.borrow();
+        if p.is_empty() {
+            None
+        } else {
+            Some(p.join("/"))
+        }
+    })
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Stats {
+    pub bytes_sent: usize,
+    pub bytes_recv: usize,
+    pub broadcasts: usize,
+    pub to_king: usize,
+    pub from_king: usize,
+    pub all_to_all: usize,
+    /// Calls that wait for other parties. Sending to and then receiving from the king is two.
+    pub rounds: usize,
+    /// The size of the largest message sent or received, in bytes.
+    pub max_message: usize,
+    /// Bytes that the king passed on from one party to another, with
+    /// [`Topology::Star`](crate::Topology::Star).
+    pub relayed: usize,
+    /// The same counts for each phase, in the order the phases first started.
+    pub phases: Vec<(String, Stats)>,
+}
+
+impl Stats {
+    /// Count a network call with `f`, in the totals and in the current phase.
+    pub(crate) fn count(&mut self, f: impl Fn(&mut Stats)) {
+        f(self);
+        if let Some(name) = current_phase() {
+            let i = match self.phases.iter().position(|(n, _)| *n == name) {
+                Some(i) => i,
+                None => {
+                    self.phases.push((name, Stats::default()));
+                    self.phases.len() - 1
+                }
+            };
+            f(&mut self.phases[i].1);
+        }
+    }
+
+    /// Note a message of `len` bytes.
+    pub(crate) fn message(&mut self, len: usize) {
+        self.max_message = self.max_message.max(len);
+    }
+
+    fn counts(&self) -> [(&'static str, usize); 9] {
+        [
+            ("bytes_sent", self.bytes_sent),
+            ("bytes_recv", self.bytes_recv),
+            ("broadcasts", self.broadcasts),
+            ("to_king", self.to_king),
+            ("from_king", self.from_king),
+            ("all_to_all", self.all_to_all),
+            ("rounds", self.rounds),
+            ("max_message", self.max_message),
+            ("relayed", self.relayed),
+        ]
+    }
+
+    /// A CSV table with a header, then one row for the totals (phase `total`) and one per phase.
+    pub fn to_csv(&self) -> String {
+        let mut out = String::from("phase");
+        for (key, _) in self.counts().iter() {
+            write!(out, ",{}", key).unwrap();
+        }
+        out.push('\n');
+        let rows =
+            std::iter::once(("total", self)).chain(self.phases.iter().map(|(n, s)| (&n[..], s)));
+        for (name, stats) in rows {
+            write!(out, "\"{}\"", name.replace('"', "\"\"")).unwrap();
+            for (_, count) in stats.counts().iter() {
+                write!(out, ",{}", count).unwrap();
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    /// A JSON object with the totals, and a `phases` array with a `phase` name and the counts
+    /// for each phase.
+    pub fn to_json(&self) -> String {
+        fn counts(out: &mut String, stats: &Stats) {
+            let fields: Vec<String> = stats
+                .counts()
+                .iter()
+                .map(|(key, count)| format!("\"{}\":{}", key, count))
+                .collect();
+            out.push_str(&fields.join(","));
+        }
+        let mut out = String::from("{");
+        counts(&mut out, self);
+        out.push_str(",\"phases\":[");
+        for (i, (name, stats)) in self.phases.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            out.push_str("{\"phase\":\"");
+            for c in name.chars() {
+                match c {
+                    '"' | '\\' => write!(out, "\\{}", c).unwrap(),
+                    c if c.is_control() => write!(out, "\\u{:04x}", c as u32).unwrap(),
+                    c => out.push(c),
+                }
+            }
+            out.push_str("\",");
+            counts(&mut out, stats);
+            out.push('}');
+        }
+        out.push_str("]}");
+        out
+    }
+}
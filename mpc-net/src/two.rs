@@ -4,10 +4,11 @@ use std::fs::File;
 use std::io::{BufRead, BufReader, Read, Write};
 use std::net::{SocketAddr, TcpListener, TcpStream};
 use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
 use ark_std::{end_timer, start_timer};
 
-use super::{MpcNet, Stats};
+use super::{MpcNet, MpcNetError, Stats, Transport};
 
 #[macro_use]
 lazy_static! {
@@ -21,6 +22,9 @@ macro_rules! get_ch {
     };
 }
 
+/// How long to wait for the other party to connect.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+
 pub struct FieldChannel {
     /// Empty if unitialized
     pub stream: Option<TcpStream>,
@@ -64,39 +68,74 @@ impl FieldChannel {
         self.talk_first = id == 0;
     }
 
+    /// The id of the other party.
+    #[inline]
+    fn other(&self) -> usize {
+        if self.talk_first {
+            1
+        } else {
+            0
+        }
+    }
+
+    fn disconnected(&self, e: std::io::Error) -> MpcNetError {
+        MpcNetError::Disconnected {
+            party: self.other(),
+            reason: e.to_string(),
+        }
+    }
+
     #[inline]
-    pub fn connect(&mut self) {
+    pub fn connect(&mut self) -> Result<(), MpcNetError> {
         debug!("I am {}, connecting to {}", self.self_addr, self.other_addr);
+        let deadline = Instant::now() + CONNECT_TIMEOUT;
+        let timeout = MpcNetError::Timeout {
+            party: self.other(),
+        };
         self.stream = Some(if self.talk_first {
             debug!("Attempting to contact peer");
+            let mut ms_waited = 0;
             loop {
-                let mut ms_waited = 0;
                 match TcpStream::connect(self.other_addr) {
                     Ok(s) => break s,
                     Err(e) => {
                         if e.kind() == std::io::ErrorKind::ConnectionRefused {
+                            if Instant::now() > deadline {
+                                return Err(timeout);
+                            }
                             ms_waited += 100;
-                            std::thread::sleep(std::time::Duration::from_millis(100));
+                            std::thread::sleep(Duration::from_millis(100));
                             if ms_waited % 3_000 == 0 {
                                 debug!("Still waiting");
-                            } else if ms_waited > 30_000 {
-                                panic!("Could not find peer in 30s");
                             }
                         } else {
-                            panic!("Error during FieldChannel::new: {}", e);
+                            return Err(self.disconnected(e));
                         }
                     }
                 }
             }
         } else {
-            let listener = TcpListener::bind(self.self_addr).unwrap();
+            let listener = TcpListener::bind(self.self_addr)
+                .unwrap_or_else(|e| panic!("Could not listen on {}: {}", self.self_addr, e));
+            listener.set_nonblocking(true).unwrap();
             debug!("Waiting for peer to contact us");
-            let (stream, _addr) = listener.accept().unwrap();
-            stream
+            loop {
+                match listener.accept() {
+                    Ok((stream, _addr)) => break stream,
+                    Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                        if Instant::now() > deadline {
+                            return Err(timeout);
+                        }
+                        std::thread::sleep(Duration::from_millis(10));
+                    }
+                    Err(e) => panic!("Could not accept connections on {}: {}", self.self_addr, e),
+                }
+            }
         });
         // disable nagle's alg
         self.stream.as_mut().unwrap().set_nodelay(true).unwrap();
         self.stream.as_mut().unwrap().set_nonblocking(true).unwrap();
+        Ok(())
     }
     #[inline]
     pub fn stream(&mut self) -> &mut TcpStream {
@@ -106,31 +145,45 @@ impl FieldChannel {
     }
 
     #[inline]
-    pub fn send_slice(&mut self, v: &[u8]) {
+    pub fn send_slice(&mut self, v: &[u8]) -> Result<(), MpcNetError> {
         let s = self.stream();
-        s.set_nonblocking(false).unwrap();
         let bytes = (v.len() as u64).to_ne_bytes();
-        s.write_all(&bytes[..]).unwrap();
-        s.write_all(v).unwrap();
-        s.set_nonblocking(true).unwrap();
-        self.stats.bytes_sent += bytes.len() + v.len();
+        let r = (|| {
+            s.set_nonblocking(false)?;
+            s.write_all(&bytes[..])?;
+            s.write_all(v)?;
+            s.set_nonblocking(true)
+        })();
+        r.map_err(|e| self.disconnected(e))?;
+        self.stats.count(|stats| {
+            stats.bytes_sent += bytes.len() + v.len();
+            stats.message(v.len());
+        });
+        Ok(())
     }
 
     #[inline]
-    pub fn recv_vec(&mut self) -> Vec<u8> {
+    pub fn recv_vec(&mut self) -> Result<Vec<u8>, MpcNetError> {
         let s = self.stream();
         let mut len = [0u8; 8];
-        s.set_nonblocking(false).unwrap();
-        s.read_exact(&mut len[..]).unwrap();
-        let mut bytes = vec![0u8; u64::from_ne_bytes(len) as usize];
-        s.read_exact(&mut bytes[..]).unwrap();
-        s.set_nonblocking(true).unwrap();
-        self.stats.bytes_recv += bytes.len() + len.len();
-        bytes
+        let r = (|| {
+            s.set_nonblocking(false)?;
+            s.read_exact(&mut len[..])?;
+            let mut bytes = vec![0u8; u64::from_ne_bytes(len) as usize];
+            s.read_exact(&mut bytes[..])?;
+            s.set_nonblocking(true)?;
+            Ok(bytes)
+        })();
+        let bytes = r.map_err(|e| self.disconnected(e))?;
+        self.stats.count(|stats| {
+            stats.bytes_recv += bytes.len() + len.len();
+            stats.message(bytes.len());
+        });
+        Ok(bytes)
     }
 
     #[inline]
-    pub fn exchange_bytes(&mut self, bytes_out: &[u8]) -> std::io::Result<Vec<u8>> {
+    pub fn exchange_bytes(&mut self, bytes_out: &[u8]) -> Result<Vec<u8>, MpcNetError> {
         let timer = start_timer!(|| format!("Exchanging {}", bytes_out.len()));
         let s = self.stream();
         let n = bytes_out.len();
@@ -148,7 +201,7 @@ impl FieldChannel {
                         if e.kind() == std::io::ErrorKind::WouldBlock {
                         } else if e.kind() == std::io::ErrorKind::Interrupted {
                         } else {
-                            return Err(e);
+                            return Err(self.disconnected(e));
                         }
                     }
                 }
@@ -162,15 +215,19 @@ impl FieldChannel {
                         if e.kind() == std::io::ErrorKind::WouldBlock {
                         } else if e.kind() == std::io::ErrorKind::Interrupted {
                         } else {
-                            return Err(e);
+                            return Err(self.disconnected(e));
                         }
                     }
                 }
             }
         }
-        self.stats.broadcasts += 1;
-        self.stats.bytes_sent += n;
-        self.stats.bytes_recv += n;
+        self.stats.count(|stats| {
+            stats.broadcasts += 1;
+            stats.rounds += 1;
+            stats.bytes_sent += n;
+            stats.bytes_recv += n;
+            stats.message(n);
+        });
         end_timer!(timer);
         Ok(bytes_in)
     }
@@ -188,15 +245,16 @@ impl FieldChannel {
 
 #[inline]
 /// Initialize the MPC
-pub fn init_from_path(path: &str, id: usize) {
+pub fn init_from_path(path: &str, id: usize) -> Result<(), MpcNetError> {
     let mut ch = get_ch!();
     assert!(
         ch.stream.is_none(),
         "FieldChannel should no be re-intialized. Did you call init(..) twice?"
     );
     ch.init_from_path(path, id);
-    ch.connect();
+    ch.connect()?;
     debug!("Connected");
+    Ok(())
 }
 
 #[inline]
@@ -205,7 +263,7 @@ pub fn deinit() {
 }
 
 #[inline]
-pub fn exchange_bytes(bytes_out: &[u8]) -> std::io::Result<Vec<u8>> {
+pub fn exchange_bytes(bytes_out: &[u8]) -> Result<Vec<u8>, MpcNetError> {
     CH.lock()
         .expect("Poisoned FieldChannel")
         .exchange_bytes(bytes_out)
@@ -255,8 +313,18 @@ impl MpcNet for MpcTwoNet {
     }
 
     #[inline]
-    fn init_from_file(path: &str, party_id: usize) {
-        get_ch!().init_from_path(path, party_id);
+    fn init_from_file(
+        path: &str,
+        party_id: usize,
+        transport: Transport,
+    ) -> Result<(), MpcNetError> {
+        assert!(
+            matches!(transport, Transport::Tcp),
+            "MpcTwoNet only supports plain TCP"
+        );
+        let mut ch = get_ch!();
+        ch.init_from_path(path, party_id);
+        ch.connect()
     }
 
     #[inline]
@@ -280,45 +348,93 @@ impl MpcNet for MpcTwoNet {
     }
 
     #[inline]
-    fn broadcast_bytes(bytes: &[u8]) -> Vec<Vec<u8>> {
-        let other = get_ch!().exchange_bytes(bytes).unwrap();
-        if Self::am_king() {
+    fn broadcast_bytes(bytes: &[u8]) -> Result<Vec<Vec<u8>>, MpcNetError> {
+        let other = get_ch!().exchange_bytes(bytes)?;
+        Ok(if Self::am_king() {
             vec![bytes.to_vec(), other]
         } else {
             vec![other, bytes.to_vec()]
-        }
+        })
     }
 
     #[inline]
-    fn send_bytes_to_king(bytes: &[u8]) -> Option<Vec<Vec<u8>>> {
+    fn send_bytes_to_king(bytes: &[u8]) -> Result<Option<Vec<Vec<u8>>>, MpcNetError> {
         let mut ch = get_ch!();
-        ch.stats.to_king += 1;
+        ch.stats.count(|stats| {
+            stats.to_king += 1;
+            stats.rounds += 1;
+        });
         if ch.talk_first {
-            let other = ch.recv_vec();
+            let other = ch.recv_vec()?;
             debug_assert_eq!(bytes.len(), other.len());
-            Some(vec![bytes.to_vec(), other])
+            Ok(Some(vec![bytes.to_vec(), other]))
         } else {
-            ch.send_slice(bytes);
-            None
+            ch.send_slice(bytes)?;
+            Ok(None)
         }
     }
 
     #[inline]
-    fn recv_bytes_from_king(bytes: Option<Vec<Vec<u8>>>) -> Vec<u8> {
+    fn send_bytes_to(to: usize, bytes: &[u8]) -> Result<Option<Vec<Vec<u8>>>, MpcNetError> {
+        assert!(to < 2, "There is no party {}", to);
+        if to == 0 {
+            return Self::send_bytes_to_king(bytes);
+        }
         let mut ch = get_ch!();
-        ch.stats.from_king += 1;
+        ch.stats.count(|stats| stats.rounds += 1);
+        if ch.talk_first {
+            ch.send_slice(bytes)?;
+            Ok(None)
+        } else {
+            let other = ch.recv_vec()?;
+            Ok(Some(vec![other, bytes.to_vec()]))
+        }
+    }
+
+    #[inline]
+    fn recv_bytes_from_king(bytes: Option<Vec<Vec<u8>>>) -> Result<Vec<u8>, MpcNetError> {
+        let mut ch = get_ch!();
+        ch.stats.count(|stats| {
+            stats.from_king += 1;
+            stats.rounds += 1;
+        });
         if ch.talk_first {
             let mut bytes = bytes.expect("king needs bytes");
             assert_eq!(bytes.len(), 2);
-            ch.send_slice(&bytes.pop().unwrap());
-            bytes.pop().unwrap()
+            ch.send_slice(&bytes.pop().unwrap())?;
+            Ok(bytes.pop().unwrap())
         } else {
             ch.recv_vec()
         }
     }
 
     #[inline]
-    fn pass_to_next_bytes(bytes: &[u8]) -> Vec<u8> {
-        get_ch!().exchange_bytes(bytes).unwrap()
+    fn pass_to_next_bytes(bytes: &[u8]) -> Result<Vec<u8>, MpcNetError> {
+        get_ch!().exchange_bytes(bytes)
+    }
+
+    #[inline]
+    fn pass_around_bytes(bytes: &[u8], hops: usize) -> Result<Vec<Vec<u8>>, MpcNetError> {
+        assert_eq!(hops, 1, "Cannot pass {} hops around 2 parties", hops);
+        Ok(vec![get_ch!().exchange_bytes(bytes)?])
+    }
+
+    #[inline]
+    fn all_to_all_bytes(mut bytes: Vec<Vec<u8>>) -> Result<Vec<Vec<u8>>, MpcNetError> {
+        assert_eq!(bytes.len(), 2);
+        let mut ch = get_ch!();
+        ch.stats.count(|stats| {
+            stats.all_to_all += 1;
+            stats.rounds += 1;
+        });
+        if ch.talk_first {
+            ch.send_slice(&bytes[1])?;
+            bytes[1] = ch.recv_vec()?;
+        } else {
+            let from_king = ch.recv_vec()?;
+            ch.send_slice(&bytes[0])?;
+            bytes[0] = from_king;
+        }
+        Ok(bytes)
     }
 }
@@ -4,16 +4,109 @@
 use ark_ff::Field;
 use ark_poly::univariate::DensePolynomial;
 use ark_poly_commit::{LabeledCommitment, LabeledPolynomial, PCCommitment};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Read, SerializationError, Write};
 use mpc_trait::{struct_mpc_wire_impl, MpcWire};
 
-/// Check that S(X)*(P(X) + P(wX)) + (1-S(X))*P(X)*P(WX) - P(WWX) = Q(X)*Z(X)
-/// where Z vanishes on the gate domain, and Q is existential
+/// Serialization field by field, for commitments and openings that serialize. The derives would
+/// need the bounds on the struct itself; a parameter's bound here is the one the struct declares.
+macro_rules! struct_serialize_impl {
+    ($s:ident<$($g:ident $(: $b:path)?),*>; $($x:ident),*) => {
+        impl<$($g: CanonicalSerialize $(+ $b)?),*> CanonicalSerialize for $s<$($g),*> {
+            fn serialize<W: Write>(&self, mut writer: W) -> Result<(), SerializationError> {
+                $(self.$x.serialize(&mut writer)?;)*
+                Ok(())
+            }
+            fn serialized_size(&self) -> usize {
+                0 $(+ self.$x.serialized_size())*
+            }
+        }
+        impl<$($g: CanonicalDeserialize $(+ $b)?),*> CanonicalDeserialize for $s<$($g),*> {
+            fn deserialize<R: Read>(mut reader: R) -> Result<Self, SerializationError> {
+                Ok(Self {
+                    $($x: CanonicalDeserialize::deserialize(&mut reader)?,)*
+                })
+            }
+        }
+    };
+}
+
+/// One of each gate selector: a gate's output is
+/// q_L*a + q_R*b + q_M*a*b + q_O*c + q_C, which must be zero.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct Selectors<T> {
+    /// q_L, for the left input
+    pub l: T,
+    /// q_R, for the right input
+    pub r: T,
+    /// q_M, for the product of the inputs
+    pub m: T,
+    /// q_O, for the output
+    pub o: T,
+    /// q_C, the constant
+    pub c: T,
+}
+
+impl<T> Selectors<T> {
+    pub fn map<U>(self, mut f: impl FnMut(T) -> U) -> Selectors<U> {
+        Selectors {
+            l: f(self.l),
+            r: f(self.r),
+            m: f(self.m),
+            o: f(self.o),
+            c: f(self.c),
+        }
+    }
+    pub fn as_ref(&self) -> Selectors<&T> {
+        Selectors {
+            l: &self.l,
+            r: &self.r,
+            m: &self.m,
+            o: &self.o,
+            c: &self.c,
+        }
+    }
+}
+
+impl<F: Field> Selectors<F> {
+    /// The gate's output on inputs `a` and `b`, and output `c`.
+    pub fn apply(&self, a: F, b: F, c: F) -> F {
+        self.l * a + self.r * b + self.m * a * b + self.o * c + self.c
+    }
+}
+
+/// A lookup table and its selector, over the gates.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub struct Table<T> {
+    /// T, the table, padded with its last entry
+    pub t: T,
+    /// q_K, one at the gates whose left input must be in the table
+    pub k: T,
+}
+
+impl<T> Table<T> {
+    pub fn map<U>(self, mut f: impl FnMut(T) -> U) -> Table<U> {
+        Table {
+            t: f(self.t),
+            k: f(self.k),
+        }
+    }
+    pub fn as_ref(&self) -> Table<&T> {
+        Table {
+            t: &self.t,
+            k: &self.k,
+        }
+    }
+}
+
+/// Check that S_L(X)*P(X) + S_R(X)*P(wX) + S_M(X)*P(X)*P(wX) + S_O(X)*P(wwX) + S_C(X) + PI(X)
+/// = Q(X)*Z(X), where Z vanishes on the gate domain, PI holds the public inputs, and Q is
+/// existential
 #[derive(Clone, Debug)]
 pub struct GateProof<C, O> {
     /// Q commitment
     pub q_cmt: C,
-    /// S(x) proof
-    pub s_open: O,
+    /// S_*(x) proofs
+    pub s_open: Selectors<O>,
     /// Q(x) proof
     pub q_open: O,
     /// P(x) proof
@@ -24,19 +117,6 @@ pub struct GateProof<C, O> {
     pub p_w2_open: O,
 }
 
-/// Check that P(X) agree with v(X) for the public wires
-/// via P(X) - v(X) = Q(X)*Z(X)
-/// where Z vanishes on the public wires
-#[derive(Clone, Debug)]
-pub struct PublicProof<C, O> {
-    /// Q commitment
-    pub q_cmt: C,
-    /// Q(x) proof
-    pub q_open: O,
-    /// P(x) proof
-    pub p_open: O,
-}
-
 /// Proof that some polynomial f has a product pi over a domain
 #[derive(Clone, Debug)]
 pub struct ProductProof<C, O> {
@@ -78,6 +158,49 @@ pub struct WiringProof<C, O> {
     pub l2_q_x_open: O,
 }
 
+/// Check that the left input of each gate with q_K is in the table (plookup, over the gates).
+///
+/// F is the lookup column: the left input where q_K is one, and T's first entry elsewhere. H_1
+/// and H_2 hold F and T sorted together in the order of T, alternately. With
+/// G(a, b) = gamma*(1 + beta) + a + beta*b, the ratio
+/// L(X) = G(F(X), F(X))*G(T(X), T(wX)) / (G(H_1(X), H_2(X))*G(H_2(X), H_1(wX)))
+/// multiplies to one over the gates, and
+/// L(X)*G(H_1(X), H_2(X))*G(H_2(X), H_1(wX)) - G(F(X), F(X))*G(T(X), T(wX))
+/// + alpha*q_K(X)*(P(X) - F(X)) = Q(X)*Z(X)
+#[derive(Clone, Debug)]
+pub struct LookupProof<C, O> {
+    /// F commitment
+    pub f_cmt: C,
+    /// H_1 commitment
+    pub h1_cmt: C,
+    /// H_2 commitment
+    pub h2_cmt: C,
+    /// L commitment
+    pub l_cmt: C,
+    /// proof that L multiplies to 1 over the gates
+    pub l_prod_pf: ProductProof<C, O>,
+    /// Q commitment
+    pub q_cmt: C,
+    /// T(x) and q_K(x) proofs
+    pub table_open: Table<O>,
+    /// T(w*x) proof
+    pub t_w_open: O,
+    /// P(x) proof
+    pub p_open: O,
+    /// F(x) proof
+    pub f_open: O,
+    /// H_1(x) proof
+    pub h1_open: O,
+    /// H_1(w*x) proof
+    pub h1_w_open: O,
+    /// H_2(x) proof
+    pub h2_open: O,
+    /// L(x) proof
+    pub l_open: O,
+    /// Q(x) proof
+    pub q_open: O,
+}
+
 /// Plonk proof
 #[derive(Clone)]
 pub struct Proof<F, C, O> {
@@ -85,36 +208,55 @@ pub struct Proof<F, C, O> {
     pub p_cmt: C,
     /// Proof of wiring
     pub wiring: WiringProof<C, (F, O)>,
-    /// Proof of gates
+    /// Proof of gates, and so of the public inputs
     pub gates: GateProof<C, (F, O)>,
-    /// Proof of gates
-    pub public: PublicProof<C, (F, O)>,
+    /// Proof of lookups, if the circuit has a table
+    pub lookup: Option<LookupProof<C, (F, O)>>,
 }
 
+struct_serialize_impl!(Selectors<T>; l, r, m, o, c);
+struct_serialize_impl!(GateProof<C, O>; q_cmt, s_open, q_open, p_open, p_w_open, p_w2_open);
+struct_serialize_impl!(ProductProof<C, O>; t_cmt, q_cmt, t_wk_open, t_r_open, t_wr_open, f_wr_open, q_r_open);
+struct_serialize_impl!(WiringProof<C, O>; l1_cmt, l1_prod_pf, l2_q_cmt, p_x_open, w_x_open, l1_x_open, l2_q_x_open);
+struct_serialize_impl!(Table<T>; t, k);
+struct_serialize_impl!(LookupProof<C, O>; f_cmt, h1_cmt, h2_cmt, l_cmt, l_prod_pf, q_cmt, table_open, t_w_open, p_open, f_open, h1_open, h1_w_open, h2_open, l_open, q_open);
+struct_serialize_impl!(Proof<F, C, O>; p_cmt, wiring, gates, lookup);
+
 #[derive(Clone)]
 pub struct ProverKey<F: Field, C: PCCommitment, PcCk> {
     pub w: LabeledPolynomial<F, DensePolynomial<F>>,
     pub w_cmt: LabeledCommitment<C>,
-    pub s: LabeledPolynomial<F, DensePolynomial<F>>,
-    pub s_cmt: LabeledCommitment<C>,
+    pub s: Selectors<LabeledPolynomial<F, DensePolynomial<F>>>,
+    pub s_cmt: Selectors<LabeledCommitment<C>>,
+    pub table: Option<Table<LabeledPolynomial<F, DensePolynomial<F>>>>,
+    pub table_cmt: Option<Table<LabeledCommitment<C>>>,
     pub pc_ck: PcCk,
 }
 
 #[derive(Clone)]
 pub struct VerifierKey<C: PCCommitment, PcVk> {
     pub w_cmt: LabeledCommitment<C>,
-    pub s_cmt: LabeledCommitment<C>,
+    pub s_cmt: Selectors<LabeledCommitment<C>>,
+    pub table_cmt: Option<Table<LabeledCommitment<C>>>,
     pub pc_vk: PcVk,
 }
 
-impl<C: MpcWire, O: MpcWire> MpcWire for GateProof<C, O> {
-    struct_mpc_wire_impl!(GateProof<C, O>;
-        (C, q_cmt), (O, s_open), (O, q_open), (O, p_open), (O, p_w_open), (O, p_w2_open));
+struct_serialize_impl!(ProverKey<F: Field, C: PCCommitment, PcCk>; w, w_cmt, s, s_cmt, table, table_cmt, pc_ck);
+struct_serialize_impl!(VerifierKey<C: PCCommitment, PcVk>; w_cmt, s_cmt, table_cmt, pc_vk);
+
+impl<T: MpcWire> MpcWire for Selectors<T> {
+    struct_mpc_wire_impl!(Selectors<T>;
+        (T, l), (T, r), (T, m), (T, o), (T, c));
+}
+
+impl<T: MpcWire> MpcWire for Table<T> {
+    struct_mpc_wire_impl!(Table<T>;
+        (T, t), (T, k));
 }
 
-impl<C: MpcWire, O: MpcWire> MpcWire for PublicProof<C, O> {
-    struct_mpc_wire_impl!(PublicProof<C, O>;
-        (C, q_cmt), (O, q_open), (O, p_open));
+impl<C: MpcWire, O: MpcWire> MpcWire for GateProof<C, O> {
+    struct_mpc_wire_impl!(GateProof<C, O>;
+        (C, q_cmt), (Selectors<O>, s_open), (O, q_open), (O, p_open), (O, p_w_open), (O, p_w2_open));
 }
 impl<C: MpcWire, O: MpcWire> MpcWire for ProductProof<C, O> {
     struct_mpc_wire_impl!(ProductProof<C, O>;
@@ -126,12 +268,17 @@ impl<C: MpcWire, O: MpcWire> MpcWire for WiringProof<C, O> {
         (C, l1_cmt), (ProductProof<C, O>, l1_prod_pf), (C, l2_q_cmt), (O, p_x_open), (O, w_x_open), (O, l1_x_open), (O, l2_q_x_open));
 }
 
+impl<C: MpcWire, O: MpcWire> MpcWire for LookupProof<C, O> {
+    struct_mpc_wire_impl!(LookupProof<C, O>;
+        (C, f_cmt), (C, h1_cmt), (C, h2_cmt), (C, l_cmt), (ProductProof<C, O>, l_prod_pf), (C, q_cmt), (Table<O>, table_open), (O, t_w_open), (O, p_open), (O, f_open), (O, h1_open), (O, h1_w_open), (O, h2_open), (O, l_open), (O, q_open));
+}
+
 impl<F: MpcWire, C: MpcWire, O: MpcWire> MpcWire for Proof<F, C, O> {
     struct_mpc_wire_impl!(Proof<F, PC>;
         (C, p_cmt),
         (WiringProof<C, (F, O)>, wiring),
         (GateProof<C, (F, O)>, gates),
-        (PublicProof<C, (F, O)>, public)
+        (Option<LookupProof<C, (F, O)>>, lookup)
     );
 }
 
@@ -11,11 +11,11 @@ pub mod data_structures;
 pub use data_structures::*;
 pub mod relations;
 pub use relations::*;
+mod lookup;
+pub use lookup::MAX_SHARED_LEN as MAX_SHARED_LOOKUP_LEN;
 pub mod reveal;
 mod util;
 
-// use log::debug;
-
 use blake2::Blake2s;
 
 use ark_ff::{FftField, Field};
@@ -23,9 +23,9 @@ use ark_ff::{FftField, Field};
 use ark_poly_commit::{LabeledCommitment, LabeledPolynomial, PCRandomness, PolynomialCommitment};
 
 use ark_poly::{
-    domain::EvaluationDomain,
+    domain::{EvaluationDomain, Radix2EvaluationDomain},
     univariate::{DenseOrSparsePolynomial, DensePolynomial},
-    Polynomial, UVPolynomial,
+    Evaluations, Polynomial, UVPolynomial,
 };
 
 use ark_std::{end_timer, rand::RngCore, start_timer};
@@ -53,28 +53,57 @@ pub fn setup<'r, F: FftField, PC: PolynomialCommitment<F, DensePolynomial<F>>>(
         Some(&[circ.domains.wires.size() - 1]),
     )
     .unwrap();
-    let w = LabeledPolynomial::new("w".into(), circ.w.clone(), None, None);
-    let (mut cs, rs) = PC::commit(&ck, once(&w), None).unwrap();
-    assert_eq!(cs.len(), 1);
-    assert_eq!(rs.len(), 1);
-    let w_cmt = cs.pop().unwrap();
-    let s = LabeledPolynomial::new("s".into(), circ.s.clone(), None, None);
-    let (mut cs, rs) = PC::commit(&ck, once(&s), None).unwrap();
-    assert_eq!(cs.len(), 1);
-    assert_eq!(rs.len(), 1);
-    let s_cmt = cs.pop().unwrap();
+    let commit = |label: &str, p: &DensePolynomial<F>| {
+        let p = LabeledPolynomial::new(label.into(), p.clone(), None, None);
+        let (mut cs, rs) = PC::commit(&ck, once(&p), None).unwrap();
+        assert_eq!(cs.len(), 1);
+        assert_eq!(rs.len(), 1);
+        (p, cs.pop().unwrap())
+    };
+    let (w, w_cmt) = commit("w", &circ.w);
+    let (s_l, s_l_cmt) = commit("s_l", &circ.s.l);
+    let (s_r, s_r_cmt) = commit("s_r", &circ.s.r);
+    let (s_m, s_m_cmt) = commit("s_m", &circ.s.m);
+    let (s_o, s_o_cmt) = commit("s_o", &circ.s.o);
+    let (s_c, s_c_cmt) = commit("s_c", &circ.s.c);
+    let s = Selectors {
+        l: s_l,
+        r: s_r,
+        m: s_m,
+        o: s_o,
+        c: s_c,
+    };
+    let s_cmt = Selectors {
+        l: s_l_cmt,
+        r: s_r_cmt,
+        m: s_m_cmt,
+        o: s_o_cmt,
+        c: s_c_cmt,
+    };
+    let (table, table_cmt) = circ
+        .lookup
+        .as_ref()
+        .map(|lookup| {
+            let (t, t_cmt) = commit("t", &lookup.t);
+            let (k, k_cmt) = commit("q_k", &lookup.k);
+            (Table { t, k }, Table { t: t_cmt, k: k_cmt })
+        })
+        .unzip();
     (
         ProverKey {
             pc_ck: ck,
             s_cmt: s_cmt.clone(),
             w_cmt: w_cmt.clone(),
+            table_cmt: table_cmt.clone(),
             s,
             w,
+            table,
         },
         VerifierKey {
             pc_vk: vk,
             s_cmt,
             w_cmt,
+            table_cmt,
         },
     )
 }
@@ -115,6 +144,7 @@ where
         domain: D,
     ) -> ProductProof<PC::Commitment, (F, PC::Proof)> {
         let timer = start_timer!(|| "prove_unit_product");
+        let _phase = mpc_net::phase("prove_unit_product");
         let t_evals = {
             let mut t = f.evaluate_over_domain_by_ref(domain);
             F::partial_products_in_place(&mut t.evals);
@@ -204,6 +234,7 @@ where
         dom: D,
     ) -> WiringProof<PC::Commitment, (F, PC::Proof)> {
         let timer = start_timer!(|| "prove_wiring");
+        let _phase = mpc_net::phase("prove_wiring");
         let y = self.fs_rng.borrow_mut().gen::<F>();
         let z = self.fs_rng.borrow_mut().gen::<F>();
         let p_evals = p.evaluate_over_domain_by_ref(dom);
@@ -261,83 +292,175 @@ where
         }
     }
 
-    fn prove_public(
+    fn prove_gates(
         &self,
         p: &LabeledPolynomial<F, DensePolynomial<F>>,
         p_cmt: &LabeledCommitment<PC::Commitment>,
         p_rand: &PC::Randomness,
         circ: &relations::flat::CircuitLayout<F>,
-    ) -> PublicProof<PC::Commitment, (F, PC::Proof)> {
-        let timer = start_timer!(|| "prove_public");
-        let points: Vec<(F, F)> = circ
+    ) -> GateProof<PC::Commitment, (F, PC::Proof)> {
+        let timer = start_timer!(|| "prove_gates");
+        let _phase = mpc_net::phase("prove_gates");
+        let w = circ.domains.wires.group_gen;
+        let pw = util::shift(p.polynomial().clone(), w);
+        let pww = util::shift(p.polynomial().clone(), w * w);
+        // The public inputs are on the left wires of their gates.
+        let p_evals = circ.evaluate_over_gates(p.polynomial());
+        let inputs: HashMap<String, F> = circ
             .public_indices
             .iter()
-            .map(|(_, i)| {
-                let x = circ.domains.wires.element(*i);
-                let y = p.evaluate(&x);
-                (x, y)
-            })
+            .map(|(var, i)| (var.clone(), p_evals[*i]))
             .collect();
-        let v = util::interpolate(&points);
-        let z = circ.vanishing_poly_on_inputs();
-        let (q, _r) = DenseOrSparsePolynomial::DPolynomial(Cow::Owned(p.polynomial() - &v))
-            .divide_with_q_and_r(&DenseOrSparsePolynomial::DPolynomial(Cow::Borrowed(&z)))
+        let pi = circ.public_input_poly(&inputs);
+        let d = &(&(&circ.s.l * p.polynomial()) + &(&circ.s.r * &pw))
+            + &(&(&(&circ.s.m * &(p.polynomial() * &pw)) + &(&circ.s.o * &pww))
+                + &(&circ.s.c + &pi));
+        let (q, _r) = DenseOrSparsePolynomial::DPolynomial(Cow::Owned(d))
+            .divide_with_q_and_r(&DenseOrSparsePolynomial::SPolynomial(Cow::Owned(
+                circ.domains.gates.vanishing_polynomial(),
+            )))
             .unwrap();
-        let (q_cmt, q, q_rand) = self.commit("pub_q", q, None, None).unwrap();
+        // debug_assert!(r.is_zero());
+        let (q_cmt, q, q_rand) = self.commit("gates_q", q, None, None).unwrap();
         let x = self.fs_rng.borrow_mut().gen::<F>();
+        let s_open = Selectors {
+            l: self.eval(&self.pk.s.l, &PC::Randomness::empty(), &self.pk.s_cmt.l, x),
+            r: self.eval(&self.pk.s.r, &PC::Randomness::empty(), &self.pk.s_cmt.r, x),
+            m: self.eval(&self.pk.s.m, &PC::Randomness::empty(), &self.pk.s_cmt.m, x),
+            o: self.eval(&self.pk.s.o, &PC::Randomness::empty(), &self.pk.s_cmt.o, x),
+            c: self.eval(&self.pk.s.c, &PC::Randomness::empty(), &self.pk.s_cmt.c, x),
+        }
+        .map(Result::unwrap);
+        let p_open = self.eval(p, p_rand, p_cmt, x).unwrap();
         let q_open = self.eval(&q, &q_rand, &q_cmt, x).unwrap();
-        let p_open = self.eval(&p, &p_rand, &p_cmt, x).unwrap();
-        //debug_assert!( p_open.0 - v.evaluate(&x), q_open.0 * z.evaluate(&x));
+        let p_w_open = self.eval(p, p_rand, p_cmt, w * x).unwrap();
+        let p_w2_open = self.eval(p, p_rand, p_cmt, w * w * x).unwrap();
         end_timer!(timer);
-        PublicProof {
-            q_open,
-            q_cmt: q_cmt.commitment,
+        GateProof {
+            q_cmt: q_cmt.commitment,//good
+            s_open,
             p_open,
+            q_open,
+            p_w_open,
+            p_w2_open,
         }
     }
 
-    fn prove_gates(
+    /// Prove that the left input of each gate with q_K is in the table.
+    fn prove_lookup(
         &self,
         p: &LabeledPolynomial<F, DensePolynomial<F>>,
         p_cmt: &LabeledCommitment<PC::Commitment>,
         p_rand: &PC::Randomness,
+        table: &Table<LabeledPolynomial<F, DensePolynomial<F>>>,
         circ: &relations::flat::CircuitLayout<F>,
-    ) -> GateProof<PC::Commitment, (F, PC::Proof)> {
-        let timer = start_timer!(|| "prove_gates");
-        let w = circ.domains.wires.group_gen;
-        let pw = util::shift(p.polynomial().clone(), w);
-        let pww = util::shift(p.polynomial().clone(), w * w);
-        let d = &(&circ.s * &(p.polynomial() + &pw)
-            + &(&(&circ.s * &-F::one()) + &F::one()) * &(p.polynomial() * &pw))
-            - &pww;
+    ) -> LookupProof<PC::Commitment, (F, PC::Proof)> {
+        let timer = start_timer!(|| "prove_lookup");
+        let _phase = mpc_net::phase("prove_lookup");
+        let dom = circ.domains.gates;
+        let w = dom.group_gen;
+        let over_gates = |evals: Vec<F>| Evaluations::from_vec_and_domain(evals, dom);
+        // The evaluations of a(wX), from those of a(X)
+        let shift_evals = |a: &Evaluations<F, Radix2EvaluationDomain<F>>| {
+            over_gates(
+                a.evals
+                    .iter()
+                    .cycle()
+                    .skip(1)
+                    .take(a.evals.len())
+                    .cloned()
+                    .collect(),
+            )
+        };
+        let t_evals = table.t.evaluate_over_domain_by_ref(dom);
+        let k_evals = table.k.evaluate_over_domain_by_ref(dom);
+        let p_evals = circ.evaluate_over_gates(p.polynomial());
+        let f_evals = over_gates(
+            p_evals
+                .evals
+                .iter()
+                .zip(&k_evals.evals)
+                .map(|(p, k)| *k * p + (F::one() - k) * t_evals[0])
+                .collect(),
+        );
+        let (f_cmt, f, f_rand) = self
+            .commit("f", f_evals.clone().interpolate(), None, None)
+            .unwrap();
+        let sort_timer = start_timer!(|| "sort");
+        let s = lookup::sorted_union(&f_evals.evals, &t_evals.evals);
+        end_timer!(sort_timer);
+        let h1_evals = over_gates(s.iter().step_by(2).cloned().collect());
+        let h2_evals = over_gates(s.iter().skip(1).step_by(2).cloned().collect());
+        let (h1_cmt, h1, h1_rand) = self
+            .commit("h1", h1_evals.clone().interpolate(), None, None)
+            .unwrap();
+        let (h2_cmt, h2, h2_rand) = self
+            .commit("h2", h2_evals.clone().interpolate(), None, None)
+            .unwrap();
+
+        let beta = self.fs_rng.borrow_mut().gen::<F>();
+        let gamma = self.fs_rng.borrow_mut().gen::<F>();
+        let gamma_beta = gamma * (F::one() + beta);
+        let g_evals = |a: &Evaluations<F, Radix2EvaluationDomain<F>>,
+                       b: &Evaluations<F, Radix2EvaluationDomain<F>>| {
+            &(a + &(b * &beta)) + &gamma_beta
+        };
+        let num_evals = &g_evals(&f_evals, &f_evals) * &g_evals(&t_evals, &shift_evals(&t_evals));
+        let den_evals =
+            &g_evals(&h1_evals, &h2_evals) * &g_evals(&h2_evals, &shift_evals(&h1_evals));
+        let (l_cmt, l, l_rand) = self
+            .commit("l", (&num_evals / &den_evals).interpolate(), None, None)
+            .unwrap();
+        let l_prod_pf = self.prove_unit_product(&l, &l_cmt, &l_rand, dom);
+
+        let alpha = self.fs_rng.borrow_mut().gen::<F>();
+        let g = |a: &DensePolynomial<F>, b: &DensePolynomial<F>| &(a + &(b * &beta)) + &gamma_beta;
+        let t = table.t.polynomial();
+        let num = &g(&f, &f) * &g(t, &util::shift(t.clone(), w));
+        let den = &g(&h1, &h2) * &g(&h2, &util::shift(h1.polynomial().clone(), w));
+        let d = &(&(l.polynomial() * &den) - &num)
+            + &(&(table.k.polynomial() * &(p.polynomial() - f.polynomial())) * &alpha);
         let (q, _r) = DenseOrSparsePolynomial::DPolynomial(Cow::Owned(d))
             .divide_with_q_and_r(&DenseOrSparsePolynomial::SPolynomial(Cow::Owned(
-                circ.domains.gates.vanishing_polynomial(),
+                dom.vanishing_polynomial(),
             )))
             .unwrap();
-        // debug_assert!(r.is_zero());
-        let (q_cmt, q, q_rand) = self.commit("gates_q", q, None, None).unwrap();
+        let (q_cmt, q, q_rand) = self.commit("lookup_q", q, None, None).unwrap();
+
         let x = self.fs_rng.borrow_mut().gen::<F>();
-        let s_open = self
-            .eval(&self.pk.s, &PC::Randomness::empty(), &self.pk.s_cmt, x)
+        let table_cmt = self.pk.table_cmt.as_ref().unwrap();
+        let table_open = Table {
+            t: self.eval(&table.t, &PC::Randomness::empty(), &table_cmt.t, x),
+            k: self.eval(&table.k, &PC::Randomness::empty(), &table_cmt.k, x),
+        }
+        .map(Result::unwrap);
+        let t_w_open = self
+            .eval(&table.t, &PC::Randomness::empty(), &table_cmt.t, w * x)
             .unwrap();
         let p_open = self.eval(p, p_rand, p_cmt, x).unwrap();
+        let f_open = self.eval(&f, &f_rand, &f_cmt, x).unwrap();
+        let h1_open = self.eval(&h1, &h1_rand, &h1_cmt, x).unwrap();
+        let h1_w_open = self.eval(&h1, &h1_rand, &h1_cmt, w * x).unwrap();
+        let h2_open = self.eval(&h2, &h2_rand, &h2_cmt, x).unwrap();
+        let l_open = self.eval(&l, &l_rand, &l_cmt, x).unwrap();
         let q_open = self.eval(&q, &q_rand, &q_cmt, x).unwrap();
-        let p_w_open = self.eval(p, p_rand, p_cmt, w * x).unwrap();
-        let p_w2_open = self.eval(p, p_rand, p_cmt, w * w * x).unwrap();
-        //        debug_assert_eq!(
-        //            s_open.0 * (p_open.0 + p_w_open.0) + (F::one() - s_open.0) * p_open.0 * p_w_open.0
-        //                - p_w2_open.0,
-        //            q_open.0 * circ.domains.gates.evaluate_vanishing_polynomial(x)
-        //        );
         end_timer!(timer);
-        GateProof {
-            q_cmt: q_cmt.commitment,//good
-            s_open,
+        LookupProof {
+            f_cmt: f_cmt.commitment,
+            h1_cmt: h1_cmt.commitment,
+            h2_cmt: h2_cmt.commitment,
+            l_cmt: l_cmt.commitment,
+            l_prod_pf,
+            q_cmt: q_cmt.commitment,
+            table_open,
+            t_w_open,
             p_open,
+            f_open,
+            h1_open,
+            h1_w_open,
+            h2_open,
+            l_open,
             q_open,
-            p_w_open,
-            p_w2_open,
         }
     }
 
@@ -352,6 +475,7 @@ where
         x: F,
     ) -> Result<(F, PC::Proof), Error<PC::Error>> {
         let timer = start_timer!(|| format!("open: {}", p.label()));
+        let _phase = mpc_net::phase("open");
         let pf_p = PC::open(
             &self.pk.pc_ck,
             once(p),
@@ -388,6 +512,7 @@ where
     > {
         // debug!("commit: {}", label);
         let timer = start_timer!(|| format!("commit: {}", label));
+        let _phase = mpc_net::phase("commit");
         let label_p = LabeledPolynomial::new(format!("{}", label), p, degree, hiding_bound);
         let (mut cs, mut rs) = PC::commit(
             &self.pk.pc_ck,
@@ -420,15 +545,19 @@ where
                 None,
             )
             .unwrap();
-        
-        let public = self.prove_public(&p, &p_cmt, &p_rand, circ);
+
         let gates = self.prove_gates(&p, &p_cmt, &p_rand, circ);
         let wiring = self.prove_wiring(&p, &p_cmt, &p_rand, circ.domains.wires);
+        let lookup = self
+            .pk
+            .table
+            .as_ref()
+            .map(|table| self.prove_lookup(&p, &p_cmt, &p_rand, table, circ));
         Proof {
             p_cmt: p_cmt.commitment,
             wiring,
             gates,
-            public,
+            lookup,
         }
     }
 }
@@ -458,24 +587,26 @@ where
         f_cmt: &LabeledCommitment<PC::Commitment>,
         pf: ProductProof<PC::Commitment, (F, PC::Proof)>,
         domain: D,
-    ) {
+    ) -> Result<(), Error<PC::Error>> {
         let k = domain.size();
         let w = domain.element(1);
-        let t_cmt = self.recv_commit("t", pf.t_cmt, None);
-        let q_cmt = self.recv_commit("q", pf.q_cmt, None);
+        let t_cmt = self.recv_commit("t", pf.t_cmt, None)?;
+        let q_cmt = self.recv_commit("q", pf.q_cmt, None)?;
         let r = self.fs_rng.borrow_mut().gen::<F>();
         // Check commitments
-        let f_wr = self.check(f_cmt, w * r, &pf.f_wr_open);
-        let q_r = self.check(&q_cmt, r, &pf.q_r_open);
-        let t_r = self.check(&t_cmt, r, &pf.t_r_open);
-        let t_wr = self.check(&t_cmt, w * r, &pf.t_wr_open);
-        let t_wk = self.check(&t_cmt, domain.element(k - 1), &pf.t_wk_open);
+        let f_wr = self.check(f_cmt, w * r, &pf.f_wr_open)?;
+        let q_r = self.check(&q_cmt, r, &pf.q_r_open)?;
+        let t_r = self.check(&t_cmt, r, &pf.t_r_open)?;
+        let t_wr = self.check(&t_cmt, w * r, &pf.t_wr_open)?;
+        let t_wk = self.check(&t_cmt, domain.element(k - 1), &pf.t_wk_open)?;
         // Check partial product
         let l = t_wr - t_r * f_wr;
         let r = domain.evaluate_vanishing_polynomial(r) * q_r;
-        assert_eq!(l, r, "Partial product failure: \n{}\nnot equal to\n{}", l, r);
         // Check total product is 1
-        assert_eq!(t_wk, F::one());
+        if l != r || t_wk != F::one() {
+            return Err(Error::Rejected(VerificationError::ProductCheckFailed));
+        }
+        Ok(())
     }
     /// Receive a commitment
     ///
@@ -485,63 +616,89 @@ where
         label: impl ark_std::fmt::Display,
         c: PC::Commitment,
         degree: Option<usize>,
-    ) -> LabeledCommitment<PC::Commitment> {
+    ) -> Result<LabeledCommitment<PC::Commitment>, Error<PC::Error>> {
         let label_c = LabeledCommitment::new(format!("{}", label), c, degree);
-        self.fs_rng
-            .borrow_mut()
-            .absorb(&ark_ff::to_bytes![label_c].expect("failed serialization"));
-        label_c
-    }
-
-    #[track_caller]
-    fn check(&self, cmt: &LabeledCommitment<PC::Commitment>, x: F, open: &(F, PC::Proof)) -> F {
-        assert!(
-            PC::check(
-                &self.vk.pc_vk,
-                once(cmt),
-                &x,
-                once(open.0),
-                &open.1,
-                F::one(), // Okay b/c a single commit
-                None,
-            )
-            .unwrap(),
-            "Verification failed: {} at {}",
-            cmt.label(),
-            x
+        let bytes = ark_ff::to_bytes![label_c]
+            .map_err(|e| Error::Serialization(label_c.label().clone(), e))?;
+        self.fs_rng.borrow_mut().absorb(&bytes);
+        Ok(label_c)
+    }
+
+    /// The value that `open` claims for `cmt` at `x`, if its proof checks out.
+    fn check(
+        &self,
+        cmt: &LabeledCommitment<PC::Commitment>,
+        x: F,
+        open: &(F, PC::Proof),
+    ) -> Result<F, Error<PC::Error>> {
+        let ok = PC::check(
+            &self.vk.pc_vk,
+            once(cmt),
+            &x,
+            once(open.0),
+            &open.1,
+            F::one(), // Okay b/c a single commit
+            None,
         );
-        open.0
+        match ok {
+            Ok(true) => Ok(open.0),
+            _ => Err(Error::Rejected(VerificationError::BadOpening(format!(
+                "{} at {}",
+                cmt.label(),
+                x
+            )))),
+        }
     }
 
+    /// Check that `pf` proves that `circ` is satisfied with the inputs `public`.
+    ///
+    /// A proof that does not verify gives [`Error::Rejected`], with the check it failed. Also fails
+    /// if the verifier's circuit has a witness, or if `public` does not name exactly the circuit's
+    /// public inputs.
     pub fn verify(
         &self,
         circ: &relations::flat::CircuitLayout<F>,
         pf: Proof<F, PC::Commitment, PC::Proof>,
         public: &HashMap<String, F>,
-    ) {
-        assert!(circ.p.is_none());
+    ) -> Result<(), Error<PC::Error>> {
+        if circ.p.is_some() {
+            return Err(Error::CircuitHasWitness);
+        }
         let n_gates = circ.domains.gates.size();
         let n_wires = n_gates * 3;
-        let p = self.recv_commit("p", pf.p_cmt, Some(n_wires - 1));
-        self.verify_public(&circ, &p, pf.public, public);
-        self.verify_gates(&p, &circ, pf.gates);
-        self.verify_wiring(&p, circ.domains.wires, pf.wiring);
+        let p = self.recv_commit("p", pf.p_cmt, Some(n_wires - 1))?;
+        self.verify_gates(&p, &circ, pf.gates, public)?;
+        self.verify_wiring(&p, circ.domains.wires, pf.wiring)?;
+        match (&self.vk.table_cmt, pf.lookup) {
+            (Some(table_cmt), Some(lookup)) => {
+                self.verify_lookup(&p, table_cmt, circ.domains.gates, lookup)
+            }
+            (None, None) => Ok(()),
+            _ => Err(Error::Rejected(VerificationError::LookupMismatch)),
+        }
     }
 
+    /// PI(x), for the public inputs `public`, which must be exactly the circuit's.
     fn verify_public(
         &self,
         circ: &relations::flat::CircuitLayout<F>,
-        p_cmt: &LabeledCommitment<PC::Commitment>,
-        pf: PublicProof<PC::Commitment, (F, PC::Proof)>,
         public: &HashMap<String, F>,
-    ) {
-        let q_cmt = self.recv_commit("pub_q", pf.q_cmt, None);
-        let x = self.fs_rng.borrow_mut().gen::<F>();
-        let p_val = self.check(p_cmt, x, &pf.p_open);
-        let q_val = self.check(&q_cmt, x, &pf.q_open);
-        let z = circ.vanishing_poly_on_inputs();
-        let v = circ.inputs_poly(public);
-        assert_eq!(p_val - v.evaluate(&x), q_val * z.evaluate(&x));
+        x: F,
+    ) -> Result<F, Error<PC::Error>> {
+        if let Some(var) = public
+            .keys()
+            .find(|var| !circ.public_indices.contains_key(*var))
+        {
+            return Err(Error::PublicInputMismatch(var.clone()));
+        }
+        if let Some(var) = circ
+            .public_indices
+            .keys()
+            .find(|var| !public.contains_key(*var))
+        {
+            return Err(Error::MissingPublicInput(var.clone()));
+        }
+        Ok(circ.public_input_poly(public).evaluate(&x))
     }
 
     fn verify_gates(
@@ -549,41 +706,88 @@ where
         p_cmt: &LabeledCommitment<PC::Commitment>,
         circ: &relations::flat::CircuitLayout<F>,
         pf: GateProof<PC::Commitment, (F, PC::Proof)>,
-    ) {
-        let q_cmt = self.recv_commit("gates_q", pf.q_cmt, None);
+        public: &HashMap<String, F>,
+    ) -> Result<(), Error<PC::Error>> {
+        let q_cmt = self.recv_commit("gates_q", pf.q_cmt, None)?;
         let x = self.fs_rng.borrow_mut().gen::<F>();
         let w = circ.domains.wires.group_gen;
-        let s = self.check(&self.vk.s_cmt, x, &pf.s_open);
-        let q = self.check(&q_cmt, x, &pf.q_open);
-        let p = self.check(p_cmt, x, &pf.p_open);
-        let pw = self.check(p_cmt, x * w, &pf.p_w_open);
-        let pww = self.check(p_cmt, x * w * w, &pf.p_w2_open);
-        assert_eq!(
-            s * (p + pw) + (F::one() - s) * p * pw - pww,
-            q * circ.domains.gates.evaluate_vanishing_polynomial(x)
-        );
+        let s = Selectors {
+            l: self.check(&self.vk.s_cmt.l, x, &pf.s_open.l)?,
+            r: self.check(&self.vk.s_cmt.r, x, &pf.s_open.r)?,
+            m: self.check(&self.vk.s_cmt.m, x, &pf.s_open.m)?,
+            o: self.check(&self.vk.s_cmt.o, x, &pf.s_open.o)?,
+            c: self.check(&self.vk.s_cmt.c, x, &pf.s_open.c)?,
+        };
+        let q = self.check(&q_cmt, x, &pf.q_open)?;
+        let p = self.check(p_cmt, x, &pf.p_open)?;
+        let pw = self.check(p_cmt, x * w, &pf.p_w_open)?;
+        let pww = self.check(p_cmt, x * w * w, &pf.p_w2_open)?;
+        let pi = self.verify_public(circ, public, x)?;
+        if s.apply(p, pw, pww) + pi != q * circ.domains.gates.evaluate_vanishing_polynomial(x) {
+            return Err(Error::Rejected(VerificationError::GateCheckFailed));
+        }
+        Ok(())
+    }
+    fn verify_lookup(
+        &self,
+        p_cmt: &LabeledCommitment<PC::Commitment>,
+        table_cmt: &Table<LabeledCommitment<PC::Commitment>>,
+        dom: Radix2EvaluationDomain<F>,
+        pf: LookupProof<PC::Commitment, (F, PC::Proof)>,
+    ) -> Result<(), Error<PC::Error>> {
+        let f_cmt = self.recv_commit("f", pf.f_cmt, None)?;
+        let h1_cmt = self.recv_commit("h1", pf.h1_cmt, None)?;
+        let h2_cmt = self.recv_commit("h2", pf.h2_cmt, None)?;
+        let beta = self.fs_rng.borrow_mut().gen::<F>();
+        let gamma = self.fs_rng.borrow_mut().gen::<F>();
+        let l_cmt = self.recv_commit("l", pf.l_cmt, None)?;
+        self.verify_unit_product(&l_cmt, pf.l_prod_pf, dom)?;
+        let alpha = self.fs_rng.borrow_mut().gen::<F>();
+        let q_cmt = self.recv_commit("lookup_q", pf.q_cmt, None)?;
+        let x = self.fs_rng.borrow_mut().gen::<F>();
+        let w = dom.group_gen;
+
+        let t = self.check(&table_cmt.t, x, &pf.table_open.t)?;
+        let k = self.check(&table_cmt.k, x, &pf.table_open.k)?;
+        let t_w = self.check(&table_cmt.t, w * x, &pf.t_w_open)?;
+        let p = self.check(p_cmt, x, &pf.p_open)?;
+        let f = self.check(&f_cmt, x, &pf.f_open)?;
+        let h1 = self.check(&h1_cmt, x, &pf.h1_open)?;
+        let h1_w = self.check(&h1_cmt, w * x, &pf.h1_w_open)?;
+        let h2 = self.check(&h2_cmt, x, &pf.h2_open)?;
+        let l = self.check(&l_cmt, x, &pf.l_open)?;
+        let q = self.check(&q_cmt, x, &pf.q_open)?;
+        let g = |a: F, b: F| gamma * (F::one() + beta) + a + beta * b;
+        if l * g(h1, h2) * g(h2, h1_w) - g(f, f) * g(t, t_w) + alpha * k * (p - f)
+            != q * dom.evaluate_vanishing_polynomial(x)
+        {
+            return Err(Error::Rejected(VerificationError::LookupCheckFailed));
+        }
+        Ok(())
     }
     fn verify_wiring<D: EvaluationDomain<F>>(
         &self,
         p_cmt: &LabeledCommitment<PC::Commitment>,
         dom: D,
         pf: WiringProof<PC::Commitment, (F, PC::Proof)>,
-    ) {
+    ) -> Result<(), Error<PC::Error>> {
         let y = self.fs_rng.borrow_mut().gen::<F>();
         let z = self.fs_rng.borrow_mut().gen::<F>();
-        let l1 = self.recv_commit("l1", pf.l1_cmt, None);
-        self.verify_unit_product(&l1, pf.l1_prod_pf, dom);
-        let l2_q = self.recv_commit("l2_q", pf.l2_q_cmt, None);
+        let l1 = self.recv_commit("l1", pf.l1_cmt, None)?;
+        self.verify_unit_product(&l1, pf.l1_prod_pf, dom)?;
+        let l2_q = self.recv_commit("l2_q", pf.l2_q_cmt, None)?;
         let x = self.fs_rng.borrow_mut().gen::<F>();
 
-        let l2_q_x = self.check(&l2_q, x, &pf.l2_q_x_open);
-        let w_x = self.check(&self.vk.w_cmt, x, &pf.w_x_open);
-        let l1_x = self.check(&l1, x, &pf.l1_x_open);
-        let p_x = self.check(&p_cmt, x, &pf.p_x_open);
-        assert_eq!(
-            (p_x + y * x + z) * l1_x - (p_x + y * w_x + z),
-            l2_q_x * dom.evaluate_vanishing_polynomial(x)
-        );
+        let l2_q_x = self.check(&l2_q, x, &pf.l2_q_x_open)?;
+        let w_x = self.check(&self.vk.w_cmt, x, &pf.w_x_open)?;
+        let l1_x = self.check(&l1, x, &pf.l1_x_open)?;
+        let p_x = self.check(&p_cmt, x, &pf.p_x_open)?;
+        if (p_x + y * x + z) * l1_x - (p_x + y * w_x + z)
+            != l2_q_x * dom.evaluate_vanishing_polynomial(x)
+        {
+            return Err(Error::Rejected(VerificationError::WiringCheckFailed));
+        }
+        Ok(())
     }
 }
 
@@ -597,6 +801,33 @@ pub enum Error<PCE: 'static + std::error::Error> {
     DomainDivisionFailed,
     #[error("Eq check for domain division failed")]
     DomainCheckFailed,
+    #[error("The verifier's circuit must not have a witness")]
+    CircuitHasWitness,
+    #[error("Public input {0:?} is not one of the circuit's")]
+    PublicInputMismatch(String),
+    #[error("Missing public input {0:?}")]
+    MissingPublicInput(String),
+    #[error("Could not serialize commitment {0:?}: {1}")]
+    Serialization(String, std::io::Error),
+    #[error("Rejected proof: {0}")]
+    Rejected(VerificationError),
+}
+
+/// Why a proof failed to verify.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum VerificationError {
+    #[error("An opening failed to verify: {0}")]
+    BadOpening(String),
+    #[error("A product check failed")]
+    ProductCheckFailed,
+    #[error("The wiring check failed")]
+    WiringCheckFailed,
+    #[error("The gate check failed")]
+    GateCheckFailed,
+    #[error("The lookup check failed")]
+    LookupCheckFailed,
+    #[error("The proof and the circuit disagree on whether there are lookups")]
+    LookupMismatch,
 }
 
 pub type Result<T, PCE> = std::result::Result<T, PCE>;
@@ -633,7 +864,7 @@ where
         circ: &relations::flat::CircuitLayout<F>,
         pf: Proof<F, PC::Commitment, PC::Proof>,
         public: &HashMap<String, F>,
-    ) {
+    ) -> Result<(), Error<PC::Error>> {
         let ver = Verifier::<F, PC>::new(vk);
         ver.verify(circ, pf, public)
     }
@@ -669,9 +900,195 @@ mod tests {
             t
         };
 
-        let srs = Pl::universal_setup(steps, setup_rng);
+        let srs = Pl::universal_setup(circ.domains.gates.size(), setup_rng);
+        let (pk, vk) = Pl::circuit_setup(&srs, &v_circ);
+        let pf = Pl::prove(&pk, &circ, zk_rng);
+        Pl::verify(&vk, &v_circ, pf, &public).unwrap();
+        let pf = Pl::prove(&pk, &circ, zk_rng);
+        assert!(matches!(
+            Pl::verify(&vk, &circ, pf, &public),
+            Err(Error::CircuitHasWitness)
+        ));
+    }
+
+    /// y = 3x^2 - 5x + 7, with `x` and `y` public: through a general gate, and again through a
+    /// scaling and a raw constraint. Also x + y = 11, which pins x to 2 (or -4/3).
+    fn general_gates_circuit(x: Option<F>) -> relations::structured::PlonkCircuit<F> {
+        use ark_ff::{One, Zero};
+        use relations::structured::*;
+        let mut c = PlonkCircuit::<F>::new(x.is_some());
+        let x = c.new_pub_var(|| x.unwrap(), "x".to_owned());
+        let x2 = c.new_prod(x, x);
+        let y = c.new_gate(x2, x, F::from(3u64), -F::from(5u64), F::zero(), F::from(7u64));
+        c.publicize_var(y, "y".to_owned());
+        let five_x = c.new_scale(x, F::from(5u64));
+        c.enforce(
+            (x2, five_x, y),
+            Selectors {
+                l: F::from(3u64),
+                r: -F::one(),
+                m: F::zero(),
+                o: -F::one(),
+                c: F::from(7u64),
+            },
+        );
+        let eleven = c.new_const(F::from(11u64));
+        c.enforce(
+            (x, y, eleven),
+            Selectors {
+                l: F::one(),
+                r: F::one(),
+                o: -F::one(),
+                ..Selectors::default()
+            },
+        );
+        c
+    }
+
+    fn prove_general_gates(public: &[(&str, u64)]) -> Result<(), Error<<PC as PolynomialCommitment<F, P>>::Error>> {
+        use relations::flat::*;
+        let c = general_gates_circuit(Some(F::from(2u64)));
+        let circ = CircuitLayout::from_circuit(&c);
+        let v_circ = CircuitLayout::from_circuit(&general_gates_circuit(None));
+        let public: HashMap<String, F> =
+            public.iter().map(|(n, v)| (n.to_string(), F::from(*v))).collect();
+
+        let setup_rng = &mut ark_std::test_rng();
+        let zk_rng = &mut ark_std::test_rng();
+        let srs = Pl::universal_setup(circ.domains.gates.size(), setup_rng);
+        let (pk, vk) = Pl::circuit_setup(&srs, &v_circ);
+        let pf = Pl::prove(&pk, &circ, zk_rng);
+        Pl::verify(&vk, &v_circ, pf, &public)
+    }
+
+    #[test]
+    fn plonk_general_gates() {
+        prove_general_gates(&[("x", 2), ("y", 9)]).unwrap();
+    }
+
+    #[test]
+    fn plonk_wrong_public_input() {
+        assert!(matches!(
+            prove_general_gates(&[("x", 2), ("y", 10)]),
+            Err(Error::Rejected(VerificationError::GateCheckFailed))
+        ));
+        assert!(matches!(
+            prove_general_gates(&[("x", 2)]),
+            Err(Error::MissingPublicInput(var)) if var == "y"
+        ));
+        assert!(matches!(
+            prove_general_gates(&[("x", 2), ("y", 9), ("z", 0)]),
+            Err(Error::PublicInputMismatch(var)) if var == "z"
+        ));
+    }
+
+    /// With public `x` and `y`: `w = x^2`, `(x + 2w + 3) * (w - 1) = y`, and `x + w = 6`.
+    struct AffineCircuit {
+        x: Option<F>,
+    }
+
+    impl ark_relations::r1cs::ConstraintSynthesizer<F> for AffineCircuit {
+        fn generate_constraints(
+            self,
+            cs: ark_relations::r1cs::ConstraintSystemRef<F>,
+        ) -> Result<(), ark_relations::r1cs::SynthesisError> {
+            use ark_ff::One;
+            use ark_relations::{
+                lc,
+                r1cs::{SynthesisError::AssignmentMissing, Variable},
+            };
+            let w_val = self.x.map(|x| x * x);
+            let y_val =
+                w_val.map(|w| (self.x.unwrap() + w.double() + F::from(3u64)) * (w - F::one()));
+            let x = cs.new_input_variable(|| self.x.ok_or(AssignmentMissing))?;
+            let y = cs.new_input_variable(|| y_val.ok_or(AssignmentMissing))?;
+            let w = cs.new_witness_variable(|| w_val.ok_or(AssignmentMissing))?;
+            cs.enforce_constraint(lc!() + x, lc!() + x, lc!() + w)?;
+            cs.enforce_constraint(
+                lc!() + x + (F::from(2u64), w) + (F::from(3u64), Variable::One),
+                lc!() + w - Variable::One,
+                lc!() + y,
+            )?;
+            cs.enforce_constraint(
+                lc!() + x + w,
+                lc!() + Variable::One,
+                lc!() + (F::from(6u64), Variable::One),
+            )?;
+            Ok(())
+        }
+    }
+
+    fn prove_r1cs(public: &[u64]) -> Result<(), Error<<PC as PolynomialCommitment<F, P>>::Error>> {
+        use relations::{flat::*, r1cs, structured::*};
+        let x = Some(F::from(2u64));
+        let circ = CircuitLayout::from_circuit(
+            &PlonkCircuit::from_synthesizer(AffineCircuit { x }, true).unwrap(),
+        );
+        let v_circ = CircuitLayout::from_circuit(
+            &PlonkCircuit::from_synthesizer(AffineCircuit { x: None }, false).unwrap(),
+        );
+        let public: Vec<F> = public.iter().map(|x| F::from(*x)).collect();
+        let public = r1cs::public_inputs(&public);
+
+        let setup_rng = &mut ark_std::test_rng();
+        let zk_rng = &mut ark_std::test_rng();
+        let srs = Pl::universal_setup(circ.domains.gates.size(), setup_rng);
+        let (pk, vk) = Pl::circuit_setup(&srs, &v_circ);
+        let pf = Pl::prove(&pk, &circ, zk_rng);
+        Pl::verify(&vk, &v_circ, pf, &public)
+    }
+
+    #[test]
+    fn plonk_from_r1cs() {
+        prove_r1cs(&[2, 39]).unwrap();
+    }
+
+    #[test]
+    fn plonk_from_r1cs_wrong_public_input() {
+        assert!(matches!(
+            prove_r1cs(&[2, 40]),
+            Err(Error::Rejected(VerificationError::GateCheckFailed))
+        ));
+    }
+
+    /// y = x + 9, with `y` public, and both `x` and `y` below 16.
+    fn range_circuit(x: Option<F>) -> relations::structured::PlonkCircuit<F> {
+        use ark_ff::{One, Zero};
+        use relations::structured::*;
+        let mut c = PlonkCircuit::<F>::new(x.is_some());
+        c.set_table(PlonkCircuit::range_table(4));
+        let x = c.new_var(|| x.unwrap());
+        let y = c.new_gate(x, x, F::one(), F::zero(), F::zero(), F::from(9u64));
+        c.publicize_var(y, "y".to_owned());
+        c.lookup(x);
+        c.lookup(y);
+        c
+    }
+
+    fn prove_range(x: u64) {
+        use relations::flat::*;
+        let circ = CircuitLayout::from_circuit(&range_circuit(Some(F::from(x))));
+        let v_circ = CircuitLayout::from_circuit(&range_circuit(None));
+        let public: HashMap<String, F> =
+            vec![("y".to_owned(), F::from(x + 9))].into_iter().collect();
+        circ.check(&public);
+
+        let setup_rng = &mut ark_std::test_rng();
+        let zk_rng = &mut ark_std::test_rng();
+        let srs = Pl::universal_setup(circ.domains.gates.size(), setup_rng);
         let (pk, vk) = Pl::circuit_setup(&srs, &v_circ);
         let pf = Pl::prove(&pk, &circ, zk_rng);
-        Pl::verify(&vk, &v_circ, pf, &public);
+        Pl::verify(&vk, &v_circ, pf, &public).unwrap();
+    }
+
+    #[test]
+    fn plonk_lookup() {
+        prove_range(5);
+    }
+
+    #[test]
+    #[should_panic]
+    fn plonk_lookup_out_of_range() {
+        prove_range(10);
     }
 }
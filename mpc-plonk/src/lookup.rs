@@ -0,0 +1,162 @@
+//! The witness for lookups: the looked-up values and the table, sorted together.
+use ark_ff::Field;
+use std::collections::HashMap;
+use std::iter;
+
+/// The most entries, lookups and table together, that [`sorted_union`] sorts when the lookups are
+/// shared: the work to sort them grows with the square of the entries.
+pub const MAX_SHARED_LEN: usize = 1 << 13;
+
+/// `f` and `table` together, in the order of `table`: each entry of the table, then the values of
+/// `f` that equal it. Every value of `f` must be in `table`, whose entries must be distinct, but
+/// for repeats of the last entry at the end, as padding.
+pub fn sorted_union<F: Field>(f: &[F], table: &[F]) -> Vec<F> {
+    if f.iter().any(|x| x.is_shared()) {
+        shared_sorted_union(f, table)
+    } else {
+        let index: HashMap<F, usize> = table.iter().enumerate().map(|(j, t)| (*t, j)).collect();
+        let mut counts = vec![1; table.len()];
+        for x in f {
+            counts[*index.get(x).expect("A lookup is not in the table")] += 1;
+        }
+        table
+            .iter()
+            .zip(counts)
+            .flat_map(|(t, n)| iter::repeat_n(*t, n))
+            .collect()
+    }
+}
+
+/// [`sorted_union`] without comparing the values of `f`, which we cannot do with shares: only
+/// with products and public linear maps.
+///
+/// How often `table[j]` occurs in `f` is the sum of `L_j(f_i)`, for the Lagrange basis over the
+/// table, so it follows from the power sums of `f`. That count fixes where each entry starts in
+/// the result. The differences between consecutive entries of the result are zero except at those
+/// starts, so they follow, through the Lagrange basis over the positions, from the power sums of
+/// the starts weighted by the differences between consecutive table entries.
+///
+/// The power sums take about `2 log2(n)` rounds, for `n` entries in all, and `n` products for each
+/// entry. The Lagrange bases are public, but computing them and applying them to the shared power
+/// sums takes `O(n^2)` local work, so we refuse more than [`MAX_SHARED_LEN`] entries.
+fn shared_sorted_union<F: Field>(f: &[F], padded_table: &[F]) -> Vec<F> {
+    let n = f.len() + padded_table.len();
+    assert!(
+        n <= MAX_SHARED_LEN,
+        "Cannot sort {} shared lookup entries; the most is {}",
+        n,
+        MAX_SHARED_LEN
+    );
+    let last = padded_table.last().unwrap();
+    let table = &padded_table[..=padded_table.iter().position(|t| t == last).unwrap()];
+    assert!(table.len() >= 2, "A table needs two entries or more");
+    let f_sums = power_sums(f, &vec![F::one(); f.len()], table.len());
+    let mut starts = Vec::with_capacity(table.len());
+    let mut start = F::zero();
+    for l in lagrange_basis(table) {
+        starts.push(start);
+        start += F::one() + dot(&l, &f_sums);
+    }
+    // The first entry starts at zero; the others start where the result steps up to them.
+    let steps: Vec<F> = table.windows(2).map(|t| t[1] - t[0]).collect();
+    let start_sums = power_sums(&starts[1..], &steps, n);
+    let positions: Vec<F> = (0..n as u64).map(F::from).collect();
+    lagrange_basis(&positions)
+        .scan(table[0], |s, l| {
+            *s += dot(&l, &start_sums);
+            Some(*s)
+        })
+        .collect()
+}
+
+/// `sum_i weights[i] * xs[i]^e`, for each `e < m`, where the weights are public.
+///
+/// Each batch of products multiplies the powers so far by the highest, about doubling them, so
+/// this takes about `log2(m)` rounds, and `m` products for each of `xs`.
+fn power_sums<F: Field>(xs: &[F], weights: &[F], m: usize) -> Vec<F> {
+    // `powers[e]` holds `xs[i]^e`, for each `i`.
+    let mut powers = vec![vec![F::one(); xs.len()], xs.to_vec()];
+    powers.truncate(m);
+    while powers.len() < m && !xs.is_empty() {
+        let top = powers.len() - 1;
+        let count = top.min(m - powers.len());
+        let mut products: Vec<F> = powers[1..=count].concat();
+        let tops: Vec<F> = (0..count).flat_map(|_| powers[top].iter().cloned()).collect();
+        F::batch_product_in_place(&mut products, &tops);
+        powers.extend(products.chunks(xs.len()).map(|c| c.to_vec()));
+    }
+    powers.resize(m, vec![F::zero(); xs.len()]);
+    powers.iter().map(|p| dot(weights, p)).collect()
+}
+
+/// The coefficients of the Lagrange basis polynomials over `nodes`, one at a time.
+fn lagrange_basis<F: Field>(nodes: &[F]) -> impl Iterator<Item = Vec<F>> + '_ {
+    // The coefficients of Z(X), the product of each X - node, from the lowest.
+    let mut z = vec![F::one()];
+    for x in nodes {
+        z.insert(0, F::zero());
+        for i in 0..z.len() - 1 {
+            let next = z[i + 1];
+            z[i] -= next * x;
+        }
+    }
+    nodes.iter().map(move |x| {
+        // Z(X) / (X - x), scaled to one at x
+        let mut l = vec![F::zero(); nodes.len()];
+        let mut carry = F::zero();
+        for i in (0..nodes.len()).rev() {
+            carry = z[i + 1] + carry * x;
+            l[i] = carry;
+        }
+        let at_x = l.iter().rev().fold(F::zero(), |acc, c| acc * x + c);
+        let scale = at_x.inverse().expect("The nodes must be distinct");
+        l.iter_mut().for_each(|c| *c *= scale);
+        l
+    })
+}
+
+fn dot<F: Field>(a: &[F], b: &[F]) -> F {
+    a.iter().zip(b).map(|(a, b)| *a * b).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bls12_377::Fr;
+
+    #[test]
+    fn sorted_union_without_comparisons() {
+        let table: Vec<Fr> = [3u64, 9, 4, 7].iter().map(|t| Fr::from(*t)).collect();
+        let f: Vec<Fr> = [7u64, 3, 7, 9, 7].iter().map(|x| Fr::from(*x)).collect();
+        let expected: Vec<Fr> = [3u64, 3, 9, 9, 4, 7, 7, 7, 7]
+            .iter()
+            .map(|s| Fr::from(*s))
+            .collect();
+        assert_eq!(sorted_union(&f, &table), expected);
+        assert_eq!(shared_sorted_union(&f, &table), expected);
+        let padded = [&table[..], &[table[3]; 2]].concat();
+        let expected = [&expected[..], &[table[3]; 2]].concat();
+        assert_eq!(sorted_union(&f, &padded), expected);
+        assert_eq!(shared_sorted_union(&f, &padded), expected);
+    }
+
+    #[test]
+    fn power_sums_by_doubling() {
+        let xs: Vec<Fr> = [2u64, 5, 11].iter().map(|x| Fr::from(*x)).collect();
+        let weights: Vec<Fr> = [1u64, 3, 7].iter().map(|w| Fr::from(*w)).collect();
+        for m in 0..12 {
+            let expected: Vec<Fr> = (0..m as u64)
+                .map(|e| dot(&weights, &xs.iter().map(|x| x.pow([e])).collect::<Vec<_>>()))
+                .collect();
+            assert_eq!(power_sums(&xs, &weights, m), expected);
+        }
+        assert_eq!(power_sums::<Fr>(&[], &[], 3), vec![Fr::from(0u8); 3]);
+    }
+
+    #[test]
+    #[should_panic(expected = "shared lookup entries")]
+    fn shared_sorts_are_limited() {
+        let table: Vec<Fr> = (0..MAX_SHARED_LEN as u64).map(Fr::from).collect();
+        shared_sorted_union(&table[..1], &table);
+    }
+}
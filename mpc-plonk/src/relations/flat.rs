@@ -6,25 +6,27 @@ use ark_poly::{
     evaluations::univariate::Evaluations,
     univariate::DensePolynomial,
     Polynomial,
-    UVPolynomial,
 };
 
 use std::iter;
 
 use crate::util::shift;
+use crate::{Selectors, Table};
 
-use super::structured::PlonkCircuit;
+use super::structured::{Gate, PlonkCircuit};
 
 #[derive(Clone)]
 pub struct CircuitLayout<F: FftField> {
     /// Wiring permutation polynomial
     pub w: DensePolynomial<F>,
-    /// Gate selection polynomial
-    pub s: DensePolynomial<F>,
+    /// Gate selector polynomials
+    pub s: Selectors<DensePolynomial<F>>,
     /// Map from variables to indices in the layout
     pub vars_to_indices: HashMap<u32, Vec<usize>>,
-    /// Public variables
+    /// Public variables, by the gate that takes each
     pub public_indices: HashMap<String, usize>,
+    /// Lookup table and selector polynomials, if the circuit has lookups
+    pub lookup: Option<Table<DensePolynomial<F>>>,
     /// Wire value polynomial
     pub p: Option<DensePolynomial<F>>,
     /// Domains over which the polynomials have meaning
@@ -34,42 +36,97 @@ pub struct CircuitLayout<F: FftField> {
 impl<F: FftField> CircuitLayout<F> {
     pub fn from_circuit(c: &PlonkCircuit<F>) -> Self {
         let domains = Domains::from_circuit(c);
-        // Our layout is products followed by sums
+        // Our layout is the public inputs' gates, in variable order, then the lookups' gates, then
+        // the other gates, then padding. A wire with no variable is free.
+        let mut pub_vars: Vec<(u32, &String)> = c.pub_vars.iter().map(|(v, n)| (*v, n)).collect();
+        pub_vars.sort();
+        let n_gates = domains.gates.size();
+        let gates: Vec<([Option<u32>; 3], Selectors<F>)> = pub_vars
+            .iter()
+            .map(|(v, _)| ([Some(*v), None, None], Gate::public(*v).s))
+            .chain(
+                c.lookups
+                    .iter()
+                    .map(|v| ([Some(*v), None, None], Selectors::default())),
+            )
+            .chain(
+                c.gates
+                    .iter()
+                    .map(|g| ([Some(g.wires.0), Some(g.wires.1), Some(g.wires.2)], g.s)),
+            )
+            .chain(iter::repeat(([None; 3], Selectors::default())))
+            .take(n_gates)
+            .collect();
 
-        // Start with gate selector polynomial
-        let gate_selector_evals = Evaluations::<F, Radix2EvaluationDomain<F>>::from_vec_and_domain(
-            iter::repeat(F::zero())
-                .take(c.prods.len())
-                .chain(iter::repeat(F::one()).take(c.sums.len()))
-                .collect(),
-            domains.gates.clone(),
-        );
+        // Start with gate selector polynomials
+        let selector = |f: fn(&Selectors<F>) -> F| {
+            Evaluations::<F, Radix2EvaluationDomain<F>>::from_vec_and_domain(
+                gates.iter().map(|(_, s)| f(s)).collect(),
+                domains.gates,
+            )
+            .interpolate()
+        };
+        let s = Selectors {
+            l: selector(|s| s.l),
+            r: selector(|s| s.r),
+            m: selector(|s| s.m),
+            o: selector(|s| s.o),
+            c: selector(|s| s.c),
+        };
+
+        // The table, padded with its last entry, and q_K, which picks out the lookups' gates
+        let lookup = if c.lookups.is_empty() {
+            None
+        } else {
+            let over_gates = |evals: Vec<F>| {
+                Evaluations::from_vec_and_domain(evals, domains.gates).interpolate()
+            };
+            let last = *c.table.last().unwrap();
+            let t = c
+                .table
+                .iter()
+                .cloned()
+                .chain(iter::repeat(last))
+                .take(n_gates)
+                .collect();
+            let lookup_gates = pub_vars.len()..pub_vars.len() + c.lookups.len();
+            let k = (0..n_gates)
+                .map(|i| {
+                    if lookup_gates.contains(&i) {
+                        F::one()
+                    } else {
+                        F::zero()
+                    }
+                })
+                .collect();
+            Some(Table {
+                t: over_gates(t),
+                k: over_gates(k),
+            })
+        };
 
         // Get powers of w for wire permuation poly
-        let n_wires = c.n_gates() * 3;
+        let n_wires = n_gates * 3;
         let wire_g = domains.wires.group_gen;
         let wire_g_pows: Vec<F> = iter::successors(Some(F::one()), |f| Some(wire_g * f))
             .take(n_wires)
             .collect();
         // Manifest layout
-        let var_layout: Vec<u32> = c
-            .prods
-            .iter()
-            .chain(c.sums.iter())
-            .flat_map(|(in0, in1, out)| vec![*in0, *in1, *out])
-            .collect();
+        let var_layout: Vec<Option<u32>> = gates.iter().flat_map(|(wires, _)| wires.to_vec()).collect();
         // Assemble cycles
         let vars_to_indices = {
             let mut vars_to_indices: HashMap<u32, Vec<usize>> =
                 (0..c.n_vars).map(|i| (i, Vec::new())).collect();
             for (i, v) in var_layout.iter().enumerate() {
-                vars_to_indices.get_mut(v).unwrap().push(i);
+                if let Some(v) = v {
+                    vars_to_indices.get_mut(v).unwrap().push(i);
+                }
             }
             vars_to_indices
         };
-        // Write cycles into evaluations
+        // Write cycles into evaluations; free wires map to themselves
         let mut wire_evals = Evaluations::<F, MixedRadixEvaluationDomain<F>>::from_vec_and_domain(
-            vec![F::zero(); n_wires],
+            wire_g_pows.clone(),
             domains.wires.clone(),
         );
         for (_var, indices) in &vars_to_indices {
@@ -120,18 +177,15 @@ impl<F: FftField> CircuitLayout<F> {
             }
         CircuitLayout {
             w,
-            s: gate_selector_evals.interpolate(),
+            s,
             domains: domains.clone(),
             p,
-            public_indices: c
-                .pub_vars
-                .iter()
-                .filter_map(|(v, name)| {
-                    vars_to_indices
-                        .get(v)
-                        .and_then(|is| is.first().map(|i| (name.clone(), *i)))
-                })
+            public_indices: pub_vars
+                .into_iter()
+                .enumerate()
+                .map(|(i, (_, name))| (name.clone(), i))
                 .collect(),
+            lookup,
             vars_to_indices,
         }
     }
@@ -195,30 +249,35 @@ impl<F: FftField> CircuitLayout<F> {
         }
     }
 
-    fn check_inputs(&self, public_wires: &HashMap<String, F>) {
-        if let Some(p) = &self.p {
-            let wire_g = self.domains.wires.group_gen;
-            for (variable, value) in public_wires {
-                let idx = self
-                    .public_indices
-                    .get(variable)
-                    .unwrap_or_else(|| panic!("Missing public wire {:?}", variable));
-                assert_eq!(&p.evaluate(&wire_g.pow(&[*idx as u64])), value);
-            }
-        }
-    }
-
-    fn check_gates(&self) {
+    fn check_gates(&self, public_wires: &HashMap<String, F>) {
         let wire_g = self.domains.wires.group_gen;
         if let Some(p) = &self.p {
             let p_x_evals = self.evaluate_over_gates(p);
             let p_wx_evals = self.evaluate_over_gates(&shift(p.clone(), wire_g));
             let p_wwx_evals = self.evaluate_over_gates(&shift(p.clone(), wire_g * wire_g));
-            let s_evals = self.evaluate_over_gates(&self.s);
-            let a = &s_evals * &(&p_x_evals + &p_wx_evals);
-            let b = &(&(&s_evals * &-F::one()) + &F::one()) * &(&p_x_evals * &p_wx_evals);
-            let c = &a + &b;
-            assert_eq!(c, p_wwx_evals);
+            let s_evals = self.s.as_ref().map(|s| self.evaluate_over_gates(s).evals);
+            let pi_evals = self.evaluate_over_gates(&self.public_input_poly(public_wires));
+            for i in 0..self.domains.gates.size() {
+                let s = s_evals.as_ref().map(|s| s[i]);
+                assert_eq!(
+                    s.apply(p_x_evals[i], p_wx_evals[i], p_wwx_evals[i]) + pi_evals[i],
+                    F::zero(),
+                    "Gate {} does not hold",
+                    i
+                );
+            }
+        }
+    }
+
+    fn check_lookups(&self) {
+        if let (Some(p), Some(lookup)) = (&self.p, &self.lookup) {
+            let p_evals = self.evaluate_over_gates(p);
+            let table = lookup.as_ref().map(|p| self.evaluate_over_gates(p).evals);
+            for i in 0..self.domains.gates.size() {
+                if table.k[i] == F::one() {
+                    assert!(table.t.contains(&p_evals[i]), "Lookup {} is not in the table", i);
+                }
+            }
         }
     }
 
@@ -242,41 +301,26 @@ impl<F: FftField> CircuitLayout<F> {
         }
     }
 
-    /// Returns the monic polynomial which vanishes at the input pins
-    pub fn vanishing_poly_on_inputs(&self) -> DensePolynomial<F> {
-        let roots: Vec<F> = self
-            .public_indices
-            .iter()
-            .map(|(_, i)| self.domains.wires.element(*i))
-            .collect();
-        poly_from_roots(&roots)
-    }
-
-    pub fn inputs_poly(
-        &self,
-        inputs: &HashMap<String, F>,
-    ) -> DensePolynomial<F> {
-        assert!(inputs.len() > 0);
-        let points: Vec<(F, F)> = inputs
-            .iter()
-            .map(|(var, val)| {
-                let idx = self.public_indices[var];
-                let x = self.domains.wires.element(idx);
-                (x, *val)
-            })
-            .collect();
-        crate::util::interpolate(&points)
+    /// PI(X): minus each public input at its gate, and zero at the other gates.
+    pub fn public_input_poly(&self, inputs: &HashMap<String, F>) -> DensePolynomial<F> {
+        let mut evals = vec![F::zero(); self.domains.gates.size()];
+        for (var, i) in &self.public_indices {
+            let val = inputs
+                .get(var)
+                .unwrap_or_else(|| panic!("Missing public wire {:?}", var));
+            evals[*i] = -*val;
+        }
+        Evaluations::from_vec_and_domain(evals, self.domains.gates).interpolate()
     }
 
-
     pub fn check(&self, public_wires: &HashMap<String, F>) {
-        self.check_gates();
+        self.check_gates(public_wires);
+        self.check_lookups();
         self.check_wiring();
-        self.check_inputs(public_wires);
     }
 }
 
-/// We assume a power-of-two number of gates.
+/// We pad the gates to a power of two, and to the lookup table.
 /// We use a 2^r*3-sized domain for wires and a 2^r-sized domain for gates.
 #[derive(Clone, Debug)]
 pub struct Domains<F: FftField> {
@@ -291,9 +335,9 @@ impl<F: FftField> Domains<F> {
             Some(3),
             "We require the scalar field's multiplicative group to have a subgroup of order 3"
         );
-        let n = c.n_gates();
+        let n = c.n_gates().max(c.table.len());
         let gates = Radix2EvaluationDomain::new(n).expect("gate domain");
-        let wires = MixedRadixEvaluationDomain::new(3 * n).expect("wire domain");
+        let wires = MixedRadixEvaluationDomain::new(3 * gates.size()).expect("wire domain");
         assert!(3 * gates.size() == wires.size());
         let wire_g = wires.group_gen;
         assert_eq!(wire_g * wire_g * wire_g, gates.group_gen);
@@ -301,12 +345,6 @@ impl<F: FftField> Domains<F> {
     }
 }
 
-fn poly_from_roots<F: FftField>(roots: &[F]) -> DensePolynomial<F> {
-    roots.iter().fold(
-        DensePolynomial::from_coefficients_vec(vec![F::one()]),
-        |acc, r| acc.naive_mul(&DensePolynomial::from_coefficients_vec(vec![-*r, F::one()])),
-    )
-}
 
 
 
@@ -333,8 +371,7 @@ mod tests {
     fn circuit_polys() {
         for steps in &[1, 3] {
             let c = PlonkCircuit::<F>::new_squaring_circuit(*steps, None);
-            let d = Domains::from_circuit(&c);
-            let polys = CircuitLayout::from_circuit(&c, &d);
+            let polys = CircuitLayout::from_circuit(&c);
             polys.check_connection_degree(3);
         }
     }
@@ -345,8 +382,7 @@ mod tests {
             let c = PlonkCircuit::<F>::new_squaring_circuit(*steps, Some(start));
             let res = (0..*steps).fold(start, |a, _| a * a);
             let public: HashMap<String, F> = vec![("out".to_owned(), res)].into_iter().collect();
-            let d = Domains::from_circuit(&c);
-            let polys = CircuitLayout::from_circuit(&c, &d);
+            let polys = CircuitLayout::from_circuit(&c);
             polys.check_connection_degree(3);
             polys.check(&public);
         }
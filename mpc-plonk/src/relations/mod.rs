@@ -5,3 +5,4 @@
 
 pub mod structured;
 pub mod flat;
+pub mod r1cs;
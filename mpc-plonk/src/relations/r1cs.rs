@@ -0,0 +1,116 @@
+//! Compiling rank-1 constraint systems to Plonk gates, so that any [`ConstraintSynthesizer`] that
+//! Groth16 and Marlin prove runs on Plonk too.
+use super::structured::PlonkCircuit;
+use crate::Selectors;
+use ark_ff::Field;
+use ark_relations::r1cs::{
+    ConstraintSynthesizer, ConstraintSystem, ConstraintSystemRef, SynthesisError, SynthesisMode,
+};
+use std::collections::HashMap;
+
+type Var = u32;
+
+/// The name that a compiled circuit gives public input `i`, counting from zero after the
+/// constant.
+pub fn input_name(i: usize) -> String {
+    format!("input{}", i)
+}
+
+/// The public inputs `inputs` (without the constant), by the names a compiled circuit gives them.
+pub fn public_inputs<F: Field>(inputs: &[F]) -> HashMap<String, F> {
+    inputs
+        .iter()
+        .enumerate()
+        .map(|(i, x)| (input_name(i), *x))
+        .collect()
+}
+
+/// A linear combination, once reduced to `k*v + constant`.
+struct Reduced<F> {
+    k: F,
+    v: Option<Var>,
+    constant: F,
+}
+
+impl<F: Field> PlonkCircuit<F> {
+    /// Synthesize `circuit`, with its values if `values`, and compile it to gates.
+    pub fn from_synthesizer<C: ConstraintSynthesizer<F>>(
+        circuit: C,
+        values: bool,
+    ) -> Result<Self, SynthesisError> {
+        let cs = ConstraintSystem::new_ref();
+        if !values {
+            cs.set_mode(SynthesisMode::Setup);
+        }
+        circuit.generate_constraints(cs.clone())?;
+        cs.finalize();
+        Ok(Self::from_r1cs(&cs))
+    }
+
+    /// Compile the finalized constraint system `cs`. Every variable but the constant becomes a
+    /// Plonk variable, and the instance variables become public inputs, named by [`input_name`].
+    /// Each constraint takes one gate, and each linear combination of `n > 1` variables takes
+    /// `n - 1` sum gates more.
+    pub fn from_r1cs(cs: &ConstraintSystemRef<F>) -> Self {
+        let matrices = cs
+            .to_matrices()
+            .expect("Compiling to Plonk needs the constraint matrices");
+        let cs = cs.borrow().unwrap();
+        let mut self_ = PlonkCircuit::new(!cs.is_in_setup_mode());
+        // Column `i > 0` of the matrices is our variable `i - 1`; column 0 is the constant.
+        for i in 1..matrices.num_instance_variables {
+            self_.new_pub_var(|| cs.instance_assignment[i], input_name(i - 1));
+        }
+        for i in 0..matrices.num_witness_variables {
+            self_.new_var(|| cs.witness_assignment[i]);
+        }
+        assert!(
+            self_.n_vars > 0,
+            "Cannot compile a constraint system without variables"
+        );
+        for ((a, b), c) in matrices.a.iter().zip(&matrices.b).zip(&matrices.c) {
+            let a = self_.reduce(a);
+            let b = self_.reduce(b);
+            let c = self_.reduce(c);
+            // A term without a variable has `k = 0`, so any variable fills its wire.
+            let any = a.v.or(b.v).or(c.v).unwrap_or(0);
+            // (k_a*a + c_a) * (k_b*b + c_b) - (k_c*c + c_c) = 0
+            self_.enforce(
+                (a.v.unwrap_or(any), b.v.unwrap_or(any), c.v.unwrap_or(any)),
+                Selectors {
+                    l: a.k * b.constant,
+                    r: a.constant * b.k,
+                    m: a.k * b.k,
+                    o: -c.k,
+                    c: a.constant * b.constant - c.constant,
+                },
+            );
+        }
+        self_
+    }
+
+    /// Reduce `lc`, a row of a constraint matrix, to at most one variable, with sum gates.
+    fn reduce(&mut self, lc: &[(F, usize)]) -> Reduced<F> {
+        let mut constant = F::zero();
+        let mut terms = Vec::new();
+        for (k, i) in lc {
+            if *i == 0 {
+                constant += k;
+            } else {
+                terms.push((*k, *i as Var - 1));
+            }
+        }
+        let (k, v) = match terms.as_slice() {
+            [] => (F::zero(), None),
+            [(k, v)] => (*k, Some(*v)),
+            [(k0, v0), (k1, v1), rest @ ..] => {
+                let mut acc = self.new_gate(*v0, *v1, *k0, *k1, F::zero(), F::zero());
+                for (k, v) in rest {
+                    acc = self.new_gate(acc, *v, F::one(), *k, F::zero(), F::zero());
+                }
+                (F::one(), Some(acc))
+            }
+        };
+        Reduced { k, v, constant }
+    }
+}
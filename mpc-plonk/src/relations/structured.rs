@@ -1,23 +1,78 @@
+use crate::Selectors;
 use ark_ff::Field;
 use std::collections::HashMap;
 
 pub struct PlonkCircuit<F: Field> {
     pub n_vars: u32,
+    /// Public variables, each of which gets a gate of its own
     pub pub_vars: HashMap<Var, String>,
-    pub prods: Vec<(Var, Var, Var)>,
-    pub sums: Vec<(Var, Var, Var)>,
+    pub gates: Vec<Gate<F>>,
+    /// The lookup table, whose entries must be distinct
+    pub table: Vec<F>,
+    /// Variables that must be in the table, each of which gets a gate of its own
+    pub lookups: Vec<Var>,
     pub values: Option<Vec<F>>,
 }
 
 type Var = u32;
 
+/// An arithmetic gate: its selectors, applied to its wires, must give zero.
+#[derive(Clone, Copy, Debug)]
+pub struct Gate<F: Field> {
+    /// Left input, right input, and output
+    pub wires: (Var, Var, Var),
+    pub s: Selectors<F>,
+}
+
+impl<F: Field> Gate<F> {
+    /// `c = a + b`
+    pub fn sum(a: Var, b: Var, c: Var) -> Self {
+        Self::new((a, b, c), F::one(), F::one(), F::zero(), F::zero())
+    }
+    /// `c = a * b`
+    pub fn prod(a: Var, b: Var, c: Var) -> Self {
+        Self::new((a, b, c), F::zero(), F::zero(), F::one(), F::zero())
+    }
+    /// `c = q_l*a + q_r*b + q_m*a*b + q_c`
+    pub fn new(wires: (Var, Var, Var), q_l: F, q_r: F, q_m: F, q_c: F) -> Self {
+        Self {
+            wires,
+            s: Selectors {
+                l: q_l,
+                r: q_r,
+                m: q_m,
+                o: -F::one(),
+                c: q_c,
+            },
+        }
+    }
+    /// A gate that holds for any values of `v`: for padding.
+    pub fn zero(v: Var) -> Self {
+        Self {
+            wires: (v, v, v),
+            s: Selectors::default(),
+        }
+    }
+    /// The gate for a public input `v`: `v` must equal the input.
+    pub fn public(v: Var) -> Self {
+        Self {
+            wires: (v, v, v),
+            s: Selectors {
+                l: F::one(),
+                ..Selectors::default()
+            },
+        }
+    }
+}
+
 impl<F: Field> PlonkCircuit<F> {
     pub fn new(values: bool) -> Self {
         Self {
             n_vars: 0,
             pub_vars: HashMap::new(),
-            prods: Vec::new(),
-            sums: Vec::new(),
+            gates: Vec::new(),
+            table: Vec::new(),
+            lookups: Vec::new(),
             values: if values { Some(Vec::new()) } else { None },
         }
     }
@@ -34,38 +89,75 @@ impl<F: Field> PlonkCircuit<F> {
             );
         }
     }
-    pub fn new_sum(&mut self, a: Var, b: Var) -> Var {
+    /// A new variable, `q_l*a + q_r*b + q_m*a*b + q_c`.
+    pub fn new_gate(&mut self, a: Var, b: Var, q_l: F, q_r: F, q_m: F, q_c: F) -> Var {
         self.values.as_mut().map(|v| {
-            let o = v[a as usize] + v[b as usize];
+            let (a, b) = (v[a as usize], v[b as usize]);
+            let mut o = a * q_l + b * q_r + q_c;
+            // Shared values would pay a multiplication for nothing.
+            if !q_m.is_zero() {
+                o += a * b * q_m;
+            }
             v.push(o);
         });
-        self.sums.push((a, b, self.n_vars));
+        self.gates.push(Gate::new((a, b, self.n_vars), q_l, q_r, q_m, q_c));
         self.n_vars += 1;
         self.n_vars - 1
     }
+    pub fn new_sum(&mut self, a: Var, b: Var) -> Var {
+        self.new_gate(a, b, F::one(), F::one(), F::zero(), F::zero())
+    }
     pub fn new_prod(&mut self, a: Var, b: Var) -> Var {
-        self.values.as_mut().map(|v| {
-            let o = v[a as usize] * v[b as usize];
-            v.push(o);
-        });
-        self.prods.push((a, b, self.n_vars));
-        self.n_vars += 1;
-        self.n_vars - 1
+        self.new_gate(a, b, F::zero(), F::zero(), F::one(), F::zero())
+    }
+    /// A new variable, `k*a`.
+    pub fn new_scale(&mut self, a: Var, k: F) -> Var {
+        self.new_gate(a, a, k, F::zero(), F::zero(), F::zero())
+    }
+    /// A new variable, `k`. Needs some variable to wire into the gate.
+    pub fn new_const(&mut self, k: F) -> Var {
+        assert!(self.n_vars > 0, "A constant needs another variable first");
+        self.new_gate(0, 0, F::zero(), F::zero(), F::zero(), k)
+    }
+    /// Constrain `q_l*a + q_r*b + q_m*a*b + q_o*c + q_c` to be zero.
+    pub fn enforce(&mut self, wires: (Var, Var, Var), s: Selectors<F>) {
+        self.gates.push(Gate { wires, s });
     }
     pub fn new_pub_var(&mut self, value: impl FnOnce() -> F, name: String) -> Var {
         let v = self.new_var(value);
         self.publicize_var(v, name);
         v
     }
+    /// Set the lookup table. A table needs two distinct entries or more, and fits in the gates
+    /// (which are padded to it).
+    pub fn set_table(&mut self, table: Vec<F>) {
+        assert!(table.len() >= 2, "A table needs two entries or more");
+        let distinct: std::collections::HashSet<&F> = table.iter().collect();
+        assert_eq!(
+            distinct.len(),
+            table.len(),
+            "A table's entries must be distinct"
+        );
+        self.table = table;
+    }
+    /// The table for range checks: `0` to `2^bits - 1`.
+    pub fn range_table(bits: u32) -> Vec<F> {
+        (0..1u64 << bits).map(F::from).collect()
+    }
+    /// Constrain `v` to be in the table.
+    pub fn lookup(&mut self, v: Var) {
+        assert!(!self.table.is_empty(), "A lookup needs a table first");
+        self.lookups.push(v);
+    }
+    /// The gates, counting one for each public variable and each lookup.
     pub fn n_gates(&self) -> usize {
-        self.prods.len() + self.sums.len()
+        self.gates.len() + self.pub_vars.len() + self.lookups.len()
     }
     pub fn pad_to_power_of_2(&mut self) {
         let n = self.n_gates().next_power_of_two();
         assert!(self.n_vars > 0, "Cannot pad an empty circuit!");
         for _ in self.n_gates()..n {
-            let v = self.n_vars - 1;
-            self.new_sum(v, v);
+            self.gates.push(Gate::zero(0));
         }
         assert!(self.n_gates().is_power_of_two());
     }
@@ -75,8 +167,8 @@ impl<F: Field> PlonkCircuit<F> {
         for _ in 0..steps {
             v = self_.new_prod(v, v);
         }
-        self_.pad_to_power_of_2();
         self_.publicize_var(v, "out".to_owned());
+        self_.pad_to_power_of_2();
         self_
     }
 }
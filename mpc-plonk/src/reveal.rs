@@ -4,16 +4,22 @@ use mpc_algebra::*;
 
 use crate::*;
 
+impl<T: Reveal> Reveal for Selectors<T> {
+    type Base = Selectors<T::Base>;
+    struct_reveal_impl!(Selectors<T>, Selectors;
+        (T, l), (T, r), (T, m), (T, o), (T, c));
+}
+
+impl<T: Reveal> Reveal for Table<T> {
+    type Base = Table<T::Base>;
+    struct_reveal_impl!(Table<T>, Table;
+        (T, t), (T, k));
+}
+
 impl<C: Reveal, O: Reveal> Reveal for GateProof<C, O> {
     type Base = GateProof<C::Base, O::Base>;
     struct_reveal_impl!(GateProof<C, O>, GateProof;
-        (C, q_cmt), (O, s_open), (O, q_open), (O, p_open), (O, p_w_open), (O, p_w2_open));
-}
-
-impl<C: Reveal, O: Reveal> Reveal for PublicProof<C, O> {
-    type Base = PublicProof<C::Base, O::Base>;
-    struct_reveal_impl!(PublicProof<C, O>, PublicProof;
-        (C, q_cmt), (O, q_open), (O, p_open));
+        (C, q_cmt), (Selectors<O>, s_open), (O, q_open), (O, p_open), (O, p_w_open), (O, p_w2_open));
 }
 
 impl<C: Reveal, O: Reveal> Reveal for ProductProof<C, O> {
@@ -28,13 +34,19 @@ impl<C: Reveal, O: Reveal> Reveal for WiringProof<C, O> {
         (C, l1_cmt), (ProductProof<C, O>, l1_prod_pf), (C, l2_q_cmt), (O, p_x_open), (O, w_x_open), (O, l1_x_open), (O, l2_q_x_open));
 }
 
+impl<C: Reveal, O: Reveal> Reveal for LookupProof<C, O> {
+    type Base = LookupProof<C::Base, O::Base>;
+    struct_reveal_impl!(LookupProof<C, O>, LookupProof;
+        (C, f_cmt), (C, h1_cmt), (C, h2_cmt), (C, l_cmt), (ProductProof<C, O>, l_prod_pf), (C, q_cmt), (Table<O>, table_open), (O, t_w_open), (O, p_open), (O, f_open), (O, h1_open), (O, h1_w_open), (O, h2_open), (O, l_open), (O, q_open));
+}
+
 impl<F: Reveal, C: Reveal, O: Reveal> Reveal for Proof<F, C, O> {
     type Base = Proof<F::Base, C::Base, O::Base>;
     struct_reveal_impl!(Proof<F, PC>, Proof;
         (C, p_cmt),
         (WiringProof<C, (F, O)>, wiring),
         (GateProof<C, (F, O)>, gates),
-        (PublicProof<C, (F, O)>, public)
+        (Option<LookupProof<C, (F, O)>>, lookup)
     );
 }
 
@@ -50,5 +62,5 @@ impl<E: PairingEngine, S: PairingShare<E>> Reveal
         marlin_pc::Commitment<E>,
         marlin_pc::CommitterKey<E>,
     >;
-    struct_reveal_simp_impl!(ProverKey; w, s, w_cmt, s_cmt, pc_ck);
+    struct_reveal_simp_impl!(ProverKey; w, s, w_cmt, s_cmt, table, table_cmt, pc_ck);
 }
@@ -17,6 +17,8 @@ pub fn shift<F: FftField>(mut f: DensePolynomial<F>, a: F) -> DensePolynomial<F>
     f
 }
 
+/// The polynomial of least degree through `points`
+#[allow(dead_code)]
 pub fn interpolate<F: FftField>(points: &[(F, F)]) -> DensePolynomial<F> {
     let k = points.len();
     let mut scaled_lagrange_basis: Vec<DensePolynomial<F>> = (0..k)
@@ -4,23 +4,28 @@ use log::debug;
 
 use ark_bls12_377::Fr;
 use ark_ec::{group::Group, AffineCurve, PairingEngine, ProjectiveCurve};
-use ark_ff::Field;
+use ark_ff::{Field, SquareRootField};
 use ark_poly::domain::radix2::Radix2EvaluationDomain;
 use ark_poly::univariate::DensePolynomial;
 use ark_poly::{EvaluationDomain, Polynomial, UVPolynomial};
 use ark_poly_commit::marlin_pc;
 use ark_poly_commit::PolynomialCommitment;
+use ark_r1cs_std::{alloc::AllocVar, fields::fp::FpVar, R1CSVar};
+use ark_relations::r1cs::ConstraintSystem;
 use ark_serialize::CanonicalSerialize;
 use ark_std::rand::SeedableRng;
 use std::borrow::Cow;
 use std::path::PathBuf;
+use std::time::Duration;
 
 use mpc_algebra::com::ComField;
+use mpc_algebra::gadgets::{self, BitDecomposition};
 use mpc_algebra::honest_but_curious as hbc;
 use mpc_algebra::malicious_majority as mm;
 use mpc_algebra::*;
 use mpc_trait::MpcWire;
-use mpc_net::{MpcNet, MpcMultiNet};
+use mpc_net::shape::{parse_bandwidth, parse_duration};
+use mpc_net::{LinkShape, MpcNet, MpcMultiNet, Topology, Transport};
 
 use clap::arg_enum;
 use merlin::Transcript;
@@ -47,6 +52,7 @@ arg_enum! {
         PairingDh,
         PairingProd,
         PairingDiv,
+        PairingBatch,
         Groth16,
         Marlin,
         PolyEval,
@@ -59,6 +65,7 @@ arg_enum! {
         PcTwoCom,
         Plonk,
         PolyDiv,
+        Bits,
     }
 }
 
@@ -89,6 +96,27 @@ struct Opt {
     #[structopt(long, default_value = "0")]
     party: u8,
 
+    /// Secret key: authenticate and encrypt connections with Noise. The hosts file must then list
+    /// each party's public key after its address.
+    #[structopt(long, parse(from_os_str))]
+    key: Option<PathBuf>,
+
+    /// Emulated latency of every link, like 40ms (replaces the hosts file's conditions)
+    #[structopt(long, default_value = "0ms", parse(try_from_str = parse_duration))]
+    latency: Duration,
+
+    /// Emulated jitter of every link, like 5ms
+    #[structopt(long, default_value = "0ms", parse(try_from_str = parse_duration))]
+    jitter: Duration,
+
+    /// Emulated bandwidth of every link, like 100mbit
+    #[structopt(long, parse(try_from_str = parse_bandwidth))]
+    bandwidth: Option<u64>,
+
+    /// How the parties connect: mesh (everyone to everyone) or star (everyone through party 0)
+    #[structopt(long, default_value = "mesh")]
+    topology: Topology,
+
     /// Computation to perform
     #[structopt()]
     computation: Computation,
@@ -119,9 +147,10 @@ impl Opt {
             Computation::NaiveMsm | Computation::GroupOps => {
                 ComputationDomain::Group
             }
-            Computation::PairingDh | Computation::PairingProd | Computation::PairingDiv => {
-                ComputationDomain::Pairing
-            }
+            Computation::PairingDh
+            | Computation::PairingProd
+            | Computation::PairingDiv
+            | Computation::PairingBatch => ComputationDomain::Pairing,
             Computation::Marlin
             | Computation::Groth16
             | Computation::Plonk
@@ -579,6 +608,41 @@ impl Computation {
                 assert_eq!(gtabcd, gtabcd2);
                 vec![]
             }
+            Computation::PairingBatch => {
+                // (a * g1, g2) * (g1, b * g2) * (c * g1, d * g2) * (g1, g2)
+                //      = ((a + b + c * d + 1) * g1, g2)
+                assert_eq!(4, inputs.len());
+                let a = inputs[0];
+                let b = inputs[1];
+                let c = inputs[2];
+                let d = inputs[3];
+                let e = a + b + c * d + <P as PairingEngine>::Fr::from(1u64);
+                let g1 = <P as PairingEngine>::G1Projective::prime_subgroup_generator();
+                let g2 = <P as PairingEngine>::G2Projective::prime_subgroup_generator();
+                let g1a = <<P as PairingEngine>::G1Projective as Group>::mul(&g1, &a);
+                let g2b = <<P as PairingEngine>::G2Projective as Group>::mul(&g2, &b);
+                let g1c = <<P as PairingEngine>::G1Projective as Group>::mul(&g1, &c);
+                let g2d = <<P as PairingEngine>::G2Projective as Group>::mul(&g2, &d);
+                let g1e = <<P as PairingEngine>::G1Projective as Group>::mul(&g1, &e);
+                let prep1 = |g: <P as PairingEngine>::G1Projective| -> P::G1Prepared {
+                    g.into_affine().into()
+                };
+                let prep2 = |g: <P as PairingEngine>::G2Projective| -> P::G2Prepared {
+                    g.into_affine().into()
+                };
+                let pairs = vec![
+                    (prep1(g1a), prep2(g2)),
+                    (prep1(g1), prep2(g2b)),
+                    (prep1(g1c), prep2(g2d)),
+                    (prep1(g1), prep2(g2)),
+                ];
+                let mut prod = P::product_of_pairings(&pairs);
+                let mut expected = P::pairing(g1e, g2);
+                prod.publicize();
+                expected.publicize();
+                assert_eq!(prod, expected);
+                vec![]
+            }
             c => unimplemented!("Cannot run_pairing {:?}", c),
         };
         println!("Outputs:");
@@ -664,7 +728,7 @@ impl Computation {
         }
         outputs
     }
-    fn run_field<F: ComField>(&self, mut inputs: Vec<F>) -> Vec<F> {
+    fn run_field<F: ComField + BitDecomposition + SquareRootField>(&self, mut inputs: Vec<F>) -> Vec<F> {
         let outputs = match self {
             Computation::Fft => {
                 let d = Radix2EvaluationDomain::<F>::new(inputs.len()).unwrap();
@@ -684,6 +748,35 @@ impl Computation {
             Computation::Sum => {
                 vec![inputs.into_iter().fold(F::from(0u32), std::ops::Add::add)]
             }
+            Computation::Bits => {
+                let cs = ConstraintSystem::<F>::new_ref();
+                for x in &inputs {
+                    let mut x_pub = *x;
+                    x_pub.publicize();
+                    // Directly, and through the gadget.
+                    let var = FpVar::new_witness(cs.clone(), || Ok(*x)).unwrap();
+                    let var_bits = gadgets::to_bits_le(&var)
+                        .unwrap()
+                        .iter()
+                        .map(|b| b.value().unwrap())
+                        .collect::<Vec<_>>();
+                    for bits in vec![x.bit_decomposition(), var_bits] {
+                        let mut acc = F::zero();
+                        for mut b in bits.into_iter().rev() {
+                            b.publicize();
+                            assert!(b.is_zero() || b.is_one());
+                            acc.double_in_place();
+                            acc += b;
+                        }
+                        assert_eq!(acc, x_pub);
+                    }
+                    let sq = x.square();
+                    let mut root = sq.sqrt().unwrap();
+                    root.publicize();
+                    assert_eq!(root.square(), x_pub.square());
+                }
+                vec![]
+            }
             Computation::Product => {
                 assert_eq!(inputs.len(), 2);
                 let product = inputs[0] * inputs[1];
@@ -872,7 +965,24 @@ fn main() -> () {
         env_logger::init();
     }
     let domain = opt.domain();
-    MpcMultiNet::init_from_file(opt.hosts.to_str().unwrap(), opt.party as usize);
+    MpcMultiNet::init_with_topology(
+        opt.hosts.to_str().unwrap(),
+        opt.party as usize,
+        Transport::from_secret_key(opt.key.clone()),
+        opt.topology,
+    )
+    .unwrap_or_else(|e| panic!("Could not connect: {}", e));
+    let shape = LinkShape {
+        latency: opt.latency,
+        jitter: opt.jitter,
+        bandwidth: opt.bandwidth,
+    };
+    if shape != LinkShape::default() {
+        MpcMultiNet::shape_links(shape);
+    }
+    // These computations have no offline phase: they multiply with fixed, public triples, which
+    // is insecure.
+    mpc_algebra::preprocessing::use_dummy();
     debug!("Start");
     if opt.spdz {
         let inputs = opt
@@ -897,6 +1007,10 @@ fn main() -> () {
                 let generator = mm::MpcGroup::<ark_bls12_377::G1Projective>::from_public(ark_bls12_377::G1Projective::prime_subgroup_generator());
                 opt.computation.run_group::<mm::MpcGroup<ark_bls12_377::G1Projective>>(inputs, generator);
             }
+            ComputationDomain::Pairing => {
+                opt.computation
+                    .run_pairing::<mm::MpcPairingEngine<ark_bls12_377::Bls12_377>>(inputs);
+            }
             d => panic!("Bad domain: {:?}", d),
         }
     } else {
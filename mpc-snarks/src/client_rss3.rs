@@ -14,12 +14,14 @@ use ark_serialize::CanonicalSerialize;
 use ark_std::rand::SeedableRng;
 use std::borrow::Cow;
 use std::path::PathBuf;
+use std::time::Duration;
 
 use mpc_algebra::com::ComField;
 use mpc_algebra::honest_majority_rss3 as hm_rss3;
 use mpc_algebra::*;
 use mpc_trait::MpcWire;
-use mpc_net::{MpcNet, MpcMultiNet};
+use mpc_net::shape::{parse_bandwidth, parse_duration};
+use mpc_net::{LinkShape, MpcNet, MpcMultiNet, Topology, Transport};
 
 use clap::arg_enum;
 use merlin::Transcript;
@@ -88,6 +90,27 @@ struct Opt {
     #[structopt(long, default_value = "0")]
     party: u8,
 
+    /// Secret key: authenticate and encrypt connections with Noise. The hosts file must then list
+    /// each party's public key after its address.
+    #[structopt(long, parse(from_os_str))]
+    key: Option<PathBuf>,
+
+    /// Emulated latency of every link, like 40ms (replaces the hosts file's conditions)
+    #[structopt(long, default_value = "0ms", parse(try_from_str = parse_duration))]
+    latency: Duration,
+
+    /// Emulated jitter of every link, like 5ms
+    #[structopt(long, default_value = "0ms", parse(try_from_str = parse_duration))]
+    jitter: Duration,
+
+    /// Emulated bandwidth of every link, like 100mbit
+    #[structopt(long, parse(try_from_str = parse_bandwidth))]
+    bandwidth: Option<u64>,
+
+    /// How the parties connect: mesh (everyone to everyone) or star (everyone through party 0)
+    #[structopt(long, default_value = "mesh")]
+    topology: Topology,
+
     /// Computation to perform
     #[structopt()]
     computation: Computation,
@@ -871,7 +894,24 @@ fn main() -> () {
         env_logger::init();
     }
     let domain = opt.domain();
-    MpcMultiNet::init_from_file(opt.hosts.to_str().unwrap(), opt.party as usize);
+    MpcMultiNet::init_with_topology(
+        opt.hosts.to_str().unwrap(),
+        opt.party as usize,
+        Transport::from_secret_key(opt.key.clone()),
+        opt.topology,
+    )
+    .unwrap_or_else(|e| panic!("Could not connect: {}", e));
+    let shape = LinkShape {
+        latency: opt.latency,
+        jitter: opt.jitter,
+        bandwidth: opt.bandwidth,
+    };
+    if shape != LinkShape::default() {
+        MpcMultiNet::shape_links(shape);
+    }
+    // These computations have no offline phase: they multiply with fixed, public triples, which
+    // is insecure.
+    mpc_algebra::preprocessing::use_dummy();
     debug!("Start");
     if opt.rss3 {
         let inputs = opt
@@ -0,0 +1,576 @@
+//! Delegated proving: a client that holds a witness has `n` servers prove a statement about it
+//! together, so that no server learns the witness, and only the client learns the proof.
+//!
+//! Before any job, [`setup`] writes a proving key and a verifying key for each circuit and proof
+//! system to a directory: the servers need the proving keys, and the client the verifying keys.
+//!
+//! For a job, the client sends each server a [`Job`], naming the circuit and the proof system,
+//! ahead of its shares of the witness (see [`client::send_to_parties_with`]). The servers check
+//! that they can all take the job, prove it together, and each sends the client its shares of the
+//! proof (see [`client::output_message`]), or why they turned the job down. The client puts the
+//! proof together and verifies it. [`serve`] is a server's side of a job, and [`delegate`] the
+//! client's.
+//!
+//! Sharings that multiply with Beaver triples (SPDZ and HBC) need a fresh preprocessing store for
+//! every job, dealt ahead of time (see [`mpc_algebra::preprocessing`]). The client learns no
+//! server's secrets: with SPDZ, it gets no MAC key shares, and relies on the proof verifying.
+use ark_ec::PairingEngine;
+use ark_ff::{FftField, Field};
+use ark_groth16::{generate_random_parameters, prepare_verifying_key, verify_proof};
+use ark_marlin::{IndexProverKey, IndexVerifierKey, Marlin};
+use ark_poly::univariate::DensePolynomial;
+use ark_poly::EvaluationDomain;
+use ark_poly_commit::marlin::marlin_pc::MarlinKZG10;
+use ark_poly_commit::PolynomialCommitment;
+use ark_relations::{
+    lc,
+    r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError, Variable},
+};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Read, SerializationError, Write};
+use blake2::Blake2s;
+use clap::arg_enum;
+use log::{debug, warn};
+use mpc_algebra::channel::MpcSerNet;
+use mpc_algebra::client::{self, ClientShare};
+use mpc_algebra::preprocessing::{self, FieldPreprocessing, PreprocessingError};
+use mpc_algebra::share::encoding::ser_err_to_io;
+use mpc_algebra::{FieldShare, MpcField, MpcPairingEngine, PairingShare, Reveal};
+use mpc_net::noise::{PublicKey, SecretKey};
+use mpc_net::{MpcMultiNet as Net, MpcNet, MpcNetError, MpcSession};
+use mpc_plonk::relations::flat::CircuitLayout;
+use mpc_plonk::relations::r1cs;
+use mpc_plonk::relations::structured::PlonkCircuit;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+type MpcFr<E, S> = MpcField<<E as PairingEngine>::Fr, <S as PairingShare<E>>::FrShare>;
+type MpcE<E, S> = MpcPairingEngine<E, S>;
+type KzgMarlin<Fr, E> = Marlin<Fr, MarlinKZG10<E, DensePolynomial<Fr>>, Blake2s>;
+type MarlinPc<E> = MarlinKZG10<E, DensePolynomial<<E as PairingEngine>::Fr>>;
+type MarlinPcPlonk<Fr, E> = mpc_plonk::Plonk<Fr, MarlinKZG10<E, DensePolynomial<Fr>>>;
+type PlonkProverKey<E> = mpc_plonk::ProverKey<
+    <E as PairingEngine>::Fr,
+    <MarlinPc<E> as PolynomialCommitment<<E as PairingEngine>::Fr, DensePolynomial<<E as PairingEngine>::Fr>>>::Commitment,
+    <MarlinPc<E> as PolynomialCommitment<<E as PairingEngine>::Fr, DensePolynomial<<E as PairingEngine>::Fr>>>::CommitterKey,
+>;
+type PlonkVerifierKey<E> = mpc_plonk::VerifierKey<
+    <MarlinPc<E> as PolynomialCommitment<<E as PairingEngine>::Fr, DensePolynomial<<E as PairingEngine>::Fr>>>::Commitment,
+    <MarlinPc<E> as PolynomialCommitment<<E as PairingEngine>::Fr, DensePolynomial<<E as PairingEngine>::Fr>>>::VerifierKey,
+>;
+
+arg_enum! {
+    #[derive(PartialEq, Eq, Debug, Clone, Copy)]
+    pub enum ProofSystem {
+        Groth16,
+        Marlin,
+        Plonk,
+    }
+}
+
+arg_enum! {
+    #[derive(PartialEq, Eq, Debug, Clone, Copy)]
+    pub enum MpcAlg {
+        Spdz,
+        Hbc,
+        Gsz,
+        Rss3,
+        Rss,
+    }
+}
+
+/// A circuit that the servers know how to prove, by name.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Circuit {
+    /// `x^(2^n)` is the public output, for the secret `x`. Named `squaring-<n>`.
+    Squaring(usize),
+}
+
+impl Circuit {
+    /// How many field elements the witness has.
+    pub fn witness_len(&self) -> usize {
+        match self {
+            Circuit::Squaring(n) => n + 1,
+        }
+    }
+
+    /// The witness for the secret `x`.
+    pub fn witness<F: Field>(&self, x: F) -> Vec<F> {
+        match self {
+            Circuit::Squaring(n) => std::iter::successors(Some(x), |a| Some(a.square()))
+                .take(n + 1)
+                .collect(),
+        }
+    }
+
+    /// The public inputs that go with `witness`.
+    pub fn public_inputs<F: Field>(&self, witness: &[F]) -> Vec<F> {
+        match self {
+            Circuit::Squaring(_) => vec![*witness.last().unwrap()],
+        }
+    }
+
+    /// The constraints, with the values of `witness` if we have them.
+    fn r1cs<F: Field>(&self, witness: Option<Vec<F>>) -> SquaringCircuit<F> {
+        match self {
+            Circuit::Squaring(n) => SquaringCircuit {
+                chain: match witness {
+                    Some(w) => w.into_iter().map(Some).collect(),
+                    None => vec![None; n + 1],
+                },
+            },
+        }
+    }
+
+    /// The constraints as Plonk gates, with the values of `witness` if we have them.
+    fn plonk<F: FftField>(&self, witness: Option<Vec<F>>) -> CircuitLayout<F> {
+        let values = witness.is_some();
+        CircuitLayout::from_circuit(
+            &PlonkCircuit::from_synthesizer(self.r1cs(witness), values).unwrap(),
+        )
+    }
+
+    /// The public inputs that go with `witness`, by the names that the Plonk gates give them.
+    fn plonk_public_inputs<F: Field>(&self, witness: &[F]) -> HashMap<String, F> {
+        r1cs::public_inputs(&self.public_inputs(witness))
+    }
+
+    /// How large to make Marlin's universal parameters: constraints, variables, and non-zero
+    /// entries.
+    fn marlin_bounds(&self) -> (usize, usize, usize) {
+        match self {
+            Circuit::Squaring(n) => (*n, n + 2, 3 * n),
+        }
+    }
+}
+
+impl fmt::Display for Circuit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Circuit::Squaring(n) => write!(f, "squaring-{}", n),
+        }
+    }
+}
+
+impl FromStr for Circuit {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s.strip_prefix("squaring-").map(str::parse) {
+            Some(Ok(n)) if n > 0 => Ok(Circuit::Squaring(n)),
+            _ => Err(format!("{} is not a circuit; try squaring-<n>", s)),
+        }
+    }
+}
+
+/// What the client asks the servers to prove.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub struct Job {
+    pub system: ProofSystem,
+    pub circuit: Circuit,
+}
+
+impl fmt::Display for Job {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} for {}", self.system, self.circuit)
+    }
+}
+
+/// By name, so that the encoding does not depend on the order of the variants.
+impl CanonicalSerialize for Job {
+    fn serialize<W: Write>(&self, writer: W) -> Result<(), SerializationError> {
+        (self.system.to_string(), self.circuit.to_string()).serialize(writer)
+    }
+    fn serialized_size(&self) -> usize {
+        (self.system.to_string(), self.circuit.to_string()).serialized_size()
+    }
+}
+
+impl CanonicalDeserialize for Job {
+    fn deserialize<R: Read>(reader: R) -> Result<Self, SerializationError> {
+        let (system, circuit) = <(String, String)>::deserialize(reader)?;
+        Ok(Job {
+            system: system.parse().map_err(|_| SerializationError::InvalidData)?,
+            circuit: circuit.parse().map_err(|_| SerializationError::InvalidData)?,
+        })
+    }
+}
+
+/// Where the proving key (`"pk"`) or verifying key (`"vk"`) for `job` is, in `dir`.
+pub fn key_path(dir: &Path, job: &Job, kind: &str) -> PathBuf {
+    dir.join(format!(
+        "{}.{}.{}",
+        job.circuit,
+        job.system.to_string().to_lowercase(),
+        kind
+    ))
+}
+
+fn save<T: CanonicalSerialize>(value: &T, path: &Path) -> io::Result<()> {
+    let mut w = BufWriter::new(File::create(path)?);
+    value.serialize(&mut w).map_err(ser_err_to_io)
+}
+
+fn load<T: CanonicalDeserialize>(path: &Path) -> Result<T, SerializationError> {
+    T::deserialize(BufReader::new(File::open(path)?))
+}
+
+/// Generate the proving and verifying keys for `job`, and write them to `dir`.
+pub fn setup<E: PairingEngine>(dir: &Path, job: &Job) -> io::Result<()> {
+    let rng = &mut rand::thread_rng();
+    let (pk_path, vk_path) = (key_path(dir, job, "pk"), key_path(dir, job, "vk"));
+    match job.system {
+        ProofSystem::Groth16 => {
+            let pk = generate_random_parameters::<E, _, _>(job.circuit.r1cs(None), rng).unwrap();
+            save(&pk.vk, &vk_path)?;
+            save(&pk, &pk_path)
+        }
+        ProofSystem::Marlin => {
+            let (constraints, variables, non_zero) = job.circuit.marlin_bounds();
+            let srs =
+                KzgMarlin::<E::Fr, E>::universal_setup(constraints, variables, non_zero, rng)
+                    .unwrap();
+            let (pk, vk) = KzgMarlin::<E::Fr, E>::index(&srs, job.circuit.r1cs(None)).unwrap();
+            save(&vk, &vk_path)?;
+            save(&pk, &pk_path)
+        }
+        ProofSystem::Plonk => {
+            let circuit = job.circuit.plonk::<E::Fr>(None);
+            let srs = MarlinPcPlonk::<E::Fr, E>::universal_setup(circuit.domains.gates.size(), rng);
+            let (pk, vk) = MarlinPcPlonk::<E::Fr, E>::circuit_setup(&srs, &circuit);
+            save(&vk, &vk_path)?;
+            save(&pk, &pk_path)
+        }
+    }
+}
+
+/// At every server, together, in `session`: wait for a job from the client, which contacts us at
+/// `addr` (our address in the hosts file), prove it with the proving key in `dir`, and send the
+/// client our shares of the proof. The connections must be secured with Noise, with `keys`, our secret key
+/// and the client's public key.
+///
+/// `store` is our preprocessing for this job, which sharings that multiply with Beaver triples
+/// need; it must not be used for any other job. For SPDZ, it also holds our share of the job's
+/// MAC key.
+///
+/// Serve each job in a session of its own, named by [`next_job_id`], and end the session
+/// afterwards, so that a job that fails leaves no frames or state behind for the next one.
+///
+/// If the servers cannot take the job, they tell the client why, and return the job all the same.
+/// [`MpcNetError::Client`] means the client did not send us a job properly, and the servers can
+/// wait for the next one; any other error, that a server failed or cheated.
+///
+/// # Panics
+///
+/// If the sharing needs preprocessing and there is no `store` (unless the session opted into
+/// [`preprocessing::use_dummy`]), or `store` was dealt to another server.
+pub fn serve<E: PairingEngine, S: PairingShare<E>>(
+    session: &MpcSession,
+    dir: &Path,
+    store: Option<FieldPreprocessing<E::Fr, S::FrShare>>,
+    addr: SocketAddr,
+    keys: (&SecretKey, &PublicKey),
+) -> Result<Job, MpcNetError>
+where
+    S::FrShare: ClientShare<Base = E::Fr>,
+{
+    // The provers do their arithmetic in the thread's current session.
+    session.enter(|| {
+        S::FrShare::init_protocol();
+        let installed = match store {
+            Some(store) => preprocessing::install(store),
+            None if <S::FrShare as FieldShare<E::Fr>>::USES_TRIPLES && !preprocessing::uses_dummy() => {
+                Err(PreprocessingError::NotInstalled {
+                    share: std::any::type_name::<S::FrShare>(),
+                })
+            }
+            None => Ok(()),
+        };
+        if let Err(e) = installed {
+            panic!("Cannot serve a job: {}", e);
+        }
+        let served = session.run(|| {
+            let (job, witness) =
+                client::recv_from_client_with::<MpcFr<E, S>, Job>(session, addr, keys)?;
+            debug!("Proving {}", job);
+            let mut reply = Vec::new();
+            match prove::<E, S>(dir, &job, witness) {
+                Ok(output) => {
+                    None::<String>.serialize(&mut reply).unwrap();
+                    reply.extend(output);
+                }
+                Err(NotProved::Rejected(reason)) => {
+                    warn!("Turning down {}: {}", job, reason);
+                    Some(reason).serialize(&mut reply).unwrap();
+                }
+                Err(NotProved::Net(e)) => return Err(e),
+            }
+            mpc_net::client::send_to_client(addr, keys, &reply)?;
+            Ok(job)
+        });
+        preprocessing::uninstall::<E::Fr, S::FrShare>();
+        S::FrShare::deinit_protocol();
+        served.and_then(|served| served)
+    })
+}
+
+/// Why the servers did not prove a job.
+enum NotProved {
+    /// The servers cannot take the job, for this reason, which they tell the client.
+    Rejected(String),
+    /// A server failed or cheated.
+    Net(MpcNetError),
+}
+
+impl From<MpcNetError> for NotProved {
+    fn from(e: MpcNetError) -> Self {
+        NotProved::Net(e)
+    }
+}
+
+/// Our shares of the proof of `job` for `witness`, as the message to the client, or why the
+/// servers did not prove it.
+fn prove<E: PairingEngine, S: PairingShare<E>>(
+    dir: &Path,
+    job: &Job,
+    witness: Vec<MpcFr<E, S>>,
+) -> Result<Vec<u8>, NotProved>
+where
+    S::FrShare: ClientShare<Base = E::Fr>,
+{
+    let len = job.circuit.witness_len();
+    let witness = agree(if witness.len() == len {
+        Ok(witness)
+    } else {
+        Err(format!("{} needs {} witness values, not {}", job.circuit, len, witness.len()))
+    })?;
+    let pk_path = key_path(dir, job, "pk");
+    let pk_error = |e: SerializationError| format!("cannot load {}: {}", pk_path.display(), e);
+    let output = match job.system {
+        ProofSystem::Groth16 => {
+            let pk = agree(load::<ark_groth16::ProvingKey<E>>(&pk_path).map_err(pk_error))?;
+            let mpc_pk = Reveal::from_public(pk);
+            // The prover's r and s are public (see `create_random_proof`), so every server must
+            // draw the same ones.
+            let rng = &mut StdRng::from_seed(common_seed()?);
+            let proof = crate::groth::prover::create_random_proof::<MpcE<E, S>, _, _>(
+                job.circuit.r1cs(Some(witness)),
+                &mpc_pk,
+                rng,
+            )
+            .unwrap();
+            client::output_message::<MpcFr<E, S>, _>(&proof)
+        }
+        ProofSystem::Marlin => {
+            let pk = agree(load::<IndexProverKey<E::Fr, MarlinPc<E>>>(&pk_path).map_err(pk_error))?;
+            let mpc_pk = IndexProverKey::from_public(pk);
+            let zk_rng = &mut rand::thread_rng();
+            let proof = KzgMarlin::<MpcFr<E, S>, MpcE<E, S>>::prove(
+                &mpc_pk,
+                job.circuit.r1cs(Some(witness)),
+                zk_rng,
+            )
+            .unwrap();
+            client::output_message::<MpcFr<E, S>, _>(&proof)
+        }
+        ProofSystem::Plonk => {
+            let pk = agree(load::<PlonkProverKey<E>>(&pk_path).map_err(pk_error))?;
+            let mpc_pk = Reveal::from_public(pk);
+            let zk_rng = &mut rand::thread_rng();
+            let proof = MarlinPcPlonk::<MpcFr<E, S>, MpcE<E, S>>::prove(
+                &mpc_pk,
+                &job.circuit.plonk(Some(witness)),
+                zk_rng,
+            );
+            client::output_message::<MpcFr<E, S>, _>(&proof)
+        }
+    };
+    Ok(output)
+}
+
+/// `ours` if every server has what it needs, and otherwise the first server's problem.
+fn agree<T>(ours: Result<T, String>) -> Result<T, NotProved> {
+    let problems: Vec<Option<String>> = Net::broadcast(&ours.as_ref().err().cloned())?;
+    match problems.into_iter().enumerate().find_map(|(i, p)| p.map(|p| (i, p))) {
+        Some((server, problem)) => {
+            Err(NotProved::Rejected(format!("server {}: {}", server, problem)))
+        }
+        None => ours.map_err(NotProved::Rejected),
+    }
+}
+
+/// A seed that every server gets, from all of them.
+fn common_seed() -> Result<[u8; 32], MpcNetError> {
+    let ours: [u8; 32] = rand::thread_rng().gen();
+    let all = Net::broadcast_bytes(&ours)?;
+    let mut seed = [0u8; 32];
+    for (party, theirs) in all.into_iter().enumerate() {
+        if theirs.len() != seed.len() {
+            return Err(MpcNetError::Malformed { party });
+        }
+        seed.iter_mut().zip(theirs).for_each(|(s, t)| *s ^= t);
+    }
+    Ok(seed)
+}
+
+/// The id of the next job, which every server gets from `session` (one that lasts across jobs,
+/// like the default session): the highest of the servers' `next`, the id each expects next.
+///
+/// Servers pick each job's preprocessing and session by its id, so they must agree on it even if
+/// one of them lost count, and a store that any server has used is never used again.
+pub fn next_job_id(session: &MpcSession, next: u64) -> Result<u64, MpcNetError> {
+    let all = session.broadcast_bytes(&next.to_le_bytes())?;
+    let mut id = 0;
+    for (party, theirs) in all.into_iter().enumerate() {
+        let theirs: [u8; 8] = theirs[..].try_into().map_err(|_| MpcNetError::Malformed { party })?;
+        id = id.max(u64::from_le_bytes(theirs));
+    }
+    Ok(id)
+}
+
+/// Why a delegated job did not give the client a valid proof.
+#[derive(Debug)]
+pub enum DelegationError {
+    /// We could not load the verifying key.
+    Key { path: PathBuf, reason: String },
+    /// The connection to a server failed, or a server sent bad shares.
+    Net(MpcNetError),
+    /// The servers turned the job down.
+    Rejected(String),
+    /// The proof does not verify.
+    Invalid,
+}
+
+impl fmt::Display for DelegationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DelegationError::Key { path, reason } => {
+                write!(f, "cannot load {}: {}", path.display(), reason)
+            }
+            DelegationError::Net(e) => write!(f, "{}", e),
+            DelegationError::Rejected(reason) => write!(f, "the servers turned the job down: {}", reason),
+            DelegationError::Invalid => write!(f, "the proof does not verify"),
+        }
+    }
+}
+
+impl std::error::Error for DelegationError {}
+
+impl From<MpcNetError> for DelegationError {
+    fn from(e: MpcNetError) -> Self {
+        DelegationError::Net(e)
+    }
+}
+
+/// At the client: have the servers at `parties` (as from [`mpc_net::client::read_hosts`]) prove
+/// `job` for `witness`, and verify the proof with the verifying key in `dir`. `S` and
+/// `threshold` must be as the servers have them. The connections are secured with Noise, with
+/// `key`, our secret key.
+pub fn delegate<E: PairingEngine, S: PairingShare<E>>(
+    dir: &Path,
+    parties: &[(SocketAddr, Option<PublicKey>)],
+    key: &SecretKey,
+    job: &Job,
+    witness: &[E::Fr],
+    threshold: Option<usize>,
+) -> Result<(), DelegationError>
+where
+    S::FrShare: ClientShare<Base = E::Fr>,
+{
+    let vk_path = key_path(dir, job, "vk");
+    let vk_error = |e: SerializationError| DelegationError::Key {
+        path: vk_path.clone(),
+        reason: e.to_string(),
+    };
+    let public_inputs = job.circuit.public_inputs(witness);
+    client::send_to_parties_with::<MpcFr<E, S>, _>(parties, job, witness, threshold, key)?;
+    let mut outputs = Vec::new();
+    for (party, reply) in mpc_net::client::recv_from_parties(parties, key)?
+        .into_iter()
+        .enumerate()
+    {
+        let mut rest = &reply[..];
+        match Option::<String>::deserialize(&mut rest) {
+            Ok(None) => outputs.push(rest.to_vec()),
+            Ok(Some(reason)) => return Err(DelegationError::Rejected(reason)),
+            Err(_) => return Err(MpcNetError::Malformed { party }.into()),
+        }
+    }
+    let valid = match job.system {
+        ProofSystem::Groth16 => {
+            let vk = load::<ark_groth16::VerifyingKey<E>>(&vk_path).map_err(vk_error)?;
+            let proof = client::from_output_messages::<
+                MpcFr<E, S>,
+                ark_groth16::Proof<MpcE<E, S>>,
+            >(&outputs)?;
+            verify_proof(&prepare_verifying_key(&vk), &proof, &public_inputs).unwrap_or(false)
+        }
+        ProofSystem::Marlin => {
+            let vk = load::<IndexVerifierKey<E::Fr, MarlinPc<E>>>(&vk_path).map_err(vk_error)?;
+            let proof = client::from_output_messages::<
+                MpcFr<E, S>,
+                ark_marlin::Proof<MpcFr<E, S>, MarlinKZG10<MpcE<E, S>, DensePolynomial<MpcFr<E, S>>>>,
+            >(&outputs)?;
+            let rng = &mut rand::thread_rng();
+            KzgMarlin::<E::Fr, E>::verify(&vk, &public_inputs, &proof, rng).unwrap_or(false)
+        }
+        ProofSystem::Plonk => {
+            let vk = load::<PlonkVerifierKey<E>>(&vk_path).map_err(vk_error)?;
+            type MpcPlonkProof<E, S> = mpc_plonk::Proof<
+                MpcFr<E, S>,
+                <MarlinKZG10<MpcE<E, S>, DensePolynomial<MpcFr<E, S>>> as PolynomialCommitment<
+                    MpcFr<E, S>,
+                    DensePolynomial<MpcFr<E, S>>,
+                >>::Commitment,
+                <MarlinKZG10<MpcE<E, S>, DensePolynomial<MpcFr<E, S>>> as PolynomialCommitment<
+                    MpcFr<E, S>,
+                    DensePolynomial<MpcFr<E, S>>,
+                >>::Proof,
+            >;
+            let proof = client::from_output_messages::<MpcFr<E, S>, MpcPlonkProof<E, S>>(&outputs)?;
+            let circuit = job.circuit.plonk::<E::Fr>(None);
+            let public = job.circuit.plonk_public_inputs(witness);
+            MarlinPcPlonk::<E::Fr, E>::verify(&vk, &circuit, proof, &public).is_ok()
+        }
+    };
+    if valid {
+        Ok(())
+    } else {
+        Err(DelegationError::Invalid)
+    }
+}
+
+/// Proves knowledge of `x` such that `x^(2^n)` is the public output.
+#[derive(Clone)]
+struct SquaringCircuit<F: Field> {
+    chain: Vec<Option<F>>,
+}
+
+impl<F: Field> ConstraintSynthesizer<F> for SquaringCircuit<F> {
+    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+        let squarings = self.chain.len() - 1;
+        let mut vars: Vec<Variable> = self
+            .chain
+            .iter()
+            .take(squarings)
+            .map(|o| cs.new_witness_variable(|| o.ok_or(SynthesisError::AssignmentMissing)))
+            .collect::<Result<_, _>>()?;
+        vars.push(cs.new_input_variable(|| {
+            self.chain
+                .last()
+                .unwrap()
+                .ok_or(SynthesisError::AssignmentMissing)
+        })?);
+        for i in 0..squarings {
+            cs.enforce_constraint(lc!() + vars[i], lc!() + vars[i], lc!() + vars[i + 1])?;
+        }
+        Ok(())
+    }
+}
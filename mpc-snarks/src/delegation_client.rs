@@ -0,0 +1,89 @@
+//! A client that has the delegation servers prove a job for a secret witness, and verifies the
+//! proof (see [`mpc_snarks::delegation`]).
+use ark_ff::UniformRand;
+use mpc_algebra::client::ClientShare;
+use mpc_algebra::PairingShare;
+use mpc_net::noise::SecretKey;
+use mpc_snarks::delegation::{self, Circuit, Job, MpcAlg, ProofSystem};
+use structopt::StructOpt;
+
+use std::path::PathBuf;
+use std::time::Instant;
+
+type E = ark_bls12_377::Bls12_377;
+type Fr = <E as ark_ec::PairingEngine>::Fr;
+
+#[derive(Debug, StructOpt)]
+#[structopt(name = "delegation_client", about = "Have the servers prove a job, and verify the proof")]
+struct Opt {
+    /// File with list of hosts: the servers
+    #[structopt(long, parse(from_os_str))]
+    hosts: PathBuf,
+
+    /// Our secret key, to secure the connections with Noise. The hosts file must list each
+    /// server's public key
+    #[structopt(long, parse(from_os_str))]
+    key: PathBuf,
+
+    /// The servers' algorithm
+    #[structopt(long)]
+    alg: MpcAlg,
+
+    /// The servers' --threshold, for rss
+    #[structopt(long)]
+    threshold: Option<usize>,
+
+    /// Directory holding the verifying keys
+    #[structopt(long, parse(from_os_str))]
+    keys: PathBuf,
+
+    /// Proof system
+    #[structopt(short = "p")]
+    proof_system: ProofSystem,
+
+    /// Circuit, like squaring-10. The witness is for a random secret
+    #[structopt(short = "c")]
+    circuit: Circuit,
+}
+
+impl Opt {
+    fn delegate<S: PairingShare<E>>(&self) -> Result<(), delegation::DelegationError>
+    where
+        S::FrShare: ClientShare<Base = Fr>,
+    {
+        let job = Job {
+            system: self.proof_system,
+            circuit: self.circuit,
+        };
+        let witness = job.circuit.witness(Fr::rand(&mut rand::thread_rng()));
+        let parties = mpc_net::client::read_hosts(self.hosts.to_str().unwrap());
+        let key = SecretKey::from_file(&self.key);
+        delegation::delegate::<E, S>(&self.keys, &parties, &key, &job, &witness, self.threshold)
+    }
+}
+
+fn main() {
+    env_logger::builder().format_timestamp(None).init();
+    let opt = Opt::from_args();
+    let start = Instant::now();
+    use mpc_algebra::share::{add, gsz20, rss, rss3, spdz};
+    let result = match opt.alg {
+        MpcAlg::Spdz => opt.delegate::<spdz::SpdzPairingShare<E>>(),
+        MpcAlg::Hbc => opt.delegate::<add::AdditivePairingShare<E>>(),
+        MpcAlg::Gsz => opt.delegate::<gsz20::GszPairingShare<E>>(),
+        MpcAlg::Rss3 => opt.delegate::<rss3::RSS3PairingShare<E>>(),
+        MpcAlg::Rss => opt.delegate::<rss::RssPairingShare<E>>(),
+    };
+    match result {
+        Ok(()) => println!(
+            "Verified the {} proof for {} in {:?}",
+            opt.proof_system,
+            opt.circuit,
+            start.elapsed()
+        ),
+        Err(e) => {
+            eprintln!("Delegation failed: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
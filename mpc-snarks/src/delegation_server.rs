@@ -0,0 +1,193 @@
+//! One of the servers that prove jobs for clients (see [`mpc_snarks::delegation`]), or the setup
+//! that writes their keys.
+use ark_ec::PairingEngine;
+use log::{error, warn};
+use mpc_algebra::client::ClientShare;
+use mpc_algebra::preprocessing::FieldPreprocessing;
+use mpc_algebra::PairingShare;
+use mpc_net::noise::{PublicKey, SecretKey};
+use mpc_net::multi::DEFAULT_SESSION;
+use mpc_net::{MpcMultiNet, MpcNet, MpcNetError, Topology, Transport};
+use mpc_snarks::delegation::{self, Circuit, Job, MpcAlg, ProofSystem};
+use structopt::StructOpt;
+
+use std::path::PathBuf;
+use std::time::Instant;
+
+type E = ark_bls12_377::Bls12_377;
+
+#[derive(Debug, StructOpt)]
+#[structopt(name = "delegation_server", about = "Prove jobs for clients, together with the other servers")]
+enum Opt {
+    /// Write the proving and verifying keys for a job to the key directory
+    Setup {
+        /// Directory to write the keys to
+        #[structopt(long, parse(from_os_str))]
+        keys: PathBuf,
+
+        /// Proof system
+        #[structopt(short = "p")]
+        proof_system: ProofSystem,
+
+        /// Circuit, like squaring-10
+        #[structopt(short = "c")]
+        circuit: Circuit,
+    },
+    /// Serve jobs until stopped
+    Serve {
+        /// File with list of hosts
+        #[structopt(long, parse(from_os_str))]
+        hosts: PathBuf,
+
+        /// Which server are you?
+        #[structopt(long, default_value = "0")]
+        party: u8,
+
+        /// Secret key: authenticate and encrypt connections with Noise, to the other servers and
+        /// the clients. The hosts file must list each server's public key after its address
+        #[structopt(long, parse(from_os_str))]
+        key: PathBuf,
+
+        /// The client's public key
+        #[structopt(long)]
+        client_key: PublicKey,
+
+        /// How the servers connect: mesh (everyone to everyone) or star (everyone through server 0)
+        #[structopt(long, default_value = "mesh")]
+        topology: Topology,
+
+        /// MPC algorithm
+        #[structopt(long)]
+        alg: MpcAlg,
+
+        /// Threshold for rss: how many servers may collude. Defaults to the most below half
+        #[structopt(long)]
+        threshold: Option<usize>,
+
+        /// Directory holding the proving keys
+        #[structopt(long, parse(from_os_str))]
+        keys: PathBuf,
+
+        /// Directory holding the dealt preprocessing, which spdz and hbc need: one directory per
+        /// job, named 0, 1, ..., as the deal subcommand of proof writes them
+        #[structopt(long, parse(from_os_str))]
+        preprocessing: Option<PathBuf>,
+
+        /// Stop after this many jobs
+        #[structopt(long)]
+        jobs: Option<usize>,
+    },
+}
+
+/// What every job needs, besides the keys.
+struct Server {
+    keys: PathBuf,
+    preprocessing: Option<PathBuf>,
+    party: usize,
+    hosts: PathBuf,
+    secret_key: SecretKey,
+    client_key: PublicKey,
+    threshold: Option<usize>,
+    jobs: Option<usize>,
+}
+
+impl Server {
+    fn serve<S: PairingShare<E>>(&self)
+    where
+        S::FrShare: ClientShare<Base = <E as PairingEngine>::Fr>,
+    {
+        let (addr, _) = mpc_net::client::read_hosts(self.hosts.to_str().unwrap())[self.party];
+        let keys = (&self.secret_key, &self.client_key);
+        // Session 0 is the default one, which we agree on job ids in.
+        let default = MpcMultiNet::session(DEFAULT_SESSION);
+        let mut served = 0;
+        let mut next = 0;
+        while self.jobs.is_none_or(|jobs| served < jobs) {
+            let id = delegation::next_job_id(&default, next)
+                .unwrap_or_else(|e| panic!("Lost the other servers: {}", e));
+            next = id + 1;
+            let store = self.preprocessing.as_ref().map(|dir| {
+                let path = FieldPreprocessing::<<E as PairingEngine>::Fr, S::FrShare>::store_path(
+                    &dir.join(id.to_string()),
+                    self.party,
+                );
+                FieldPreprocessing::load(&path).unwrap_or_else(|e| {
+                    panic!("Could not load the preprocessing from {}: {}", path.display(), e)
+                })
+            });
+            let start = Instant::now();
+            let session = MpcMultiNet::session(id + 1);
+            if let Some(t) = self.threshold {
+                session.enter(|| mpc_algebra::share::rss::prss::set_threshold(t));
+            }
+            let result = delegation::serve::<E, S>(&session, &self.keys, store, addr, keys);
+            session.end();
+            match result {
+                Ok(job) => println!("Served {} as job {} in {:?}", job, id, start.elapsed()),
+                Err(e @ MpcNetError::Client { .. }) => warn!("No job {}: {}", id, e),
+                Err(e) => error!("Job {} failed: {}", id, e),
+            }
+            served += 1;
+        }
+    }
+}
+
+fn main() {
+    env_logger::builder().format_timestamp(None).init();
+    match Opt::from_args() {
+        Opt::Setup {
+            keys,
+            proof_system,
+            circuit,
+        } => {
+            let job = Job {
+                system: proof_system,
+                circuit,
+            };
+            delegation::setup::<E>(&keys, &job)
+                .unwrap_or_else(|e| panic!("Could not write the keys to {}: {}", keys.display(), e));
+            println!("Wrote the keys for {} to {}", job, keys.display());
+        }
+        Opt::Serve {
+            hosts,
+            party,
+            key,
+            client_key,
+            topology,
+            alg,
+            threshold,
+            keys,
+            preprocessing,
+            jobs,
+        } => {
+            MpcMultiNet::init_with_topology(
+                hosts.to_str().unwrap(),
+                party as usize,
+                Transport::Noise {
+                    secret_key: key.clone(),
+                },
+                topology,
+            )
+            .unwrap_or_else(|e| panic!("Could not connect: {}", e));
+            let server = Server {
+                keys,
+                preprocessing,
+                party: party as usize,
+                hosts,
+                secret_key: SecretKey::from_file(&key),
+                client_key,
+                threshold,
+                jobs,
+            };
+            use mpc_algebra::share::{add, gsz20, rss, rss3, spdz};
+            match alg {
+                MpcAlg::Spdz => server.serve::<spdz::SpdzPairingShare<E>>(),
+                MpcAlg::Hbc => server.serve::<add::AdditivePairingShare<E>>(),
+                MpcAlg::Gsz => server.serve::<gsz20::GszPairingShare<E>>(),
+                MpcAlg::Rss3 => server.serve::<rss3::RSS3PairingShare<E>>(),
+                MpcAlg::Rss => server.serve::<rss::RssPairingShare<E>>(),
+            }
+            MpcMultiNet::deinit();
+        }
+    }
+}
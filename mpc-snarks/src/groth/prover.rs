@@ -93,13 +93,16 @@ where
     end_timer!(lc_time);
 
     let witness_map_time = start_timer!(|| "R1CS to QAP witness map");
+    let phase = mpc_net::phase("witness map");
     let h = R1CStoQAP::witness_map::<<E as PairingEngine>::Fr, D<<E as PairingEngine>::Fr>>(
         cs.clone(),
     )?;
+    drop(phase);
     end_timer!(witness_map_time);
 
     let prover_crypto_time = start_timer!(|| "crypto");
     let c_acc_time = start_timer!(|| "Compute C");
+    let phase = mpc_net::phase("compute C");
     let h_acc = <<E as PairingEngine>::G1Affine as AffineCurve>::multi_scalar_mul(&pk.h_query, &h);
     // Compute C
     let prover = cs.borrow().unwrap();
@@ -111,6 +114,7 @@ where
         .scalar_mul(&r)
         .scalar_mul(&s);
 
+    drop(phase);
     end_timer!(c_acc_time);
 
     let assignment: Vec<<E as PairingEngine>::Fr> = prover.instance_assignment[1..].iter().chain(prover.witness_assignment.iter()).cloned().collect();
@@ -119,13 +123,15 @@ where
 
     // Compute A
     let a_acc_time = start_timer!(|| "Compute A");
+    let phase = mpc_net::phase("compute A");
     let r_g1 = pk.delta_g1.scalar_mul(r);
     let g_a = calculate_coeff(r_g1, &pk.a_query, pk.vk.alpha_g1, &assignment);
     let s_g_a = g_a.scalar_mul(&s);
+    drop(phase);
     end_timer!(a_acc_time);
 
     // Compute B in G1 if needed
-
+    let phase = mpc_net::phase("compute B");
     let b_g1_acc_time = start_timer!(|| "Compute B in G1");
     let s_g1 = pk.delta_g1.scalar_mul(s);
     let g1_b = calculate_coeff(s_g1, &pk.b_g1_query, pk.beta_g1, &assignment);
@@ -137,15 +143,18 @@ where
     let g2_b = calculate_coeff(s_g2, &pk.b_g2_query, pk.vk.beta_g2, &assignment);
     let r_g1_b = g1_b.scalar_mul(&r);
     drop(assignment);
+    drop(phase);
 
     end_timer!(b_g2_acc_time);
 
     let c_time = start_timer!(|| "Finish C");
+    let phase = mpc_net::phase("finish C");
     let mut g_c = s_g_a;
     g_c += &r_g1_b;
     g_c -= &r_s_delta_g1;
     g_c += &l_aux_acc;
     g_c += &h_acc;
+    drop(phase);
     end_timer!(c_time);
     end_timer!(prover_crypto_time);
 
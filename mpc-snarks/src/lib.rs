@@ -1,3 +1,12 @@
+//! The MPC provers that the `proof` and `client` binaries share, for use in tests, delegated
+//! proving for the `delegation_server` and `delegation_client` binaries, circuits loaded from
+//! files, and witnesses computed inside the MPC.
+pub mod delegation;
+pub mod groth;
+pub mod r1cs_file;
+pub mod witness;
+mod silly;
+
 #[cfg(test)]
 mod tests {
     #[test]
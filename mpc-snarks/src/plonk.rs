@@ -1,5 +1,6 @@
 use ark_ff::UniformRand;
 use ark_poly::univariate::DensePolynomial;
+use ark_poly::EvaluationDomain;
 use ark_poly_commit::marlin_pc::MarlinKZG10;
 use ark_std::{end_timer, start_timer, test_rng};
 use mpc_algebra::honest_but_curious::*;
@@ -34,10 +35,10 @@ pub fn local_test_prove_and_verify(n_iters: usize) {
         t
     };
 
-    let srs = LocalPlonk::universal_setup(steps.next_power_of_two(), setup_rng);
+    let srs = LocalPlonk::universal_setup(v_circ.domains.gates.size(), setup_rng);
     let (pk, vk) = LocalPlonk::circuit_setup(&srs, &v_circ);
     let pf = LocalPlonk::prove(&pk, &circ, zk_rng);
-    LocalPlonk::verify(&vk, &v_circ, pf, &public);
+    LocalPlonk::verify(&vk, &v_circ, pf, &public).unwrap();
 }
 
 pub fn mpc_test_prove_and_verify(n_iters: usize) {
@@ -49,7 +50,7 @@ pub fn mpc_test_prove_and_verify(n_iters: usize) {
     let v_circ = CircuitLayout::from_circuit(&v_c);
     // setup
     let setup_rng = &mut test_rng();
-    let srs = LocalPlonk::universal_setup(steps.next_power_of_two(), setup_rng);
+    let srs = LocalPlonk::universal_setup(v_circ.domains.gates.size(), setup_rng);
     let (pk, vk) = LocalPlonk::circuit_setup(&srs, &v_circ);
 
     // data circuit
@@ -67,5 +68,5 @@ pub fn mpc_test_prove_and_verify(n_iters: usize) {
     let mpc_pf = MpcPlonk::prove(&mpc_pk, &circ, &mut test_rng());
     let pf = mpc_pf.reveal();
     end_timer!(t);
-    LocalPlonk::verify(&vk, &v_circ, pf, &public);
+    LocalPlonk::verify(&vk, &v_circ, pf, &public).unwrap();
 }
@@ -1,7 +1,7 @@
 #![allow(dead_code)]
 #![allow(unused_imports)]
 use ark_ec::PairingEngine;
-use ark_ff::{Field, UniformRand};
+use ark_ff::{FftField, Field, PrimeField, UniformRand, Zero};
 use ark_groth16;
 use ark_relations::{
     lc,
@@ -12,11 +12,20 @@ use ark_std::{end_timer, start_timer};
 use blake2::Blake2s;
 use clap::arg_enum;
 use log::debug;
-use mpc_algebra::{channel, MpcPairingEngine, PairingShare, Reveal};
-use mpc_net::{MpcMultiNet, MpcNet, MpcTwoNet};
+use rand::Rng;
+use mpc_algebra::client::{self as input_client, ClientShare};
+use mpc_algebra::preprocessing::{self, DealtFieldShare, FieldPreprocessing, PreprocessingCounts};
+use mpc_algebra::share::gsz20::preprocessing::{
+    self as gsz_preprocessing, GszPreprocessing, GszPreprocessingCounts,
+};
+use mpc_algebra::{channel, FieldShare, MpcField, MpcPairingEngine, PairingShare, Reveal};
+use mpc_net::noise::{PublicKey, SecretKey};
+use mpc_net::shape::{parse_bandwidth, parse_duration};
+use mpc_net::{LinkShape, MpcMultiNet, MpcNet, MpcTwoNet, Topology, Transport};
 use structopt::StructOpt;
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 mod groth;
 mod marlin;
@@ -25,15 +34,29 @@ mod silly;
 const TIMED_SECTION_LABEL: &str = "timed section";
 
 trait SnarkBench {
-    fn local<E: PairingEngine>(n: usize, timer_label: &str);
-    fn ark_local<E: PairingEngine>(_n: usize, _timer_label: &str) {
+    fn local<E: PairingEngine>(w: &Workload, timer_label: &str);
+    fn ark_local<E: PairingEngine>(_w: &Workload, _timer_label: &str) {
         unimplemented!("ark benchmark for {}", std::any::type_name::<Self>())
     }
-    fn mpc<E: PairingEngine, S: PairingShare<E>>(n: usize, timer_label: &str);
+    fn mpc<E: PairingEngine, S: PairingShare<E>>(w: &Workload, timer_label: &str);
+}
+
+/// What the benches prove: `size` squarings, or the constraints in an R1CS file.
+#[derive(Debug, Clone)]
+struct Workload {
+    computation: Computation,
+    size: usize,
+    r1cs: Option<PathBuf>,
+    witness: Option<PathBuf>,
+    /// Share only the squarings' start, and square it inside the MPC.
+    extend: bool,
 }
 
 mod squarings {
     use super::*;
+    use mpc_algebra::share::lazy;
+    use mpc_snarks::r1cs_file::{self, R1cs, R1csCircuit};
+    use mpc_snarks::witness::{extend_witness, ExtendedCircuit};
     #[derive(Clone)]
     struct RepeatedSquaringCircuit<F: Field> {
         chain: Vec<Option<F>>,
@@ -45,15 +68,6 @@ mod squarings {
                 chain: vec![None; squarings + 1],
             }
         }
-        fn from_start(f: F, squarings: usize) -> Self {
-            let mut chain = vec![Some(f)];
-            for _ in 0..squarings {
-                let mut last = chain.last().unwrap().as_ref().unwrap().clone();
-                last.square_in_place();
-                chain.push(Some(last));
-            }
-            Self { chain }
-        }
         fn from_chain(f: Vec<F>) -> Self {
             Self {
                 chain: f.into_iter().map(Some).collect(),
@@ -64,6 +78,22 @@ mod squarings {
         }
     }
 
+    /// The squarings of `start`, which it computes as it synthesizes.
+    struct SquaringsFromStart<F: Field> {
+        start: F,
+        squarings: usize,
+    }
+
+    impl<ConstraintF: Field> ConstraintSynthesizer<ConstraintF> for SquaringsFromStart<ConstraintF> {
+        fn generate_constraints(
+            self,
+            cs: ConstraintSystemRef<ConstraintF>,
+        ) -> Result<(), SynthesisError> {
+            RepeatedSquaringCircuit::from_chain(squaring_chain(self.start, self.squarings))
+                .generate_constraints(cs)
+        }
+    }
+
     pub mod groth {
         use super::*;
         use crate::ark_groth16::{generate_random_parameters, prepare_verifying_key, verify_proof};
@@ -72,17 +102,16 @@ mod squarings {
         pub struct Groth16Bench;
 
         impl SnarkBench for Groth16Bench {
-            fn local<E: PairingEngine>(n: usize, timer_label: &str) {
+            fn local<E: PairingEngine>(w: &Workload, timer_label: &str) {
                 let rng = &mut test_rng();
-                let circ_no_data = RepeatedSquaringCircuit::without_data(n);
+                let circ_no_data = w.without_data::<E::Fr>();
 
                 let params = generate_random_parameters::<E, _, _>(circ_no_data, rng).unwrap();
 
                 let pvk = prepare_verifying_key::<E>(&params.vk);
 
-                let a = E::Fr::rand(rng);
-                let circ_data = RepeatedSquaringCircuit::from_start(a, n);
-                let public_inputs = vec![circ_data.chain.last().unwrap().unwrap()];
+                let circ_data = w.with_data::<E::Fr, _>(rng);
+                let public_inputs = circ_data.public_inputs();
                 let timer = start_timer!(|| timer_label);
                 let proof = create_random_proof::<E, _, _>(circ_data, &params, rng).unwrap();
                 end_timer!(timer);
@@ -90,17 +119,16 @@ mod squarings {
                 assert!(verify_proof(&pvk, &proof, &public_inputs).unwrap());
             }
 
-            fn ark_local<E: PairingEngine>(n: usize, timer_label: &str) {
+            fn ark_local<E: PairingEngine>(w: &Workload, timer_label: &str) {
                 let rng = &mut test_rng();
-                let circ_no_data = RepeatedSquaringCircuit::without_data(n);
+                let circ_no_data = w.without_data::<E::Fr>();
 
                 let params = generate_random_parameters::<E, _, _>(circ_no_data, rng).unwrap();
 
                 let pvk = prepare_verifying_key::<E>(&params.vk);
 
-                let a = E::Fr::rand(rng);
-                let circ_data = RepeatedSquaringCircuit::from_start(a, n);
-                let public_inputs = vec![circ_data.chain.last().unwrap().unwrap()];
+                let circ_data = w.with_data::<E::Fr, _>(rng);
+                let public_inputs = circ_data.public_inputs();
                 let timer = start_timer!(|| timer_label);
                 let proof =
                     ark_groth16::create_random_proof::<E, _, _>(circ_data, &params, rng).unwrap();
@@ -109,22 +137,18 @@ mod squarings {
                 assert!(verify_proof(&pvk, &proof, &public_inputs).unwrap());
             }
 
-            fn mpc<E: PairingEngine, S: PairingShare<E>>(n: usize, timer_label: &str) {
+            fn mpc<E: PairingEngine, S: PairingShare<E>>(w: &Workload, timer_label: &str) {
                 let rng = &mut test_rng();
-                let circ_no_data = RepeatedSquaringCircuit::without_data(n);
+                let circ_no_data = w.without_data::<E::Fr>();
 
                 let params = generate_random_parameters::<E, _, _>(circ_no_data, rng).unwrap();
 
                 let pvk = prepare_verifying_key::<E>(&params.vk);
                 let mpc_params = Reveal::from_public(params);
 
-                let a = E::Fr::rand(rng);
                 let computation_timer = start_timer!(|| "do the mpc (cheat)");
-                let circ_data = mpc_squaring_circuit::<
-                    E::Fr,
-                    <MpcPairingEngine<E, S> as PairingEngine>::Fr,
-                >(a, n);
-                let public_inputs = vec![circ_data.chain.last().unwrap().unwrap().reveal()];
+                let circ_data = w.mpc_with_data::<E::Fr, S::FrShare, _>(rng);
+                let public_inputs = circ_data.public_inputs().reveal();
                 end_timer!(computation_timer);
                 MpcMultiNet::reset_stats();
                 let timer = start_timer!(|| timer_label);
@@ -132,13 +156,17 @@ mod squarings {
                     let pf = create_random_proof::<MpcPairingEngine<E, S>, _, _>(circ_data, &mpc_params, rng)
                         .unwrap();
                     let reveal_timer = start_timer!(|| "reveal");
-                    let pf = pf.reveal();
+                    let phase = mpc_net::phase("reveal");
+                    let pf = reveal_proof(pf);
+                    drop(phase);
                     end_timer!(reveal_timer);
                     pf
                 });
                 end_timer!(timer);
 
-                assert!(verify_proof(&pvk, &proof, &public_inputs).unwrap());
+                if let Some(proof) = proof {
+                    assert!(verify_proof(&pvk, &proof, &public_inputs).unwrap());
+                }
             }
         }
     }
@@ -155,17 +183,19 @@ mod squarings {
         pub struct MarlinBench;
 
         impl SnarkBench for MarlinBench {
-            fn local<E: PairingEngine>(n: usize, timer_label: &str) {
+            fn local<E: PairingEngine>(w: &Workload, timer_label: &str) {
                 let rng = &mut test_rng();
-                let circ_no_data = RepeatedSquaringCircuit::without_data(n);
+                let circ_no_data = w.without_data::<E::Fr>();
 
-                let srs = KzgMarlin::<E::Fr, E>::universal_setup(n, n + 2, 3 * n, rng).unwrap();
+                let (constraints, variables, non_zero) = w.marlin_bounds::<E::Fr>();
+                let srs =
+                    KzgMarlin::<E::Fr, E>::universal_setup(constraints, variables, non_zero, rng)
+                        .unwrap();
 
                 let (pk, vk) = KzgMarlin::<E::Fr, E>::index(&srs, circ_no_data).unwrap();
 
-                let a = E::Fr::rand(rng);
-                let circ_data = RepeatedSquaringCircuit::from_start(a, n);
-                let public_inputs = vec![circ_data.chain.last().unwrap().unwrap()];
+                let circ_data = w.with_data::<E::Fr, _>(rng);
+                let public_inputs = circ_data.public_inputs();
                 let timer = start_timer!(|| timer_label);
                 let zk_rng = &mut test_rng();
                 let proof = KzgMarlin::<E::Fr, E>::prove(&pk, circ_data, zk_rng).unwrap();
@@ -173,37 +203,39 @@ mod squarings {
                 assert!(KzgMarlin::<E::Fr, E>::verify(&vk, &public_inputs, &proof, rng).unwrap());
             }
 
-            fn mpc<E: PairingEngine, S: PairingShare<E>>(n: usize, timer_label: &str) {
+            fn mpc<E: PairingEngine, S: PairingShare<E>>(w: &Workload, timer_label: &str) {
                 let rng = &mut test_rng();
-                let circ_no_data = RepeatedSquaringCircuit::without_data(n);
+                let circ_no_data = w.without_data::<E::Fr>();
 
-                let srs = KzgMarlin::<E::Fr, E>::universal_setup(n, n + 2, 3 * n, rng).unwrap();
+                let (constraints, variables, non_zero) = w.marlin_bounds::<E::Fr>();
+                let srs =
+                    KzgMarlin::<E::Fr, E>::universal_setup(constraints, variables, non_zero, rng)
+                        .unwrap();
 
                 let (pk, vk) = KzgMarlin::<E::Fr, E>::index(&srs, circ_no_data).unwrap();
                 let mpc_pk = IndexProverKey::from_public(pk);
 
-                let a = E::Fr::rand(rng);
                 let computation_timer = start_timer!(|| "do the mpc (cheat)");
-                let circ_data = mpc_squaring_circuit::<
-                    E::Fr,
-                    <MpcPairingEngine<E, S> as PairingEngine>::Fr,
-                >(a, n);
-                let public_inputs = vec![circ_data.chain.last().unwrap().unwrap().reveal()];
+                let circ_data = w.mpc_with_data::<E::Fr, S::FrShare, _>(rng);
+                let public_inputs = circ_data.public_inputs().reveal();
                 end_timer!(computation_timer);
 
                 MpcMultiNet::reset_stats();
                 let timer = start_timer!(|| timer_label);
                 let zk_rng = &mut test_rng();
                 let proof = channel::without_cheating(|| {
-                    KzgMarlin::<
+                    let pf = KzgMarlin::<
                         <MpcPairingEngine<E, S> as PairingEngine>::Fr,
                         MpcPairingEngine<E, S>,
                     >::prove(&mpc_pk, circ_data, zk_rng)
-                    .unwrap()
-                    .reveal()
+                    .unwrap();
+                    let _phase = mpc_net::phase("reveal");
+                    reveal_proof(pf)
                 });
                 end_timer!(timer);
-                assert!(KzgMarlin::<E::Fr, E>::verify(&vk, &public_inputs, &proof, rng).unwrap());
+                if let Some(proof) = proof {
+                    assert!(KzgMarlin::<E::Fr, E>::verify(&vk, &public_inputs, &proof, rng).unwrap());
+                }
             }
         }
     }
@@ -221,17 +253,19 @@ mod squarings {
 
         impl SnarkBench for MarlinMalBench {
 
-            fn local<E: PairingEngine>(n: usize, timer_label: &str) {
+            fn local<E: PairingEngine>(w: &Workload, timer_label: &str) {
                 let rng = &mut test_rng();
-                let circ_no_data = RepeatedSquaringCircuit::without_data(n);
+                let circ_no_data = w.without_data::<E::Fr>();
 
-                let srs = KzgMarlin::<E::Fr, E>::universal_setup(n, n + 2, 3 * n, rng).unwrap();
+                let (constraints, variables, non_zero) = w.marlin_bounds::<E::Fr>();
+                let srs =
+                    KzgMarlin::<E::Fr, E>::universal_setup(constraints, variables, non_zero, rng)
+                        .unwrap();
 
                 let (pk, vk) = KzgMarlin::<E::Fr, E>::index(&srs, circ_no_data).unwrap();
 
-                let a = E::Fr::rand(rng);
-                let circ_data = RepeatedSquaringCircuit::from_start(a, n);
-                let public_inputs = vec![circ_data.chain.last().unwrap().unwrap()];
+                let circ_data = w.with_data::<E::Fr, _>(rng);
+                let public_inputs = circ_data.public_inputs();
                 let timer = start_timer!(|| timer_label);
                 let zk_rng = &mut test_rng();
                 let (proof, _) = KzgMarlin::<E::Fr, E>::prove(&pk, circ_data, zk_rng).unwrap();
@@ -243,42 +277,48 @@ mod squarings {
                 assert!(KzgMarlin::<E::Fr, E>::verify(&vk, &public_inputs, &proof, rng).unwrap());
             }
 
-            fn mpc<E: PairingEngine, S: PairingShare<E>>(n: usize, timer_label: &str) {
+            fn mpc<E: PairingEngine, S: PairingShare<E>>(w: &Workload, timer_label: &str) {
                 let rng = &mut test_rng();
-                let circ_no_data = RepeatedSquaringCircuit::without_data(n);
+                let circ_no_data = w.without_data::<E::Fr>();
 
-                let srs = KzgMarlin::<E::Fr, E>::universal_setup(n, n + 2, 3 * n, rng).unwrap();
+                let (constraints, variables, non_zero) = w.marlin_bounds::<E::Fr>();
+                let srs =
+                    KzgMarlin::<E::Fr, E>::universal_setup(constraints, variables, non_zero, rng)
+                        .unwrap();
 
                 let (pk, vk) = KzgMarlin::<E::Fr, E>::index(&srs, circ_no_data).unwrap();
                 let mpc_pk = IndexProverKey::from_public(pk);
 
-                let a = E::Fr::rand(rng);
                 let computation_timer = start_timer!(|| "do the mpc (cheat)");
-                let (circ_data, circ_data_with_plain_witness) = mpc_squaring_circuit_with_plain_witness::<
+                let (circ_data, circ_data_with_plain_witness) = w.mpc_with_plain_witness::<
                     E::Fr,
                     <MpcPairingEngine<E, S> as PairingEngine>::Fr,
-                >(a, n);
-                let public_inputs = vec![circ_data.chain.last().unwrap().unwrap().reveal()];
+                    _,
+                >(rng);
+                let public_inputs = circ_data.public_inputs().reveal();
                 end_timer!(computation_timer);
 
                 MpcMultiNet::reset_stats();
                 let timer = start_timer!(|| timer_label);
                 let zk_rng = &mut test_rng();
                 let zk_rng2 = &mut test_rng();
-                let (proof, consistency_check_proof) = channel::without_cheating(|| {
-                    KzgMarlin::<
-                        <MpcPairingEngine<E, S> as PairingEngine>::Fr,
-                        MpcPairingEngine<E, S>,
-                    >::prove(&mpc_pk, circ_data, zk_rng)
-                    .unwrap()
-                    .reveal()
+                let proofs = channel::without_cheating(|| {
+                    reveal_proof(
+                        KzgMarlin::<
+                            <MpcPairingEngine<E, S> as PairingEngine>::Fr,
+                            MpcPairingEngine<E, S>,
+                        >::prove(&mpc_pk, circ_data, zk_rng)
+                        .unwrap(),
+                    )
                 });
                 end_timer!(timer);
 
-                // Consistency Check
-                assert!(KzgMarlin::<E::Fr, E>::consistency_check(&vk, &public_inputs, circ_data_with_plain_witness, &consistency_check_proof, &proof, zk_rng2).unwrap());
+                if let Some((proof, consistency_check_proof)) = proofs {
+                    // Consistency Check
+                    assert!(KzgMarlin::<E::Fr, E>::consistency_check(&vk, &public_inputs, circ_data_with_plain_witness, &consistency_check_proof, &proof, zk_rng2).unwrap());
 
-                assert!(KzgMarlin::<E::Fr, E>::verify(&vk, &public_inputs, &proof, rng).unwrap());
+                    assert!(KzgMarlin::<E::Fr, E>::verify(&vk, &public_inputs, &proof, rng).unwrap());
+                }
             }
         }
     }
@@ -329,17 +369,19 @@ mod squarings {
         // }
 
         impl SnarkBench for MarlinMalRssCheckBench {
-            fn local<E: PairingEngine>(n: usize, timer_label: &str) {
+            fn local<E: PairingEngine>(w: &Workload, timer_label: &str) {
                 let rng = &mut test_rng();
-                let circ_no_data = RepeatedSquaringCircuit::without_data(n);
+                let circ_no_data = w.without_data::<E::Fr>();
 
-                let srs = KzgMarlin::<E::Fr, E>::universal_setup(n, n + 2, 3 * n, rng).unwrap();
+                let (constraints, variables, non_zero) = w.marlin_bounds::<E::Fr>();
+                let srs =
+                    KzgMarlin::<E::Fr, E>::universal_setup(constraints, variables, non_zero, rng)
+                        .unwrap();
 
                 let (pk, vk) = KzgMarlin::<E::Fr, E>::index(&srs, circ_no_data).unwrap();
 
-                let a = E::Fr::rand(rng);
-                let circ_data = RepeatedSquaringCircuit::from_start(a, n);
-                let public_inputs = vec![circ_data.chain.last().unwrap().unwrap()];
+                let circ_data = w.with_data::<E::Fr, _>(rng);
+                let public_inputs = circ_data.public_inputs();
                 let timer = start_timer!(|| timer_label);
                 let zk_rng = &mut test_rng();
                 let proof = KzgMarlin::<E::Fr, E>::prove(&pk, circ_data, zk_rng).unwrap();
@@ -347,48 +389,46 @@ mod squarings {
                 assert!(KzgMarlin::<E::Fr, E>::verify(&vk, &public_inputs, &proof, rng).unwrap());
             }
 
-            fn mpc<E: PairingEngine, S: PairingShare<E>>(n: usize, timer_label: &str) {
+            fn mpc<E: PairingEngine, S: PairingShare<E>>(w: &Workload, timer_label: &str) {
                 let rng = &mut test_rng();
-                let circ_no_data = RepeatedSquaringCircuit::without_data(n);
+                let circ_no_data = w.without_data::<E::Fr>();
 
-                let srs = KzgMarlin::<E::Fr, E>::universal_setup(n, n + 2, 3 * n, rng).unwrap();
+                let (constraints, variables, non_zero) = w.marlin_bounds::<E::Fr>();
+                let srs =
+                    KzgMarlin::<E::Fr, E>::universal_setup(constraints, variables, non_zero, rng)
+                        .unwrap();
 
                 let (pk, vk) = KzgMarlin::<E::Fr, E>::index(&srs, circ_no_data).unwrap();
                 let mpc_pk = IndexProverKey::from_public(pk);
 
-                let a = E::Fr::rand(rng);
                 let computation_timer = start_timer!(|| "do the mpc (cheat)");
-                let circ_data = mpc_squaring_circuit::<
-                    E::Fr,
-                    <MpcPairingEngine<E, S> as PairingEngine>::Fr,
-                >(a, n);
-                let public_inputs = vec![circ_data.chain.last().unwrap().unwrap().reveal()];
+                let circ_data = w.mpc_with_data::<E::Fr, S::FrShare, _>(rng);
+                let public_inputs = circ_data.public_inputs().reveal();
                 end_timer!(computation_timer);
 
-                let InterData = channel::without_cheating(|| {
-                    KzgMarlin::<
-                        <MpcPairingEngine<E, RSS3PairingShare<E>> as PairingEngine>::Fr,
-                        MpcPairingEngine<E, RSS3PairingShare<E>>,
+                // Both phases run over `S` until shares can be converted between them.
+                let timer = start_timer!(|| timer_label);
+                let zk_rng = &mut test_rng();
+                let proof = channel::without_cheating(|| {
+                    let inter_data = KzgMarlin::<
+                        <MpcPairingEngine<E, S> as PairingEngine>::Fr,
+                        MpcPairingEngine<E, S>,
                     >::prove_first_phase(&mpc_pk, circ_data, zk_rng)
-                    .unwrap()
-                });
-
-                
-
-                
-
-                let prove_second_phase_result = channel::without_cheating(|| {
-                    KzgMarlin::<
-                        <MpcPairingEngine<E, AdditivePairingShare<E>> as PairingEngine>::Fr,
-                        MpcPairingEngine<E, AdditivePairingShare<E>>,
-                    >::prove_second_phase(&mpc_pk, InterData, zk_rng)
-                    .unwrap()
-                    .reveal()
+                    .unwrap();
+                    let pf = KzgMarlin::<
+                        <MpcPairingEngine<E, S> as PairingEngine>::Fr,
+                        MpcPairingEngine<E, S>,
+                    >::prove_second_phase::<
+                        BenchCircuit<<MpcPairingEngine<E, S> as PairingEngine>::Fr>,
+                        _,
+                    >(&mpc_pk, inter_data, zk_rng)
+                    .unwrap();
+                    reveal_proof(pf)
                 });
-
-
                 end_timer!(timer);
-                assert!(KzgMarlin::<E::Fr, E>::verify(&vk, &public_inputs, &proof, rng).unwrap());
+                if let Some(proof) = proof {
+                    assert!(KzgMarlin::<E::Fr, E>::verify(&vk, &public_inputs, &proof, rng).unwrap());
+                }
             }
         }
     }
@@ -398,75 +438,57 @@ mod squarings {
     pub mod plonk {
         use super::*;
         use ark_poly::univariate::DensePolynomial;
+        use ark_poly::EvaluationDomain;
         use ark_poly_commit::marlin::marlin_pc::MarlinKZG10;
         use mpc_algebra::Reveal;
         use mpc_plonk::relations::flat::CircuitLayout;
+        use mpc_plonk::relations::r1cs;
         use mpc_plonk::relations::structured::PlonkCircuit;
         use mpc_plonk::*;
 
-        fn plonk_squaring_circuit<F: Field>(c: RepeatedSquaringCircuit<F>) -> PlonkCircuit<F> {
-            let n_gates = c.chain.len() as u32 - 1;
-            let n_vars = n_gates + 1;
-            let last_var = n_vars as u32 - 1;
-            let mut this = PlonkCircuit {
-                n_vars,
-                pub_vars: std::iter::once((last_var, "out".to_owned())).collect(),
-                prods: (0..(n_vars - 1)).map(|i| (i, i, i + 1)).collect(),
-                sums: Vec::new(),
-                values: c.chain.into_iter().collect(),
-            };
-            this.pad_to_power_of_2();
-            this
+        /// The gates for `c`, which we synthesize with its values if `values`.
+        fn plonk_circuit<F: FftField, C: ConstraintSynthesizer<F>>(
+            c: C,
+            values: bool,
+        ) -> CircuitLayout<F> {
+            CircuitLayout::from_circuit(&PlonkCircuit::from_synthesizer(c, values).unwrap())
         }
         type MarlinPcPlonk<Fr, E> = mpc_plonk::Plonk<Fr, MarlinKZG10<E, DensePolynomial<Fr>>>;
 
         pub struct PlonkBench;
 
         impl SnarkBench for PlonkBench {
-            fn local<E: PairingEngine>(n: usize, timer_label: &str) {
+            fn local<E: PairingEngine>(w: &Workload, timer_label: &str) {
                 let rng = &mut test_rng();
-                let circ_no_data = plonk_squaring_circuit(RepeatedSquaringCircuit::without_data(n));
-                let circ_no_data = CircuitLayout::from_circuit(&circ_no_data);
-
-                let a = E::Fr::rand(rng);
-                let circ_data = RepeatedSquaringCircuit::from_start(a, n);
-                let plonk_circ_data = plonk_squaring_circuit(circ_data.clone());
-                let plonk_circ_data = CircuitLayout::from_circuit(&plonk_circ_data);
-                let public_inputs =
-                    std::iter::once(("out".to_owned(), circ_data.chain.last().unwrap().unwrap()))
-                        .collect();
+                let circ_no_data = plonk_circuit(w.without_data::<E::Fr>(), false);
+
+                let circ_data = w.with_data::<E::Fr, _>(rng);
+                let public_inputs = r1cs::public_inputs(&circ_data.public_inputs());
+                let plonk_circ_data = plonk_circuit(circ_data, true);
                 let setup_rng = &mut test_rng();
                 let zk_rng = &mut test_rng();
                 let srs =
-                    MarlinPcPlonk::<E::Fr, E>::universal_setup(n.next_power_of_two(), setup_rng);
+                    MarlinPcPlonk::<E::Fr, E>::universal_setup(circ_no_data.domains.gates.size(), setup_rng);
                 let (pk, vk) = MarlinPcPlonk::<E::Fr, E>::circuit_setup(&srs, &circ_no_data);
                 let timer = start_timer!(|| timer_label);
                 let pf = MarlinPcPlonk::<E::Fr, E>::prove(&pk, &plonk_circ_data, zk_rng);
                 end_timer!(timer);
-                MarlinPcPlonk::<E::Fr, E>::verify(&vk, &circ_no_data, pf, &public_inputs);
+                MarlinPcPlonk::<E::Fr, E>::verify(&vk, &circ_no_data, pf, &public_inputs).unwrap();
             }
 
-            fn mpc<E: PairingEngine, S: PairingShare<E>>(n: usize, timer_label: &str) {
+            fn mpc<E: PairingEngine, S: PairingShare<E>>(w: &Workload, timer_label: &str) {
                 let rng = &mut test_rng();
-                let circ_no_data = plonk_squaring_circuit(RepeatedSquaringCircuit::without_data(n));
-                let circ_no_data = CircuitLayout::from_circuit(&circ_no_data);
+                let circ_no_data = plonk_circuit(w.without_data::<E::Fr>(), false);
 
-                let a = E::Fr::rand(rng);
-                let circ_data = mpc_squaring_circuit::<
-                    E::Fr,
-                    <MpcPairingEngine<E, S> as PairingEngine>::Fr,
-                >(a, n);
-                let plonk_circ_data = plonk_squaring_circuit(circ_data.clone());
-                let plonk_circ_data = CircuitLayout::from_circuit(&plonk_circ_data);
-                let public_inputs = std::iter::once((
-                    "out".to_owned(),
-                    circ_data.chain.last().unwrap().unwrap().reveal(),
-                ))
-                .collect();
+                let computation_timer = start_timer!(|| "do the mpc (cheat)");
+                let circ_data = w.mpc_with_data::<E::Fr, S::FrShare, _>(rng);
+                let public_inputs = r1cs::public_inputs(&circ_data.public_inputs().reveal());
+                let plonk_circ_data = plonk_circuit(circ_data, true);
+                end_timer!(computation_timer);
                 let setup_rng = &mut test_rng();
                 let zk_rng = &mut test_rng();
                 let srs =
-                    MarlinPcPlonk::<E::Fr, E>::universal_setup(n.next_power_of_two(), setup_rng);
+                    MarlinPcPlonk::<E::Fr, E>::universal_setup(circ_no_data.domains.gates.size(), setup_rng);
                 let (pk, vk) = MarlinPcPlonk::<E::Fr, E>::circuit_setup(&srs, &circ_no_data);
                 let mpc_pk = Reveal::from_public(pk);
                 MpcMultiNet::reset_stats();
@@ -477,47 +499,195 @@ mod squarings {
                         MpcPairingEngine<E, S>,
                     >::prove(&mpc_pk, &plonk_circ_data, zk_rng);
                     let reveal_timer = start_timer!(|| "reveal");
-                    let pf = pf.reveal();
+                    let phase = mpc_net::phase("reveal");
+                    let pf = reveal_proof(pf);
+                    drop(phase);
                     end_timer!(reveal_timer);
                     pf
                 });
                 end_timer!(t);
-                MarlinPcPlonk::<E::Fr, E>::verify(&vk, &circ_no_data, pf, &public_inputs);
+                if let Some(pf) = pf {
+                    MarlinPcPlonk::<E::Fr, E>::verify(&vk, &circ_no_data, pf, &public_inputs).unwrap();
+                }
             }
         }
     }
 
-    fn mpc_squaring_circuit_with_plain_witness<Fr: Field, MFr: Field + Reveal<Base = Fr>>(
-        start: Fr,
-        squarings: usize,
-    ) -> (RepeatedSquaringCircuit<MFr>, RepeatedSquaringCircuit<Fr>) {
-        let raw_chain: Vec<Fr> = std::iter::successors(Some(start), |a| Some(a.square()))
+    /// Reveal the proof to every party, or only to the one that `--reveal-to` names. `None` if
+    /// that is not us.
+    fn reveal_proof<P: Reveal>(pf: P) -> Option<P::Base> {
+        match MpcMultiNet::take_state::<RevealTo>() {
+            Some(RevealTo(to)) => pf.reveal_to(to),
+            None => Some(pf.reveal()),
+        }
+    }
+
+    /// The witness for `squarings` squarings of `start`.
+    fn squaring_chain<Fr: Field>(start: Fr, squarings: usize) -> Vec<Fr> {
+        std::iter::successors(Some(start), |a| Some(a.square()))
             .take(squarings + 1)
-            .collect();
-        let rng = &mut test_rng();
-        let chain_shares = MFr::king_share_batch(raw_chain.clone(), rng);
-        let c = RepeatedSquaringCircuit {
-            chain: chain_shares.into_iter().map(Some).collect(),
-        };
+            .collect()
+    }
 
-        let c_with_plain_witness = RepeatedSquaringCircuit {
-            chain: raw_chain.into_iter().map(Some).collect(),
-        };
+    /// Shares of `raw_chain`, or of the chain the client sent in its place, if it sent one.
+    fn share_chain<Fr: Field, MFr: Field + Reveal<Base = Fr>>(raw_chain: Vec<Fr>) -> Vec<MFr> {
+        match MpcMultiNet::take_state::<ClientWitness<MFr>>() {
+            Some(ClientWitness(chain)) => {
+                assert_eq!(
+                    chain.len(),
+                    raw_chain.len(),
+                    "The client sent {} values, but the circuit needs {}",
+                    chain.len(),
+                    raw_chain.len()
+                );
+                chain
+            }
+            None => MFr::king_share_batch(raw_chain, &mut test_rng()),
+        }
+    }
+
+    /// The circuit that a bench proves, with the witness if we have it.
+    #[derive(Clone)]
+    enum BenchCircuit<F: Field> {
+        Squaring(RepeatedSquaringCircuit<F>),
+        R1cs(R1csCircuit<F>),
+        Extended(ExtendedCircuit<F>),
+    }
 
-        (c, c_with_plain_witness)
+    impl<F: Field> BenchCircuit<F> {
+        fn public_inputs(&self) -> Vec<F> {
+            match self {
+                BenchCircuit::Squaring(c) => vec![c.chain.last().unwrap().unwrap()],
+                BenchCircuit::R1cs(c) => c.public_inputs(),
+                BenchCircuit::Extended(c) => c.public_inputs(),
+            }
+        }
     }
 
-    fn mpc_squaring_circuit<Fr: Field, MFr: Field + Reveal<Base = Fr>>(
-        start: Fr,
-        squarings: usize,
-    ) -> RepeatedSquaringCircuit<MFr> {
-        let raw_chain: Vec<Fr> = std::iter::successors(Some(start), |a| Some(a.square()))
-            .take(squarings + 1)
-            .collect();
-        let rng = &mut test_rng();
-        let chain_shares = MFr::king_share_batch(raw_chain, rng);
-        RepeatedSquaringCircuit {
-            chain: chain_shares.into_iter().map(Some).collect(),
+    impl<ConstraintF: Field> ConstraintSynthesizer<ConstraintF> for BenchCircuit<ConstraintF> {
+        fn generate_constraints(
+            self,
+            cs: ConstraintSystemRef<ConstraintF>,
+        ) -> Result<(), SynthesisError> {
+            match self {
+                BenchCircuit::Squaring(c) => c.generate_constraints(cs),
+                BenchCircuit::R1cs(c) => c.generate_constraints(cs),
+                BenchCircuit::Extended(c) => c.generate_constraints(cs),
+            }
+        }
+    }
+
+    impl Workload {
+        fn r1cs<F: PrimeField>(&self) -> R1cs<F> {
+            let path = self.r1cs.as_ref().expect("-c r1cs needs --r1cs");
+            R1cs::load(path)
+                .unwrap_or_else(|e| panic!("Could not load {}: {}", path.display(), e))
+        }
+
+        /// The values that party 0 (or the client) shares: the squarings of a random start, or
+        /// every wire after the constant in the witness file. `None` if there is no witness file.
+        pub fn witness<F: PrimeField, R: Rng>(&self, rng: &mut R) -> Option<Vec<F>> {
+            match self.computation {
+                Computation::Squaring => Some(squaring_chain(F::rand(rng), self.size)),
+                Computation::R1cs => {
+                    let path = self.witness.as_ref()?;
+                    let witness = r1cs_file::load_witness(path)
+                        .unwrap_or_else(|e| panic!("Could not load {}: {}", path.display(), e));
+                    self.r1cs::<F>()
+                        .check(&witness)
+                        .unwrap_or_else(|e| panic!("Bad witness in {}: {}", path.display(), e));
+                    Some(witness[1..].to_vec())
+                }
+            }
+        }
+
+        /// The values that party 0 (or the client) shares: just the start with
+        /// `--extend-witness`, else the whole witness.
+        pub fn inputs<F: PrimeField, R: Rng>(&self, rng: &mut R) -> Option<Vec<F>> {
+            let mut witness = self.witness(rng)?;
+            if self.extend {
+                witness.truncate(1);
+            }
+            Some(witness)
+        }
+
+        /// The circuit, with `witness` if we have it, and the constraints' coefficients mapped
+        /// from `Fr` by `coeff`.
+        fn circuit<Fr: PrimeField, F: Field>(
+            &self,
+            witness: Option<Vec<F>>,
+            coeff: impl Fn(Fr) -> F,
+        ) -> BenchCircuit<F> {
+            match self.computation {
+                Computation::Squaring => BenchCircuit::Squaring(match witness {
+                    Some(w) => RepeatedSquaringCircuit::from_chain(w),
+                    None => RepeatedSquaringCircuit::without_data(self.size),
+                }),
+                Computation::R1cs => BenchCircuit::R1cs(self.r1cs::<Fr>().map(coeff).circuit(witness)),
+            }
+        }
+
+        fn without_data<F: PrimeField>(&self) -> BenchCircuit<F> {
+            self.circuit(None, |c: F| c)
+        }
+
+        fn with_data<F: PrimeField, R: Rng>(&self, rng: &mut R) -> BenchCircuit<F> {
+            let witness = self.witness(rng).expect("Proving an R1CS file needs its --witness");
+            self.circuit(Some(witness), |c: F| c)
+        }
+
+        /// The circuit, with shares of the witness: from party 0, or from the client if it sent
+        /// them. Only party 0 needs the witness file. With `--extend-witness`, the parties
+        /// compute all but the start themselves.
+        fn mpc_with_data<Fr: PrimeField, S: FieldShare<Fr>, R: Rng>(
+            &self,
+            rng: &mut R,
+        ) -> BenchCircuit<MpcField<Fr, S>> {
+            let inputs = self
+                .inputs::<Fr, _>(rng)
+                .unwrap_or_else(|| vec![Fr::zero(); self.r1cs::<Fr>().num_wires - 1]);
+            let shares = share_chain(inputs);
+            if self.extend {
+                let timer = start_timer!(|| "extend the witness");
+                let circuit = SquaringsFromStart {
+                    start: lazy::defer(shares[0]),
+                    squarings: self.size,
+                };
+                let circuit = extend_witness(circuit).unwrap();
+                end_timer!(timer);
+                return BenchCircuit::Extended(circuit);
+            }
+            self.circuit(Some(shares), MpcField::from_public)
+        }
+
+        /// The circuit with shares of the witness, and with the witness itself, which every
+        /// party then needs.
+        fn mpc_with_plain_witness<Fr: PrimeField, MFr: Field + Reveal<Base = Fr>, R: Rng>(
+            &self,
+            rng: &mut R,
+        ) -> (BenchCircuit<MFr>, BenchCircuit<Fr>) {
+            assert!(
+                MpcMultiNet::take_state::<ClientWitness<MFr>>().is_none(),
+                "This proof system needs the plain witness, so it cannot take one from a client"
+            );
+            assert!(!self.extend, "This proof system needs the plain witness, so it cannot --extend-witness");
+            let witness = self
+                .witness::<Fr, _>(rng)
+                .expect("This proof system needs the plain witness, so every party needs --witness");
+            let shares = MFr::king_share_batch(witness.clone(), &mut test_rng());
+            (
+                self.circuit(Some(shares), MFr::from_public),
+                self.circuit(Some(witness), |c: Fr| c),
+            )
+        }
+
+        /// How large to make Marlin's universal parameters: constraints, variables, and non-zero
+        /// entries.
+        fn marlin_bounds<F: PrimeField>(&self) -> (usize, usize, usize) {
+            match self.computation {
+                Computation::Squaring => (self.size, self.size + 2, 3 * self.size),
+                Computation::R1cs => self.r1cs::<F>().marlin_bounds(),
+            }
         }
     }
 
@@ -550,6 +720,12 @@ mod squarings {
     }
 }
 
+/// The witness that the client sent, until the bench uses it.
+struct ClientWitness<T>(Vec<T>);
+
+/// The only party to reveal the proof to, until the bench reveals it.
+struct RevealTo(usize);
+
 #[derive(Debug, StructOpt)]
 struct ShareInfo {
     /// File with list of hosts
@@ -560,46 +736,221 @@ struct ShareInfo {
     #[structopt(long, default_value = "0")]
     party: u8,
 
+    /// Secret key: authenticate and encrypt connections with Noise. The hosts file must then list
+    /// each party's public key after its address.
+    #[structopt(long, parse(from_os_str))]
+    key: Option<PathBuf>,
+
+    /// Emulated latency of every link, like 40ms (replaces the hosts file's conditions)
+    #[structopt(long, default_value = "0ms", parse(try_from_str = parse_duration))]
+    latency: Duration,
+
+    /// Emulated jitter of every link, like 5ms
+    #[structopt(long, default_value = "0ms", parse(try_from_str = parse_duration))]
+    jitter: Duration,
+
+    /// Emulated bandwidth of every link, like 100mbit
+    #[structopt(long, parse(try_from_str = parse_bandwidth))]
+    bandwidth: Option<u64>,
+
+    /// How the parties connect: mesh (everyone to everyone) or star (everyone through party 0)
+    #[structopt(long, default_value = "mesh")]
+    topology: Topology,
+
     /// Use spdz?
     #[structopt(long)]
     alg: MpcAlg,
+
+    /// Threshold for rss: how many parties may collude. Defaults to the most below half
+    #[structopt(long)]
+    threshold: Option<usize>,
+
+    /// Directory holding the preprocessing stores written by the dealer (or, for gsz, an offline
+    /// run)
+    #[structopt(long, parse(from_os_str))]
+    preprocessing: Option<PathBuf>,
+
+    /// Without --preprocessing, use fixed, public triples instead of failing. Insecure: for
+    /// benchmarks only
+    #[structopt(long)]
+    dummy_preprocessing: bool,
+
+    /// Take the witness from a client (see the client subcommand), instead of from party 0. Needs
+    /// --key and --client-key, to authenticate the client
+    #[structopt(long)]
+    from_client: bool,
+
+    /// The client's public key, for --from-client
+    #[structopt(long)]
+    client_key: Option<PublicKey>,
+
+    /// Reveal the proof to this party only, which verifies it. The others do not learn it
+    #[structopt(long)]
+    reveal_to: Option<usize>,
+
+    /// Write communication statistics, per phase, to this file: CSV if it ends in .csv, JSON
+    /// otherwise
+    #[structopt(long, parse(from_os_str))]
+    stats: Option<PathBuf>,
 }
 
 impl ShareInfo {
     fn setup(&self) {
-        MpcMultiNet::init_from_file(self.hosts.to_str().unwrap(), self.party as usize)
+        MpcMultiNet::init_with_topology(
+            self.hosts.to_str().unwrap(),
+            self.party as usize,
+            Transport::from_secret_key(self.key.clone()),
+            self.topology,
+        )
+        .unwrap_or_else(|e| panic!("Could not connect: {}", e));
+        let shape = LinkShape {
+            latency: self.latency,
+            jitter: self.jitter,
+            bandwidth: self.bandwidth,
+        };
+        if shape != LinkShape::default() {
+            MpcMultiNet::shape_links(shape);
+        }
+        if let Some(t) = self.threshold {
+            mpc_algebra::share::rss::prss::set_threshold(t);
+        }
     }
     fn teardown(&self) {
-        debug!("Stats: {:#?}", MpcMultiNet::stats());
+        let stats = MpcMultiNet::stats();
+        debug!("Stats: {:#?}", stats);
+        if let Some(path) = &self.stats {
+            let out = match path.extension() {
+                Some(ext) if ext == "csv" => stats.to_csv(),
+                _ => stats.to_json(),
+            };
+            std::fs::write(path, out)
+                .unwrap_or_else(|e| panic!("Could not write {}: {}", path.display(), e));
+        }
         MpcMultiNet::deinit();
     }
-    fn run<E: PairingEngine, B: SnarkBench>(
+    fn run<E: PairingEngine, B: SnarkBench>(&self, workload: &Workload, _b: B, timed_label: &str) {
+        match self.alg {
+            MpcAlg::Spdz => self.run_online::<E, mpc_algebra::share::spdz::SpdzPairingShare<E>, B>(
+                workload,
+                timed_label,
+            ),
+            MpcAlg::Hbc => self.run_online::<E, mpc_algebra::share::add::AdditivePairingShare<E>, B>(
+                workload,
+                timed_label,
+            ),
+            MpcAlg::Gsz => self.run_online::<E, mpc_algebra::share::gsz20::GszPairingShare<E>, B>(
+                workload,
+                timed_label,
+            ),
+            MpcAlg::Rss3 => self.run_online::<E, mpc_algebra::share::rss3::RSS3PairingShare<E>, B>(
+                workload,
+                timed_label,
+            ),
+            MpcAlg::Rss => self.run_online::<E, mpc_algebra::share::rss::RssPairingShare<E>, B>(
+                workload,
+                timed_label,
+            ),
+        }
+    }
+    fn run_online<E: PairingEngine, S: PairingShare<E>, B: SnarkBench>(
         &self,
-        computation: Computation,
-        computation_size: usize,
-        _b: B,
+        workload: &Workload,
         timed_label: &str,
-    ) {
-        match computation {
-            Computation::Squaring => match self.alg {
-                MpcAlg::Spdz => B::mpc::<E, mpc_algebra::share::spdz::SpdzPairingShare<E>>(
-                    computation_size,
-                    timed_label,
-                ),
-                MpcAlg::Hbc => B::mpc::<E, mpc_algebra::share::add::AdditivePairingShare<E>>(
-                    computation_size,
-                    timed_label,
-                ),
-                MpcAlg::Gsz => B::mpc::<E, mpc_algebra::share::gsz20::GszPairingShare<E>>(
-                    computation_size,
-                    timed_label,
-                ),
-                MpcAlg::Rss3 => B::mpc::<E, mpc_algebra::share::rss3::RSS3PairingShare<E>>(
-                    computation_size,
-                    timed_label,
-                ),
-            },
+    ) where
+        S::FrShare: ClientShare<Base = E::Fr>,
+    {
+        if workload.computation == Computation::R1cs && self.party == 0 && !self.from_client {
+            assert!(workload.witness.is_some(), "Party 0 shares the witness, so it needs --witness");
         }
+        S::FrShare::init_protocol();
+        if self.from_client {
+            let client_timer = start_timer!(|| "receive witness from client");
+            self.recv_witness::<E::Fr, S::FrShare>();
+            end_timer!(client_timer);
+        }
+        if let Some(to) = self.reveal_to {
+            assert!(to < MpcMultiNet::n_parties(), "--reveal-to {}: there is no such party", to);
+            MpcMultiNet::with_state(|| RevealTo(to), |_| ());
+        }
+        if let Some(dir) = &self.preprocessing {
+            let load_timer = start_timer!(|| "load preprocessing");
+            self.install_preprocessing::<E, S>(dir);
+            end_timer!(load_timer);
+        } else if self.dummy_preprocessing {
+            preprocessing::use_dummy();
+        }
+        B::mpc::<E, S>(workload, timed_label);
+        self.uninstall_preprocessing::<E, S>();
+        S::FrShare::deinit_protocol();
+    }
+    /// Wait for the client's witness, and keep our shares of it for the bench.
+    fn recv_witness<F: Field, S: FieldShare<F> + ClientShare<Base = F>>(&self) {
+        let (addr, _) = mpc_net::client::read_hosts(self.hosts.to_str().unwrap())[self.party as usize];
+        // Without Noise, we could not tell the client from whoever contacts us first.
+        let key = SecretKey::from_file(
+            self.key.as_ref().expect("--from-client needs --key, to authenticate the client"),
+        );
+        let client_key = self
+            .client_key
+            .as_ref()
+            .expect("--from-client needs the client's --client-key");
+        let session = MpcMultiNet::current_session();
+        let chain = input_client::recv_from_client::<MpcField<F, S>>(&session, addr, (&key, client_key))
+            .unwrap_or_else(|e| panic!("Could not get the witness: {}", e));
+        session.with_state(|| ClientWitness(chain), |_| ());
+    }
+    fn install_preprocessing<E: PairingEngine, S: PairingShare<E>>(&self, dir: &Path) {
+        let party = self.party as usize;
+        match self.alg {
+            MpcAlg::Gsz => {
+                let path = GszPreprocessing::<E::Fr, E::Fr>::store_path(dir, party);
+                let store = GszPreprocessing::<E::Fr, E::Fr>::load(&path).unwrap_or_else(|e| {
+                    panic!("Could not load preprocessing from {}: {}", path.display(), e)
+                });
+                gsz_preprocessing::install(store);
+            }
+            _ => {
+                let path = FieldPreprocessing::<E::Fr, S::FrShare>::store_path(dir, party);
+                let store = FieldPreprocessing::<E::Fr, S::FrShare>::load(&path).unwrap_or_else(|e| {
+                    panic!("Could not load preprocessing from {}: {}", path.display(), e)
+                });
+                preprocessing::install(store).unwrap_or_else(|e| {
+                    panic!("Could not install preprocessing from {}: {}", path.display(), e)
+                });
+            }
+        }
+    }
+    fn uninstall_preprocessing<E: PairingEngine, S: PairingShare<E>>(&self) {
+        match self.alg {
+            MpcAlg::Gsz => {
+                if let Some(used) = gsz_preprocessing::uninstall::<E::Fr, E::Fr>() {
+                    println!("Preprocessing used: {:?}", used);
+                }
+            }
+            _ => {
+                if let Some(used) = preprocessing::uninstall::<E::Fr, S::FrShare>() {
+                    println!("Preprocessing used: {:?}", used);
+                }
+            }
+        }
+    }
+    fn run_offline<E: PairingEngine>(&self, counts: PreprocessingCounts, out: &Path) {
+        match self.alg {
+            MpcAlg::Gsz => self.gsz_offline::<E>(counts, out),
+            alg => panic!("{} preprocessing is dealt, not generated together: see the deal subcommand", alg),
+        }
+    }
+    /// GSZ multiplications consume double random sharings rather than triples, and an inversion
+    /// consumes one of each kind of random sharing.
+    fn gsz_offline<E: PairingEngine>(&self, counts: PreprocessingCounts, out: &Path) {
+        let store = GszPreprocessing::<E::Fr, E::Fr>::generate(GszPreprocessingCounts {
+            rands: counts.rands + counts.inv_pairs,
+            double_rands: counts.triples + counts.inv_pairs,
+        });
+        let path = GszPreprocessing::<E::Fr, E::Fr>::store_path(out, self.party as usize);
+        store.save(&path).unwrap_or_else(|e| {
+            panic!("Could not write preprocessing to {}: {}", path.display(), e)
+        });
     }
 }
 
@@ -610,6 +961,7 @@ arg_enum! {
         Hbc,
         Gsz,
         Rss3,
+        Rss,
     }
 }
 
@@ -617,6 +969,7 @@ arg_enum! {
     #[derive(PartialEq, Debug, Clone, Copy)]
     pub enum Computation {
         Squaring,
+        R1cs,
     }
 }
 
@@ -637,6 +990,77 @@ enum FieldOpt {
         #[structopt(flatten)]
         party_info: ShareInfo,
     },
+    /// Run the gsz offline phase together, writing each party's preprocessing to its own store
+    Offline {
+        #[structopt(flatten)]
+        party_info: ShareInfo,
+
+        /// Directory to write the preprocessing stores to
+        #[structopt(long, parse(from_os_str))]
+        out: PathBuf,
+
+        /// Number of Beaver triples to generate (double random sharings, for gsz)
+        #[structopt(long)]
+        triples: usize,
+
+        /// Number of inverse pairs to generate
+        #[structopt(long, default_value = "0")]
+        inv_pairs: usize,
+
+        /// Number of random shares to generate
+        #[structopt(long, default_value = "0")]
+        rands: usize,
+    },
+    /// Run the offline phase as a dealer that takes no part in the online phase, writing every
+    /// party's preprocessing store. For spdz and hbc; rss and rss3 need no preprocessing
+    Deal {
+        /// The parties' algorithm
+        #[structopt(long)]
+        alg: MpcAlg,
+
+        /// Number of parties
+        #[structopt(long)]
+        parties: usize,
+
+        /// Directory to write the preprocessing stores to
+        #[structopt(long, parse(from_os_str))]
+        out: PathBuf,
+
+        /// Number of Beaver triples to deal
+        #[structopt(long)]
+        triples: usize,
+
+        /// Number of inverse pairs to deal
+        #[structopt(long, default_value = "0")]
+        inv_pairs: usize,
+
+        /// Number of random shares to deal
+        #[structopt(long, default_value = "0")]
+        rands: usize,
+
+        /// Number of input masks to deal each party, for the values it shares
+        #[structopt(long, default_value = "0")]
+        inputs: usize,
+    },
+    /// Deal a witness to the parties, as a client outside them, so that no party sees it
+    Client {
+        /// File with list of hosts
+        #[structopt(long, parse(from_os_str))]
+        hosts: PathBuf,
+
+        /// Our secret key, to secure the connections with Noise. The hosts file must list each
+        /// party's public key
+        #[structopt(long, parse(from_os_str))]
+        key: PathBuf,
+
+        /// The parties' algorithm
+        #[structopt(long)]
+        alg: MpcAlg,
+
+        /// The parties' --threshold, for rss
+        #[structopt(long)]
+        threshold: Option<usize>,
+    },
     Local,
     ArkLocal,
 }
@@ -644,36 +1068,120 @@ enum FieldOpt {
 impl FieldOpt {
     fn setup(&self) {
         match self {
-            FieldOpt::Mpc { party_info, .. } => party_info.setup(),
+            FieldOpt::Mpc { party_info, .. } | FieldOpt::Offline { party_info, .. } => {
+                party_info.setup()
+            }
             _ => {}
         }
     }
     fn teardown(&self) {
         match self {
-            FieldOpt::Mpc { party_info, .. } => party_info.teardown(),
+            FieldOpt::Mpc { party_info, .. } | FieldOpt::Offline { party_info, .. } => {
+                println!("Stats: {:#?}", MpcMultiNet::stats());
+                party_info.teardown()
+            }
             _ => {}
         }
-        println!("Stats: {:#?}", MpcMultiNet::stats());
     }
-    fn run<E: PairingEngine, B: SnarkBench>(
-        &self,
-        computation: Computation,
-        computation_size: usize,
-        b: B,
-        timed_label: &str,
-    ) {
+    fn run<E: PairingEngine, B: SnarkBench>(&self, workload: &Workload, b: B, timed_label: &str) {
         self.setup();
         match self {
-            FieldOpt::Mpc { party_info, .. } => {
-                party_info.run::<E, B>(computation, computation_size, b, timed_label)
-            }
-            FieldOpt::Local => B::local::<E>(computation_size, timed_label),
-            FieldOpt::ArkLocal => B::ark_local::<E>(computation_size, timed_label),
+            FieldOpt::Mpc { party_info, .. } => party_info.run::<E, B>(workload, b, timed_label),
+            FieldOpt::Offline {
+                party_info,
+                out,
+                triples,
+                inv_pairs,
+                rands,
+            } => party_info.run_offline::<E>(
+                PreprocessingCounts {
+                    triples: *triples,
+                    inv_pairs: *inv_pairs,
+                    rands: *rands,
+                    inputs: 0,
+                },
+                out,
+            ),
+            FieldOpt::Deal {
+                alg,
+                parties,
+                out,
+                triples,
+                inv_pairs,
+                rands,
+                inputs,
+            } => deal_preprocessing::<E::Fr>(
+                *alg,
+                *parties,
+                PreprocessingCounts {
+                    triples: *triples,
+                    inv_pairs: *inv_pairs,
+                    rands: *rands,
+                    inputs: *inputs,
+                },
+                out,
+            ),
+            FieldOpt::Client {
+                hosts,
+                key,
+                alg,
+                threshold,
+            } => deal_witness::<E::Fr>(hosts, key, *alg, *threshold, workload),
+            FieldOpt::Local => B::local::<E>(workload, timed_label),
+            FieldOpt::ArkLocal => B::ark_local::<E>(workload, timed_label),
         }
         self.teardown();
     }
 }
 
+/// As the dealer: write a preprocessing store for each of `n_parties` parties to `out`.
+fn deal_preprocessing<F: Field>(alg: MpcAlg, n_parties: usize, counts: PreprocessingCounts, out: &Path) {
+    use mpc_algebra::share::{add, spdz};
+    match alg {
+        MpcAlg::Spdz => deal_stores::<F, spdz::SpdzFieldShare<F>>(n_parties, counts, out),
+        MpcAlg::Hbc => deal_stores::<F, add::AdditiveFieldShare<F>>(n_parties, counts, out),
+        MpcAlg::Gsz => panic!("gsz parties generate their preprocessing together: see the offline subcommand"),
+        MpcAlg::Rss3 | MpcAlg::Rss => panic!("{} needs no preprocessing", alg),
+    }
+}
+
+fn deal_stores<F: Field, S: DealtFieldShare<F>>(n_parties: usize, counts: PreprocessingCounts, out: &Path) {
+    let stores = FieldPreprocessing::<F, S>::deal(counts, n_parties, &mut rand::thread_rng());
+    for (party, store) in stores.iter().enumerate() {
+        let path = FieldPreprocessing::<F, S>::store_path(out, party);
+        store.save(&path).unwrap_or_else(|e| {
+            panic!("Could not write preprocessing to {}: {}", path.display(), e)
+        });
+    }
+    println!("Dealt {:?} to each of {} parties", counts, n_parties);
+}
+
+/// As the client: send the parties shares of the witness, for squarings of a random start or from
+/// the witness file.
+fn deal_witness<F: PrimeField>(
+    hosts: &Path,
+    key: &Path,
+    alg: MpcAlg,
+    threshold: Option<usize>,
+    workload: &Workload,
+) {
+    use mpc_algebra::share::{add, gsz20, rss, rss3, spdz};
+    let chain = workload
+        .inputs::<F, _>(&mut rand::thread_rng())
+        .expect("The client needs the --witness");
+    let parties = mpc_net::client::read_hosts(hosts.to_str().unwrap());
+    let key = &SecretKey::from_file(key);
+    let sent = match alg {
+        MpcAlg::Spdz => input_client::send_to_parties::<spdz::SpdzFieldShare<F>>(&parties, &chain, threshold, key),
+        MpcAlg::Hbc => input_client::send_to_parties::<add::AdditiveFieldShare<F>>(&parties, &chain, threshold, key),
+        MpcAlg::Gsz => input_client::send_to_parties::<gsz20::GszFieldShare<F>>(&parties, &chain, threshold, key),
+        MpcAlg::Rss3 => input_client::send_to_parties::<rss3::RSS3FieldShare<F>>(&parties, &chain, threshold, key),
+        MpcAlg::Rss => input_client::send_to_parties::<rss::RssFieldShare<F>>(&parties, &chain, threshold, key),
+    };
+    sent.unwrap_or_else(|e| panic!("Could not send the witness: {}", e));
+    println!("Sent shares of {} values to {} parties", chain.len(), parties.len());
+}
+
 #[derive(Debug, StructOpt)]
 #[structopt(name = "proof", about = "Standard and MPC proofs")]
 struct Opt {
@@ -689,6 +1197,20 @@ struct Opt {
     #[structopt(long, default_value = "10")]
     computation_size: usize,
 
+    /// For -c r1cs: the constraints, as a circom .r1cs file or an arkworks dump
+    #[structopt(long, parse(from_os_str))]
+    r1cs: Option<PathBuf>,
+
+    /// For -c r1cs: the witness, as a circom .wtns file or an arkworks dump. Only its holder
+    /// needs it: party 0, or the client
+    #[structopt(long, parse(from_os_str))]
+    witness: Option<PathBuf>,
+
+    /// For -c squaring: share only the start, and compute the squarings inside the MPC. Give it
+    /// to the client too
+    #[structopt(long)]
+    extend_witness: bool,
+
     #[structopt(subcommand)]
     field: FieldOpt,
 }
@@ -702,35 +1224,41 @@ fn main() {
     //         .filter_level(log::LevelFilter::Debug)
     //         .init();
     env_logger::builder().format_timestamp(None).init();
+    let workload = Workload {
+        computation: opt.computation,
+        size: opt.computation_size,
+        r1cs: opt.r1cs.clone(),
+        witness: opt.witness.clone(),
+        extend: opt.extend_witness,
+    };
+    assert!(
+        !workload.extend || workload.computation == Computation::Squaring,
+        "--extend-witness needs a circuit that computes its witness, so only -c squaring takes it"
+    );
 
     match opt.proof_system {
         ProofSystem::Groth16 => opt.field.run::<ark_bls12_377::Bls12_377, _>(
-            opt.computation,
-            opt.computation_size,
+            &workload,
             squarings::groth::Groth16Bench,
             TIMED_SECTION_LABEL,
         ),
         ProofSystem::Plonk => opt.field.run::<ark_bls12_377::Bls12_377, _>(
-            opt.computation,
-            opt.computation_size,
+            &workload,
             squarings::plonk::PlonkBench,
             TIMED_SECTION_LABEL,
         ),
         ProofSystem::Marlin => opt.field.run::<ark_bls12_377::Bls12_377, _>(
-            opt.computation,
-            opt.computation_size,
+            &workload,
             squarings::marlin::MarlinBench,
             TIMED_SECTION_LABEL,
         ),
         ProofSystem::Marlin_Mal => opt.field.run::<ark_bls12_377::Bls12_377, _>(
-            opt.computation,
-            opt.computation_size,
+            &workload,
             squarings::marlin_mal::MarlinMalBench,
             TIMED_SECTION_LABEL,
         ),
         ProofSystem::Marlin_Mal_Rss_Check => opt.field.run::<ark_bls12_377::Bls12_377, _>(
-            opt.computation,
-            opt.computation_size,
+            &workload,
             squarings::marlin_mal_rss_check::MarlinMalRssCheckBench,
             TIMED_SECTION_LABEL,
         ),
@@ -0,0 +1,496 @@
+//! Rank-1 constraint systems and their witnesses, loaded from files, so that circuits from other
+//! tools can be proven.
+//!
+//! Two formats are read: circom's binary `.r1cs` and `.wtns` files, and arkworks dumps (an
+//! [`R1cs`] or a witness `Vec<F>` written with [`CanonicalSerialize`]), which [`R1cs::dump`] writes
+//! for any [`ConstraintSynthesizer`]. Either way, wire 0 is the constant 1, the next
+//! [`R1cs::num_public`] wires are the public inputs, and the rest are private. A witness assigns
+//! every wire, wire 0 included.
+use ark_ff::{BigInteger, Field, FromBytes, PrimeField};
+use ark_relations::{
+    lc,
+    r1cs::{
+        ConstraintSynthesizer, ConstraintSystem, ConstraintSystemRef, LinearCombination,
+        SynthesisError, Variable,
+    },
+};
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Read, SerializationError, Write};
+use mpc_algebra::share::encoding::ser_err_to_io;
+
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter};
+use std::path::Path;
+
+/// A linear combination of wires, as (coefficient, wire) terms.
+pub type Lc<F> = Vec<(F, usize)>;
+
+/// Constraints `a * b = c` over wires.
+#[derive(Clone, Debug, PartialEq, CanonicalSerialize, CanonicalDeserialize)]
+pub struct R1cs<F: Field> {
+    /// How many wires are public inputs, after the constant.
+    pub num_public: usize,
+    /// How many wires there are, the constant included.
+    pub num_wires: usize,
+    pub constraints: Vec<(Lc<F>, Lc<F>, Lc<F>)>,
+}
+
+impl<F: Field> R1cs<F> {
+    /// The constraints and the witness of `circuit`, which must have its witness.
+    pub fn from_synthesizer<C: ConstraintSynthesizer<F>>(
+        circuit: C,
+    ) -> Result<(Self, Vec<F>), SynthesisError> {
+        let cs = ConstraintSystem::new_ref();
+        circuit.generate_constraints(cs.clone())?;
+        cs.finalize();
+        let matrices = cs.to_matrices().unwrap();
+        let r1cs = R1cs {
+            num_public: matrices.num_instance_variables - 1,
+            num_wires: matrices.num_instance_variables + matrices.num_witness_variables,
+            constraints: matrices
+                .a
+                .into_iter()
+                .zip(matrices.b)
+                .zip(matrices.c)
+                .map(|((a, b), c)| (a, b, c))
+                .collect(),
+        };
+        let cs = cs.borrow().unwrap();
+        let witness = cs
+            .instance_assignment
+            .iter()
+            .chain(&cs.witness_assignment)
+            .cloned()
+            .collect();
+        Ok((r1cs, witness))
+    }
+
+    /// Write the constraints of `circuit` to `r1cs_path`, and its witness to `witness_path`, as
+    /// arkworks dumps.
+    pub fn dump<C: ConstraintSynthesizer<F>>(
+        circuit: C,
+        r1cs_path: &Path,
+        witness_path: &Path,
+    ) -> io::Result<()> {
+        let (r1cs, witness) = Self::from_synthesizer(circuit)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        save(&r1cs, r1cs_path)?;
+        save(&witness, witness_path)
+    }
+
+    /// The same constraints, with each coefficient mapped through `f`; for instance, to
+    /// [`Reveal::from_public`](mpc_algebra::Reveal::from_public).
+    pub fn map<G: Field>(&self, f: impl Fn(F) -> G) -> R1cs<G> {
+        let map_lc = |lc: &Lc<F>| lc.iter().map(|(coeff, wire)| (f(*coeff), *wire)).collect();
+        R1cs {
+            num_public: self.num_public,
+            num_wires: self.num_wires,
+            constraints: self
+                .constraints
+                .iter()
+                .map(|(a, b, c)| (map_lc(a), map_lc(b), map_lc(c)))
+                .collect(),
+        }
+    }
+
+    /// How many terms the `a`, `b` and `c` linear combinations have, in total.
+    pub fn num_non_zero(&self) -> usize {
+        self.constraints
+            .iter()
+            .map(|(a, b, c)| a.len() + b.len() + c.len())
+            .sum()
+    }
+
+    /// How large to make Marlin's universal parameters for these constraints: constraints,
+    /// variables, and non-zero entries.
+    pub fn marlin_bounds(&self) -> (usize, usize, usize) {
+        // Marlin pads the public inputs, the constant included, to a power of two.
+        let inputs = (self.num_public + 1).next_power_of_two();
+        let variables = inputs + self.num_wires - self.num_public - 1;
+        (self.constraints.len(), variables, self.num_non_zero())
+    }
+
+    /// Whether `witness` assigns every wire, starting with the constant 1, and satisfies every
+    /// constraint. `Err` says why not.
+    pub fn check(&self, witness: &[F]) -> Result<(), String> {
+        if witness.len() != self.num_wires {
+            return Err(format!(
+                "the witness has {} wires, but the constraints have {}",
+                witness.len(),
+                self.num_wires
+            ));
+        }
+        if witness[0] != F::one() {
+            return Err("the witness does not start with 1".to_owned());
+        }
+        let eval = |lc: &Lc<F>| -> F {
+            lc.iter()
+                .map(|(coeff, wire)| *coeff * witness[*wire])
+                .sum()
+        };
+        match self
+            .constraints
+            .iter()
+            .position(|(a, b, c)| eval(a) * eval(b) != eval(c))
+        {
+            Some(i) => Err(format!("the witness does not satisfy constraint {}", i)),
+            None => Ok(()),
+        }
+    }
+
+    /// The circuit for these constraints, with the values of every wire but the constant, if we
+    /// have them.
+    pub fn circuit(&self, wires: Option<Vec<F>>) -> R1csCircuit<F> {
+        R1csCircuit {
+            r1cs: self.clone(),
+            wires,
+        }
+    }
+
+    /// Every wire must exist, for the constraints to make sense.
+    fn validate(self) -> Result<Self, SerializationError> {
+        if self.num_wires == 0 || self.num_public >= self.num_wires {
+            return Err(invalid(format!(
+                "{} public inputs among {} wires",
+                self.num_public, self.num_wires
+            )));
+        }
+        let wires = self
+            .constraints
+            .iter()
+            .flat_map(|(a, b, c)| a.iter().chain(b).chain(c));
+        match wires.map(|(_, wire)| *wire).find(|wire| *wire >= self.num_wires) {
+            Some(wire) => Err(invalid(format!(
+                "wire {}, but there are only {}",
+                wire, self.num_wires
+            ))),
+            None => Ok(self),
+        }
+    }
+}
+
+impl<F: PrimeField> R1cs<F> {
+    /// Load constraints: from a circom file if `path` ends in `.r1cs`, and otherwise from an
+    /// arkworks dump.
+    pub fn load(path: &Path) -> Result<Self, SerializationError> {
+        let reader = BufReader::new(File::open(path)?);
+        match path.extension() {
+            Some(ext) if ext == "r1cs" => Self::read_circom(reader),
+            _ => Self::deserialize(reader)?.validate(),
+        }
+    }
+
+    /// Read a circom `.r1cs` file, whose field must be `F`.
+    pub fn read_circom<R: Read>(reader: R) -> Result<Self, SerializationError> {
+        let mut sections = read_sections(reader, b"r1cs", 1)?;
+        let mut header = section(&mut sections, 1)?;
+        let n8 = read_field_header::<F>(&mut header)?;
+        let num_wires = read_u32(&mut header)? as usize;
+        let num_outputs = read_u32(&mut header)? as usize;
+        let num_public_inputs = read_u32(&mut header)? as usize;
+        let _num_private_inputs = read_u32(&mut header)?;
+        let _num_labels = read_u64(&mut header)?;
+        let num_constraints = read_u32(&mut header)? as usize;
+
+        let mut body = section(&mut sections, 2)?;
+        let mut read_lc = || -> Result<Lc<F>, SerializationError> {
+            let terms = read_u32(&mut body)?;
+            (0..terms)
+                .map(|_| {
+                    let wire = read_u32(&mut body)? as usize;
+                    Ok((read_element(&mut body, n8)?, wire))
+                })
+                .collect()
+        };
+        let constraints = (0..num_constraints)
+            .map(|_| Ok((read_lc()?, read_lc()?, read_lc()?)))
+            .collect::<Result<_, SerializationError>>()?;
+        R1cs {
+            num_public: num_outputs + num_public_inputs,
+            num_wires,
+            constraints,
+        }
+        .validate()
+    }
+}
+
+/// Load a witness: from a circom file if `path` ends in `.wtns`, and otherwise from an arkworks
+/// dump.
+pub fn load_witness<F: PrimeField>(path: &Path) -> Result<Vec<F>, SerializationError> {
+    let reader = BufReader::new(File::open(path)?);
+    match path.extension() {
+        Some(ext) if ext == "wtns" => read_circom_witness(reader),
+        _ => Vec::deserialize(reader),
+    }
+}
+
+/// Read a circom `.wtns` file, whose field must be `F`.
+pub fn read_circom_witness<F: PrimeField, R: Read>(reader: R) -> Result<Vec<F>, SerializationError> {
+    let mut sections = read_sections(reader, b"wtns", 2)?;
+    let mut header = section(&mut sections, 1)?;
+    let n8 = read_field_header::<F>(&mut header)?;
+    let len = read_u32(&mut header)?;
+    let mut body = section(&mut sections, 2)?;
+    (0..len).map(|_| read_element(&mut body, n8)).collect()
+}
+
+/// The constraints of an [`R1cs`], as a circuit that any of the proof systems can take. The
+/// values of the wires, if we have them, are those after the constant: shares of them, in MPC.
+#[derive(Clone)]
+pub struct R1csCircuit<F: Field> {
+    pub r1cs: R1cs<F>,
+    pub wires: Option<Vec<F>>,
+}
+
+impl<F: Field> R1csCircuit<F> {
+    /// The values of the public inputs.
+    pub fn public_inputs(&self) -> Vec<F> {
+        let wires = self.wires.as_ref().expect("no witness");
+        wires[..self.r1cs.num_public].to_vec()
+    }
+}
+
+impl<F: Field> ConstraintSynthesizer<F> for R1csCircuit<F> {
+    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+        let R1csCircuit { r1cs, wires } = self;
+        if let Some(wires) = &wires {
+            if wires.len() + 1 != r1cs.num_wires {
+                return Err(SynthesisError::Unsatisfiable);
+            }
+        }
+        let value = |wire: usize| {
+            wires
+                .as_ref()
+                .map(|w| w[wire - 1])
+                .ok_or(SynthesisError::AssignmentMissing)
+        };
+        let mut vars = vec![Variable::One];
+        for wire in 1..r1cs.num_wires {
+            vars.push(if wire <= r1cs.num_public {
+                cs.new_input_variable(|| value(wire))?
+            } else {
+                cs.new_witness_variable(|| value(wire))?
+            });
+        }
+        let lc = |terms: &Lc<F>| -> LinearCombination<F> {
+            terms
+                .iter()
+                .fold(lc!(), |lc, (coeff, wire)| lc + (*coeff, vars[*wire]))
+        };
+        for (a, b, c) in &r1cs.constraints {
+            cs.enforce_constraint(lc(a), lc(b), lc(c))?;
+        }
+        Ok(())
+    }
+}
+
+fn save<T: CanonicalSerialize>(value: &T, path: &Path) -> io::Result<()> {
+    let mut w = BufWriter::new(File::create(path)?);
+    value.serialize(&mut w).map_err(ser_err_to_io)
+}
+
+fn invalid(reason: String) -> SerializationError {
+    io::Error::new(io::ErrorKind::InvalidData, reason).into()
+}
+
+/// The sections of a circom file, by type, after checking its magic and version.
+fn read_sections<R: Read>(
+    mut reader: R,
+    magic: &[u8; 4],
+    version: u32,
+) -> Result<Vec<(u32, Vec<u8>)>, SerializationError> {
+    let mut found = [0u8; 4];
+    reader.read_exact(&mut found)?;
+    if &found != magic {
+        return Err(invalid(format!(
+            "not a circom {} file",
+            String::from_utf8_lossy(magic)
+        )));
+    }
+    let found = read_u32(&mut reader)?;
+    if found != version {
+        return Err(invalid(format!("version {}, not {}", found, version)));
+    }
+    let n_sections = read_u32(&mut reader)?;
+    (0..n_sections)
+        .map(|_| {
+            let kind = read_u32(&mut reader)?;
+            let len = read_u64(&mut reader)?;
+            let mut contents = Vec::new();
+            (&mut reader).take(len).read_to_end(&mut contents)?;
+            if contents.len() as u64 != len {
+                return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+            }
+            Ok((kind, contents))
+        })
+        .collect()
+}
+
+/// Take the section of type `kind`, to read.
+fn section(
+    sections: &mut Vec<(u32, Vec<u8>)>,
+    kind: u32,
+) -> Result<io::Cursor<Vec<u8>>, SerializationError> {
+    match sections.iter().position(|(k, _)| *k == kind) {
+        Some(i) => Ok(io::Cursor::new(sections.swap_remove(i).1)),
+        None => Err(invalid(format!("no section {}", kind))),
+    }
+}
+
+/// Read the size of a field element and the field's modulus, which must be `F`'s. Returns the
+/// size.
+fn read_field_header<F: PrimeField>(reader: &mut impl Read) -> Result<usize, SerializationError> {
+    let n8 = read_u32(reader)? as usize;
+    let mut modulus = vec![0u8; n8];
+    reader.read_exact(&mut modulus)?;
+    let mut ours: Vec<u8> = F::characteristic()
+        .iter()
+        .flat_map(|limb| limb.to_le_bytes().to_vec())
+        .collect();
+    ours.resize(n8.max(ours.len()), 0);
+    let theirs_is_ours = ours[..n8] == modulus[..] && ours[n8..].iter().all(|b| *b == 0);
+    if !theirs_is_ours {
+        return Err(invalid("the file is over another field".to_owned()));
+    }
+    Ok(n8)
+}
+
+/// Read a field element of `n8` bytes. It must be below the modulus: a file written for a larger
+/// field must not load as different values.
+fn read_element<F: PrimeField>(reader: &mut impl Read, n8: usize) -> Result<F, SerializationError> {
+    let mut bytes = vec![0u8; n8];
+    reader.read_exact(&mut bytes)?;
+    let size = F::BigInt::NUM_LIMBS * 8;
+    if bytes.len() > size && bytes[size..].iter().any(|b| *b != 0) {
+        return Err(SerializationError::InvalidData);
+    }
+    bytes.resize(size, 0);
+    F::from_repr(F::BigInt::read(&bytes[..])?).ok_or(SerializationError::InvalidData)
+}
+
+fn read_u32(reader: &mut impl Read) -> Result<u32, SerializationError> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64(reader: &mut impl Read) -> Result<u64, SerializationError> {
+    let mut bytes = [0u8; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ark_bls12_377::Fr;
+    use ark_ff::{FpParameters, UniformRand};
+
+    /// Proves knowledge of `x` such that `x^3 + x + 5` is the public output.
+    struct CubicCircuit<F: Field>(Option<F>);
+
+    impl<F: Field> ConstraintSynthesizer<F> for CubicCircuit<F> {
+        fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+            let x = self.0.ok_or(SynthesisError::AssignmentMissing);
+            let out = cs.new_input_variable(|| Ok(x? * x? * x? + x? + F::from(5u64)))?;
+            let x_var = cs.new_witness_variable(|| x)?;
+            let x2 = cs.new_witness_variable(|| Ok(x?.square()))?;
+            let x3 = cs.new_witness_variable(|| Ok(x?.square() * x?))?;
+            cs.enforce_constraint(lc!() + x_var, lc!() + x_var, lc!() + x2)?;
+            cs.enforce_constraint(lc!() + x2, lc!() + x_var, lc!() + x3)?;
+            cs.enforce_constraint(
+                lc!() + x3 + x_var + (F::from(5u64), Variable::One),
+                lc!() + Variable::One,
+                lc!() + out,
+            )?;
+            Ok(())
+        }
+    }
+
+    /// A circom binary file: its magic, version, and sections by type.
+    fn circom_file(magic: &[u8], version: u32, sections: Vec<(u32, Vec<u8>)>) -> Vec<u8> {
+        let mut file = magic.to_vec();
+        file.extend(&version.to_le_bytes());
+        file.extend(&(sections.len() as u32).to_le_bytes());
+        for (kind, contents) in sections {
+            file.extend(&kind.to_le_bytes());
+            file.extend(&(contents.len() as u64).to_le_bytes());
+            file.extend(contents);
+        }
+        file
+    }
+
+    /// `f` as circom writes it: 32 bytes, little-endian, not in Montgomery form.
+    fn circom_element(f: Fr) -> Vec<u8> {
+        f.into_repr().to_bytes_le()
+    }
+
+    /// The field header of a circom file over `Fr`: the size of an element and the modulus.
+    fn circom_field_header() -> Vec<u8> {
+        let mut header = 32u32.to_le_bytes().to_vec();
+        header.extend(<Fr as PrimeField>::Params::MODULUS.to_bytes_le());
+        header
+    }
+
+    #[test]
+    fn r1cs_from_circom_files() {
+        // Wires: the constant, the output, x, x^2 and x^3, as `CubicCircuit` numbers them.
+        let lc = |terms: &[(u64, u32)]| {
+            let mut bytes = (terms.len() as u32).to_le_bytes().to_vec();
+            for (coeff, wire) in terms {
+                bytes.extend(&wire.to_le_bytes());
+                bytes.extend(circom_element(Fr::from(*coeff)));
+            }
+            bytes
+        };
+        let mut header = circom_field_header();
+        for n in &[5u32, 1, 0, 1] {
+            header.extend(&n.to_le_bytes());
+        }
+        header.extend(&5u64.to_le_bytes());
+        header.extend(&3u32.to_le_bytes());
+        let constraints = [
+            lc(&[(1, 2)]),
+            lc(&[(1, 2)]),
+            lc(&[(1, 3)]),
+            lc(&[(1, 3)]),
+            lc(&[(1, 2)]),
+            lc(&[(1, 4)]),
+            lc(&[(5, 0), (1, 2), (1, 4)]),
+            lc(&[(1, 0)]),
+            lc(&[(1, 1)]),
+        ]
+        .concat();
+        let r1cs_file = circom_file(b"r1cs", 1, vec![(2, constraints), (1, header)]);
+        let r1cs = R1cs::<Fr>::read_circom(&r1cs_file[..]).unwrap();
+
+        let x = Fr::rand(&mut ark_std::test_rng());
+        let (expected, witness) = R1cs::from_synthesizer(CubicCircuit(Some(x))).unwrap();
+        assert_eq!(r1cs, expected);
+        let mut header = circom_field_header();
+        header.extend(&(witness.len() as u32).to_le_bytes());
+        let values = witness.iter().flat_map(|w| circom_element(*w)).collect();
+        let wtns_file = circom_file(b"wtns", 2, vec![(1, header), (2, values)]);
+        let read = read_circom_witness::<Fr, _>(&wtns_file[..]).unwrap();
+        assert_eq!(read, witness);
+        // Values must be below the modulus, not reduced: the modulus itself does not load.
+        let mut unreduced = wtns_file.clone();
+        let last_at = unreduced.len() - 32;
+        unreduced[last_at..].copy_from_slice(&circom_field_header()[4..]);
+        assert!(read_circom_witness::<Fr, _>(&unreduced[..]).is_err());
+        assert_eq!(r1cs.check(&read), Ok(()));
+        let mut wrong = read;
+        wrong[1] += Fr::from(1u64);
+        assert!(r1cs.check(&wrong).is_err());
+
+        // A file over another field, or with a wire that does not exist, does not load.
+        let mut other_field = r1cs_file.clone();
+        let modulus_at = r1cs_file.len() - 32 - 4 * 4 - 8 - 4;
+        other_field[modulus_at] ^= 1;
+        assert!(R1cs::<Fr>::read_circom(&other_field[..]).is_err());
+        let mut bad_wire = r1cs_file;
+        // The first term of the first constraint names wire 2; make it wire 9.
+        let first_wire_at = 4 + 4 + 4 + 4 + 8 + 4;
+        bad_wire[first_wire_at] = 9;
+        assert!(R1cs::<Fr>::read_circom(&bad_wire[..]).is_err());
+    }
+}
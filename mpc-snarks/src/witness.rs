@@ -0,0 +1,69 @@
+//! Witness extension inside the MPC: the parties share only a circuit's inputs, and compute every
+//! other wire themselves while the circuit synthesizes, so no party needs to know the witness.
+//!
+//! [`extend_witness`] runs any arkworks circuit over [`LazyMpcField`], whose products wait until
+//! a value is needed (see [`lazy`]); what is still pending when synthesis ends is computed a layer
+//! at a time, with the independent products of each layer in one batch. The result is an
+//! [`ExtendedCircuit`], which hands the provers the constraint system it made.
+use ark_ff::{Field, PrimeField};
+use ark_relations::r1cs::{
+    ConstraintSynthesizer, ConstraintSystem, ConstraintSystemRef, SynthesisError,
+};
+use ark_std::{end_timer, start_timer};
+use mpc_algebra::share::lazy::{self, LazyFieldShare};
+use mpc_algebra::{FieldShare, MpcField};
+
+/// The field to write a circuit over for [`extend_witness`]: shares of type `S`, lazily
+/// multiplied. Make its inputs with [`lazy::defer`].
+pub type LazyMpcField<F, S> = MpcField<F, LazyFieldShare<F, S>>;
+
+/// A circuit whose witness has been computed: it hands over the constraint system that computing
+/// it made.
+#[derive(Clone, Debug)]
+pub struct ExtendedCircuit<F: Field> {
+    cs: ConstraintSystem<F>,
+}
+
+impl<F: Field> ExtendedCircuit<F> {
+    /// The values of the instance variables, after the constant.
+    pub fn public_inputs(&self) -> Vec<F> {
+        self.cs.instance_assignment[1..].to_vec()
+    }
+}
+
+impl<F: Field> ConstraintSynthesizer<F> for ExtendedCircuit<F> {
+    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+        let mut cs = cs.borrow_mut().ok_or(SynthesisError::MissingCS)?;
+        assert!(
+            cs.num_instance_variables == 1 && cs.num_witness_variables == 0 && cs.num_constraints == 0,
+            "An extended circuit must be synthesized into an empty constraint system"
+        );
+        // The prover decides how to synthesize and optimize; we decide the rest.
+        let (mode, goal) = (cs.mode, cs.optimization_goal);
+        *cs = self.cs;
+        cs.mode = mode;
+        cs.optimization_goal = goal;
+        Ok(())
+    }
+}
+
+/// Synthesize `circuit`, computing its witness from the shares it was made with. Every party must
+/// call this with the same circuit, apart from the shares.
+pub fn extend_witness<F, S, C>(circuit: C) -> Result<ExtendedCircuit<MpcField<F, S>>, SynthesisError>
+where
+    F: PrimeField,
+    S: FieldShare<F>,
+    C: ConstraintSynthesizer<LazyMpcField<F, S>>,
+{
+    let timer = start_timer!(|| "Witness extension");
+    lazy::adopt_preprocessing::<F, S>();
+    let cs = ConstraintSystem::new_ref();
+    let synthesized = circuit.generate_constraints(cs.clone());
+    lazy::restore_preprocessing::<F, S>();
+    synthesized?;
+    let cs = std::mem::take(&mut *cs.borrow_mut().ok_or(SynthesisError::MissingCS)?);
+    let cs = cs.map_field(lazy::force);
+    lazy::clear::<F, S>();
+    end_timer!(timer);
+    Ok(ExtendedCircuit { cs })
+}
@@ -0,0 +1,869 @@
+//! End-to-end MPC proofs for each proof system and sharing scheme, with all parties simulated in
+//! this process by [`MpcMultiNet::simulate`].
+use ark_bls12_377::Bls12_377;
+use ark_ec::PairingEngine;
+use ark_ff::{FftField, Field, UniformRand};
+use ark_poly::univariate::DensePolynomial;
+use ark_poly::EvaluationDomain;
+use ark_poly_commit::marlin::marlin_pc::MarlinKZG10;
+use ark_relations::{
+    lc,
+    r1cs::{ConstraintSynthesizer, ConstraintSystemRef, SynthesisError, Variable},
+};
+use ark_std::test_rng;
+use blake2::Blake2s;
+use mpc_algebra::share::add::AdditivePairingShare;
+use mpc_algebra::share::gsz20::GszPairingShare;
+use mpc_algebra::share::rss::RssPairingShare;
+use mpc_algebra::share::rss3::RSS3PairingShare;
+use mpc_algebra::share::spdz::SpdzPairingShare;
+use mpc_algebra::client::{self, ClientShare};
+use mpc_algebra::preprocessing::{FieldPreprocessing, PreprocessingCounts};
+use mpc_algebra::{channel, preprocessing, MpcPairingEngine, PairingShare, Reveal};
+use mpc_net::noise::{PublicKey, SecretKey};
+use mpc_net::{MpcMultiNet, MpcNet, MpcNetError, OrAbort, Topology};
+use mpc_plonk::relations::flat::CircuitLayout;
+use mpc_plonk::relations::r1cs;
+use mpc_plonk::relations::structured::PlonkCircuit;
+use mpc_snarks::delegation;
+
+use std::net::SocketAddr;
+
+type E = Bls12_377;
+type Fr = <E as PairingEngine>::Fr;
+type MpcFr<S> = <MpcPairingEngine<E, S> as PairingEngine>::Fr;
+
+const SQUARINGS: usize = 4;
+
+/// Proves knowledge of `x` such that `x^(2^n)` is the public output.
+#[derive(Clone)]
+struct RepeatedSquaringCircuit<F: Field> {
+    chain: Vec<Option<F>>,
+}
+
+impl<F: Field> RepeatedSquaringCircuit<F> {
+    fn without_data(squarings: usize) -> Self {
+        Self {
+            chain: vec![None; squarings + 1],
+        }
+    }
+    fn squarings(&self) -> usize {
+        self.chain.len() - 1
+    }
+}
+
+impl<F: Field> ConstraintSynthesizer<F> for RepeatedSquaringCircuit<F> {
+    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+        let mut vars: Vec<Variable> = self
+            .chain
+            .iter()
+            .take(self.squarings())
+            .map(|o| cs.new_witness_variable(|| o.ok_or(SynthesisError::AssignmentMissing)))
+            .collect::<Result<_, _>>()?;
+        vars.push(cs.new_input_variable(|| {
+            self.chain
+                .last()
+                .unwrap()
+                .ok_or(SynthesisError::AssignmentMissing)
+        })?);
+        for i in 0..self.squarings() {
+            cs.enforce_constraint(lc!() + vars[i], lc!() + vars[i], lc!() + vars[i + 1])?;
+        }
+        Ok(())
+    }
+}
+
+/// The king shares the chain of squares of a random field element.
+fn mpc_squaring_circuit<S: PairingShare<E>>(squarings: usize) -> RepeatedSquaringCircuit<MpcFr<S>> {
+    let rng = &mut test_rng();
+    let start = Fr::rand(rng);
+    let raw_chain: Vec<Fr> = std::iter::successors(Some(start), |a| Some(a.square()))
+        .take(squarings + 1)
+        .collect();
+    RepeatedSquaringCircuit {
+        chain: MpcFr::<S>::king_share_batch(raw_chain, rng)
+            .into_iter()
+            .map(Some)
+            .collect(),
+    }
+}
+
+/// The Plonk gates for `c`, which we synthesize with its values if `values`.
+fn plonk_circuit<F: FftField, C: ConstraintSynthesizer<F>>(c: C, values: bool) -> CircuitLayout<F> {
+    CircuitLayout::from_circuit(&PlonkCircuit::from_synthesizer(c, values).unwrap())
+}
+
+/// Run `prove` as each of `n_parties` parties, sharing with `S`, with dummy preprocessing.
+fn simulate<S: PairingShare<E>>(n_parties: usize, prove: fn()) {
+    simulate_with::<S>(n_parties, Topology::Mesh, prove)
+}
+
+fn simulate_with<S: PairingShare<E>>(n_parties: usize, topology: Topology, prove: fn()) {
+    MpcMultiNet::simulate_with(n_parties, topology, move || {
+        S::FrShare::init_protocol();
+        preprocessing::use_dummy();
+        prove();
+        S::FrShare::deinit_protocol();
+    });
+}
+
+/// In a session with this, only the given party learns the proof.
+struct RevealTo(usize);
+
+/// Run `prove` as each of `n_parties` parties, revealing the proof to party `to` only.
+fn simulate_revealing_to<S: PairingShare<E>>(
+    n_parties: usize,
+    topology: Topology,
+    to: usize,
+    prove: fn(),
+) {
+    MpcMultiNet::simulate_with(n_parties, topology, move || {
+        S::FrShare::init_protocol();
+        preprocessing::use_dummy();
+        MpcMultiNet::with_state(|| RevealTo(to), |_| ());
+        prove();
+        S::FrShare::deinit_protocol();
+    });
+}
+
+/// Reveal `proof` to every party, or to the one party in [`RevealTo`], if the session has it.
+fn reveal_proof<P: Reveal>(proof: P) -> Option<P::Base> {
+    match MpcMultiNet::take_state::<RevealTo>() {
+        Some(RevealTo(to)) => {
+            let revealed = proof.reveal_to(to);
+            assert_eq!(revealed.is_some(), MpcMultiNet::party_id() == to);
+            revealed
+        }
+        None => Some(proof.reveal()),
+    }
+}
+
+fn groth16<S: PairingShare<E>>() {
+    use ark_groth16::{generate_random_parameters, prepare_verifying_key, verify_proof};
+    use mpc_snarks::groth::prover::create_random_proof;
+
+    let rng = &mut test_rng();
+    let circ_no_data = RepeatedSquaringCircuit::without_data(SQUARINGS);
+    let params = generate_random_parameters::<E, _, _>(circ_no_data, rng).unwrap();
+    let pvk = prepare_verifying_key::<E>(&params.vk);
+    let mpc_params = Reveal::from_public(params);
+
+    let circ_data = mpc_squaring_circuit::<S>(SQUARINGS);
+    let public_inputs = vec![circ_data.chain.last().unwrap().unwrap().reveal()];
+    let proof = channel::without_cheating(|| {
+        reveal_proof(
+            create_random_proof::<MpcPairingEngine<E, S>, _, _>(circ_data, &mpc_params, rng)
+                .unwrap(),
+        )
+    });
+    if let Some(proof) = proof {
+        assert!(verify_proof(&pvk, &proof, &public_inputs).unwrap());
+    }
+}
+
+fn marlin<S: PairingShare<E>>() {
+    use ark_marlin::{IndexProverKey, Marlin};
+    type KzgMarlin<Fr, E> = Marlin<Fr, MarlinKZG10<E, DensePolynomial<Fr>>, Blake2s>;
+
+    let rng = &mut test_rng();
+    let n = SQUARINGS;
+    let circ_no_data = RepeatedSquaringCircuit::without_data(n);
+    let srs = KzgMarlin::<Fr, E>::universal_setup(n, n + 2, 3 * n, rng).unwrap();
+    let (pk, vk) = KzgMarlin::<Fr, E>::index(&srs, circ_no_data).unwrap();
+    let mpc_pk = IndexProverKey::from_public(pk);
+
+    let circ_data = mpc_squaring_circuit::<S>(n);
+    let public_inputs = vec![circ_data.chain.last().unwrap().unwrap().reveal()];
+    let zk_rng = &mut test_rng();
+    let proof = channel::without_cheating(|| {
+        reveal_proof(
+            KzgMarlin::<MpcFr<S>, MpcPairingEngine<E, S>>::prove(&mpc_pk, circ_data, zk_rng)
+                .unwrap(),
+        )
+    });
+    if let Some(proof) = proof {
+        assert!(KzgMarlin::<Fr, E>::verify(&vk, &public_inputs, &proof, rng).unwrap());
+    }
+}
+
+fn plonk<S: PairingShare<E>>() {
+    type MarlinPcPlonk<Fr, E> = mpc_plonk::Plonk<Fr, MarlinKZG10<E, DensePolynomial<Fr>>>;
+
+    let n = SQUARINGS;
+    let circ_no_data = plonk_circuit(RepeatedSquaringCircuit::without_data(n), false);
+
+    let circ_data = mpc_squaring_circuit::<S>(n);
+    let public_inputs = r1cs::public_inputs(&[circ_data.chain.last().unwrap().unwrap().reveal()]);
+    let plonk_circ_data = plonk_circuit(circ_data, true);
+    let srs = MarlinPcPlonk::<Fr, E>::universal_setup(circ_no_data.domains.gates.size(), &mut test_rng());
+    let (pk, vk) = MarlinPcPlonk::<Fr, E>::circuit_setup(&srs, &circ_no_data);
+    let mpc_pk = Reveal::from_public(pk);
+    let zk_rng = &mut test_rng();
+    let proof = channel::without_cheating(|| {
+        reveal_proof(MarlinPcPlonk::<MpcFr<S>, MpcPairingEngine<E, S>>::prove(
+            &mpc_pk,
+            &plonk_circ_data,
+            zk_rng,
+        ))
+    });
+    if let Some(proof) = proof {
+        MarlinPcPlonk::<Fr, E>::verify(&vk, &circ_no_data, proof, &public_inputs).unwrap();
+    }
+}
+
+#[test]
+fn groth16_hbc() {
+    simulate::<AdditivePairingShare<E>>(2, groth16::<AdditivePairingShare<E>>);
+}
+
+#[test]
+fn groth16_spdz() {
+    simulate::<SpdzPairingShare<E>>(2, groth16::<SpdzPairingShare<E>>);
+}
+
+#[test]
+fn groth16_gsz() {
+    simulate::<GszPairingShare<E>>(3, groth16::<GszPairingShare<E>>);
+}
+
+#[test]
+fn groth16_rss3() {
+    simulate::<RSS3PairingShare<E>>(3, groth16::<RSS3PairingShare<E>>);
+}
+
+#[test]
+fn marlin_hbc() {
+    simulate::<AdditivePairingShare<E>>(2, marlin::<AdditivePairingShare<E>>);
+}
+
+#[test]
+fn marlin_spdz() {
+    simulate::<SpdzPairingShare<E>>(2, marlin::<SpdzPairingShare<E>>);
+}
+
+#[test]
+fn marlin_gsz() {
+    simulate::<GszPairingShare<E>>(3, marlin::<GszPairingShare<E>>);
+}
+
+#[test]
+fn marlin_rss3() {
+    simulate::<RSS3PairingShare<E>>(3, marlin::<RSS3PairingShare<E>>);
+}
+
+#[test]
+fn plonk_hbc() {
+    simulate::<AdditivePairingShare<E>>(2, plonk::<AdditivePairingShare<E>>);
+}
+
+#[test]
+fn plonk_spdz() {
+    simulate::<SpdzPairingShare<E>>(2, plonk::<SpdzPairingShare<E>>);
+}
+
+#[test]
+fn plonk_gsz() {
+    simulate::<GszPairingShare<E>>(3, plonk::<GszPairingShare<E>>);
+}
+
+#[test]
+fn plonk_rss3() {
+    simulate::<RSS3PairingShare<E>>(3, plonk::<RSS3PairingShare<E>>);
+}
+
+#[test]
+fn groth16_rss() {
+    simulate::<RssPairingShare<E>>(4, groth16::<RssPairingShare<E>>);
+}
+
+#[test]
+fn marlin_rss() {
+    simulate::<RssPairingShare<E>>(5, marlin::<RssPairingShare<E>>);
+}
+
+#[test]
+fn plonk_rss() {
+    simulate::<RssPairingShare<E>>(7, plonk::<RssPairingShare<E>>);
+}
+
+#[test]
+fn plonk_rss_star() {
+    simulate_with::<RssPairingShare<E>>(5, Topology::Star, plonk::<RssPairingShare<E>>);
+}
+
+#[test]
+fn groth16_spdz_reveal_to() {
+    type S = SpdzPairingShare<E>;
+    simulate_revealing_to::<S>(3, Topology::Mesh, 1, groth16::<S>);
+}
+
+#[test]
+fn marlin_gsz_reveal_to() {
+    type S = GszPairingShare<E>;
+    simulate_revealing_to::<S>(3, Topology::Mesh, 2, marlin::<S>);
+}
+
+#[test]
+fn plonk_rss3_reveal_to() {
+    type S = RSS3PairingShare<E>;
+    simulate_revealing_to::<S>(3, Topology::Mesh, 0, plonk::<S>);
+}
+
+#[test]
+fn plonk_hbc_reveal_to() {
+    type S = AdditivePairingShare<E>;
+    simulate_revealing_to::<S>(2, Topology::Mesh, 1, plonk::<S>);
+}
+
+#[test]
+fn groth16_rss_star_reveal_to() {
+    type S = RssPairingShare<E>;
+    simulate_revealing_to::<S>(5, Topology::Star, 3, groth16::<S>);
+}
+
+#[test]
+fn groth16_rss3_star() {
+    simulate_with::<RSS3PairingShare<E>>(3, Topology::Star, groth16::<RSS3PairingShare<E>>);
+}
+
+#[test]
+fn plonk_gsz_star() {
+    simulate_with::<GszPairingShare<E>>(3, Topology::Star, plonk::<GszPairingShare<E>>);
+}
+
+/// Addresses at which a client can reach `n` simulated parties, from `port` up.
+fn local_addrs(port: u16, n: usize) -> Vec<SocketAddr> {
+    (0..n)
+        .map(|i| SocketAddr::from(([127, 0, 0, 1], port + i as u16)))
+        .collect()
+}
+
+/// Secret keys for `n` parties at `addrs` and for a client, and the parties' addresses and public
+/// keys, as the client reads them from the hosts file.
+fn client_keys(addrs: &[SocketAddr]) -> (Vec<SecretKey>, SecretKey, Vec<(SocketAddr, Option<PublicKey>)>) {
+    let keys: Vec<SecretKey> = addrs.iter().map(|_| SecretKey::generate()).collect();
+    let parties = addrs
+        .iter()
+        .zip(&keys)
+        .map(|(addr, key)| (*addr, Some(key.public_key())))
+        .collect();
+    (keys, SecretKey::generate(), parties)
+}
+
+/// A client shares three values among `n` parties, which compute with them.
+fn client_inputs<S: PairingShare<E>>(n: usize, port: u16)
+where
+    S::FrShare: ClientShare<Base = Fr>,
+{
+    let rng = &mut test_rng();
+    let xs: Vec<Fr> = (0..3).map(|_| Fr::rand(rng)).collect();
+    let addrs = local_addrs(port, n);
+    let (keys, client_key, parties) = client_keys(&addrs);
+    let client_public_key = client_key.public_key();
+    let values = xs.clone();
+    let client = std::thread::spawn(move || {
+        client::send_to_parties::<MpcFr<S>>(&parties, &values, None, &client_key)
+    });
+    let opened = MpcMultiNet::simulate(n, move || {
+        S::FrShare::init_protocol();
+        preprocessing::use_dummy();
+        let id = MpcMultiNet::party_id();
+        let keys = (&keys[id], &client_public_key);
+        let shared = client::recv_from_client::<MpcFr<S>>(&MpcMultiNet::current_session(), addrs[id], keys).or_abort();
+        let opened = (shared[0] * shared[1] + shared[2]).reveal();
+        S::FrShare::deinit_protocol();
+        opened
+    });
+    client.join().unwrap().unwrap();
+    assert!(opened.into_iter().all(|x| x == xs[0] * xs[1] + xs[2]));
+}
+
+#[test]
+fn client_inputs_for_each_scheme() {
+    client_inputs::<AdditivePairingShare<E>>(2, 28100);
+    client_inputs::<SpdzPairingShare<E>>(2, 28110);
+    client_inputs::<GszPairingShare<E>>(3, 28120);
+    client_inputs::<RSS3PairingShare<E>>(3, 28130);
+    client_inputs::<RssPairingShare<E>>(5, 28140);
+}
+
+#[test]
+fn client_must_hold_its_key() {
+    type S = SpdzPairingShare<E>;
+    let n = 3;
+    let addrs = local_addrs(28150, n);
+    let (keys, client_key, parties) = client_keys(&addrs);
+    let client_public_key = client_key.public_key();
+    let x = Fr::rand(&mut test_rng());
+    let client = std::thread::spawn(move || {
+        let impostor = SecretKey::generate();
+        let refused = client::send_to_parties::<MpcFr<S>>(&parties, &[x], None, &impostor);
+        assert!(matches!(refused, Err(MpcNetError::Disconnected { party: 0, .. })));
+        client::send_to_parties::<MpcFr<S>>(&parties, &[x], None, &client_key)
+    });
+    let opened = MpcMultiNet::simulate(n, move || {
+        <S as PairingShare<E>>::FrShare::init_protocol();
+        preprocessing::use_dummy();
+        let id = MpcMultiNet::party_id();
+        let keys = (&keys[id], &client_public_key);
+        let shared = client::recv_from_client::<MpcFr<S>>(&MpcMultiNet::current_session(), addrs[id], keys).or_abort();
+        let opened = shared[0].reveal();
+        <S as PairingShare<E>>::FrShare::deinit_protocol();
+        opened
+    });
+    client.join().unwrap().unwrap();
+    assert_eq!(opened, vec![x; n]);
+}
+
+/// A client shares three values among `n` parties, which compute with them and send the result
+/// back to the client only.
+fn client_outputs<S: PairingShare<E>>(n: usize, port: u16)
+where
+    S::FrShare: ClientShare<Base = Fr>,
+{
+    let rng = &mut test_rng();
+    let xs: Vec<Fr> = (0..3).map(|_| Fr::rand(rng)).collect();
+    let addrs = local_addrs(port, n);
+    let (keys, client_key, parties) = client_keys(&addrs);
+    let client_public_key = client_key.public_key();
+    let values = xs.clone();
+    let client = std::thread::spawn(move || {
+        client::send_to_parties::<MpcFr<S>>(&parties, &values, None, &client_key)?;
+        client::recv_from_parties::<MpcFr<S>, MpcFr<S>>(&parties, &client_key)
+    });
+    MpcMultiNet::simulate(n, move || {
+        S::FrShare::init_protocol();
+        preprocessing::use_dummy();
+        let id = MpcMultiNet::party_id();
+        let keys = (&keys[id], &client_public_key);
+        let shared = client::recv_from_client::<MpcFr<S>>(&MpcMultiNet::current_session(), addrs[id], keys).or_abort();
+        let result = shared[0] * shared[1] + shared[2];
+        client::reveal_to_client::<MpcFr<S>, _>(&result, addrs[id], keys).or_abort();
+        S::FrShare::deinit_protocol();
+    });
+    assert_eq!(client.join().unwrap().unwrap(), xs[0] * xs[1] + xs[2]);
+}
+
+#[test]
+fn client_outputs_for_each_scheme() {
+    client_outputs::<AdditivePairingShare<E>>(2, 28160);
+    client_outputs::<SpdzPairingShare<E>>(2, 28170);
+    client_outputs::<GszPairingShare<E>>(3, 28180);
+    client_outputs::<RSS3PairingShare<E>>(3, 28190);
+    client_outputs::<RssPairingShare<E>>(5, 28200);
+}
+
+#[test]
+fn client_gets_groth16_proof() {
+    use ark_groth16::{generate_random_parameters, prepare_verifying_key, verify_proof, Proof};
+    use mpc_snarks::groth::prover::create_random_proof;
+    type S = SpdzPairingShare<E>;
+    type MpcE = MpcPairingEngine<E, S>;
+
+    let n = 3;
+    let rng = &mut test_rng();
+    let circ_no_data = RepeatedSquaringCircuit::without_data(SQUARINGS);
+    let params = generate_random_parameters::<E, _, _>(circ_no_data, rng).unwrap();
+    let pvk = prepare_verifying_key::<E>(&params.vk);
+    let addrs = local_addrs(28210, n);
+    let (keys, client_key, parties) = client_keys(&addrs);
+    let client_public_key = client_key.public_key();
+    let client = std::thread::spawn(move || {
+        client::recv_from_parties::<MpcFr<S>, Proof<MpcE>>(&parties, &client_key)
+    });
+    let public_inputs = MpcMultiNet::simulate(n, move || {
+        <S as PairingShare<E>>::FrShare::init_protocol();
+        preprocessing::use_dummy();
+        let rng = &mut test_rng();
+        let mpc_params = Reveal::from_public(params.clone());
+        let circ_data = mpc_squaring_circuit::<S>(SQUARINGS);
+        let public_inputs = vec![circ_data.chain.last().unwrap().unwrap().reveal()];
+        let proof = create_random_proof::<MpcE, _, _>(circ_data, &mpc_params, rng).unwrap();
+        let id = MpcMultiNet::party_id();
+        let keys = (&keys[id], &client_public_key);
+        client::reveal_to_client::<MpcFr<S>, _>(&proof, addrs[id], keys).or_abort();
+        <S as PairingShare<E>>::FrShare::deinit_protocol();
+        public_inputs
+    });
+    let proof = client.join().unwrap().unwrap();
+    assert!(verify_proof(&pvk, &proof, &public_inputs[0]).unwrap());
+}
+
+/// `n` servers prove `jobs` in turn for a client, which checks each proof, or that the servers
+/// turned the job down when its keys were never set up. `stores` holds each job's preprocessing,
+/// one store per server, and is empty for sharings that need none.
+fn delegate_jobs<S: PairingShare<E>>(
+    n: usize,
+    port: u16,
+    jobs: Vec<(delegation::Job, bool)>,
+    stores: Vec<Vec<FieldPreprocessing<Fr, S::FrShare>>>,
+) where
+    S::FrShare: ClientShare<Base = Fr>,
+{
+    let dir = std::env::temp_dir().join(format!("delegation-{}-{}", std::process::id(), port));
+    std::fs::create_dir_all(&dir).unwrap();
+    for (job, _) in jobs.iter().filter(|(_, set_up)| *set_up) {
+        delegation::setup::<E>(&dir, job).unwrap();
+    }
+    let addrs = local_addrs(port, n);
+    let (keys, client_key, parties) = client_keys(&addrs);
+    let client_public_key = client_key.public_key();
+    let client_dir = dir.clone();
+    let server_dir = dir.clone();
+    let client_jobs = jobs.clone();
+    let client = std::thread::spawn(move || {
+        for (job, set_up) in client_jobs {
+            let witness = job.circuit.witness(Fr::rand(&mut test_rng()));
+            let result = delegation::delegate::<E, S>(&client_dir, &parties, &client_key, &job, &witness, None);
+            match result {
+                Ok(()) => assert!(set_up, "{} should have been turned down", job),
+                Err(delegation::DelegationError::Rejected(_)) => assert!(!set_up, "{} was turned down", job),
+                Err(e) => panic!("{}: {}", job, e),
+            }
+        }
+    });
+    let n_jobs = jobs.len();
+    let served = MpcMultiNet::simulate(n, move || {
+        let party = MpcMultiNet::party_id();
+        // As the daemon does: each job in its own session, named by the id the servers agree on.
+        // Party 1 loses count, which the others make up for.
+        let default = MpcMultiNet::current_session();
+        let mut next = 0;
+        (0..n_jobs)
+            .map(|_| {
+                let id = delegation::next_job_id(&default, if party == 1 { 0 } else { next }).unwrap();
+                next = id + 1;
+                let store = stores.get(id as usize).map(|stores| stores[party].clone());
+                let keys = (&keys[party], &client_public_key);
+                let session = MpcMultiNet::session(id + 1);
+                let served = delegation::serve::<E, S>(&session, &server_dir, store, addrs[party], keys);
+                session.end();
+                served.or_abort()
+            })
+            .collect::<Vec<_>>()
+    });
+    client.join().unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    let jobs: Vec<_> = jobs.into_iter().map(|(job, _)| job).collect();
+    assert_eq!(served, vec![jobs; n]);
+}
+
+#[test]
+fn delegation_for_each_proof_system() {
+    use delegation::{Circuit, Job, ProofSystem};
+    let job = |system| Job {
+        system,
+        circuit: Circuit::Squaring(SQUARINGS),
+    };
+    // Every job draws from its own store.
+    let counts = PreprocessingCounts {
+        triples: 1 << 14,
+        inv_pairs: 1 << 8,
+        rands: 1 << 10,
+        inputs: 1 << 10,
+    };
+    let stores = (0..3)
+        .map(|_| FieldPreprocessing::deal(counts, 3, &mut test_rng()))
+        .collect();
+    delegate_jobs::<SpdzPairingShare<E>>(
+        3,
+        28220,
+        vec![
+            (job(ProofSystem::Groth16), true),
+            (job(ProofSystem::Marlin), true),
+            (job(ProofSystem::Plonk), true),
+        ],
+        stores,
+    );
+    delegate_jobs::<RssPairingShare<E>>(5, 28230, vec![(job(ProofSystem::Groth16), true)], Vec::new());
+}
+
+#[test]
+#[should_panic(expected = "Cannot serve a job")]
+fn delegation_needs_preprocessing() {
+    let addrs = local_addrs(28250, 3);
+    let (keys, client_key, _) = client_keys(&addrs);
+    let client_public_key = client_key.public_key();
+    MpcMultiNet::simulate(3, move || {
+        let id = MpcMultiNet::party_id();
+        let keys = (&keys[id], &client_public_key);
+        let session = MpcMultiNet::session(1);
+        delegation::serve::<E, SpdzPairingShare<E>>(&session, &std::env::temp_dir(), None, addrs[id], keys)
+    });
+}
+
+#[test]
+fn delegation_turns_down_jobs_without_keys() {
+    use delegation::{Circuit, Job, ProofSystem};
+    let job = |n| Job {
+        system: ProofSystem::Groth16,
+        circuit: Circuit::Squaring(n),
+    };
+    // The servers carry on with the next job after turning one down.
+    delegate_jobs::<GszPairingShare<E>>(3, 28240, vec![(job(3), false), (job(4), true)], Vec::new());
+}
+
+/// Proves knowledge of `x` such that `x^3 + x + 5` is the public output: unlike the squarings,
+/// with coefficients and the constant.
+struct CubicCircuit<F: Field>(Option<F>);
+
+impl<F: Field> ConstraintSynthesizer<F> for CubicCircuit<F> {
+    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+        let x = self.0.ok_or(SynthesisError::AssignmentMissing);
+        let out = cs.new_input_variable(|| Ok(x? * x? * x? + x? + F::from(5u64)))?;
+        let x_var = cs.new_witness_variable(|| x)?;
+        let x2 = cs.new_witness_variable(|| Ok(x?.square()))?;
+        let x3 = cs.new_witness_variable(|| Ok(x?.square() * x?))?;
+        cs.enforce_constraint(lc!() + x_var, lc!() + x_var, lc!() + x2)?;
+        cs.enforce_constraint(lc!() + x2, lc!() + x_var, lc!() + x3)?;
+        cs.enforce_constraint(
+            lc!() + x3 + x_var + (F::from(5u64), Variable::One),
+            lc!() + Variable::One,
+            lc!() + out,
+        )?;
+        Ok(())
+    }
+}
+
+/// Dump `CubicCircuit` to files, load it back, and prove it with Groth16, Marlin and Plonk among
+/// `n` parties.
+fn prove_r1cs_file<S: PairingShare<E>>(n: usize) {
+    use ark_groth16::{generate_random_parameters, prepare_verifying_key, verify_proof};
+    use ark_marlin::{IndexProverKey, Marlin};
+    use mpc_snarks::groth::prover::create_random_proof;
+    use mpc_snarks::r1cs_file::{load_witness, R1cs};
+    type KzgMarlin<Fr, E> = Marlin<Fr, MarlinKZG10<E, DensePolynomial<Fr>>, Blake2s>;
+
+    let dir = std::env::temp_dir().join(format!("r1cs-{}-{}", std::process::id(), n));
+    std::fs::create_dir_all(&dir).unwrap();
+    let (r1cs_path, witness_path) = (dir.join("cubic.r1cs.bin"), dir.join("cubic.wtns.bin"));
+    let x = Fr::rand(&mut test_rng());
+    R1cs::dump(CubicCircuit(Some(x)), &r1cs_path, &witness_path).unwrap();
+    let r1cs = R1cs::<Fr>::load(&r1cs_path).unwrap();
+    let witness: Vec<Fr> = load_witness(&witness_path).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    r1cs.check(&witness).unwrap();
+
+    let rng = &mut test_rng();
+    let params = generate_random_parameters::<E, _, _>(r1cs.circuit(None), rng).unwrap();
+    let pvk = prepare_verifying_key::<E>(&params.vk);
+    let public_inputs = witness[1..=r1cs.num_public].to_vec();
+    MpcMultiNet::simulate(n, move || {
+        S::FrShare::init_protocol();
+        preprocessing::use_dummy();
+        let rng = &mut test_rng();
+        let mpc_r1cs = r1cs.map(MpcFr::<S>::from_public);
+        // Only the king draws from this rng, so it must not be the one that Groth16 draws its
+        // public randomness from.
+        let wires = MpcFr::<S>::king_share_batch(witness[1..].to_vec(), &mut test_rng());
+        let circuit = mpc_r1cs.circuit(Some(wires));
+
+        let mpc_params = Reveal::from_public(params.clone());
+        let proof = create_random_proof::<MpcPairingEngine<E, S>, _, _>(circuit.clone(), &mpc_params, rng)
+            .unwrap()
+            .reveal();
+        assert!(verify_proof(&pvk, &proof, &public_inputs).unwrap());
+
+        // Marlin's keys cannot cross threads, so every party makes the same ones.
+        let (constraints, variables, non_zero) = r1cs.marlin_bounds();
+        let srs = KzgMarlin::<Fr, E>::universal_setup(constraints, variables, non_zero, &mut test_rng())
+            .unwrap();
+        let (pk, vk) = KzgMarlin::<Fr, E>::index(&srs, r1cs.circuit(None)).unwrap();
+        let mpc_pk = IndexProverKey::from_public(pk);
+        let proof = KzgMarlin::<MpcFr<S>, MpcPairingEngine<E, S>>::prove(&mpc_pk, circuit.clone(), rng)
+            .unwrap()
+            .reveal();
+        assert!(KzgMarlin::<Fr, E>::verify(&vk, &public_inputs, &proof, rng).unwrap());
+
+        type MarlinPcPlonk<Fr, E> = mpc_plonk::Plonk<Fr, MarlinKZG10<E, DensePolynomial<Fr>>>;
+        let circ_no_data = plonk_circuit(r1cs.circuit(None), false);
+        let srs = MarlinPcPlonk::<Fr, E>::universal_setup(circ_no_data.domains.gates.size(), &mut test_rng());
+        let (pk, vk) = MarlinPcPlonk::<Fr, E>::circuit_setup(&srs, &circ_no_data);
+        let mpc_pk = Reveal::from_public(pk);
+        let proof = MarlinPcPlonk::<MpcFr<S>, MpcPairingEngine<E, S>>::prove(
+            &mpc_pk,
+            &plonk_circuit(circuit, true),
+            rng,
+        )
+        .reveal();
+        MarlinPcPlonk::<Fr, E>::verify(&vk, &circ_no_data, proof, &r1cs::public_inputs(&public_inputs)).unwrap();
+        S::FrShare::deinit_protocol();
+    });
+}
+
+#[test]
+fn r1cs_file_spdz() {
+    prove_r1cs_file::<SpdzPairingShare<E>>(2);
+}
+
+#[test]
+fn r1cs_file_rss() {
+    prove_r1cs_file::<RssPairingShare<E>>(5);
+}
+
+const CUBES: usize = 5;
+
+/// Proves knowledge of `xs` whose cubes sum to the public output, with `r1cs-std` gadgets. The
+/// cubes are independent, so extending the witness takes two layers of products.
+struct SumOfCubesCircuit<F: Field>(Vec<Option<F>>);
+
+impl<F: ark_ff::PrimeField> ConstraintSynthesizer<F> for SumOfCubesCircuit<F> {
+    fn generate_constraints(self, cs: ConstraintSystemRef<F>) -> Result<(), SynthesisError> {
+        use ark_r1cs_std::{alloc::AllocVar, eq::EqGadget, fields::fp::FpVar, fields::FieldVar, R1CSVar};
+        let mut sum = FpVar::zero();
+        for x in self.0 {
+            let x = FpVar::new_witness(cs.clone(), || x.ok_or(SynthesisError::AssignmentMissing))?;
+            sum += x.square()? * &x;
+        }
+        let out = FpVar::new_input(cs, || sum.value())?;
+        out.enforce_equal(&sum)
+    }
+}
+
+/// Share only the inputs of `SumOfCubesCircuit`, extend its witness among `n` parties, and prove
+/// it with Groth16 and Marlin.
+fn prove_extended_witness<S: PairingShare<E>>(n: usize) {
+    use ark_groth16::{generate_random_parameters, prepare_verifying_key, verify_proof};
+    use ark_marlin::{IndexProverKey, Marlin};
+    use mpc_algebra::share::lazy;
+    use mpc_snarks::groth::prover::create_random_proof;
+    use mpc_snarks::r1cs_file::R1cs;
+    use mpc_snarks::witness::extend_witness;
+    type KzgMarlin<Fr, E> = Marlin<Fr, MarlinKZG10<E, DensePolynomial<Fr>>, Blake2s>;
+
+    let rng = &mut test_rng();
+    let xs: Vec<Fr> = (0..CUBES).map(|_| Fr::rand(rng)).collect();
+    let out: Fr = xs.iter().map(|x| x.square() * x).sum();
+    let (r1cs, _) = R1cs::from_synthesizer(SumOfCubesCircuit(xs.iter().cloned().map(Some).collect()))
+        .unwrap();
+    let params = generate_random_parameters::<E, _, _>(SumOfCubesCircuit(vec![None; CUBES]), rng)
+        .unwrap();
+    let pvk = prepare_verifying_key::<E>(&params.vk);
+    MpcMultiNet::simulate(n, move || {
+        S::FrShare::init_protocol();
+        preprocessing::use_dummy();
+        let rng = &mut test_rng();
+        let extend = || {
+            let inputs = MpcFr::<S>::king_share_batch(xs.clone(), &mut test_rng());
+            let circuit = SumOfCubesCircuit(inputs.into_iter().map(|x| Some(lazy::defer(x))).collect());
+            extend_witness(circuit).unwrap()
+        };
+        let circuit = extend();
+        assert_eq!(circuit.public_inputs().reveal(), vec![out]);
+
+        let mpc_params = Reveal::from_public(params.clone());
+        let proof = create_random_proof::<MpcPairingEngine<E, S>, _, _>(circuit, &mpc_params, rng)
+            .unwrap()
+            .reveal();
+        assert!(verify_proof(&pvk, &proof, &[out]).unwrap());
+
+        // Marlin's keys cannot cross threads, so every party makes the same ones.
+        let (constraints, variables, non_zero) = r1cs.marlin_bounds();
+        let srs = KzgMarlin::<Fr, E>::universal_setup(constraints, variables, non_zero, &mut test_rng())
+            .unwrap();
+        let (pk, vk) = KzgMarlin::<Fr, E>::index(&srs, SumOfCubesCircuit(vec![None; CUBES])).unwrap();
+        let mpc_pk = IndexProverKey::from_public(pk);
+        let proof = KzgMarlin::<MpcFr<S>, MpcPairingEngine<E, S>>::prove(&mpc_pk, extend(), rng)
+            .unwrap()
+            .reveal();
+        assert!(KzgMarlin::<Fr, E>::verify(&vk, &[out], &proof, rng).unwrap());
+        S::FrShare::deinit_protocol();
+    });
+}
+
+#[test]
+fn extended_witness_spdz() {
+    prove_extended_witness::<SpdzPairingShare<E>>(3);
+}
+
+#[test]
+fn extended_witness_gsz() {
+    prove_extended_witness::<GszPairingShare<E>>(3);
+}
+
+#[test]
+fn extended_witness_rss() {
+    prove_extended_witness::<RssPairingShare<E>>(5);
+}
+
+/// y = 3x^2 - 5x + 7 and z = 2y + 1, with `x` private: every kind of gate.
+fn general_gates_circuit<F: Field>(x: Option<F>) -> PlonkCircuit<F> {
+    let mut c = PlonkCircuit::new(x.is_some());
+    let x = c.new_var(|| x.unwrap());
+    let x2 = c.new_prod(x, x);
+    let y = c.new_gate(x2, x, F::from(3u64), -F::from(5u64), F::zero(), F::from(7u64));
+    let two_y = c.new_scale(y, F::from(2u64));
+    let one = c.new_const(F::one());
+    let z = c.new_sum(two_y, one);
+    c.publicize_var(y, "y".to_owned());
+    c.publicize_var(z, "z".to_owned());
+    c
+}
+
+fn plonk_general_gates<S: PairingShare<E>>() {
+    type MarlinPcPlonk<Fr, E> = mpc_plonk::Plonk<Fr, MarlinKZG10<E, DensePolynomial<Fr>>>;
+
+    let x = Fr::rand(&mut test_rng());
+    let y = x.square() * Fr::from(3u64) - x * Fr::from(5u64) + Fr::from(7u64);
+    let public_inputs = vec![("y".to_owned(), y), ("z".to_owned(), y.double() + Fr::from(1u64))]
+        .into_iter()
+        .collect();
+    let circ_no_data = CircuitLayout::from_circuit(&general_gates_circuit::<Fr>(None));
+    let x_share = MpcFr::<S>::king_share(x, &mut test_rng());
+    let circ_data = CircuitLayout::from_circuit(&general_gates_circuit(Some(x_share)));
+    let srs = MarlinPcPlonk::<Fr, E>::universal_setup(circ_no_data.domains.gates.size(), &mut test_rng());
+    let (pk, vk) = MarlinPcPlonk::<Fr, E>::circuit_setup(&srs, &circ_no_data);
+    let mpc_pk = Reveal::from_public(pk);
+    let proof = MarlinPcPlonk::<MpcFr<S>, MpcPairingEngine<E, S>>::prove(&mpc_pk, &circ_data, &mut test_rng())
+        .reveal();
+    MarlinPcPlonk::<Fr, E>::verify(&vk, &circ_no_data, proof, &public_inputs).unwrap();
+}
+
+#[test]
+fn plonk_general_gates_spdz() {
+    simulate::<SpdzPairingShare<E>>(3, plonk_general_gates::<SpdzPairingShare<E>>);
+}
+
+/// y = x + 9, with `y` public, and both `x` and `y` below 16.
+fn range_circuit<F: Field>(x: Option<F>) -> PlonkCircuit<F> {
+    let mut c = PlonkCircuit::new(x.is_some());
+    c.set_table(PlonkCircuit::range_table(4));
+    let x = c.new_var(|| x.unwrap());
+    let y = c.new_gate(x, x, F::one(), F::zero(), F::zero(), F::from(9u64));
+    c.publicize_var(y, "y".to_owned());
+    c.lookup(x);
+    c.lookup(y);
+    c
+}
+
+/// The parties sort the lookups and the table without learning `x`.
+fn plonk_lookup<S: PairingShare<E>>() {
+    type MarlinPcPlonk<Fr, E> = mpc_plonk::Plonk<Fr, MarlinKZG10<E, DensePolynomial<Fr>>>;
+
+    let x = Fr::from(5u64);
+    let public_inputs = std::iter::once(("y".to_owned(), Fr::from(14u64))).collect();
+    let circ_no_data = CircuitLayout::from_circuit(&range_circuit::<Fr>(None));
+    let x_share = MpcFr::<S>::king_share(x, &mut test_rng());
+    let circ_data = CircuitLayout::from_circuit(&range_circuit(Some(x_share)));
+    let srs = MarlinPcPlonk::<Fr, E>::universal_setup(circ_no_data.domains.gates.size(), &mut test_rng());
+    let (pk, vk) = MarlinPcPlonk::<Fr, E>::circuit_setup(&srs, &circ_no_data);
+    let mpc_pk = Reveal::from_public(pk);
+    let proof = MarlinPcPlonk::<MpcFr<S>, MpcPairingEngine<E, S>>::prove(&mpc_pk, &circ_data, &mut test_rng())
+        .reveal();
+    MarlinPcPlonk::<Fr, E>::verify(&vk, &circ_no_data, proof, &public_inputs).unwrap();
+}
+
+#[test]
+fn plonk_lookup_spdz() {
+    simulate::<SpdzPairingShare<E>>(3, plonk_lookup::<SpdzPairingShare<E>>);
+}
+
+#[test]
+fn plonk_lookup_gsz() {
+    simulate::<GszPairingShare<E>>(3, plonk_lookup::<GszPairingShare<E>>);
+}
+
+#[test]
+fn plonk_lookup_rss() {
+    simulate::<RssPairingShare<E>>(5, plonk_lookup::<RssPairingShare<E>>);
+}
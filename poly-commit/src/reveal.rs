@@ -32,8 +32,12 @@ impl<E: PrimeField, S: FieldShare<E>> Reveal
 impl<E: PairingEngine, S: PairingShare<E>> Reveal for kzg10::Commitment<MpcPairingEngine<E, S>> {
     type Base = kzg10::Commitment<E>;
 
-    fn reveal(self) -> Self::Base {
-        kzg10::Commitment(self.0.reveal())
+    fn try_reveal(self) -> Result<Self::Base, MpcNetError> {
+        Ok(kzg10::Commitment(self.0.try_reveal()?))
+    }
+
+    fn reveal_to(self, to: usize) -> Option<Self::Base> {
+        self.0.reveal_to(to).map(kzg10::Commitment)
     }
 
     fn from_add_shared(b: Self::Base) -> Self {
@@ -110,12 +114,21 @@ where
     C::Base: PCCommitment,
 {
     type Base = LabeledCommitment<C::Base>;
-    fn reveal(self) -> Self::Base {
-        LabeledCommitment::new(
+    fn try_reveal(self) -> Result<Self::Base, MpcNetError> {
+        Ok(LabeledCommitment::new(
             self.label().clone(),
-            self.commitment.clone().reveal(),
+            self.commitment.clone().try_reveal()?,
             self.degree_bound(),
-        )
+        ))
+    }
+
+    fn reveal_to(self, to: usize) -> Option<Self::Base> {
+        let commitment = self.commitment.clone().reveal_to(to)?;
+        Some(LabeledCommitment::new(
+            self.label().clone(),
+            commitment,
+            self.degree_bound(),
+        ))
     }
 
     fn from_add_shared(b: Self::Base) -> Self {
@@ -139,13 +152,13 @@ impl<F: PrimeField, S: FieldShare<F>> Reveal
     for LabeledPolynomial<MpcField<F, S>, DensePolynomial<MpcField<F, S>>>
 {
     type Base = LabeledPolynomial<F, DensePolynomial<F>>;
-    fn reveal(self) -> Self::Base {
-        LabeledPolynomial::new(
+    fn try_reveal(self) -> Result<Self::Base, MpcNetError> {
+        Ok(LabeledPolynomial::new(
             self.label().clone(),
-            self.polynomial().clone().reveal(),
+            self.polynomial().clone().try_reveal()?,
             self.degree_bound(),
             self.hiding_bound(),
-        )
+        ))
     }
 
     fn from_add_shared(b: Self::Base) -> Self {
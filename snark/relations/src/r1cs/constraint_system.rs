@@ -157,6 +157,45 @@ impl<F: Field> ConstraintSystem<F> {
         ConstraintSystemRef::new(Self::new())
     }
 
+    /// Map every field element in `self`, in its assignments and its linear
+    /// combinations, with `f`. For moving a constraint system between two
+    /// representations of the same field, so `f` must preserve the field
+    /// operations.
+    pub fn map_field<G: Field>(self, mut f: impl FnMut(F) -> G) -> ConstraintSystem<G> {
+        let lc_map = self
+            .lc_map
+            .into_iter()
+            .map(|(index, lc)| {
+                let terms = lc.0.into_iter().map(|(coeff, var)| (f(coeff), var));
+                (index, LinearCombination(terms.collect()))
+            })
+            .collect();
+        let lc_assignment_cache = self
+            .lc_assignment_cache
+            .borrow()
+            .iter()
+            .map(|(index, value)| (*index, f(*value)))
+            .collect();
+        ConstraintSystem {
+            mode: self.mode,
+            num_instance_variables: self.num_instance_variables,
+            num_witness_variables: self.num_witness_variables,
+            num_constraints: self.num_constraints,
+            num_linear_combinations: self.num_linear_combinations,
+            optimization_goal: self.optimization_goal,
+            instance_assignment: self.instance_assignment.into_iter().map(&mut f).collect(),
+            witness_assignment: self.witness_assignment.into_iter().map(&mut f).collect(),
+            cache_map: Rc::new(RefCell::new(BTreeMap::new())),
+            lc_map,
+            #[cfg(feature = "std")]
+            constraint_traces: self.constraint_traces,
+            a_constraints: self.a_constraints,
+            b_constraints: self.b_constraints,
+            c_constraints: self.c_constraints,
+            lc_assignment_cache: Rc::new(RefCell::new(lc_assignment_cache)),
+        }
+    }
+
     /// Set `self.mode` to `mode`.
     pub fn set_mode(&mut self, mode: SynthesisMode) {
         self.mode = mode;
@@ -122,7 +122,7 @@ pub mod inner {
                 end_info,
                 message,
                 final_time,
-                pad = 75 - indent_amount
+                pad = 75usize.saturating_sub(indent_amount)
             );
         }};
     }